@@ -0,0 +1,12 @@
+//! Feeds random data through the decoding of packets from the map server.
+//! Decoding may fail, but it should never panic.
+//!
+//! Run with `cargo fuzz run packet_decoding` from the root of the repository.
+#![no_main]
+
+use korangar::network::PacketVersion;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = korangar::network::decode_map_server_data(data, PacketVersion::default());
+});
@@ -3,13 +3,30 @@ use proc_macro2::Span;
 use quote::quote;
 use syn::{Attribute, DataEnum, DataStruct, Generics, Ident};
 
-use super::helper::byte_convertable_helper;
+use super::helper::{byte_convertable_helper, round_trip_test, ByteConvertableImplementations};
 use crate::utils::*;
 
 pub fn derive_byte_convertable_struct(data_struct: DataStruct, generics: Generics, name: Ident) -> InterfaceTokenStream {
-    let (from_bytes_implementations, implemented_fields, to_bytes_implementations, delimiter) = byte_convertable_helper(data_struct);
+    let ByteConvertableImplementations {
+        from_bytes: from_bytes_implementations,
+        implemented_fields,
+        to_bytes: to_bytes_implementations,
+        arbitrary: arbitrary_implementations,
+        version_boundaries,
+        delimiter,
+        ..
+    } = byte_convertable_helper(data_struct, &name);
     let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
 
+    let round_trip_test = round_trip_test(
+        &name,
+        &generics,
+        quote!(crate::loaders::check_round_trip),
+        &version_boundaries,
+        quote!(|value, version| crate::loaders::ByteConvertable::to_versioned_bytes(value, Some(version), None)),
+        quote!(|byte_stream| crate::loaders::ByteConvertable::from_bytes(byte_stream, None)),
+    );
+
     let instanciate = match delimiter {
         proc_macro2::Delimiter::Brace => quote!(Self { #(#implemented_fields),* }),
         proc_macro2::Delimiter::Parenthesis => quote!(Self ( #(#implemented_fields),* )),
@@ -19,20 +36,37 @@ pub fn derive_byte_convertable_struct(data_struct: DataStruct, generics: Generic
     quote! {
         impl #impl_generics crate::loaders::ByteConvertable for #name #type_generics #where_clause {
 
-            fn from_bytes(byte_stream: &mut crate::loaders::ByteStream, length_hint: Option<usize>) -> Self {
+            fn from_bytes(byte_stream: &mut crate::loaders::ByteStream, length_hint: Option<usize>) -> ::std::result::Result<Self, crate::loaders::ConversionError> {
                 assert!(length_hint.is_none(), "structs may not have a length hint");
                 let base_offset = byte_stream.get_offset();
                 #(#from_bytes_implementations)*
-                #instanciate
+                Ok(#instanciate)
             }
 
-            // Temporary until serialization is always possible
-            #[allow(unreachable_code)]
             fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+                self.to_versioned_bytes(None, length_hint)
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn to_versioned_bytes(&self, version: Option<crate::loaders::InternalVersion>, length_hint: Option<usize>) -> Vec<u8> {
+                assert!(length_hint.is_none(), "structs may not have a length hint");
+                let mut bytes = Vec::new();
+                #(#to_bytes_implementations)*
+                bytes
+            }
+        }
+
+        #[cfg(any(test, feature = "arbitrary"))]
+        impl #impl_generics crate::loaders::Arbitrary for #name #type_generics #where_clause {
+
+            fn arbitrary(generator: &mut crate::loaders::Generator, length_hint: Option<usize>) -> Self {
                 assert!(length_hint.is_none(), "structs may not have a length hint");
-                [#(#to_bytes_implementations),*].concat()
+                #(#arbitrary_implementations)*
+                #instanciate
             }
         }
+
+        #round_trip_test
     }
     .into()
 }
@@ -49,6 +83,16 @@ pub fn derive_byte_convertable_enum(
         .map(|attribute| attribute.parse_args().unwrap())
         .unwrap_or_else(|| Ident::new("u8", Span::call_site()));
 
+    let type_name = name.to_string();
+    let round_trip_test = round_trip_test(
+        &name,
+        &generics,
+        quote!(crate::loaders::check_round_trip),
+        &[],
+        quote!(|value, _version| crate::loaders::ByteConvertable::to_bytes(value, None)),
+        quote!(|byte_stream| crate::loaders::ByteConvertable::from_bytes(byte_stream, None)),
+    );
+
     let mut current_index = 0usize;
     let mut indices = Vec::new();
     let mut values = Vec::new();
@@ -70,16 +114,18 @@ pub fn derive_byte_convertable_enum(
     quote! {
         impl #impl_generics ByteConvertable for #name #type_generics #where_clause {
 
-            fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+            fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> ::std::result::Result<Self, crate::loaders::ConversionError> {
                 assert!(length_hint.is_none(), "length hint may not be given to enums");
-                match #numeric_type::from_bytes(byte_stream, None) as usize {
-                    #( #indices => Self::#values, )*
-                    invalid => panic!("invalid value {}", invalid),
+                let offset = byte_stream.get_offset();
+                match #numeric_type::from_bytes(byte_stream, None)? as usize {
+                    #( #indices => Ok(Self::#values), )*
+                    value => Err(crate::loaders::ConversionError::from_kind(
+                        offset,
+                        crate::loaders::ConversionErrorKind::InvalidEnumValue { type_name: #type_name, value },
+                    )),
                 }
             }
 
-            // Temporary until serialization is always possible
-            #[allow(unreachable_code)]
             fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
                 assert!(length_hint.is_none(), "length hint may not be given to enums");
                 match self {
@@ -87,6 +133,19 @@ pub fn derive_byte_convertable_enum(
                 }
             }
         }
+
+        #[cfg(any(test, feature = "arbitrary"))]
+        impl #impl_generics crate::loaders::Arbitrary for #name #type_generics #where_clause {
+
+            fn arbitrary(generator: &mut crate::loaders::Generator, length_hint: Option<usize>) -> Self {
+                assert!(length_hint.is_none(), "length hint may not be given to enums");
+                let values = [#( Self::#values ),*];
+                let index = generator.choose(values.len());
+                values.into_iter().nth(index).unwrap()
+            }
+        }
+
+        #round_trip_test
     }
     .into()
 }
@@ -1,20 +1,54 @@
 use proc_macro::TokenStream as InterfaceTokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::quote;
-use syn::{DataStruct, Generics, Ident};
+use syn::{Attribute, DataStruct, Generics, Ident};
+
+use crate::utils::*;
 
 pub fn derive_fixed_byte_size_struct(data_struct: DataStruct, generics: Generics, name: Ident) -> InterfaceTokenStream {
     let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
-    let types = data_struct.fields.iter().map(|field| field.ty.clone());
+    // fields with a length hint, like strings, take up exactly that many bytes
+    let sizes = data_struct.fields.into_iter().map(|mut field| {
+        let field_type = field.ty;
+
+        match get_unique_attribute(&mut field.attrs, "length_hint") {
+            Some(attribute) => {
+                let length_hint: TokenStream = attribute.parse_args().expect("failed to parse length hint");
+                quote!(((#length_hint) as usize))
+            }
+            None => quote!(<#field_type as crate::loaders::FixedByteSize>::size_in_bytes()),
+        }
+    });
 
     quote! {
         impl #impl_generics const crate::loaders::FixedByteSize for #name #type_generics #where_clause {
 
             fn size_in_bytes() -> usize {
                 let mut total = 0;
-                #(total += <#types as crate::loaders::FixedByteSize>::size_in_bytes();)*
+                #(total += #sizes;)*
                 total
             }
         }
     }
     .into()
 }
+
+pub fn derive_fixed_byte_size_enum(generics: Generics, mut attributes: Vec<Attribute>, name: Ident) -> InterfaceTokenStream {
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    // enums are stored as their numeric value, see the enum implementation of
+    // ByteConvertable
+    let numeric_type = get_unique_attribute(&mut attributes, "numeric_type")
+        .map(|attribute| attribute.parse_args().unwrap())
+        .unwrap_or_else(|| Ident::new("u8", Span::call_site()));
+
+    quote! {
+        impl #impl_generics const crate::loaders::FixedByteSize for #name #type_generics #where_clause {
+
+            fn size_in_bytes() -> usize {
+                <#numeric_type as crate::loaders::FixedByteSize>::size_in_bytes()
+            }
+        }
+    }
+    .into()
+}
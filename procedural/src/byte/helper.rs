@@ -1,6 +1,8 @@
+use std::collections::HashSet;
+
 use proc_macro2::{Delimiter, Group, TokenStream, TokenTree};
 use quote::{format_ident, quote};
-use syn::{DataStruct, Field};
+use syn::{Attribute, DataStruct, Field, Generics, Ident, LitInt, LitStr};
 
 use crate::utils::*;
 
@@ -31,10 +33,68 @@ fn remove_self_from_stream(token_stream: TokenStream) -> TokenStream {
     new_stream
 }
 
-pub fn byte_convertable_helper(data_struct: DataStruct) -> (Vec<TokenStream>, Vec<TokenStream>, Vec<TokenStream>, Delimiter) {
+/// Collects the names of all fields that are accessed through `self`.
+fn collect_self_fields(token_stream: TokenStream, field_names: &mut HashSet<String>) {
+    let mut iterator = token_stream.into_iter();
+
+    while let Some(token) = iterator.next() {
+        match token {
+            TokenTree::Group(group) => collect_self_fields(group.stream(), field_names),
+            TokenTree::Ident(ident) if ident == "self" => {
+                // skip the '.' after self
+                iterator.next();
+
+                if let Some(field) = iterator.next() {
+                    field_names.insert(field.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fields that are used to calculate the length or repeat count of other
+/// fields.
+fn get_length_fields(fields: &[Field]) -> HashSet<String> {
+    let mut field_names = HashSet::new();
+
+    fields
+        .iter()
+        .flat_map(|field| field.attrs.iter())
+        .filter(|attribute| attribute.path().is_ident("length_hint") || attribute.path().is_ident("repeating"))
+        .filter_map(|attribute: &Attribute| match &attribute.meta {
+            syn::Meta::List(list) => Some(list.tokens.clone()),
+            syn::Meta::Path(_) | syn::Meta::NameValue(_) => None,
+        })
+        .for_each(|token_stream| collect_self_fields(token_stream, &mut field_names));
+
+    field_names
+}
+
+pub struct ByteConvertableImplementations {
+    pub from_bytes: Vec<TokenStream>,
+    pub implemented_fields: Vec<TokenStream>,
+    pub to_bytes: Vec<TokenStream>,
+    pub arbitrary: Vec<TokenStream>,
+    /// Versions at which the layout of the type changes.
+    pub version_boundaries: Vec<TokenStream>,
+    /// Packet versions at which the layout of the packet changes.
+    pub packet_version_boundaries: Vec<u32>,
+    /// The size of every field in bytes, or `None` if the size of any field
+    /// depends on the data.
+    pub fixed_sizes: Option<Vec<TokenStream>>,
+    pub packet_length: Option<TokenStream>,
+    pub delimiter: Delimiter,
+}
+
+pub fn byte_convertable_helper(data_struct: DataStruct, name: &Ident) -> ByteConvertableImplementations {
     let mut from_bytes_implementations = vec![];
     let mut implemented_fields = vec![];
     let mut to_bytes_implementations = vec![];
+    let mut arbitrary_implementations = vec![];
+    let mut version_boundaries = vec![];
+    let mut packet_version_boundaries = vec![];
+    let mut fixed_sizes = Some(vec![]);
     let mut packet_length = None;
 
     let (fields, delimiter): (Vec<Field>, _) = match data_struct.fields {
@@ -43,51 +103,123 @@ pub fn byte_convertable_helper(data_struct: DataStruct) -> (Vec<TokenStream>, Ve
         syn::Fields::Unit => panic!("unit types are not supported"),
     };
 
+    let length_fields = get_length_fields(&fields);
+
     for (counter, mut field) in fields.into_iter().enumerate() {
         let counter_ident = format_ident!("_{}", counter);
         let counter_index = syn::Index::from(counter);
         let field_variable = field.ident.as_ref().map(|ident| quote!(#ident)).unwrap_or(quote!(#counter_ident));
         let field_identifier = field.ident.as_ref().map(|ident| quote!(#ident)).unwrap_or(quote!(#counter_index));
         let field_type = field.ty;
+        let field_name = format!("{name}::{field_identifier}");
+        let context = LitStr::new(&field_name, name.span());
 
         let is_version = get_unique_attribute(&mut field.attrs, "version").is_some();
 
         let is_packet_length = get_unique_attribute(&mut field.attrs, "packet_length").is_some();
 
-        let length_hint = get_unique_attribute(&mut field.attrs, "length_hint")
-            .map(|attribute| match attribute.meta {
-                syn::Meta::List(list) => list.tokens,
-                syn::Meta::Path(_) | syn::Meta::NameValue(_) => panic!("expected token stream in attribute"),
-            })
+        let raw_length_hint = get_unique_attribute(&mut field.attrs, "length_hint").map(|attribute| match attribute.meta {
+            syn::Meta::List(list) => list.tokens,
+            syn::Meta::Path(_) | syn::Meta::NameValue(_) => panic!("expected token stream in attribute"),
+        });
+
+        let length_hint = raw_length_hint
+            .clone()
             .map(|length_hint| quote!(((#length_hint) as usize).into()))
             .unwrap_or(quote!(None));
 
         let from_length_hint = remove_self_from_stream(length_hint.clone());
 
-        let repeating: Option<TokenStream> = get_unique_attribute(&mut field.attrs, "repeating").map(|attribute| match attribute.meta {
-            syn::Meta::List(list) => remove_self_from_stream(list.tokens),
+        let to_repeating: Option<TokenStream> = get_unique_attribute(&mut field.attrs, "repeating").map(|attribute| match attribute.meta {
+            syn::Meta::List(list) => list.tokens,
             syn::Meta::Path(_) | syn::Meta::NameValue(_) => panic!("expected token stream in attribute"),
         });
+        let repeating = to_repeating.clone().map(remove_self_from_stream);
 
         let repeating_remaining = get_unique_attribute(&mut field.attrs, "repeating_remaining").is_some();
 
         let version_smaller = get_unique_attribute(&mut field.attrs, "version_smaller")
             .map(|attribute| attribute.parse_args().expect("failed to parse version"))
             .map(|version: Version| (version.major, version.minor))
-            .map(|(major, minor)| quote!(smaller(#major, #minor)));
+            .map(|(major, minor)| {
+                version_boundaries.push(quote!((#major, #minor)));
+                quote!(smaller(#major, #minor))
+            });
 
         let version_equals_or_above = get_unique_attribute(&mut field.attrs, "version_equals_or_above")
             .map(|attribute| attribute.parse_args().expect("failed to parse version"))
             .map(|version: Version| (version.major, version.minor))
-            .map(|(major, minor)| quote!(equals_or_above(#major, #minor)));
+            .map(|(major, minor)| {
+                version_boundaries.push(quote!((#major, #minor)));
+                quote!(equals_or_above(#major, #minor))
+            });
 
         assert!(
             version_smaller.is_none() || version_equals_or_above.is_none(),
             "version restriction may only be specified once"
         );
         let version_function = version_smaller.or(version_equals_or_above);
-        let version_restricted = version_function.is_some();
-        let is_repeating = repeating.is_some();
+
+        // packet versions are dates, so a field may be restricted to a range of them
+        let packet_version_functions: Vec<TokenStream> = [
+            ("packet_version_smaller", quote!(smaller)),
+            ("packet_version_equals_or_above", quote!(equals_or_above)),
+        ]
+        .into_iter()
+        .filter_map(|(attribute_name, function)| {
+            get_unique_attribute(&mut field.attrs, attribute_name).map(|attribute| {
+                let packet_version = attribute
+                    .parse_args::<LitInt>()
+                    .and_then(|date| date.base10_parse::<u32>())
+                    .expect("failed to parse packet version");
+                packet_version_boundaries.push(packet_version);
+                quote!(#function(#packet_version))
+            })
+        })
+        .collect();
+
+        assert!(
+            version_function.is_none() || packet_version_functions.is_empty(),
+            "a field may not depend on both the version and the packet version"
+        );
+
+        let packet_version_condition = |packet_version: TokenStream| -> Option<TokenStream> {
+            if packet_version_functions.is_empty() {
+                return None;
+            }
+
+            let conditions = packet_version_functions.iter().map(|function| quote!(#packet_version.#function));
+            Some(quote!(#(#conditions)&&*))
+        };
+
+        // the size of the field is only known without the data if it doesn't depend on
+        // other fields or the version
+        let mut self_fields = HashSet::new();
+        if let Some(length_hint) = &raw_length_hint {
+            collect_self_fields(length_hint.clone(), &mut self_fields);
+        }
+
+        let fixed_size = match (&raw_length_hint, &to_repeating, &version_function) {
+            _ if repeating_remaining || !self_fields.is_empty() => None,
+            (_, Some(_), _) | (_, _, Some(_)) => None,
+            (Some(length_hint), None, None) => Some(quote!(((#length_hint) as usize))),
+            (None, None, None) if !packet_version_functions.is_empty() => {
+                Some(quote!(<#field_type as crate::loaders::FixedByteSizeWrapper>::size_in_bytes()))
+            }
+            (None, None, None) => Some(quote!(<#field_type as crate::loaders::FixedByteSize>::size_in_bytes())),
+        };
+
+        // fields that depend on the packet version still have a fixed size for every
+        // packet version
+        let fixed_size = match packet_version_condition(quote!(packet_version)) {
+            Some(condition) => fixed_size.map(|size| quote!((if #condition { #size } else { 0 }))),
+            None => fixed_size,
+        };
+
+        fixed_sizes = fixed_sizes.zip(fixed_size).map(|(mut sizes, size)| {
+            sizes.push(size);
+            sizes
+        });
 
         if is_packet_length {
             assert!(counter == 0, "packet_length must always be the first field");
@@ -98,20 +230,36 @@ pub fn byte_convertable_helper(data_struct: DataStruct) -> (Vec<TokenStream>, Ve
         let from_implementation = quote!(crate::loaders::ByteConvertable::from_bytes(byte_stream, #from_length_hint));
 
         // wrap base implementation in a loop if the element can appear multiple times
-        let from_implementation = match repeating {
-            Some(repeat_count) => quote!((0..(#repeat_count)).map(|_| #from_implementation).collect()),
+        let from_implementation = match &repeating {
+            Some(repeat_count) => {
+                quote!((0..(#repeat_count)).map(|_| #from_implementation).collect::<::std::result::Result<_, crate::loaders::ConversionError>>())
+            }
             None if repeating_remaining => {
                 let packet_length = packet_length
                     .as_ref()
                     .expect("repeating_remaining is used but no packet_length attribute is set");
-                quote!((0..((#packet_length - ((byte_stream.get_offset() - base_offset) as u16) - 2) / (<#field_type as crate::loaders::FixedByteSizeWrapper>::size_in_bytes() as u16))).map(|_| #from_implementation).collect())
+                quote!({
+                    let read_length = byte_stream.get_offset() - base_offset + 2;
+                    let element_size = <#field_type as crate::loaders::FixedByteSizeWrapper>::size_in_bytes();
+
+                    match (#packet_length as usize).checked_sub(read_length) {
+                        Some(remaining) => (0..remaining / element_size).map(|_| #from_implementation).collect::<::std::result::Result<_, crate::loaders::ConversionError>>(),
+                        None => Err(crate::loaders::ConversionError::invalid_data(
+                            byte_stream.get_offset(),
+                            format!("packet length {} is smaller than the {} bytes read", #packet_length, read_length),
+                        )),
+                    }
+                })
             }
             None => from_implementation,
         };
 
+        // add the name of the field to the error
+        let from_implementation = quote!(#from_implementation.map_err(|error| error.add_context(#context))?);
+
         // wrap the potentially looped implementation in an option if it has a version
         // restriction
-        let from_implementation = match version_function {
+        let from_implementation = match &version_function {
             Some(function) => {
                 quote! {
                     let #field_variable = match byte_stream.get_version().#function {
@@ -120,31 +268,172 @@ pub fn byte_convertable_helper(data_struct: DataStruct) -> (Vec<TokenStream>, Ve
                     };
                 }
             }
-            None => quote!(let #field_variable = #from_implementation;),
+            None => match packet_version_condition(quote!(byte_stream.get_packet_version())) {
+                Some(condition) => quote! {
+                    let #field_variable = match #condition {
+                        true => Some(#from_implementation),
+                        false => None,
+                    };
+                },
+                None => quote!(let #field_variable = #from_implementation;),
+            },
+        };
+
+        // base arbitrary implementation. values that other fields depend on are kept
+        // small
+        let arbitrary_implementation = quote!(crate::loaders::Arbitrary::arbitrary(generator, #from_length_hint));
+        let arbitrary_implementation = match length_fields.contains(&field_identifier.to_string()) {
+            true => quote!(generator.length(|generator| #arbitrary_implementation)),
+            false => arbitrary_implementation,
+        };
+
+        // wrap base implementation in a loop if the element can appear multiple times
+        let arbitrary_implementation = match &repeating {
+            Some(repeat_count) => quote!((0..(#repeat_count)).map(|_| #arbitrary_implementation).collect()),
+            None if repeating_remaining => quote!(crate::loaders::Arbitrary::arbitrary(generator, None)),
+            None => arbitrary_implementation,
+        };
+
+        // only generate the value if the version of the generator requires it
+        let arbitrary_implementation = match &version_function {
+            Some(function) => {
+                quote! {
+                    let #field_variable: #field_type = match generator.get_version().#function {
+                        true => Some(#arbitrary_implementation),
+                        false => None,
+                    };
+                }
+            }
+            None => match packet_version_condition(quote!(generator.get_packet_version())) {
+                Some(condition) => quote! {
+                    let #field_variable: #field_type = match #condition {
+                        true => Some(#arbitrary_implementation),
+                        false => None,
+                    };
+                },
+                None => quote!(let #field_variable: #field_type = #arbitrary_implementation;),
+            },
         };
 
-        // base to byte implementation
-        let to_implementation = match is_repeating || version_restricted {
-            true => quote!({
-                panic!("implement for to_bytes aswell");
-                [0u8].as_slice()
-            }),
-            false => quote!(crate::loaders::ByteConvertable::to_bytes(&self.#field_identifier, #length_hint).as_slice()),
+        // base to bytes implementation
+        let to_implementation = quote!(bytes.extend(crate::loaders::ByteConvertable::to_versioned_bytes(value, version, #length_hint)););
+
+        // wrap base implementation in a loop if the element can appear multiple times
+        let to_implementation = match to_repeating {
+            Some(repeat_count) => {
+                let message = LitStr::new(&format!("{field_name} does not match its repeat count"), name.span());
+                quote! {
+                    assert_eq!(value.len(), (0..(#repeat_count)).count(), #message);
+                    for value in value.iter() {
+                        #to_implementation
+                    }
+                }
+            }
+            None if repeating_remaining => quote! {
+                for value in value.iter() {
+                    #to_implementation
+                }
+            },
+            None => to_implementation,
+        };
+
+        // only write the field if the version requires it. a missing value is a bug in
+        // the code that created the data, so it's not recoverable
+        let to_implementation = match version_function {
+            Some(function) => {
+                let missing_version = LitStr::new(&format!("{field_name} can only be serialized with a version"), name.span());
+                let missing_value = LitStr::new(&format!("{field_name} is required by the version"), name.span());
+                quote! {
+                    if version.expect(#missing_version).#function {
+                        let value = self.#field_identifier.as_ref().expect(#missing_value);
+                        #to_implementation
+                    }
+                }
+            }
+            None => match packet_version_condition(quote!(packet_version)) {
+                Some(condition) => {
+                    let missing_value = LitStr::new(&format!("{field_name} is required by the packet version"), name.span());
+                    quote! {
+                        if #condition {
+                            let value = self.#field_identifier.as_ref().expect(#missing_value);
+                            #to_implementation
+                        }
+                    }
+                }
+                None => quote! {{
+                    let value = &self.#field_identifier;
+                    #to_implementation
+                }},
+            },
         };
 
         implemented_fields.push(quote!(#field_variable));
         from_bytes_implementations.push(from_implementation);
         to_bytes_implementations.push(to_implementation);
+        arbitrary_implementations.push(arbitrary_implementation);
 
         if is_version {
             from_bytes_implementations.push(quote!(byte_stream.set_version(#field_variable);));
+            to_bytes_implementations.push(quote!(let version = Some(crate::loaders::InternalVersion::from(self.#field_identifier));));
         }
     }
 
-    (
-        from_bytes_implementations,
+    ByteConvertableImplementations {
+        from_bytes: from_bytes_implementations,
         implemented_fields,
-        to_bytes_implementations,
+        to_bytes: to_bytes_implementations,
+        arbitrary: arbitrary_implementations,
+        version_boundaries,
+        packet_version_boundaries,
+        fixed_sizes,
+        packet_length,
         delimiter,
-    )
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let characters: Vec<char> = name.chars().collect();
+    let mut snake_case = String::new();
+
+    for (index, character) in characters.iter().enumerate() {
+        // Acronyms like `BGRA` stay in one word.
+        let previous_lowercase = index > 0 && characters[index - 1].is_lowercase();
+        let next_lowercase = characters.get(index + 1).is_some_and(|next| next.is_lowercase());
+
+        if character.is_uppercase() && index > 0 && (previous_lowercase || next_lowercase) {
+            snake_case.push('_');
+        }
+
+        snake_case.push(character.to_ascii_lowercase());
+    }
+
+    snake_case
+}
+
+/// Generates a test that serializes and deserializes random values of the type.
+/// Generic types are not tested, since the test needs a concrete type.
+/// `check` is the function that checks the values for every version in
+/// `version_boundaries`.
+pub fn round_trip_test(
+    name: &Ident,
+    generics: &Generics,
+    check: TokenStream,
+    version_boundaries: &[TokenStream],
+    to_bytes: TokenStream,
+    from_bytes: TokenStream,
+) -> TokenStream {
+    if !generics.params.is_empty() {
+        return TokenStream::new();
+    }
+
+    let test_name = format_ident!("{}_round_trip", to_snake_case(&name.to_string()));
+    let type_name = name.to_string();
+
+    quote! {
+        #[cfg(test)]
+        #[test]
+        fn #test_name() {
+            #check::<#name>(#type_name, &[#(#version_boundaries),*], #to_bytes, #from_bytes);
+        }
+    }
 }
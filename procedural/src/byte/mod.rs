@@ -4,5 +4,5 @@ mod helper;
 mod packet;
 
 pub use self::convertable::{derive_byte_convertable_enum, derive_byte_convertable_struct};
-pub use self::fixed_size::derive_fixed_byte_size_struct;
+pub use self::fixed_size::{derive_fixed_byte_size_enum, derive_fixed_byte_size_struct};
 pub use self::packet::derive_packet_struct;
@@ -1,8 +1,9 @@
 use proc_macro::TokenStream as InterfaceTokenStream;
+use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Attribute, DataStruct, Generics, Ident};
 
-use super::helper::byte_convertable_helper;
+use super::helper::{byte_convertable_helper, round_trip_test, ByteConvertableImplementations};
 use crate::utils::*;
 
 pub fn derive_packet_struct(
@@ -20,15 +21,69 @@ pub fn derive_packet_struct(
         .expect("failed to parse packet header");
     let is_ping = get_unique_attribute(&mut attributes, "ping").is_some();
 
+    // headers of older packet versions, checked from the oldest to the newest
+    let mut older_signatures: Vec<PacketSignatureSmaller> = get_attributes(&mut attributes, "header_smaller")
+        .into_iter()
+        .map(|attribute| attribute.parse_args().expect("failed to parse packet header"))
+        .collect();
+    older_signatures.sort_by_key(|older_signature| older_signature.packet_version);
+
     let signature = packet_signature.signature;
-    let (from_bytes_implementations, implemented_fields, to_bytes_implementations, delimiter) = byte_convertable_helper(data_struct);
+    let older_packet_versions: Vec<u32> = older_signatures
+        .iter()
+        .map(|older_signature| older_signature.packet_version)
+        .collect();
+    let older_signatures: Vec<u16> = older_signatures.iter().map(|older_signature| older_signature.signature).collect();
+    let ByteConvertableImplementations {
+        from_bytes: from_bytes_implementations,
+        implemented_fields,
+        to_bytes: to_bytes_implementations,
+        arbitrary: arbitrary_implementations,
+        mut packet_version_boundaries,
+        fixed_sizes,
+        packet_length,
+        delimiter,
+        ..
+    } = byte_convertable_helper(data_struct, &name);
+
+    let length = match (&packet_length, fixed_sizes) {
+        (Some(_), _) => quote!(crate::network::PacketLength::Variable),
+        (None, Some(fixed_sizes)) => quote!(crate::network::PacketLength::Fixed(2 #(+ #fixed_sizes)*)),
+        (None, None) => panic!("{packet_name} needs a packet_length field, since its size is not fixed"),
+    };
+
+    // the packet length can only be known once all other fields are generated. if
+    // the length hints of the fields don't match the packet length, the size of
+    // the packet changes after updating it
+    let length_mismatch = format!("length hints of {packet_name} don't match the packet length");
+    let arbitrary_packet_length = packet_length.map(|packet_length| {
+        quote! {
+            packet.#packet_length = crate::network::Packet::to_bytes(&packet, generator.get_packet_version()).len() as _;
+            assert_eq!(crate::network::Packet::to_bytes(&packet, generator.get_packet_version()).len(), packet.#packet_length as usize, #length_mismatch);
+        }
+    });
+
+    let fixed_length_mismatch = format!("size of {packet_name} doesn't match its fixed length");
+    packet_version_boundaries.extend(&older_packet_versions);
+    let packet_version_boundaries: Vec<TokenStream> = packet_version_boundaries
+        .into_iter()
+        .map(|packet_version| quote!(#packet_version))
+        .collect();
+
+    let round_trip_test = round_trip_test(
+        &name,
+        &generics,
+        quote!(crate::loaders::check_packet_round_trip),
+        &packet_version_boundaries,
+        quote!(|packet, packet_version| crate::network::Packet::to_bytes(packet, packet_version)),
+        quote!(<#name as crate::network::Packet>::try_from_bytes),
+    );
 
     let instanciate = match delimiter {
         proc_macro2::Delimiter::Brace => quote!(Self { #(#implemented_fields),* }),
         proc_macro2::Delimiter::Parenthesis => quote!(Self ( #(#implemented_fields),* )),
         _ => panic!(),
     };
-    let to_bytes = quote!([&#signature.to_le_bytes()[..], #(#to_bytes_implementations),*].concat());
 
     quote! {
 
@@ -37,38 +92,76 @@ pub fn derive_packet_struct(
             const PACKET_NAME: &'static str = #packet_name;
             const IS_PING: bool = #is_ping;
 
-            fn header() -> u16 {
+            fn header(packet_version: crate::network::PacketVersion) -> u16 {
+                #(if packet_version.smaller(#older_packet_versions) {
+                    return #older_signatures;
+                })*
+
                 #signature
             }
 
-            // Temporary until serialization is always possible
-            #[allow(unreachable_code)]
-            fn to_bytes(&self) -> Vec<u8> {
-                #to_bytes
+            #[allow(unused_variables)]
+            fn length(packet_version: crate::network::PacketVersion) -> crate::network::PacketLength {
+                #length
             }
-        }
 
-        impl #impl_generics #name #type_generics #where_clause {
+            #[allow(clippy::redundant_closure_call)]
+            fn try_from_bytes(byte_stream: &mut crate::loaders::ByteStream) -> ::std::result::Result<Self, crate::loaders::ConversionError> {
+                let offset = byte_stream.get_offset();
+                let header = Self::header(byte_stream.get_packet_version());
 
-            fn try_from_bytes(byte_stream: &mut crate::loaders::ByteStream) -> Result<Self, String> {
+                if !byte_stream.match_signature(header) {
+                    let found = u16::from_le_bytes([byte_stream.peek(0)?, byte_stream.peek(1)?]);
+                    let kind = crate::loaders::ConversionErrorKind::IncorrectSignature { expected: header, found };
+                    return Err(crate::loaders::ConversionError::from_kind(offset, kind));
+                }
 
-                let result = match byte_stream.match_signature(Self::header()) {
-                    true => {
-                        let base_offset = byte_stream.get_offset();
-                        #(#from_bytes_implementations)*
-                        Ok( #instanciate )
-                    },
-                    false => Err(format!("invalid signature 0x{:02x} 0x{:02x}", byte_stream.peek(0), byte_stream.peek(1))),
-                };
+                let result: ::std::result::Result<Self, crate::loaders::ConversionError> = (|| {
+                    let base_offset = byte_stream.get_offset();
+                    #(#from_bytes_implementations)*
+                    Ok(#instanciate)
+                })();
 
-                #[cfg(feature = "debug")]
-                if let Ok(packet) = &result {
-                    byte_stream.incoming_packet(packet);
+                match &result {
+                    #[cfg(feature = "debug")]
+                    Ok(packet) => byte_stream.incoming_packet(packet),
+                    #[cfg(not(feature = "debug"))]
+                    Ok(_) => {}
+                    Err(_) => byte_stream.set_offset(offset),
                 }
 
                 result
             }
+
+            #[allow(unused_variables, unused_mut)]
+            fn to_bytes(&self, packet_version: crate::network::PacketVersion) -> Vec<u8> {
+                let version: Option<crate::loaders::InternalVersion> = None;
+                let mut bytes = Self::header(packet_version).to_le_bytes().to_vec();
+                #(#to_bytes_implementations)*
+                bytes
+            }
+        }
+
+        #[cfg(any(test, feature = "arbitrary"))]
+        impl #impl_generics crate::loaders::Arbitrary for #name #type_generics #where_clause {
+
+            #[allow(unused_mut)]
+            fn arbitrary(generator: &mut crate::loaders::Generator, length_hint: Option<usize>) -> Self {
+                assert!(length_hint.is_none(), "packets may not have a length hint");
+                #(#arbitrary_implementations)*
+                let mut packet = #instanciate;
+                #arbitrary_packet_length
+
+                let packet_version = generator.get_packet_version();
+                if let crate::network::PacketLength::Fixed(length) = <Self as crate::network::Packet>::length(packet_version) {
+                    assert_eq!(crate::network::Packet::to_bytes(&packet, packet_version).len(), length, #fixed_length_mismatch);
+                }
+
+                packet
+            }
         }
+
+        #round_trip_test
     }
     .into()
 }
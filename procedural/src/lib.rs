@@ -77,13 +77,19 @@ pub fn derive_toggle(token_stream: InterfaceTokenStream) -> InterfaceTokenStream
     }
 }
 
-#[proc_macro_derive(FixedByteSize)]
+#[proc_macro_derive(FixedByteSize, attributes(numeric_type, length_hint))]
 pub fn derive_fixed_byte_size(token_stream: InterfaceTokenStream) -> InterfaceTokenStream {
-    let DeriveInput { ident, generics, data, .. } = parse(token_stream).expect("failed to parse token stream");
+    let DeriveInput {
+        ident,
+        attrs,
+        generics,
+        data,
+        ..
+    } = parse(token_stream).expect("failed to parse token stream");
 
     match data {
         Data::Struct(data_struct) => derive_fixed_byte_size_struct(data_struct, generics, ident),
-        Data::Enum(..) => panic!("enum types may not be derived"),
+        Data::Enum(..) => derive_fixed_byte_size_enum(generics, attrs, ident),
         Data::Union(..) => panic!("union types may not be derived"),
     }
 }
@@ -119,8 +125,26 @@ pub fn derive_byte_convertable(token_stream: InterfaceTokenStream) -> InterfaceT
 }
 
 /// Derive the Packet trait. A packet header must be specified and all fields
-/// must implement ByteConvertable.
-#[proc_macro_derive(Packet, attributes(packet_length, header, ping, length_hint, repeating, repeating_remaining))]
+/// must implement ByteConvertable. Servers built for older packet versions may
+/// use a different header, which is specified with
+/// `#[header_smaller(packet_version, header)]`. Fields that only exist in some
+/// packet versions are wrapped in an `Option` and restricted with
+/// `#[packet_version_smaller(packet_version)]` and
+/// `#[packet_version_equals_or_above(packet_version)]`.
+#[proc_macro_derive(
+    Packet,
+    attributes(
+        packet_length,
+        header,
+        header_smaller,
+        ping,
+        length_hint,
+        repeating,
+        repeating_remaining,
+        packet_version_smaller,
+        packet_version_equals_or_above
+    )
+)]
 pub fn derive_packet(token_stream: InterfaceTokenStream) -> InterfaceTokenStream {
     let DeriveInput {
         ident,
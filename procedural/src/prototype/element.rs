@@ -10,7 +10,8 @@ pub fn derive_prototype_element_struct(
     attributes: Vec<Attribute>,
     name: Ident,
 ) -> InterfaceTokenStream {
-    let (initializers, is_unnamed, _window_title, _window_class) = prototype_element_helper(data_struct, attributes, name.to_string());
+    let (initializers, text_writers, is_unnamed, _window_title, _window_class) =
+        prototype_element_helper(data_struct, attributes, name.to_string());
     let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
 
     if initializers.len() == 1 && is_unnamed {
@@ -19,6 +20,10 @@ pub fn derive_prototype_element_struct(
                 fn to_element(&self, display: String) -> crate::interface::ElementCell {
                     crate::interface::PrototypeElement::to_element(&self.0, display)
                 }
+
+                fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+                    crate::interface::PrototypeElement::write_text(&self.0, display, indentation, text)
+                }
             }
         }
         .into();
@@ -30,6 +35,11 @@ pub fn derive_prototype_element_struct(
                 let elements: Vec<crate::interface::ElementCell> = vec![#(#initializers),*];
                 std::rc::Rc::new(std::cell::RefCell::new(crate::interface::Expandable::new(display, elements, false)))
             }
+
+            fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+                crate::interface::write_text_line(text, indentation, display);
+                #(#text_writers;)*
+            }
         }
     }
     .into()
@@ -53,6 +63,12 @@ pub fn derive_prototype_element_enum(data_enum: DataEnum, generics: Generics, na
                     #( Self::#variants => crate::interface::PrototypeElement::to_element(&#variant_strings, display), )*
                 }
             }
+
+            fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+                match self {
+                    #( Self::#variants => crate::interface::PrototypeElement::write_text(&#variant_strings, display, indentation, text), )*
+                }
+            }
         }
     }
     .into()
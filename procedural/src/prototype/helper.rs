@@ -8,7 +8,7 @@ pub fn prototype_element_helper(
     data_struct: DataStruct,
     mut attributes: Vec<Attribute>,
     name: String,
-) -> (Vec<TokenStream>, bool, TokenStream, Option<TokenStream>) {
+) -> (Vec<TokenStream>, Vec<TokenStream>, bool, TokenStream, Option<TokenStream>) {
     let (fields, is_unnamed): (Vec<Field>, bool) = match data_struct.fields {
         syn::Fields::Named(named_fields) => (named_fields.named.into_iter().collect(), false),
         syn::Fields::Unnamed(unnamed_fields) => (unnamed_fields.unnamed.into_iter().collect(), true),
@@ -25,6 +25,7 @@ pub fn prototype_element_helper(
         .map(|window_class: LitStr| quote!(#window_class));
 
     let mut initializers = vec![];
+    let mut text_writers = vec![];
 
     let mut counter: usize = 0;
     for mut field in fields {
@@ -44,7 +45,9 @@ pub fn prototype_element_helper(
             .unwrap_or_else(|| str::replace(&field_variable.to_string(), "_", " "));
 
         initializers.push(quote!(crate::interface::PrototypeElement::to_element(&self.#field_identifier, #display_name.to_string())));
+        text_writers
+            .push(quote!(crate::interface::PrototypeElement::write_text(&self.#field_identifier, #display_name, indentation + 1, text)));
     }
 
-    (initializers, is_unnamed, window_title, window_class)
+    (initializers, text_writers, is_unnamed, window_title, window_class)
 }
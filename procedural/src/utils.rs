@@ -16,6 +16,28 @@ impl Parse for PacketSignature {
     }
 }
 
+/// Header that a packet uses if the packet version is smaller than
+/// `packet_version`.
+#[derive(Clone)]
+pub struct PacketSignatureSmaller {
+    pub packet_version: u32,
+    pub signature: u16,
+}
+
+impl Parse for PacketSignatureSmaller {
+    fn parse(input: ParseStream) -> Result<Self, Error> {
+        let packet_version: LitInt = input.parse().expect("packet version must be a date like 20211103");
+        input
+            .parse::<Punct>()
+            .expect("packet version and header must be seperated by commas");
+        let signature: LitInt = input.parse().expect("packet header must be u16");
+        Ok(PacketSignatureSmaller {
+            packet_version: packet_version.base10_parse::<u32>()?,
+            signature: signature.base10_parse::<u16>()?,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Version {
     pub major: LitInt,
@@ -41,3 +63,9 @@ pub fn get_unique_attribute(attributes: &mut Vec<Attribute>, name: &str) -> Opti
 
     return_attribute
 }
+
+pub fn get_attributes(attributes: &mut Vec<Attribute>, name: &str) -> Vec<Attribute> {
+    attributes
+        .extract_if(|attribute| attribute.path().segments[0].ident == name)
+        .collect()
+}
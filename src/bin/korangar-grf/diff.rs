@@ -0,0 +1,62 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use korangar::loaders::archive::Archive;
+use korangar::loaders::GameFileLoader;
+
+/// Maps the lowercase path of every file to the path used by the archive.
+fn collect_paths(archive: &dyn Archive) -> BTreeMap<String, String> {
+    let mut file_paths = Vec::new();
+    archive.get_file_paths(&mut file_paths);
+
+    file_paths
+        .into_iter()
+        .map(|file_path| (file_path.to_lowercase(), file_path))
+        .collect()
+}
+
+fn content_hash(archive: &dyn Archive, file_path: &str) -> Option<u64> {
+    archive.get_file_by_path(file_path).ok().map(|data| {
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+        hasher.finish()
+    })
+}
+
+pub fn diff(first_path: &str, second_path: &str) -> Result<(), String> {
+    let first = GameFileLoader::load_archive_from_path(first_path).map_err(|error| error.to_string())?;
+    let second = GameFileLoader::load_archive_from_path(second_path).map_err(|error| error.to_string())?;
+
+    let first_paths = collect_paths(first.as_ref());
+    let second_paths = collect_paths(second.as_ref());
+
+    let mut removed = 0;
+    let mut added = 0;
+    let mut changed = 0;
+
+    for (normalized_path, first_file_path) in &first_paths {
+        let Some(second_file_path) = second_paths.get(normalized_path) else {
+            println!("- {normalized_path}");
+            removed += 1;
+            continue;
+        };
+
+        let first_hash = content_hash(first.as_ref(), first_file_path);
+        let second_hash = content_hash(second.as_ref(), second_file_path);
+
+        if first_hash != second_hash {
+            println!("~ {normalized_path}");
+            changed += 1;
+        }
+    }
+
+    for normalized_path in second_paths.keys().filter(|path| !first_paths.contains_key(*path)) {
+        println!("+ {normalized_path}");
+        added += 1;
+    }
+
+    println!("{removed} removed, {added} added, {changed} changed");
+
+    Ok(())
+}
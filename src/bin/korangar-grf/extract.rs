@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use korangar::loaders::archive::folder::FolderArchive;
+use korangar::loaders::archive::{Archive, Writable};
+use korangar::loaders::GameFileLoader;
+
+use crate::pattern;
+
+pub fn extract(archive_path: &str, folder_path: &str, pattern: Option<&str>) -> Result<(), String> {
+    let archive = GameFileLoader::load_archive_from_path(archive_path).map_err(|error| error.to_string())?;
+    let mut folder = FolderArchive::from_path(Path::new(folder_path)).map_err(|error| error.to_string())?;
+    folder.create();
+
+    let mut file_paths = Vec::new();
+    archive.get_file_paths(&mut file_paths);
+    file_paths.retain(|file_path| pattern.map_or(true, |pattern| pattern::matches(pattern, file_path)));
+    file_paths.sort();
+
+    for file_path in &file_paths {
+        let data = archive
+            .get_file_by_path(file_path)
+            .map_err(|error| format!("failed to read from {archive_path}: {error}"))?;
+
+        folder.add_file(file_path, data);
+        println!("{file_path}");
+    }
+
+    println!("extracted {} files to {folder_path}", file_paths.len());
+
+    Ok(())
+}
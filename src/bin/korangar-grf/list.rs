@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use korangar::loaders::archive::native::NativeArchive;
+use korangar::loaders::archive::Archive;
+use korangar::loaders::GameFileLoader;
+
+use crate::pattern;
+
+fn is_grf(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("grf"))
+}
+
+pub fn list(archive_path: &str, pattern: Option<&str>) -> Result<(), String> {
+    match is_grf(Path::new(archive_path)) {
+        true => list_grf(archive_path, pattern),
+        false => list_archive(archive_path, pattern),
+    }
+}
+
+/// Lists the file table of a grf, including the flags and the compressed size
+/// of every entry.
+fn list_grf(archive_path: &str, pattern: Option<&str>) -> Result<(), String> {
+    let archive = NativeArchive::from_path(Path::new(archive_path)).map_err(|error| error.to_string())?;
+
+    let mut rows: Vec<_> = archive
+        .get_file_table_rows()
+        .filter(|row| pattern.map_or(true, |pattern| pattern::matches(pattern, &row.file_name)))
+        .collect();
+    rows.sort_by(|first, second| first.file_name.cmp(&second.file_name));
+
+    println!("{:>5} {:>12} {:>12}  path", "flags", "compressed", "size");
+
+    let mut total_compressed_size = 0;
+    let mut total_uncompressed_size = 0;
+
+    for row in &rows {
+        println!(
+            "{:>#5x} {:>12} {:>12}  {}",
+            row.flags, row.compressed_size, row.uncompressed_size, row.file_name
+        );
+
+        total_compressed_size += row.compressed_size as u64;
+        total_uncompressed_size += row.uncompressed_size as u64;
+    }
+
+    println!(
+        "{} entries, {} bytes compressed, {} bytes uncompressed",
+        rows.len(),
+        total_compressed_size,
+        total_uncompressed_size
+    );
+
+    Ok(())
+}
+
+/// Lists any other kind of archive. These don't expose a file table, so every
+/// file is read to get its size.
+fn list_archive(archive_path: &str, pattern: Option<&str>) -> Result<(), String> {
+    let archive = GameFileLoader::load_archive_from_path(archive_path).map_err(|error| error.to_string())?;
+
+    let mut file_paths = Vec::new();
+    archive.get_file_paths(&mut file_paths);
+    file_paths.retain(|file_path| pattern.map_or(true, |pattern| pattern::matches(pattern, file_path)));
+    file_paths.sort();
+
+    println!("{:>12}  path", "size");
+
+    let mut total_size = 0;
+
+    for file_path in &file_paths {
+        let data = archive
+            .get_file_by_path(file_path)
+            .map_err(|error| format!("failed to read from {archive_path}: {error}"))?;
+
+        println!("{:>12}  {}", data.len(), file_path);
+        total_size += data.len() as u64;
+    }
+
+    println!("{} entries, {} bytes uncompressed", file_paths.len(), total_size);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Command line tool to inspect and modify game archives, using the same
+//! archive code as the client.
+mod diff;
+mod extract;
+mod list;
+mod modify;
+mod pack;
+mod pattern;
+mod source;
+mod verify;
+
+use std::process::ExitCode;
+
+use korangar::loaders::archive::native::ArchiveVersion;
+
+const USAGE: &str = "usage: korangar-grf <command> [arguments]
+
+commands:
+    list <archive> [pattern]                    list all files with their sizes and flags
+    extract <archive> <folder> [pattern]        extract files from an archive into a folder
+    pack <folder> <archive> [--version <0x200|0x300>]
+                                                pack the contents of a folder into a grf, or a zip
+                                                if the archive has a .zip or .pk3 extension
+    diff <archive> <archive>                    compare two archives by path and content hash
+    add <archive> <folder>                      add or replace files in an existing grf
+    remove <archive> <pattern>                  remove files from an existing grf
+    repack <archive>                            rewrite a grf to reclaim space of removed files
+    verify <archive> [report]                   check all entries and parse all known file formats,
+                                                optionally writing a ron report
+    source <path>                               print which archive of the client configuration a file is
+                                                loaded from, after applying redirects
+
+archives can be grf files, folders, zip or pk3 files or thor and rgz patches, but add, remove and repack
+only work on grf files. list only shows flags and compressed sizes for grf files. patterns may use `*` and `?`";
+
+fn parse_version(version: &str) -> Result<ArchiveVersion, String> {
+    u32::from_str_radix(version.trim_start_matches("0x"), 16)
+        .ok()
+        .and_then(ArchiveVersion::from_raw)
+        .ok_or(format!("unsupported grf version {version}"))
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    let result = match arguments.as_slice() {
+        ["list", archive] => list::list(archive, None),
+        ["list", archive, pattern] => list::list(archive, Some(pattern)),
+        ["extract", archive, folder] => extract::extract(archive, folder, None),
+        ["extract", archive, folder, pattern] => extract::extract(archive, folder, Some(pattern)),
+        ["pack", folder, archive] => pack::pack(folder, archive, ArchiveVersion::default()),
+        ["pack", folder, archive, "--version", version] => parse_version(version).and_then(|version| pack::pack(folder, archive, version)),
+        ["diff", first, second] => diff::diff(first, second),
+        ["add", archive, folder] => modify::add(archive, folder),
+        ["remove", archive, pattern] => modify::remove(archive, pattern),
+        ["repack", archive] => modify::repack(archive),
+        ["verify", archive] => verify::verify(archive, None),
+        ["verify", archive, report] => verify::verify(archive, Some(report)),
+        ["source", path] => source::source(path),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
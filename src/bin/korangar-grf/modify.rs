@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use korangar::loaders::archive::folder::FolderArchive;
+use korangar::loaders::archive::native::NativeArchiveBuilder;
+use korangar::loaders::archive::{Archive, Writable};
+
+use crate::pattern;
+
+fn open(archive_path: &str) -> Result<NativeArchiveBuilder, String> {
+    let path = Path::new(archive_path);
+
+    match path.is_file() {
+        true => Ok(NativeArchiveBuilder::open(path)),
+        false => Err(format!("{archive_path} is not a grf file")),
+    }
+}
+
+pub fn add(archive_path: &str, folder_path: &str) -> Result<(), String> {
+    let mut builder = open(archive_path)?;
+    let folder = FolderArchive::from_path(Path::new(folder_path)).map_err(|error| error.to_string())?;
+
+    let mut file_paths = Vec::new();
+    folder.get_file_paths(&mut file_paths);
+    file_paths.sort();
+
+    for file_path in &file_paths {
+        let data = folder
+            .get_file_by_path(file_path)
+            .map_err(|error| format!("failed to read from {folder_path}: {error}"))?;
+
+        builder.add_file(file_path, data);
+        println!("{file_path}");
+    }
+
+    builder.save();
+    println!("added {} files to {archive_path}", file_paths.len());
+
+    Ok(())
+}
+
+pub fn remove(archive_path: &str, pattern: &str) -> Result<(), String> {
+    let mut builder = open(archive_path)?;
+    let mut removed = 0;
+
+    let mut file_paths = Vec::new();
+    builder.get_file_paths(&mut file_paths);
+
+    for file_path in file_paths.iter().filter(|file_path| pattern::matches(pattern, file_path)) {
+        builder.remove_file(file_path);
+        println!("{file_path}");
+        removed += 1;
+    }
+
+    builder.save();
+    println!("removed {removed} files from {archive_path}");
+
+    Ok(())
+}
+
+pub fn repack(archive_path: &str) -> Result<(), String> {
+    let builder = open(archive_path)?;
+    let dead_space = builder.get_dead_space();
+
+    builder.repack();
+    println!("reclaimed {dead_space} bytes in {archive_path}");
+
+    Ok(())
+}
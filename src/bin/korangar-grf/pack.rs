@@ -0,0 +1,50 @@
+use std::path::Path;
+
+use korangar::loaders::archive::folder::FolderArchive;
+use korangar::loaders::archive::native::{ArchiveVersion, NativeArchiveBuilder};
+use korangar::loaders::archive::zip::ZipArchiveBuilder;
+use korangar::loaders::archive::{Archive, Writable};
+
+fn is_zip_archive(archive_path: &str) -> bool {
+    let extension = Path::new(archive_path).extension().and_then(|extension| extension.to_str());
+    matches!(extension.map(str::to_lowercase).as_deref(), Some("zip" | "pk3"))
+}
+
+pub fn pack(folder_path: &str, archive_path: &str, version: ArchiveVersion) -> Result<(), String> {
+    let path = Path::new(folder_path);
+
+    if !path.is_dir() {
+        return Err(format!("{folder_path} is not a folder"));
+    }
+
+    let folder = FolderArchive::from_path(path).map_err(|error| error.to_string())?;
+    let mut builder: Box<dyn Writable> = match is_zip_archive(archive_path) {
+        true => Box::new(ZipArchiveBuilder::from_path(Path::new(archive_path))),
+        false => Box::new(NativeArchiveBuilder::from_path(Path::new(archive_path), version)),
+    };
+
+    let mut file_paths = Vec::new();
+    folder.get_file_paths(&mut file_paths);
+    file_paths.sort();
+
+    for file_path in &file_paths {
+        let data = folder
+            .get_file_by_path(file_path)
+            .map_err(|error| format!("failed to read from {folder_path}: {error}"))?;
+
+        builder.add_file(file_path, data);
+    }
+
+    builder.save();
+
+    match is_zip_archive(archive_path) {
+        true => println!("packed {} files into {archive_path}", file_paths.len()),
+        false => println!(
+            "packed {} files into {archive_path} (version {:#x})",
+            file_paths.len(),
+            version.raw()
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+/// Matches an archive path against a pattern where `*` matches any number of
+/// characters and `?` matches exactly one. Matching is case insensitive and
+/// treats forward and backward slashes the same, just like the client does.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let normalize = |text: &str| text.to_lowercase().replace('/', "\\").chars().collect::<Vec<char>>();
+    let pattern = normalize(pattern);
+    let path = normalize(path);
+
+    let mut pattern_index = 0;
+    let mut path_index = 0;
+    let mut backtrack = None;
+
+    while path_index < path.len() {
+        match pattern.get(pattern_index) {
+            Some('*') => {
+                backtrack = Some((pattern_index, path_index));
+                pattern_index += 1;
+            }
+            Some(&character) if character == '?' || character == path[path_index] => {
+                pattern_index += 1;
+                path_index += 1;
+            }
+            _ => match backtrack {
+                Some((star_index, star_path_index)) => {
+                    pattern_index = star_index + 1;
+                    path_index = star_path_index + 1;
+                    backtrack = Some((star_index, star_path_index + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pattern_index..].iter().all(|&character| character == '*')
+}
+
+#[cfg(test)]
+mod pattern {
+    use super::matches;
+
+    #[test]
+    fn literal() {
+        assert!(matches("data\\prontera.rsw", "data\\prontera.rsw"));
+        assert!(!matches("data\\prontera.rsw", "data\\prontera.gnd"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.rsw", "data\\prontera.rsw"));
+        assert!(matches("data\\*\\*.spr", "data\\sprite\\npc\\1_f_maria.spr"));
+        assert!(matches("data\\?rontera.*", "data\\prontera.gat"));
+        assert!(!matches("*.rsw", "data\\prontera.rsw.bak"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn separators_and_case() {
+        assert!(matches("DATA/Texture/*", "data\\texture\\backside.bmp"));
+    }
+}
@@ -0,0 +1,16 @@
+use korangar::loaders::GameFileLoader;
+
+/// Uses the archive configuration of the client, so this has to be run from
+/// the same directory as the client.
+pub fn source(path: &str) -> Result<(), String> {
+    let mut game_file_loader = GameFileLoader::default();
+    game_file_loader.load_archives_from_settings(&[]);
+    game_file_loader.load_patched_lua_files();
+
+    let archive = game_file_loader
+        .get_source_archive(path)
+        .ok_or(format!("{path} is not in any of the archives"))?;
+
+    println!("{archive}");
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use korangar::loaders::{audit_archive, GameFileLoader};
+use ron::ser::PrettyConfig;
+
+pub fn verify(archive_path: &str, report_path: Option<&str>) -> Result<(), String> {
+    let archive = GameFileLoader::load_archive_from_path(archive_path).map_err(|error| error.to_string())?;
+    let report = audit_archive(archive_path, archive.as_ref()).map_err(|error| format!("failed to audit {archive_path}: {error}"))?;
+
+    for issue in &report.issues {
+        println!("{issue}");
+    }
+
+    println!(
+        "{} files, {} parsed, {} issues",
+        report.file_count,
+        report.parsed_count,
+        report.issues.len()
+    );
+
+    if let Some(report_path) = report_path {
+        let data = ron::ser::to_string_pretty(&report, PrettyConfig::new()).map_err(|error| error.to_string())?;
+        std::fs::write(report_path, data).map_err(|error| format!("failed to write {report_path}: {error}"))?;
+    }
+
+    match report.issues.is_empty() {
+        true => Ok(()),
+        false => Err(format!("{archive_path} has {} issues", report.issues.len())),
+    }
+}
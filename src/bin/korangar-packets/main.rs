@@ -0,0 +1,150 @@
+//! Command line tool to decode raw packets, using the same packet definitions
+//! as the client.
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+use korangar::network::{dissect_capture, Direction, DissectedPacket, Dissector, PacketVersion, ServerKind};
+
+const USAGE: &str = "usage: korangar-packets <command> [arguments]
+
+commands:
+    hex <source> <file> [--packet-version <version>]
+                                                decode a hex dump, reading from stdin if the file is `-`
+    capture <file>                              decode all records of a capture made with `korangar --capture`
+
+sources are `login`, `character` or `map` for packets sent by a server and `client` for packets sent by the client,
+hex dumps may contain whitespace and offsets followed by a colon at the start of a line";
+
+fn parse_source(source: &str) -> Result<(ServerKind, Direction), String> {
+    match source {
+        "login" => Ok((ServerKind::Login, Direction::Incoming)),
+        "character" => Ok((ServerKind::Character, Direction::Incoming)),
+        "map" => Ok((ServerKind::Map, Direction::Incoming)),
+        // The client handler knows the packets for all servers.
+        "client" => Ok((ServerKind::Login, Direction::Outgoing)),
+        _ => Err(format!("unknown source {source}")),
+    }
+}
+
+fn parse_packet_version(packet_version: &str) -> Result<PacketVersion, String> {
+    packet_version
+        .parse()
+        .map(PacketVersion)
+        .map_err(|_| format!("invalid packet version {packet_version}"))
+}
+
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = text
+        .lines()
+        .map(|line| line.split_once(':').map_or(line, |(_offset, data)| data))
+        .flat_map(str::chars)
+        .filter(|character| !character.is_whitespace())
+        .collect();
+
+    if digits.len() % 2 != 0 {
+        return Err("hex dump has an odd number of digits".to_string());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            u8::from_str_radix(&pair, 16).map_err(|_| format!("invalid hex byte {pair}"))
+        })
+        .collect()
+}
+
+fn read_input(file: &str) -> Result<String, String> {
+    match file {
+        "-" => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|error| format!("failed to read stdin: {error}"))?;
+            Ok(text)
+        }
+        file => std::fs::read_to_string(file).map_err(|error| format!("failed to read {file}: {error}")),
+    }
+}
+
+fn print_packets(packets: &[DissectedPacket], indentation: usize) {
+    let prefix = " ".repeat(indentation);
+
+    for packet in packets {
+        match packet {
+            DissectedPacket::Decoded { offset, text } => {
+                print!("{prefix}{offset:#06x} ");
+                text.lines().enumerate().for_each(|(index, line)| match index {
+                    0 => println!("{line}"),
+                    _ => println!("{prefix}       {line}"),
+                });
+            }
+            DissectedPacket::Invalid { offset, error } => println!("{prefix}{offset:#06x} invalid packet: {error}"),
+            DissectedPacket::Unknown { offset, header, length } => {
+                println!("{prefix}{offset:#06x} unknown packet {header:#06x}, {length} bytes not decoded")
+            }
+        }
+    }
+}
+
+fn hex(source: &str, file: &str, packet_version: PacketVersion) -> Result<(), String> {
+    let (server, direction) = parse_source(source)?;
+    let data = parse_hex(&read_input(file)?)?;
+
+    let packets = Dissector::new(packet_version).dissect(server, direction, &data);
+    print_packets(&packets, 0);
+
+    Ok(())
+}
+
+fn capture(file: &str) -> Result<(), String> {
+    let (packet_version, records) = dissect_capture(Path::new(file)).map_err(|error| format!("failed to load {file}: {error}"))?;
+
+    println!("packet version {packet_version}, {} records", records.len());
+
+    let mut packet_version = packet_version;
+
+    for record in records {
+        if record.packet_version != packet_version {
+            packet_version = record.packet_version;
+            println!("\npacket version changed to {packet_version}");
+        }
+
+        let direction = match record.direction {
+            Direction::Incoming => "from",
+            Direction::Outgoing => "to",
+            Direction::Keepalive => "keepalive to",
+        };
+
+        println!("\n[{:.3}s] {direction} {}", record.timestamp.as_secs_f64(), record.server);
+        print_packets(&record.packets, 2);
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    let result = match arguments.as_slice() {
+        ["hex", source, file] => hex(source, file, PacketVersion::load()),
+        ["hex", source, file, "--packet-version", packet_version] => {
+            parse_packet_version(packet_version).and_then(|packet_version| hex(source, file, packet_version))
+        }
+        ["capture", file] => capture(file),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
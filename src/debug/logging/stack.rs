@@ -13,44 +13,28 @@ lazy_static! {
     static ref STACK: Mutex<Vec<StackItem>> = Mutex::new(Vec::new());
 }
 
+// Loaders may log from worker threads, so the stack is locked instead of
+// expecting exclusive access.
 pub fn stack_size() -> usize {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.len(),
-        Err(..) => panic!(),
-    }
+    STACK.lock().unwrap().len()
 }
 
 pub fn message_offset() -> usize {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.iter().map(|item| item.size).sum(),
-        Err(..) => panic!(),
-    }
+    STACK.lock().unwrap().iter().map(|item| item.size).sum()
 }
 
 pub fn increment_stack(size: usize) {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.push(StackItem::new(0, size)),
-        Err(..) => panic!(),
-    }
+    STACK.lock().unwrap().push(StackItem::new(0, size));
 }
 
 pub fn decrement_stack() {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.pop(),
-        Err(..) => panic!(),
-    };
+    STACK.lock().unwrap().pop();
 }
 
 pub fn increment_message_count() {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.last_mut().unwrap().message_count += 1,
-        Err(..) => panic!(),
-    }
+    STACK.lock().unwrap().last_mut().unwrap().message_count += 1;
 }
 
 pub fn get_message_count() -> usize {
-    match STACK.try_lock() {
-        Ok(ref mut mutex) => mutex.last_mut().unwrap().message_count,
-        Err(..) => panic!(),
-    }
+    STACK.lock().unwrap().last().unwrap().message_count
 }
@@ -193,9 +193,10 @@ pub fn start_measurement(name: &'static str) -> ActiveMeasurement {
     unsafe { PROFILER.assume_init_ref().lock().unwrap().start_measurement(name) }
 }
 
+#[macro_export]
 macro_rules! profile_block {
     ($name:expr) => {
         #[cfg(feature = "debug")]
-        let _measurement = crate::debug::start_measurement($name);
+        let _measurement = $crate::debug::start_measurement($name);
     };
 }
@@ -136,7 +136,7 @@ impl From<ColorRGB> for Color {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub struct ColorRGBA {
     pub red: u8,
     pub green: u8,
@@ -115,17 +115,12 @@ pub struct QuestIcon {
 }
 
 impl QuestIcon {
-    pub fn new(
-        game_file_loader: &mut GameFileLoader,
-        texture_loader: &mut TextureLoader,
-        map: &Map,
-        quest_effect: QuestEffectPacket,
-    ) -> Self {
+    pub fn new(game_file_loader: &GameFileLoader, texture_loader: &mut TextureLoader, map: &Map, quest_effect: QuestEffectPacket) -> Self {
         let position = map.get_world_position(quest_effect.position.map(usize::from)) + Vector3::new(0.0, 25.0, 0.0); // TODO: get height of the entity as offset
         let effect_id = quest_effect.effect as usize;
         let texture = texture_loader
             .get(
-                &format!("À¯ÀúÀÎÅÍÆäÀÌ½º\\minimap\\quest_{}_{}.bmp", effect_id, 1 /* 1 - 3 */),
+                &format!("유저인터페이스\\minimap\\quest_{}_{}.bmp", effect_id, 1 /* 1 - 3 */),
                 game_file_loader,
             )
             .unwrap();
@@ -180,7 +175,7 @@ impl ParticleHolder {
 
     pub fn add_quest_icon(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         map: &Map,
         quest_effect: QuestEffectPacket,
@@ -116,7 +116,7 @@ impl DeferredRenderer {
     pub fn new(
         memory_allocator: Arc<MemoryAllocator>,
         buffer_allocator: &mut BufferAllocator,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         queue: Arc<Queue>,
         swapchain_format: Format,
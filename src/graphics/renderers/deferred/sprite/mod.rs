@@ -47,7 +47,7 @@ impl SpriteRenderer {
         memory_allocator: Arc<MemoryAllocator>,
         subpass: Subpass,
         viewport: Viewport,
-        #[cfg(feature = "debug")] game_file_loader: &mut GameFileLoader,
+        #[cfg(feature = "debug")] game_file_loader: &GameFileLoader,
         #[cfg(feature = "debug")] texture_loader: &mut TextureLoader,
     ) -> Self {
         let device = memory_allocator.device().clone();
@@ -51,7 +51,7 @@ impl InterfaceRenderer {
 
     pub fn new(
         memory_allocator: Arc<MemoryAllocator>,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         font_loader: Rc<RefCell<FontLoader>>,
         queue: Arc<Queue>,
@@ -47,7 +47,7 @@ impl ShadowRenderer {
 
     pub fn new(
         memory_allocator: Arc<MemoryAllocator>,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         queue: Arc<Queue>,
     ) -> Self {
@@ -11,19 +11,6 @@ pub struct Transform {
     pub scale: Vector3<f32>,
 }
 
-impl crate::loaders::ByteConvertable for Transform {
-    fn from_bytes(byte_stream: &mut crate::loaders::ByteStream, _length_hint: Option<usize>) -> Self {
-        let mut position = <Vector3<f32>>::from_bytes(byte_stream, None);
-        let rotation = <Vector3<f32>>::from_bytes(byte_stream, None);
-        let scale = <Vector3<f32>>::from_bytes(byte_stream, None);
-
-        // TODO: make this nicer
-        position.y = -position.y;
-
-        Transform::from(position, rotation.map(Deg), scale)
-    }
-}
-
 impl Transform {
     pub fn from(position: Vector3<f32>, rotation: Vector3<Deg<f32>>, scale: Vector3<f32>) -> Self {
         let rotation = rotation.map(|degrees| degrees.into());
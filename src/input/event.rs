@@ -8,6 +8,7 @@ use crate::world::MarkerIdentifier;
 
 #[derive(Clone, Debug)]
 pub enum UserEvent {
+    SelectServer(String),
     LogIn(String, String),
     LogOut,
     Exit,
@@ -71,6 +72,10 @@ pub enum UserEvent {
     #[cfg(feature = "debug")]
     OpenMapsWindow,
     #[cfg(feature = "debug")]
+    OpenMissingAssetsWindow,
+    #[cfg(feature = "debug")]
+    AuditArchives,
+    #[cfg(feature = "debug")]
     OpenCommandsWindow,
     #[cfg(feature = "debug")]
     OpenTimeWindow,
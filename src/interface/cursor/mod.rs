@@ -34,7 +34,7 @@ pub struct MouseCursor {
 }
 
 impl MouseCursor {
-    pub fn new(game_file_loader: &mut GameFileLoader, sprite_loader: &mut SpriteLoader, action_loader: &mut ActionLoader) -> Self {
+    pub fn new(game_file_loader: &GameFileLoader, sprite_loader: &mut SpriteLoader, action_loader: &mut ActionLoader) -> Self {
         let sprite = sprite_loader.get("cursors.spr", game_file_loader).unwrap();
         let actions = action_loader.get("cursors.act", game_file_loader).unwrap();
         let animation_state = AnimationState::new(ClientTick(0));
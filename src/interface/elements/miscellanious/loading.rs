@@ -0,0 +1,72 @@
+use cgmath::{Array, Vector4};
+use derive_new::new;
+use procedural::*;
+
+use crate::graphics::{InterfaceRenderer, Renderer};
+use crate::interface::{Element, *};
+use crate::loaders::MapLoadingProgress;
+
+/// Shows the progress of a [`MapLoadingJob`](crate::loaders::MapLoadingJob).
+#[derive(new)]
+pub struct LoadingBar {
+    progress: Remote<MapLoadingProgress>,
+    #[new(default)]
+    state: ElementState,
+}
+
+impl Element for LoadingBar {
+    fn get_state(&self) -> &ElementState {
+        &self.state
+    }
+
+    fn get_state_mut(&mut self) -> &mut ElementState {
+        &mut self.state
+    }
+
+    fn is_focusable(&self) -> bool {
+        false
+    }
+
+    fn resolve(&mut self, placement_resolver: &mut PlacementResolver, _interface_settings: &InterfaceSettings, _theme: &Theme) {
+        self.state.resolve(placement_resolver, &constraint!(100%, 40));
+    }
+
+    fn update(&mut self) -> Option<ChangeEvent> {
+        self.progress.consume_changed().then_some(ChangeEvent::RerenderWindow)
+    }
+
+    fn render(
+        &self,
+        render_target: &mut <InterfaceRenderer as Renderer>::Target,
+        renderer: &InterfaceRenderer,
+        _state_provider: &StateProvider,
+        interface_settings: &InterfaceSettings,
+        theme: &Theme,
+        parent_position: Position,
+        clip_size: ClipSize,
+        _hovered_element: Option<&dyn Element>,
+        _focused_element: Option<&dyn Element>,
+        _mouse_mode: &MouseInputMode,
+        _second_theme: bool,
+    ) {
+        let mut renderer = self
+            .state
+            .element_renderer(render_target, renderer, interface_settings, parent_position, clip_size);
+
+        let progress = *self.progress.borrow();
+
+        renderer.render_text(
+            progress.stage.get_description(),
+            *theme.label.text_offset,
+            *theme.label.foreground_color,
+            *theme.label.font_size,
+        );
+
+        let bar_size = Size::new(self.state.cached_size.x, self.state.cached_size.y / 3.0);
+        let offset = Position::new(0.0, self.state.cached_size.y - bar_size.y);
+        let filled_size = Size::new(bar_size.x * progress.get_fraction(), bar_size.y);
+
+        renderer.render_rectangle(offset, bar_size, Vector4::from_value(2.0), *theme.slider.rail_color);
+        renderer.render_rectangle(offset, filled_size, Vector4::from_value(2.0), *theme.slider.knob_color);
+    }
+}
@@ -2,6 +2,7 @@ mod chat;
 mod headline;
 mod input;
 mod item;
+mod loading;
 mod skill;
 mod slider;
 mod static_label;
@@ -11,6 +12,7 @@ pub use self::chat::Chat;
 pub use self::headline::Headline;
 pub use self::input::InputField;
 pub use self::item::ItemBox;
+pub use self::loading::LoadingBar;
 pub use self::skill::SkillBox;
 pub use self::slider::Slider;
 pub use self::static_label::StaticLabel;
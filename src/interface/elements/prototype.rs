@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::fmt::{Display, Write};
 use std::net::Ipv4Addr;
 use std::rc::Rc;
 
@@ -9,6 +9,19 @@ use crate::interface::{ElementCell, *};
 
 pub trait PrototypeElement {
     fn to_element(&self, display: String) -> ElementCell;
+
+    /// Writes the same information as the element as indented text, so it
+    /// can be shown outside of the interface. Only writes the display name
+    /// by default.
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        write_text_line(text, indentation, display);
+    }
+}
+
+/// Appends a line to the text form of an element, indented by two spaces per
+/// level.
+pub fn write_text_line(text: &mut String, indentation: usize, line: impl Display) {
+    let _ = writeln!(text, "{:width$}{line}", "", width = indentation * 2);
 }
 
 pub trait ElementDisplay {
@@ -133,6 +146,10 @@ where
 
         Container::new(elements).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        write_text_line(text, indentation, format_args!("{display}: {}", self.display()));
+    }
 }
 
 impl PrototypeElement for DimensionConstraint {
@@ -155,6 +172,10 @@ impl<T: PrototypeElement> PrototypeElement for std::sync::Arc<T> {
     fn to_element(&self, display: String) -> ElementCell {
         self.as_ref().to_element(display)
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        self.as_ref().write_text(display, indentation, text)
+    }
 }
 
 impl<T: PrototypeElement> PrototypeElement for Option<T> {
@@ -167,6 +188,13 @@ impl<T: PrototypeElement> PrototypeElement for Option<T> {
 
         Container::new(elements).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        match self {
+            Some(value) => value.write_text(display, indentation, text),
+            None => write_text_line(text, indentation, format_args!("{display}: none")),
+        }
+    }
 }
 
 impl<T: PrototypeElement> PrototypeElement for &[T] {
@@ -179,6 +207,13 @@ impl<T: PrototypeElement> PrototypeElement for &[T] {
 
         Expandable::new(display, elements, false).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        write_text_line(text, indentation, display);
+        self.iter()
+            .enumerate()
+            .for_each(|(index, item)| item.write_text(&index.to_string(), indentation + 1, text));
+    }
 }
 
 impl<T: PrototypeElement, const SIZE: usize> PrototypeElement for [T; SIZE] {
@@ -191,6 +226,13 @@ impl<T: PrototypeElement, const SIZE: usize> PrototypeElement for [T; SIZE] {
 
         Expandable::new(display, elements, false).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        write_text_line(text, indentation, display);
+        self.iter()
+            .enumerate()
+            .for_each(|(index, item)| item.write_text(&index.to_string(), indentation + 1, text));
+    }
 }
 
 impl<T: PrototypeElement> PrototypeElement for Vec<T> {
@@ -203,6 +245,13 @@ impl<T: PrototypeElement> PrototypeElement for Vec<T> {
 
         Expandable::new(display, elements, false).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        write_text_line(text, indentation, display);
+        self.iter()
+            .enumerate()
+            .for_each(|(index, item)| item.write_text(&index.to_string(), indentation + 1, text));
+    }
 }
 
 impl PrototypeElement for Color {
@@ -211,10 +260,19 @@ impl PrototypeElement for Color {
 
         Container::new(elements).wrap()
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        let Color { red, green, blue, alpha } = self;
+        write_text_line(text, indentation, format_args!("{display}: {red}, {green}, {blue}, {alpha}"));
+    }
 }
 
 impl<T: PrototypeElement> PrototypeElement for Rc<T> {
     fn to_element(&self, display: String) -> ElementCell {
         (**self).to_element(display)
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        (**self).write_text(display, indentation, text)
+    }
 }
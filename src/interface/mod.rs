@@ -78,7 +78,7 @@ pub struct Interface {
 
 impl Interface {
     pub fn new(
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         available_space: Size,
@@ -5,6 +5,7 @@ use std::rc::Rc;
 use derive_new::new;
 use procedural::*;
 
+use crate::graphics::Color;
 use crate::input::UserEvent;
 use crate::interface::*;
 use crate::network::LoginSettings;
@@ -12,6 +13,9 @@ use crate::network::LoginSettings;
 #[derive(new)]
 pub struct LoginWindow {
     login_settings: LoginSettings,
+    server_names: Vec<String>,
+    /// Shown above the servers if the server list couldn't be loaded.
+    server_list_error: Option<String>,
 }
 
 impl LoginWindow {
@@ -69,7 +73,26 @@ impl PrototypeWindow for LoginWindow {
             })
         };
 
-        let elements = vec![
+        let server_buttons = self.server_names.iter().map(|server_name| {
+            let name = server_name.clone();
+
+            StateButton::default()
+                .with_text(server_name.clone())
+                .with_selector(move |state_provider| state_provider.login_settings.server_name.as_ref() == Some(&name))
+                .with_event(UserEvent::SelectServer(server_name.clone()))
+                .with_transparent_background()
+                .wrap()
+        });
+
+        let mut elements = vec![Headline::new("server".to_string(), Headline::DEFAULT_SIZE).wrap()];
+        elements.extend(self.server_list_error.iter().map(|error| {
+            Text::default()
+                .with_text(error.clone())
+                .with_foreground_color(|_| Color::rgb(220, 100, 100))
+                .wrap()
+        }));
+        elements.extend(server_buttons);
+        elements.extend([
             InputField::<24>::new(username, "username", username_action, dimension!(100%)).wrap(),
             InputField::<24, true>::new(password, "password", password_action, dimension!(100%)).wrap(),
             StateButton::default()
@@ -89,7 +112,7 @@ impl PrototypeWindow for LoginWindow {
                 .with_disabled_selector(selector)
                 .with_event(Box::new(action))
                 .wrap(),
-        ];
+        ]);
 
         WindowBuilder::default()
             .with_title("Log In".to_string())
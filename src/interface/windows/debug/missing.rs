@@ -0,0 +1,55 @@
+use procedural::*;
+
+use crate::graphics::Color;
+use crate::interface::*;
+use crate::loaders::MissingAsset;
+
+pub struct MissingAssetsWindow {
+    missing_assets: Vec<MissingAsset>,
+}
+
+impl MissingAssetsWindow {
+    pub const WINDOW_CLASS: &'static str = "missing_assets";
+
+    pub fn new(missing_assets: Vec<MissingAsset>) -> Self {
+        Self { missing_assets }
+    }
+}
+
+impl PrototypeWindow for MissingAssetsWindow {
+    fn window_class(&self) -> Option<&str> {
+        Self::WINDOW_CLASS.into()
+    }
+
+    fn to_window(&self, window_cache: &WindowCache, interface_settings: &InterfaceSettings, available_space: Size) -> Window {
+        let elements = self
+            .missing_assets
+            .iter()
+            .map(|missing_asset| match &missing_asset.placeholder {
+                Some(placeholder) => Text::default()
+                    .with_text(format!("{} -> {placeholder}", missing_asset.path))
+                    .with_foreground_color(|_| Color::rgb(220, 170, 120))
+                    .wrap(),
+                None => Text::default()
+                    .with_text(missing_asset.path.clone())
+                    .with_foreground_color(|_| Color::rgb(220, 100, 100))
+                    .wrap(),
+            })
+            .collect();
+
+        let elements = vec![
+            Text::default()
+                .with_text(format!("{} missing assets", self.missing_assets.len()))
+                .wrap(),
+            ScrollView::new(elements, constraint!(100%, ?)).wrap(),
+        ];
+
+        WindowBuilder::default()
+            .with_title("Missing assets".to_string())
+            .with_class(Self::WINDOW_CLASS.to_string())
+            .with_size(constraint!(300 > 450 < 600, ? < 80%))
+            .with_elements(elements)
+            .closable()
+            .build(window_cache, interface_settings, available_space)
+    }
+}
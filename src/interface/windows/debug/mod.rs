@@ -1,6 +1,7 @@
 mod commands;
 mod inspector;
 mod maps;
+mod missing;
 #[cfg(feature = "debug")]
 mod packet;
 mod profiler;
@@ -10,6 +11,7 @@ pub use self::commands::CommandsWindow;
 #[cfg(feature = "debug")]
 pub use self::inspector::FrameInspectorWindow;
 pub use self::maps::MapsWindow;
+pub use self::missing::MissingAssetsWindow;
 #[cfg(feature = "debug")]
 pub use self::packet::PacketWindow;
 pub use self::profiler::ProfilerWindow;
@@ -0,0 +1,34 @@
+use procedural::*;
+
+use crate::interface::*;
+use crate::loaders::MapLoadingProgress;
+
+/// Shown while a map is loading in the background.
+pub struct LoadingWindow {
+    progress: Remote<MapLoadingProgress>,
+}
+
+impl LoadingWindow {
+    pub const WINDOW_CLASS: &'static str = "loading";
+
+    pub fn new(progress: Remote<MapLoadingProgress>) -> Self {
+        Self { progress }
+    }
+}
+
+impl PrototypeWindow for LoadingWindow {
+    fn window_class(&self) -> Option<&str> {
+        Self::WINDOW_CLASS.into()
+    }
+
+    fn to_window(&self, window_cache: &WindowCache, interface_settings: &InterfaceSettings, available_space: Size) -> Window {
+        let elements = vec![LoadingBar::new(self.progress.clone()).wrap()];
+
+        WindowBuilder::default()
+            .with_title("Loading".to_string())
+            .with_class(Self::WINDOW_CLASS.to_string())
+            .with_size(constraint!(300 > 400 < 500, ?))
+            .with_elements(elements)
+            .build(window_cache, interface_settings, available_space)
+    }
+}
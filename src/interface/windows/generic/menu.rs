@@ -44,6 +44,18 @@ impl PrototypeWindow for MenuWindow {
                 .with_foreground_color(|theme| *theme.button.debug_foreground_color)
                 .wrap(),
             #[cfg(feature = "debug")]
+            Button::default()
+                .with_text("missing assets")
+                .with_event(UserEvent::OpenMissingAssetsWindow)
+                .with_foreground_color(|theme| *theme.button.debug_foreground_color)
+                .wrap(),
+            #[cfg(feature = "debug")]
+            Button::default()
+                .with_text("audit archives")
+                .with_event(UserEvent::AuditArchives)
+                .with_foreground_color(|theme| *theme.button.debug_foreground_color)
+                .wrap(),
+            #[cfg(feature = "debug")]
             Button::default()
                 .with_text("commands")
                 .with_event(UserEvent::OpenCommandsWindow)
@@ -1,9 +1,11 @@
 mod chat;
 mod dialog;
 mod error;
+mod loading;
 mod menu;
 
 pub use self::chat::ChatWindow;
 pub use self::dialog::DialogWindow;
 pub use self::error::ErrorWindow;
+pub use self::loading::LoadingWindow;
 pub use self::menu::MenuWindow;
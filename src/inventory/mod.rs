@@ -50,7 +50,7 @@ pub struct Inventory {
 impl Inventory {
     pub fn fill(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         script_loader: &ScriptLoader,
         item_data: Vec<(ItemIndex, ItemId, EquipPosition, EquipPosition)>,
@@ -59,7 +59,7 @@ impl Inventory {
             .into_iter()
             .map(|item_data| {
                 let resource_name = script_loader.get_item_resource_from_id(item_data.1);
-                let full_path = format!("À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\{resource_name}.bmp");
+                let full_path = format!("유저인터페이스\\item\\{resource_name}.bmp");
                 let texture = texture_loader.get(&full_path, game_file_loader).unwrap();
                 Item {
                     index: item_data.0,
@@ -76,7 +76,7 @@ impl Inventory {
 
     pub fn add_item(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         script_loader: &ScriptLoader,
         item_index: ItemIndex,
@@ -94,7 +94,7 @@ impl Inventory {
             }
 
             let resource_name = script_loader.get_item_resource_from_id(item_id);
-            let full_path = format!("À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\{resource_name}.bmp");
+            let full_path = format!("유저인터페이스\\item\\{resource_name}.bmp");
             let texture = texture_loader.get(&full_path, game_file_loader).unwrap();
             let item = Item {
                 index: item_index,
@@ -23,7 +23,7 @@ pub struct SkillTree {
 impl SkillTree {
     pub fn fill(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         skill_data: Vec<SkillInformation>,
@@ -31,7 +31,7 @@ impl SkillTree {
         let skills = skill_data
             .into_iter()
             .map(|skill_data| {
-                let file_path = format!("¾ÆÀÌÅÛ\\{}", skill_data.skill_name);
+                let file_path = format!("아이템\\{}", skill_data.skill_name);
                 let sprite = sprite_loader.get(&format!("{file_path}.spr"), game_file_loader).unwrap();
                 let actions = action_loader.get(&format!("{file_path}.act"), game_file_loader).unwrap();
 
@@ -0,0 +1,31 @@
+#![allow(incomplete_features)]
+#![allow(clippy::too_many_arguments)]
+#![feature(option_zip)]
+#![feature(adt_const_params)]
+#![feature(arc_unwrap_or_clone)]
+#![feature(proc_macro_hygiene)]
+#![feature(negative_impls)]
+#![feature(iter_intersperse)]
+#![feature(auto_traits)]
+#![feature(let_chains)]
+#![feature(variant_count)]
+#![feature(const_trait_impl)]
+#![feature(decl_macro)]
+#![feature(thread_local)]
+#![feature(lazy_cell)]
+#![feature(div_duration)]
+#![feature(iter_next_chunk)]
+#![feature(array_try_from_fn)]
+
+#[cfg(feature = "debug")]
+#[macro_use]
+pub mod debug;
+pub mod input;
+#[macro_use]
+pub mod system;
+pub mod graphics;
+pub mod interface;
+pub mod inventory;
+pub mod loaders;
+pub mod network;
+pub mod world;
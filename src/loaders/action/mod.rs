@@ -12,7 +12,7 @@ use super::Sprite;
 use crate::debug::*;
 use crate::graphics::{Color, Renderer, SpriteRenderer};
 use crate::interface::InterfaceSettings;
-use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, MinorFirst, Version};
+use crate::loaders::{ByteConvertable, ByteStream, ChangedFiles, GameFileLoader, LoaderError, MinorFirst, Version};
 use crate::network::ClientTick;
 
 #[derive(Clone, Debug, new)]
@@ -240,24 +240,32 @@ struct ActionsData {
     pub delays: Option<Vec<f32>>,
 }
 
+fn read_actions_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<ActionsData, LoaderError> {
+    if <[u8; 2]>::from_bytes(byte_stream, None) != Ok([b'A', b'C']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    ActionsData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+/// Parses actions without caching them. Used to audit archives.
+pub(super) fn validate_actions(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_actions_data(file_path, byte_stream).map(|_| ())
+}
+
 #[derive(Default)]
 pub struct ActionLoader {
     cache: HashMap<String, Arc<Actions>>,
 }
 
 impl ActionLoader {
-    fn load(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<Actions>, String> {
+    fn load(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<Actions>, LoaderError> {
         #[cfg(feature = "debug")]
         let timer = Timer::new_dynamic(format!("load actions from {MAGENTA}{path}{NONE}"));
 
-        let bytes = game_file_loader.get(&format!("data\\sprite\\{path}"))?;
-        let mut byte_stream = ByteStream::new(&bytes);
-
-        if <[u8; 2]>::from_bytes(&mut byte_stream, None) != [b'A', b'C'] {
-            return Err(format!("failed to read magic number from {path}"));
-        }
-
-        let actions_data = ActionsData::from_bytes(&mut byte_stream, None);
+        let file_path = format!("data\\sprite\\{path}");
+        let bytes = game_file_loader.get(&file_path)?;
+        let actions_data = read_actions_data(&file_path, &mut ByteStream::new(&bytes))?;
 
         let delays = actions_data
             .delays
@@ -276,10 +284,20 @@ impl ActionLoader {
         Ok(sprite)
     }
 
-    pub fn get(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<Actions>, String> {
+    pub fn get(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<Actions>, LoaderError> {
         match self.cache.get(path) {
             Some(sprite) => Ok(sprite.clone()),
             None => self.load(path, game_file_loader),
         }
     }
+
+    /// Removes all cached actions that were changed on disk, so they are loaded
+    /// again the next time they are requested. Returns `true` if any actions
+    /// were removed.
+    pub fn invalidate(&mut self, changed_files: &ChangedFiles) -> bool {
+        let cache_size = self.cache.len();
+        self.cache
+            .retain(|path, _| !changed_files.contains(&format!("data\\sprite\\{path}")));
+        self.cache.len() != cache_size
+    }
 }
@@ -0,0 +1,293 @@
+//! Random values for round-trip tests. The `ByteConvertable` and `Packet`
+//! derives implement [`Arbitrary`] for every type and generate a test that
+//! checks that serializing and deserializing a random value is lossless.
+//!
+//! Outside of tests, the generators are only compiled with the `arbitrary`
+//! feature, which the fuzz targets enable.
+use cgmath::{Matrix3, Quaternion, Vector2, Vector3, Vector4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, InternalVersion};
+use crate::network::PacketVersion;
+
+/// Number of random values that are checked for every version.
+const ITERATIONS_PER_VERSION: u64 = 8;
+/// Maximum length of strings without a length hint.
+const MAXIMUM_STRING_LENGTH: usize = 16;
+/// Maximum number of elements of vectors without a length hint.
+const MAXIMUM_VECTOR_LENGTH: usize = 4;
+
+/// Source of randomness for [`Arbitrary`], similar to the `Unstructured`
+/// type of the `arbitrary` crate.
+pub struct Generator {
+    random: StdRng,
+    version: InternalVersion,
+    packet_version: PacketVersion,
+    generating_length: bool,
+}
+
+impl Generator {
+    pub fn new(seed: u64, version: InternalVersion, packet_version: PacketVersion) -> Self {
+        Self {
+            random: StdRng::seed_from_u64(seed),
+            version,
+            packet_version,
+            generating_length: false,
+        }
+    }
+
+    /// The version of the file that is generated. Fields that depend on the
+    /// version are only generated if the version matches.
+    pub fn get_version(&self) -> InternalVersion {
+        self.version
+    }
+
+    /// Counterpart to [`get_version`](Self::get_version) for packets.
+    pub fn get_packet_version(&self) -> PacketVersion {
+        self.packet_version
+    }
+
+    pub fn get_random(&mut self) -> &mut StdRng {
+        &mut self.random
+    }
+
+    /// Random index into a collection of `count` elements.
+    pub fn choose(&mut self, count: usize) -> usize {
+        self.random.gen_range(0..count)
+    }
+
+    /// Generates a value that other fields use as their length or repeat
+    /// count. Numbers are kept small, so expressions like
+    /// `self.packet_length - 4` don't underflow and nested counts don't
+    /// create huge values.
+    pub fn length<T>(&mut self, generate: impl FnOnce(&mut Self) -> T) -> T {
+        self.generating_length = true;
+        let value = generate(self);
+        self.generating_length = false;
+        value
+    }
+}
+
+pub trait Arbitrary: Sized {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self;
+}
+
+macro_rules! implement_integer {
+    ($($integer:ty),*) => {
+        $(impl Arbitrary for $integer {
+            fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+                assert!(length_hint.is_none(), "{} may not have a length hint", stringify!($integer));
+
+                match generator.generating_length {
+                    true => generator.random.gen_range(16..=24),
+                    false => generator.random.gen(),
+                }
+            }
+        })*
+    };
+}
+
+implement_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl Arbitrary for f32 {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "f32 may not have a length hint");
+        generator.random.gen_range(-1000.0..1000.0)
+    }
+}
+
+impl<T: Arbitrary, const SIZE: usize> Arbitrary for [T; SIZE] {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "array may not have a length hint");
+        std::array::from_fn(|_| T::arbitrary(generator, None))
+    }
+}
+
+impl Arbitrary for String {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        // Strings are stored as single bytes and terminated by a null byte, so any
+        // byte except the terminator is generated.
+        let length = generator.random.gen_range(0..=length_hint.unwrap_or(MAXIMUM_STRING_LENGTH));
+        (0..length).map(|_| generator.random.gen_range(1..=u8::MAX) as char).collect()
+    }
+}
+
+impl<T: Arbitrary + ByteConvertable> Arbitrary for Vec<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        let Some(length) = length_hint else {
+            let count = generator.random.gen_range(0..=MAXIMUM_VECTOR_LENGTH);
+            return (0..count).map(|_| T::arbitrary(generator, None)).collect();
+        };
+
+        // Fill the vector until it has exactly the size given by the length hint.
+        let version = Some(generator.version);
+        let mut vector = Vec::new();
+        let mut size = 0;
+
+        while size < length {
+            let element = T::arbitrary(generator, None);
+            size += element.to_versioned_bytes(version, None).len();
+            vector.push(element);
+        }
+
+        assert_eq!(size, length, "length hint is not a multiple of the element size");
+        vector
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vector2<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "vector2 may not have a length hint");
+        Vector2::new(T::arbitrary(generator, None), T::arbitrary(generator, None))
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vector3<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "vector3 may not have a length hint");
+        let [x, y, z] = <[T; 3]>::arbitrary(generator, None);
+        Vector3::new(x, y, z)
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vector4<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "vector4 may not have a length hint");
+        let [x, y, z, w] = <[T; 4]>::arbitrary(generator, None);
+        Vector4::new(x, y, z, w)
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Quaternion<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "quaternion may not have a length hint");
+        let [x, y, z, w] = <[T; 4]>::arbitrary(generator, None);
+        Quaternion::new(w, x, y, z)
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Matrix3<T> {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none(), "matrix may not have a length hint");
+        let [c0r0, c0r1, c0r2, c1r0, c1r1, c1r2, c2r0, c2r1, c2r2] = <[T; 9]>::arbitrary(generator, None);
+        Matrix3::new(c0r0, c0r1, c0r2, c1r0, c1r1, c1r2, c2r0, c2r1, c2r2)
+    }
+}
+
+/// The versions that a type is checked with. `boundaries` are the versions
+/// from the `version_smaller` and `version_equals_or_above` attributes of the
+/// type, so every boundary is checked from both sides. Types that don't
+/// depend on the version are still checked with the versions of the file
+/// formats, since they may contain types that do.
+fn versions_to_check(boundaries: &[(u8, u8)]) -> Vec<InternalVersion> {
+    const FILE_FORMAT_VERSIONS: [(u8, u8); 5] = [(1, 2), (1, 7), (2, 1), (2, 2), (2, 6)];
+
+    let mut versions: Vec<(u8, u8)> = boundaries
+        .iter()
+        .flat_map(|&(major, minor)| {
+            let previous = match minor {
+                0 => (major.saturating_sub(1), u8::MAX),
+                minor => (major, minor - 1),
+            };
+            [previous, (major, minor)]
+        })
+        .chain(FILE_FORMAT_VERSIONS)
+        .collect();
+
+    versions.sort_unstable();
+    versions.dedup();
+    versions
+        .into_iter()
+        .map(|(major, minor)| InternalVersion::new(major, minor))
+        .collect()
+}
+
+/// The packet versions that a packet is checked with. Like
+/// [`versions_to_check`], every boundary is checked from both sides.
+fn packet_versions_to_check(boundaries: &[u32]) -> Vec<PacketVersion> {
+    let mut packet_versions: Vec<PacketVersion> = boundaries
+        .iter()
+        .flat_map(|&date| [PacketVersion(date - 1), PacketVersion(date)])
+        .chain([PacketVersion::default()])
+        .collect();
+
+    packet_versions.sort_unstable();
+    packet_versions.dedup();
+    packet_versions
+}
+
+fn check_values<T: Arbitrary>(
+    type_name: &str,
+    version: InternalVersion,
+    packet_version: PacketVersion,
+    description: &str,
+    to_bytes: impl Fn(&T) -> Vec<u8>,
+    from_bytes: &impl Fn(&mut ByteStream) -> Result<T, ConversionError>,
+) {
+    for seed in 0..ITERATIONS_PER_VERSION {
+        let mut generator = Generator::new(seed, version, packet_version);
+        let value = T::arbitrary(&mut generator, None);
+        let bytes = to_bytes(&value);
+
+        let mut byte_stream = ByteStream::new(&bytes);
+        byte_stream.set_version(version);
+        byte_stream.set_packet_version(packet_version);
+
+        let decoded = from_bytes(&mut byte_stream)
+            .unwrap_or_else(|error| panic!("failed to deserialize {type_name} ({description}, seed {seed}): {error}"));
+
+        assert!(
+            byte_stream.is_empty(),
+            "{} bytes left after deserializing {type_name} ({description}, seed {seed})",
+            byte_stream.get_remaining_length()
+        );
+        assert_eq!(
+            to_bytes(&decoded),
+            bytes,
+            "{type_name} changed after deserializing ({description}, seed {seed})"
+        );
+    }
+}
+
+/// Checks that random values are serialized the same way after being
+/// deserialized. Values are compared by their bytes, so types don't need to
+/// implement `PartialEq`.
+pub fn check_round_trip<T: Arbitrary>(
+    type_name: &str,
+    boundaries: &[(u8, u8)],
+    to_bytes: impl Fn(&T, InternalVersion) -> Vec<u8>,
+    from_bytes: impl Fn(&mut ByteStream) -> Result<T, ConversionError>,
+) {
+    for version in versions_to_check(boundaries) {
+        let description = format!("version {version}");
+        let to_bytes = |value: &T| to_bytes(value, version);
+        check_values(
+            type_name,
+            version,
+            PacketVersion::default(),
+            &description,
+            to_bytes,
+            &from_bytes,
+        );
+    }
+}
+
+/// Same as [`check_round_trip`], but for packets, which depend on the packet
+/// version instead. `boundaries` are the dates from the packet version
+/// attributes of the packet.
+pub fn check_packet_round_trip<T: Arbitrary>(
+    type_name: &str,
+    boundaries: &[u32],
+    to_bytes: impl Fn(&T, PacketVersion) -> Vec<u8>,
+    from_bytes: impl Fn(&mut ByteStream) -> Result<T, ConversionError>,
+) {
+    // Packets don't contain versioned file data, so any version works.
+    let version = InternalVersion::new(0, 0);
+
+    for packet_version in packet_versions_to_check(boundaries) {
+        let description = format!("packet version {packet_version}");
+        let to_bytes = |value: &T| to_bytes(value, packet_version);
+        check_values(type_name, version, packet_version, &description, to_bytes, &from_bytes);
+    }
+}
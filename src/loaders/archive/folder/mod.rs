@@ -1,13 +1,20 @@
 //! An OS folder containing game assets.
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
 
 use super::{Archive, Writable};
+use crate::loaders::LoaderError;
 
 pub struct FolderArchive {
     folder_path: PathBuf,
+    /// Modification time of every file, as seen by the last call to
+    /// [`get_changed_files`](Archive::get_changed_files).
+    modification_times: Mutex<Option<HashMap<String, SystemTime>>>,
 }
 
 impl FolderArchive {
@@ -17,30 +24,76 @@ impl FolderArchive {
             false => PathBuf::from(path.replace('\\', "/")),
         }
     }
+
+    /// All files in the folder and their paths relative to it, using
+    /// backslashes as the path separator.
+    fn files(&self) -> impl Iterator<Item = (String, DirEntry)> + '_ {
+        WalkDir::new(&self.folder_path)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|file| {
+                let relative_path = file.path().strip_prefix(&self.folder_path).unwrap().to_str().unwrap();
+                (relative_path.replace('/', "\\"), file)
+            })
+    }
 }
 
 impl Archive for FolderArchive {
-    fn from_path(path: &Path) -> Self {
-        Self {
+    fn from_path(path: &Path) -> Result<Self, LoaderError> {
+        Ok(Self {
             folder_path: PathBuf::from(path),
-        }
+            modification_times: Mutex::default(),
+        })
     }
 
-    fn get_file_by_path(&mut self, asset_path: &str) -> Option<Vec<u8>> {
+    fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError> {
         let normalized_asset_path = Self::os_specific_path(asset_path);
         let full_path = self.folder_path.join(normalized_asset_path);
 
-        full_path.is_file().then(|| fs::read(full_path).ok()).flatten()
+        full_path
+            .is_file()
+            .then(|| fs::read(full_path).ok())
+            .flatten()
+            .ok_or_else(|| LoaderError::NotFound {
+                path: asset_path.to_owned(),
+            })
     }
 
     fn get_lua_files(&self, lua_files: &mut Vec<String>) {
-        let files = WalkDir::new(&self.folder_path)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| entry.file_type().is_file() && entry.path().extension().unwrap() == "lub")
-            .map(|file| String::from(file.path().strip_prefix(&self.folder_path).unwrap().to_str().unwrap()));
+        let mut file_paths = Vec::new();
+        self.get_file_paths(&mut file_paths);
+
+        lua_files.extend(file_paths.into_iter().filter(|file_path| file_path.ends_with(".lub")));
+    }
+
+    fn get_file_paths(&self, file_paths: &mut Vec<String>) {
+        file_paths.extend(self.files().map(|(file_path, _)| file_path));
+    }
+
+    fn get_changed_files(&self, changed_files: &mut Vec<String>) {
+        let modification_times: HashMap<String, SystemTime> = self
+            .files()
+            .filter_map(|(file_path, file)| Some((file_path, file.metadata().ok()?.modified().ok()?)))
+            .collect();
+
+        let mut previous_modification_times = self.modification_times.lock().unwrap();
+
+        if let Some(previous_modification_times) = previous_modification_times.as_ref() {
+            let modified = modification_times
+                .iter()
+                .filter(|(file_path, time)| previous_modification_times.get(*file_path) != Some(*time))
+                .map(|(file_path, _)| file_path.clone());
+
+            let removed = previous_modification_times
+                .keys()
+                .filter(|file_path| !modification_times.contains_key(*file_path))
+                .cloned();
+
+            changed_files.extend(modified.chain(removed));
+        }
 
-        lua_files.extend(files);
+        *previous_modification_times = Some(modification_times);
     }
 }
 
@@ -66,4 +119,44 @@ impl Writable for FolderArchive {
         // Write file contents to the file
         fs::write(&full_path, file_data).unwrap_or_else(|_| panic!("error writing to file {}", full_path.display()));
     }
+
+    fn remove_file(&mut self, file_path: &str) {
+        let normalized_asset_path = Self::os_specific_path(file_path);
+        let full_path = self.folder_path.join(normalized_asset_path);
+
+        if full_path.is_file() {
+            fs::remove_file(&full_path).unwrap_or_else(|_| panic!("error removing file {}", full_path.display()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod changes {
+    use super::FolderArchive;
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::TemporaryPath;
+
+    #[test]
+    fn added_and_removed_files() {
+        let path = TemporaryPath::new("changes");
+
+        let mut archive = FolderArchive::from_path(&path).unwrap();
+        archive.create();
+        archive.add_file("data\\first.txt", b"first".to_vec());
+
+        let mut changed_files = Vec::new();
+        archive.get_changed_files(&mut changed_files);
+        assert!(changed_files.is_empty());
+
+        archive.add_file("data\\second.txt", b"second".to_vec());
+        archive.remove_file("data\\first.txt");
+
+        archive.get_changed_files(&mut changed_files);
+        changed_files.sort();
+        assert_eq!(changed_files, ["data\\first.txt", "data\\second.txt"]);
+
+        let mut changed_files = Vec::new();
+        archive.get_changed_files(&mut changed_files);
+        assert!(changed_files.is_empty());
+    }
 }
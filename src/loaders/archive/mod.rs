@@ -1,23 +1,126 @@
 pub mod folder;
 pub mod native;
+pub mod patch;
+pub mod path;
+pub mod zip;
 
+use std::fs::File;
 use std::path::Path;
 
-pub trait Archive {
-    fn from_path(path: &Path) -> Self
+use super::{AuditIssue, ConversionError, LoaderError};
+
+/// Archives are shared between all loaders, which may read from multiple
+/// threads at once.
+pub trait Archive: Send + Sync {
+    /// Opens an existing archive. Fails if the archive can't be read or its
+    /// file table is invalid.
+    fn from_path(path: &Path) -> Result<Self, LoaderError>
     where
         Self: Sized;
 
-    /// Retrieve an asset from the Archive
-    fn get_file_by_path(&mut self, asset_path: &str) -> Option<Vec<u8>>;
+    /// Retrieve an asset from the Archive. Returns
+    /// [`LoaderError::NotFound`] if the archive does not contain the asset and
+    /// [`LoaderError::Removed`] if it hides the asset of other archives.
+    fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError>;
 
     /// Get a list of all Lua files
     fn get_lua_files(&self, lua_files: &mut Vec<String>);
+
+    /// Get a list of all files, using backslashes as the path separator
+    fn get_file_paths(&self, file_paths: &mut Vec<String>);
+
+    /// Get a list of all files that were added, modified or removed since the
+    /// last call. The first call only records the current state. Archives
+    /// that can't change while the client is running don't report anything.
+    fn get_changed_files(&self, _changed_files: &mut Vec<String>) {}
+
+    /// Check that every file in the archive can be read. Archives with an
+    /// internal file table should also check that it matches the data.
+    /// Errors are only returned if the archive itself can't be accessed.
+    fn verify(&self, issues: &mut Vec<AuditIssue>) -> std::io::Result<()> {
+        let mut file_paths = Vec::new();
+        self.get_file_paths(&mut file_paths);
+
+        for file_path in file_paths {
+            if let Err(error) = self.get_file_by_path(&file_path) {
+                issues.push(AuditIssue::Unreadable {
+                    path: file_path,
+                    reason: error.to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the exact number of bytes needed to fill `buffer`, starting at
+/// `offset`. Unlike seeking and reading, this does not modify the cursor of the
+/// file, so it can be used on a shared file handle.
+fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileExt;
+
+        file.read_exact_at(buffer, offset)
+    }
+
+    #[cfg(windows)]
+    {
+        use std::io::ErrorKind;
+        use std::os::windows::fs::FileExt;
+
+        let mut bytes_read = 0;
+
+        while bytes_read < buffer.len() {
+            match file.seek_read(&mut buffer[bytes_read..], offset + bytes_read as u64)? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                count => bytes_read += count,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Used for I/O errors while opening an archive.
+fn archive_io_error(path: &Path, error: std::io::Error) -> LoaderError {
+    LoaderError::Io {
+        path: path.display().to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Used for archives with an invalid header or file table.
+fn corrupt_archive(path: &Path, error: ConversionError) -> LoaderError {
+    LoaderError::conversion(path.display().to_string(), error)
+}
+
+/// Reads the (compressed) data of an asset. A file that is too short to
+/// contain the data is considered corrupt.
+fn read_asset_data(file: &File, asset_path: &str, offset: u64, buffer: &mut [u8]) -> Result<(), LoaderError> {
+    read_exact_at(file, buffer, offset).map_err(|error| LoaderError::Corrupt {
+        path: asset_path.to_owned(),
+        offset: offset as usize,
+        reason: error.to_string(),
+    })
+}
+
+/// Inflates zlib compressed data.
+fn inflate(asset_path: &str, data: &[u8]) -> Result<Vec<u8>, LoaderError> {
+    yazi::decompress(data, yazi::Format::Zlib)
+        .map(|(uncompressed, _checksum)| uncompressed)
+        .map_err(|error| LoaderError::Decompression {
+            path: asset_path.to_owned(),
+            reason: format!("{error:?}"),
+        })
 }
 
 pub enum ArchiveType {
     Folder,
     Native,
+    Patch,
+    Zip,
 }
 
 /// A common trait to all writable archives
@@ -26,5 +129,7 @@ pub trait Writable {
 
     fn add_file(&mut self, path: &str, asset: Vec<u8>);
 
-    fn save(&self) {}
+    fn remove_file(&mut self, path: &str);
+
+    fn save(&mut self) {}
 }
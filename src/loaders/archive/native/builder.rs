@@ -1,35 +1,160 @@
 //! Implements an writable instance of a GRF File
-//! This way, we can provide a temporal storage to files before the final write
-//! occurs while keeping it outside of the
-//! [`NativeArchive`](super::NativeArchive) implementation
+//! Files are compressed and written to disk as soon as they are added, so only
+//! the file table is kept in memory. The file table and the header are written
+//! once the archive is saved. This is kept outside of the
+//! [`NativeArchive`](super::NativeArchive) implementation.
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use yazi::{compress, CompressionLevel, Format};
 
 use super::assettable::AssetTable;
 use super::filetablerow::FileTableRow;
-use super::header::Header;
-use super::{FileTable, MAGIC_BYTES};
+use super::header::{ArchiveVersion, Header};
+use super::{load_file_table, FileTable, MAGIC_BYTES, UNPACKED_SIZE_OF_FILETABLE_PREFIX, UNPACKED_SIZE_OF_FULL_HEADER};
+#[cfg(feature = "debug")]
+use crate::debug::*;
 use crate::loaders::archive::Writable;
 use crate::loaders::ByteConvertable;
 
 pub struct NativeArchiveBuilder {
     os_file_path: PathBuf,
+    os_file_handler: File,
+    version: ArchiveVersion,
     file_table: FileTable,
-    data: Vec<u8>,
+    /// Offset relative to the end of the header where the next file will be
+    /// written.
+    data_end: u64,
 }
 
 impl NativeArchiveBuilder {
-    pub fn from_path(path: &Path) -> Self {
+    /// Creates a new, empty archive. An existing file at `path` will be
+    /// overwritten.
+    pub fn from_path(path: &Path, version: ArchiveVersion) -> Self {
+        let mut os_file_handler = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .expect("unable to create file");
+
+        // The header is only a placeholder until the archive is saved.
+        os_file_handler.write_all(MAGIC_BYTES).unwrap();
+        os_file_handler.write_all(&Header::new(0, 0, version).to_bytes(None)).unwrap();
+
         Self {
             os_file_path: PathBuf::from(path),
+            os_file_handler,
+            version,
             file_table: FileTable::new(),
-            data: Vec::new(),
+            data_end: 0,
         }
     }
+
+    /// Opens an existing archive for modification. New files are appended to
+    /// the end of the archive, so the archive stays valid until it is saved.
+    pub fn open(path: &Path) -> Self {
+        let mut os_file_handler = OpenOptions::new().read(true).write(true).open(path).expect("unable to open file");
+
+        let (file_header, file_table) = load_file_table(&mut os_file_handler, path).unwrap_or_else(|error| panic!("{error}"));
+        let file_size = os_file_handler.metadata().unwrap().len();
+
+        Self {
+            os_file_path: PathBuf::from(path),
+            os_file_handler,
+            version: file_header.get_version(),
+            file_table,
+            data_end: file_size - UNPACKED_SIZE_OF_FULL_HEADER,
+        }
+    }
+
+    pub fn get_version(&self) -> ArchiveVersion {
+        self.version
+    }
+
+    /// Get a list of all files, using the same paths as
+    /// [`NativeArchive`](super::NativeArchive).
+    pub fn get_file_paths(&self, file_paths: &mut Vec<String>) {
+        let files = self
+            .file_table
+            .iter()
+            .filter(|(_, row)| row.is_file())
+            .map(|(file_name, _)| file_name.clone());
+
+        file_paths.extend(files);
+    }
+
+    /// Number of bytes that are not occupied by files, i.e. replaced or
+    /// removed files and the file tables.
+    pub fn get_dead_space(&self) -> u64 {
+        let used_space: u64 = self.file_table.values().map(|row| row.compressed_size_aligned as u64).sum();
+        self.data_end.saturating_sub(used_space)
+    }
+
+    fn append(&mut self, data: &[u8]) -> u64 {
+        let offset = self.data_end;
+
+        self.os_file_handler
+            .seek(SeekFrom::Start(offset + UNPACKED_SIZE_OF_FULL_HEADER))
+            .unwrap();
+        self.os_file_handler.write_all(data).expect("unable to write file");
+        self.data_end += data.len() as u64;
+
+        offset
+    }
+
+    fn read_raw(&mut self, row: &FileTableRow) -> Vec<u8> {
+        let mut data = vec![0u8; row.compressed_size_aligned as usize];
+
+        self.os_file_handler
+            .seek(SeekFrom::Start(row.offset + UNPACKED_SIZE_OF_FULL_HEADER))
+            .unwrap();
+        self.os_file_handler.read_exact(&mut data).unwrap();
+
+        data
+    }
+
+    /// Rewrites the archive so that it only contains the files that are
+    /// still referenced by the file table. The data is copied one file at a
+    /// time without recompressing it.
+    pub fn repack(mut self) -> Self {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("repack {MAGENTA}{}{NONE}", self.os_file_path.display()));
+
+        let mut temporary_path = self.os_file_path.clone().into_os_string();
+        temporary_path.push(".repack");
+        let temporary_path = PathBuf::from(temporary_path);
+
+        let mut repacked = Self::from_path(&temporary_path, self.version);
+        let mut rows: Vec<FileTableRow> = self.file_table.values().cloned().collect();
+        // Keep the original order of the data to avoid seeking back and forth.
+        rows.sort_by_key(|row| row.offset);
+
+        for mut row in rows {
+            let data = self.read_raw(&row);
+            row.offset = repacked.append(&data);
+            repacked.file_table.insert(row.file_name.to_lowercase(), row);
+        }
+
+        repacked.save();
+
+        let Self { os_file_path, .. } = self;
+        drop(repacked);
+
+        std::fs::rename(&temporary_path, &os_file_path).expect("unable to replace archive");
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+
+        Self::open(&os_file_path)
+    }
 }
 
 impl Writable for NativeArchiveBuilder {
+    /// Adds a new file or replaces an existing one. The data of a replaced
+    /// file stays in the archive until it is repacked.
     fn add_file(&mut self, path: &str, asset: Vec<u8>) {
         let compressed = compress(&asset, Format::Zlib, CompressionLevel::Default).unwrap();
 
@@ -37,7 +162,7 @@ impl Writable for NativeArchiveBuilder {
         let compressed_size_aligned = compressed_size;
         let uncompressed_size = asset.len() as u32;
         let flags = 1;
-        let offset = self.data.len() as u32;
+        let offset = self.append(&compressed);
 
         let file_information = FileTableRow {
             file_name: String::from(path),
@@ -48,35 +173,95 @@ impl Writable for NativeArchiveBuilder {
             offset,
         };
 
-        self.data.extend_from_slice(&compressed);
-        self.file_table.insert(String::from(path), file_information);
+        self.file_table.insert(path.to_lowercase(), file_information);
     }
 
-    fn save(&self) {
-        let file_table_offset = self.data.len() as u32;
-        let reserved_files = 0;
-        let raw_file_count = self.file_table.len() as u32 + 7;
-        let version = 0x200;
-        let file_header = Header::new(file_table_offset, reserved_files, raw_file_count, version);
-
-        let mut bytes = Vec::new();
-
-        bytes.extend_from_slice(MAGIC_BYTES);
-        bytes.extend_from_slice(&file_header.to_bytes(None));
-        bytes.extend_from_slice(&self.data);
+    fn remove_file(&mut self, path: &str) {
+        self.file_table.remove(&path.to_lowercase());
+    }
 
+    fn save(&mut self) {
         let mut file_table_data = Vec::new();
 
         for file_information in self.file_table.values() {
-            file_table_data.extend_from_slice(&file_information.to_bytes(None));
+            file_table_data.extend_from_slice(&file_information.to_bytes(self.version));
         }
 
         let compressed_file_information_data = compress(&file_table_data, Format::Zlib, CompressionLevel::Default).unwrap();
         let file_table = AssetTable::new(compressed_file_information_data.len() as u32, file_table_data.len() as u32);
 
+        let mut bytes = Vec::new();
+
+        if self.version == ArchiveVersion::Version300 {
+            bytes.extend_from_slice(&[0; UNPACKED_SIZE_OF_FILETABLE_PREFIX]);
+        }
+
         bytes.extend_from_slice(&file_table.to_bytes(None));
         bytes.extend_from_slice(&compressed_file_information_data);
 
-        std::fs::write(&self.os_file_path, bytes).expect("unable to write file");
+        // The file table is written after all data, so any file added after saving
+        // will leave the old table behind as dead space.
+        let file_table_offset = self.append(&bytes);
+        self.os_file_handler.set_len(self.data_end + UNPACKED_SIZE_OF_FULL_HEADER).unwrap();
+
+        let file_count = self.file_table.len() as u32;
+        let file_header = Header::new(file_table_offset, file_count, self.version);
+
+        self.os_file_handler.seek(SeekFrom::Start(MAGIC_BYTES.len() as u64)).unwrap();
+        self.os_file_handler
+            .write_all(&file_header.to_bytes(None))
+            .expect("unable to write file");
+        self.os_file_handler.sync_all().expect("unable to write file");
+    }
+}
+
+#[cfg(test)]
+mod modification {
+    use super::NativeArchiveBuilder;
+    use crate::loaders::archive::native::{ArchiveVersion, NativeArchive};
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::{LoaderError, TemporaryPath};
+
+    fn modify_and_repack(version: ArchiveVersion) {
+        let path = TemporaryPath::new(&format!("modification_{version:?}.grf"));
+
+        let mut builder = NativeArchiveBuilder::from_path(&path, version);
+        builder.add_file("data\\first.txt", b"first".to_vec());
+        builder.add_file("data\\second.txt", b"second".to_vec());
+        builder.add_file("data\\유저인터페이스\\item.txt", b"item".to_vec());
+        builder.save();
+
+        let mut builder = NativeArchiveBuilder::open(&path);
+        assert_eq!(builder.get_version(), version);
+        builder.add_file("data\\First.txt", b"replaced".to_vec());
+        builder.remove_file("data\\second.txt");
+        builder.add_file("data\\third.txt", b"third".to_vec());
+        builder.save();
+        let dead_space = builder.get_dead_space();
+
+        let builder = builder.repack();
+        assert!(builder.get_dead_space() < dead_space);
+        drop(builder);
+
+        let archive = NativeArchive::from_path(&path).unwrap();
+        assert_eq!(archive.get_file_by_path("data\\first.txt"), Ok(b"replaced".to_vec()));
+        assert_eq!(
+            archive.get_file_by_path("data\\second.txt"),
+            Err(LoaderError::NotFound {
+                path: "data\\second.txt".to_owned()
+            })
+        );
+        assert_eq!(archive.get_file_by_path("data\\third.txt"), Ok(b"third".to_vec()));
+        assert_eq!(archive.get_file_by_path("data\\유저인터페이스\\item.txt"), Ok(b"item".to_vec()));
+    }
+
+    #[test]
+    fn version_200() {
+        modify_and_repack(ArchiveVersion::Version200);
+    }
+
+    #[test]
+    fn version_300() {
+        modify_and_repack(ArchiveVersion::Version300);
     }
 }
@@ -0,0 +1,422 @@
+//! Decryption of GRF entries.
+//!
+//! Gravity uses a broken version of DES with a zeroed key and only a single
+//! round, so every block can be decrypted without knowing any secret. Files
+//! flagged as [`MIXCRYPT`](super::filetablerow::FileTableRow::is_mixcrypt)
+//! additionally have some of their unencrypted blocks shuffled.
+
+const BLOCK_SIZE: usize = 8;
+/// Number of leading blocks that are always DES encrypted.
+const ENCRYPTED_HEADER_BLOCKS: usize = 20;
+/// One in every `SHUFFLE_CYCLE` unencrypted blocks is shuffled.
+const SHUFFLE_CYCLE: usize = 7;
+
+const MASK: [u8; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+
+#[rustfmt::skip]
+const INITIAL_PERMUTATION_TABLE: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2,
+    60, 52, 44, 36, 28, 20, 12, 4,
+    62, 54, 46, 38, 30, 22, 14, 6,
+    64, 56, 48, 40, 32, 24, 16, 8,
+    57, 49, 41, 33, 25, 17, 9, 1,
+    59, 51, 43, 35, 27, 19, 11, 3,
+    61, 53, 45, 37, 29, 21, 13, 5,
+    63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+#[rustfmt::skip]
+const FINAL_PERMUTATION_TABLE: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32,
+    39, 7, 47, 15, 55, 23, 63, 31,
+    38, 6, 46, 14, 54, 22, 62, 30,
+    37, 5, 45, 13, 53, 21, 61, 29,
+    36, 4, 44, 12, 52, 20, 60, 28,
+    35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26,
+    33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+#[rustfmt::skip]
+const TRANSPOSITION_TABLE: [u8; 32] = [
+    16, 7, 20, 21,
+    29, 12, 28, 17,
+    1, 15, 23, 26,
+    5, 18, 31, 10,
+    2, 8, 24, 14,
+    32, 27, 3, 9,
+    19, 13, 30, 6,
+    22, 11, 4, 25,
+];
+
+/// The eight DES S-boxes, combined into four tables that each produce two
+/// nibbles. The tables are indexed directly by the 6 bit input value.
+#[rustfmt::skip]
+const SUBSTITUTION_TABLE: [[u8; 64]; 4] = [
+    [
+        0xEF, 0x03, 0x41, 0xFD, 0xD8, 0x74, 0x1E, 0x47, 0x26, 0xEF, 0xFB, 0x22, 0xB3, 0xD8, 0x84, 0x1E,
+        0x39, 0xAC, 0xA7, 0x60, 0x62, 0xC1, 0xCD, 0xBA, 0x5C, 0x96, 0x90, 0x59, 0x05, 0x3B, 0x7A, 0x85,
+        0x40, 0xFD, 0x1E, 0xC8, 0xE7, 0x8A, 0x8B, 0x21, 0xDA, 0x43, 0x64, 0x9F, 0x2D, 0x14, 0xB1, 0x72,
+        0xF5, 0x5B, 0xC8, 0xB6, 0x9C, 0x37, 0x76, 0xEC, 0x39, 0xA0, 0xA3, 0x05, 0x52, 0x6E, 0x0F, 0xD9,
+    ],
+    [
+        0xA7, 0xDD, 0x0D, 0x78, 0x9E, 0x0B, 0xE3, 0x95, 0x60, 0x36, 0x36, 0x4F, 0xF9, 0x60, 0x5A, 0xA3,
+        0x11, 0x24, 0xD2, 0x87, 0xC8, 0x52, 0x75, 0xEC, 0xBB, 0xC1, 0x4C, 0xBA, 0x24, 0xFE, 0x8F, 0x19,
+        0xDA, 0x13, 0x66, 0xAF, 0x49, 0xD0, 0x90, 0x06, 0x8C, 0x6A, 0xFB, 0x91, 0x37, 0x8D, 0x0D, 0x78,
+        0xBF, 0x49, 0x11, 0xF4, 0x23, 0xE5, 0xCE, 0x3B, 0x55, 0xBC, 0xA2, 0x57, 0xE8, 0x22, 0x74, 0xCE,
+    ],
+    [
+        0x2C, 0xEA, 0xC1, 0xBF, 0x4A, 0x24, 0x1F, 0xC2, 0x79, 0x47, 0xA2, 0x7C, 0xB6, 0xD9, 0x68, 0x15,
+        0x80, 0x56, 0x5D, 0x01, 0x33, 0xFD, 0xF4, 0xAE, 0xDE, 0x30, 0x07, 0x9B, 0xE5, 0x83, 0x9B, 0x68,
+        0x49, 0xB4, 0x2E, 0x83, 0x1F, 0xC2, 0xB5, 0x7C, 0xA2, 0x19, 0xD8, 0xE5, 0x7C, 0x2F, 0x83, 0xDA,
+        0xF7, 0x6B, 0x90, 0xFE, 0xC4, 0x01, 0x5A, 0x97, 0x61, 0xA6, 0x3D, 0x40, 0x0B, 0x58, 0xE6, 0x3D,
+    ],
+    [
+        0x4D, 0xD1, 0xB2, 0x0F, 0x28, 0xBD, 0xE4, 0x78, 0xF6, 0x4A, 0x0F, 0x93, 0x8B, 0x17, 0xD1, 0xA4,
+        0x3A, 0xEC, 0xC9, 0x35, 0x93, 0x56, 0x7E, 0xCB, 0x55, 0x20, 0xA0, 0xFE, 0x6C, 0x89, 0x17, 0x62,
+        0x17, 0x62, 0x4B, 0xB1, 0xB4, 0xDE, 0xD1, 0x87, 0xC9, 0x14, 0x3C, 0x4A, 0x7E, 0xA8, 0xE2, 0x7D,
+        0xA0, 0x9F, 0xF6, 0x5C, 0x6A, 0x09, 0x8D, 0xF0, 0x0F, 0xE3, 0x53, 0x25, 0x95, 0x36, 0x28, 0xCB,
+    ],
+];
+
+type Block = [u8; BLOCK_SIZE];
+
+fn permute(block: &Block, table: &[u8; 64]) -> Block {
+    let mut permuted = [0; BLOCK_SIZE];
+
+    for (index, position) in table.iter().enumerate() {
+        let source = (position - 1) as usize;
+
+        if block[source >> 3] & MASK[source & 7] != 0 {
+            permuted[index >> 3] |= MASK[index & 7];
+        }
+    }
+
+    permuted
+}
+
+/// Expands the right half of the block into eight 6 bit values.
+fn expand(block: &Block) -> Block {
+    [
+        ((block[7] << 5) | (block[4] >> 3)) & 0x3F,
+        ((block[4] << 1) | (block[5] >> 7)) & 0x3F,
+        ((block[4] << 5) | (block[5] >> 3)) & 0x3F,
+        ((block[5] << 1) | (block[6] >> 7)) & 0x3F,
+        ((block[5] << 5) | (block[6] >> 3)) & 0x3F,
+        ((block[6] << 1) | (block[7] >> 7)) & 0x3F,
+        ((block[6] << 5) | (block[7] >> 3)) & 0x3F,
+        ((block[7] << 1) | (block[4] >> 7)) & 0x3F,
+    ]
+}
+
+fn substitute(block: &Block) -> Block {
+    let mut substituted = [0; BLOCK_SIZE];
+
+    for (index, table) in SUBSTITUTION_TABLE.iter().enumerate() {
+        substituted[index] = (table[block[index * 2] as usize] & 0xF0) | (table[block[index * 2 + 1] as usize] & 0x0F);
+    }
+
+    substituted
+}
+
+/// Permutes the 32 bits of the left half into the right half.
+fn transpose(block: &Block) -> Block {
+    let mut transposed = [0; BLOCK_SIZE];
+
+    for (index, position) in TRANSPOSITION_TABLE.iter().enumerate() {
+        let source = (position - 1) as usize;
+
+        if block[source >> 3] & MASK[source & 7] != 0 {
+            transposed[(index >> 3) + 4] |= MASK[index & 7];
+        }
+    }
+
+    transposed
+}
+
+fn round(block: &mut Block) {
+    let feistel = transpose(&substitute(&expand(block)));
+
+    for index in 0..4 {
+        block[index] ^= feistel[index + 4];
+    }
+}
+
+/// Since there is only a single round with a zeroed key, this function is its
+/// own inverse.
+fn des_block(block: &mut Block) {
+    let mut permuted = permute(block, &INITIAL_PERMUTATION_TABLE);
+    round(&mut permuted);
+    *block = permute(&permuted, &FINAL_PERMUTATION_TABLE);
+}
+
+fn substitute_byte(byte: u8) -> u8 {
+    match byte {
+        0x00 => 0x2B,
+        0x2B => 0x00,
+        0x6C => 0x80,
+        0x80 => 0x6C,
+        0x01 => 0x68,
+        0x68 => 0x01,
+        0x48 => 0x77,
+        0x77 => 0x48,
+        0x60 => 0xFF,
+        0xFF => 0x60,
+        0xB9 => 0xC0,
+        0xC0 => 0xB9,
+        0xFE => 0xEB,
+        0xEB => 0xFE,
+        other => other,
+    }
+}
+
+fn unshuffle_block(block: &mut Block) {
+    *block = [
+        block[3],
+        block[4],
+        block[6],
+        block[0],
+        block[1],
+        block[2],
+        block[5],
+        substitute_byte(block[7]),
+    ];
+}
+
+/// The number of unencrypted blocks between two encrypted ones depends on the
+/// number of decimal digits of the compressed size.
+fn encryption_cycle(compressed_size: usize) -> usize {
+    let digits = compressed_size.max(1).ilog10() as usize + 1;
+
+    match digits {
+        0..=2 => 1,
+        3..=4 => digits + 1,
+        5..=6 => digits + 9,
+        _ => digits + 15,
+    }
+}
+
+fn blocks(data: &mut [u8]) -> impl Iterator<Item = &mut Block> {
+    data.chunks_exact_mut(BLOCK_SIZE).map(|chunk| chunk.try_into().unwrap())
+}
+
+/// Decrypts an entry with the `MIXCRYPT` flag set.
+pub(super) fn decrypt_mixed(data: &mut [u8], compressed_size: usize) {
+    let cycle = encryption_cycle(compressed_size);
+    let mut unencrypted_index = 0;
+
+    for (index, block) in blocks(data).enumerate() {
+        if index < ENCRYPTED_HEADER_BLOCKS || index % cycle == 0 {
+            des_block(block);
+            continue;
+        }
+
+        if unencrypted_index != 0 && unencrypted_index % SHUFFLE_CYCLE == 0 {
+            unshuffle_block(block);
+        }
+
+        unencrypted_index += 1;
+    }
+}
+
+/// Decrypts an entry with the `DES` flag set, where only the first blocks
+/// are encrypted.
+pub(super) fn decrypt_header(data: &mut [u8]) {
+    blocks(data).take(ENCRYPTED_HEADER_BLOCKS).for_each(des_block);
+}
+
+#[cfg(test)]
+mod decryption {
+    use yazi::{compress, decompress, CompressionLevel, Format};
+
+    use super::{
+        blocks, decrypt_header, decrypt_mixed, des_block, encryption_cycle, substitute_byte, Block, ENCRYPTED_HEADER_BLOCKS, SHUFFLE_CYCLE,
+    };
+
+    fn shuffle_block(block: &mut Block) {
+        *block = [
+            block[3],
+            block[4],
+            block[5],
+            block[0],
+            block[1],
+            block[6],
+            block[2],
+            substitute_byte(block[7]),
+        ];
+    }
+
+    /// Mirrors what the official tools do when encrypting an entry.
+    fn encrypt_mixed(data: &mut [u8], compressed_size: usize) {
+        let cycle = encryption_cycle(compressed_size);
+        let mut unencrypted_index = 0;
+
+        for (index, block) in blocks(data).enumerate() {
+            if index < ENCRYPTED_HEADER_BLOCKS || index % cycle == 0 {
+                des_block(block);
+                continue;
+            }
+
+            if unencrypted_index != 0 && unencrypted_index % SHUFFLE_CYCLE == 0 {
+                shuffle_block(block);
+            }
+
+            unencrypted_index += 1;
+        }
+    }
+
+    /// Creates a compressed, aligned entry the same way it is stored inside a
+    /// GRF.
+    fn fixture(size: usize) -> (Vec<u8>, Vec<u8>, usize) {
+        let plain: Vec<u8> = (0..size).map(|index| (index * 7 + index / 13) as u8).collect();
+        let mut compressed = compress(&plain, Format::Zlib, CompressionLevel::Default).unwrap();
+        let compressed_size = compressed.len();
+        compressed.resize((compressed_size + 7) / 8 * 8, 0);
+        (plain, compressed, compressed_size)
+    }
+
+    /// Size of the zlib stream inside of the fixture entries, without the
+    /// alignment padding.
+    const FIXTURE_COMPRESSED_SIZE: usize = 301;
+
+    // Both entries contain the same zlib stream. They were encrypted by an
+    // independent implementation of the grfio algorithm that evaluates the
+    // standard DES tables bit by bit, so a mistake in the combined tables, the
+    // encryption cycle or the shuffling of this module makes them fail.
+    #[rustfmt::skip]
+    const MIXCRYPT_ENTRY: [u8; 304] = [
+        0x79, 0xCA, 0x44, 0x32, 0x15, 0xCC, 0xEE, 0x8B, 0xBB, 0xD3, 0xF7, 0x85, 0x05, 0x1C, 0xCB, 0x31,
+        0x53, 0xB2, 0x76, 0xC7, 0x75, 0xD1, 0xF2, 0xF1, 0x05, 0x1B, 0xD8, 0x16, 0x65, 0x02, 0x5D, 0x18,
+        0x65, 0xA6, 0x82, 0x83, 0x5B, 0x96, 0xBC, 0x6D, 0x90, 0x69, 0xE0, 0x29, 0xE5, 0xEE, 0x3D, 0x72,
+        0xBC, 0xF1, 0x91, 0x3A, 0x71, 0x4F, 0x58, 0x7B, 0xD9, 0x9E, 0x43, 0x51, 0x59, 0xC2, 0x15, 0x0B,
+        0x51, 0xB5, 0x85, 0x66, 0xCF, 0x3A, 0xBD, 0x3B, 0xF2, 0x20, 0x52, 0xE1, 0xC3, 0xF7, 0x92, 0x9D,
+        0x0F, 0x53, 0xE7, 0x65, 0xB0, 0xD8, 0x75, 0xD4, 0x5B, 0x3A, 0x31, 0x5C, 0xEA, 0xBD, 0xFD, 0x76,
+        0x31, 0xE3, 0x6B, 0x7B, 0x1C, 0xF0, 0xB4, 0x39, 0xBC, 0x33, 0xE7, 0x00, 0x91, 0x63, 0x9C, 0x7B,
+        0xEB, 0xE0, 0x62, 0x4F, 0xC4, 0x45, 0x54, 0xDA, 0x1A, 0xD6, 0x68, 0x0D, 0x00, 0x2A, 0x73, 0x5C,
+        0x98, 0x43, 0x45, 0x0D, 0x74, 0x78, 0xBC, 0xDF, 0x19, 0xAF, 0xC8, 0xE3, 0xA3, 0xA0, 0xEE, 0x5C,
+        0x9E, 0x7D, 0xCA, 0xB4, 0x38, 0x7B, 0x16, 0xA7, 0xF5, 0x18, 0x1D, 0xF6, 0x33, 0x6A, 0x5C, 0xE1,
+        0xF9, 0xAB, 0xF0, 0xDE, 0xEC, 0x9D, 0x25, 0x7B, 0xE0, 0xB1, 0xCF, 0xD2, 0x14, 0xED, 0xDE, 0x0A,
+        0x59, 0x00, 0x18, 0x30, 0xA9, 0x97, 0xD4, 0x46, 0xAB, 0x1A, 0x6E, 0x47, 0x04, 0x15, 0x6A, 0xE3,
+        0x23, 0x4E, 0x24, 0xE7, 0x5B, 0xC2, 0xA6, 0xCA, 0x9F, 0x78, 0xEB, 0x1A, 0xEF, 0x93, 0xF0, 0xB0,
+        0x85, 0xA7, 0xF1, 0x07, 0x2E, 0x7A, 0x0E, 0x6F, 0xC8, 0x78, 0xC7, 0x11, 0x90, 0x06, 0x25, 0xAA,
+        0x79, 0xA3, 0xBA, 0x13, 0x7B, 0xCA, 0xFB, 0x2E, 0xF1, 0x8A, 0x46, 0xB3, 0x61, 0xCC, 0x7C, 0xC6,
+        0x5C, 0xDE, 0xCF, 0x79, 0x97, 0x48, 0x30, 0x15, 0xA5, 0xDD, 0x6A, 0x47, 0x9D, 0x05, 0x27, 0xBC,
+        0x67, 0xCD, 0xA0, 0x34, 0x67, 0x69, 0xD8, 0xC9, 0x2E, 0x5F, 0xF7, 0xD4, 0x40, 0x90, 0x19, 0x04,
+        0x8F, 0x48, 0xEB, 0x69, 0x93, 0x8C, 0x19, 0xF2, 0x98, 0xC1, 0x70, 0x22, 0x05, 0x0B, 0x06, 0xD3,
+        0x2B, 0x66, 0x27, 0xFF, 0x1E, 0x97, 0x13, 0x36, 0xAE, 0x7E, 0x81, 0x8D, 0x32, 0x00, 0x00, 0x00,
+    ];
+
+    #[rustfmt::skip]
+    const DES_ENTRY: [u8; 304] = [
+        0x79, 0xCA, 0x44, 0x32, 0x15, 0xCC, 0xEE, 0x8B, 0xBB, 0xD3, 0xF7, 0x85, 0x05, 0x1C, 0xCB, 0x31,
+        0x53, 0xB2, 0x76, 0xC7, 0x75, 0xD1, 0xF2, 0xF1, 0x05, 0x1B, 0xD8, 0x16, 0x65, 0x02, 0x5D, 0x18,
+        0x65, 0xA6, 0x82, 0x83, 0x5B, 0x96, 0xBC, 0x6D, 0x90, 0x69, 0xE0, 0x29, 0xE5, 0xEE, 0x3D, 0x72,
+        0xBC, 0xF1, 0x91, 0x3A, 0x71, 0x4F, 0x58, 0x7B, 0xD9, 0x9E, 0x43, 0x51, 0x59, 0xC2, 0x15, 0x0B,
+        0x51, 0xB5, 0x85, 0x66, 0xCF, 0x3A, 0xBD, 0x3B, 0xF2, 0x20, 0x52, 0xE1, 0xC3, 0xF7, 0x92, 0x9D,
+        0x0F, 0x53, 0xE7, 0x65, 0xB0, 0xD8, 0x75, 0xD4, 0x5B, 0x3A, 0x31, 0x5C, 0xEA, 0xBD, 0xFD, 0x76,
+        0x31, 0xE3, 0x6B, 0x7B, 0x1C, 0xF0, 0xB4, 0x39, 0xBC, 0x33, 0xE7, 0x00, 0x91, 0x63, 0x9C, 0x7B,
+        0xEB, 0xE0, 0x62, 0x4F, 0xC4, 0x45, 0x54, 0xDA, 0x1A, 0xD6, 0x68, 0x0D, 0x00, 0x2A, 0x73, 0x5C,
+        0x98, 0x43, 0x45, 0x0D, 0x74, 0x78, 0xBC, 0xDF, 0x19, 0xAF, 0xC8, 0xE3, 0xA3, 0xA0, 0xEE, 0x5C,
+        0x9E, 0x7D, 0xCA, 0xB4, 0x38, 0x7B, 0x16, 0xA7, 0xF5, 0x18, 0x1D, 0xF6, 0x33, 0x6A, 0x5C, 0xE1,
+        0xBC, 0xFB, 0xF1, 0xCB, 0xEC, 0xC9, 0x20, 0x7F, 0xE0, 0xB1, 0xCF, 0xD2, 0x14, 0xED, 0xDE, 0x0A,
+        0x59, 0x00, 0x18, 0x30, 0xA9, 0x97, 0xD4, 0x46, 0xAB, 0x1A, 0x6E, 0x47, 0x04, 0x15, 0x6A, 0xE3,
+        0x63, 0x4A, 0x61, 0xF7, 0x4B, 0x92, 0xB3, 0xCE, 0x9F, 0x78, 0xEB, 0x1A, 0xEF, 0x93, 0xF0, 0xB0,
+        0x85, 0xA7, 0xF1, 0x07, 0x2E, 0x7A, 0x0E, 0x6F, 0xC8, 0x78, 0xC7, 0x11, 0x90, 0x06, 0x25, 0xAA,
+        0x28, 0xA6, 0xAA, 0x06, 0x6B, 0xCF, 0xFA, 0x3F, 0xF1, 0x8A, 0x46, 0xB3, 0x61, 0xCC, 0x7C, 0xC6,
+        0x79, 0x97, 0x30, 0x5C, 0xDE, 0xCF, 0x48, 0x15, 0xA5, 0xDD, 0x6A, 0x47, 0x9D, 0x05, 0x27, 0xBC,
+        0x62, 0x88, 0xE4, 0x30, 0x23, 0x79, 0x8C, 0x89, 0x2E, 0x5F, 0xF7, 0xD4, 0x40, 0x90, 0x19, 0x04,
+        0x8F, 0x48, 0xEB, 0x69, 0x93, 0x8C, 0x19, 0xF2, 0x98, 0xC1, 0x70, 0x22, 0x05, 0x0B, 0x06, 0xD3,
+        0x6A, 0x66, 0x26, 0xAE, 0x4B, 0x87, 0x02, 0x66, 0xAE, 0x7E, 0x81, 0x8D, 0x32, 0x00, 0x00, 0x00,
+    ];
+
+    /// The data that is stored in the fixture entries.
+    fn fixture_plain() -> Vec<u8> {
+        let mut state: u32 = 0x1234;
+
+        (0..290)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345) & 0x7FFFFFFF;
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mixed_fixture() {
+        let mut data = MIXCRYPT_ENTRY;
+        decrypt_mixed(&mut data, FIXTURE_COMPRESSED_SIZE);
+
+        let (decompressed, _checksum) = decompress(&data[..FIXTURE_COMPRESSED_SIZE], Format::Zlib).unwrap();
+        assert_eq!(decompressed, fixture_plain());
+    }
+
+    #[test]
+    fn header_fixture() {
+        let mut data = DES_ENTRY;
+        decrypt_header(&mut data);
+
+        let (decompressed, _checksum) = decompress(&data[..FIXTURE_COMPRESSED_SIZE], Format::Zlib).unwrap();
+        assert_eq!(decompressed, fixture_plain());
+    }
+
+    #[test]
+    fn block_known_answer() {
+        // Computed with a bit-by-bit reference implementation of a single DES
+        // round using the standard tables and a zeroed key.
+        let mut block = [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+        des_block(&mut block);
+        assert_eq!(block, [0x51, 0x76, 0x05, 0x76, 0x98, 0xEA, 0xD9, 0xEB]);
+    }
+
+    #[test]
+    fn block_is_involution() {
+        let original = [0xDE, 0xAD, 0xBE, 0xEF, 0x00, 0x2B, 0x6C, 0xFF];
+        let mut block = original;
+        des_block(&mut block);
+        assert_ne!(block, original);
+        des_block(&mut block);
+        assert_eq!(block, original);
+    }
+
+    #[test]
+    fn cycle() {
+        assert_eq!(encryption_cycle(9), 1);
+        assert_eq!(encryption_cycle(99), 1);
+        assert_eq!(encryption_cycle(100), 4);
+        assert_eq!(encryption_cycle(9999), 5);
+        assert_eq!(encryption_cycle(10000), 14);
+        assert_eq!(encryption_cycle(999999), 15);
+        assert_eq!(encryption_cycle(1000000), 22);
+    }
+
+    #[test]
+    fn mixed_round_trip() {
+        for size in [10, 500, 40_000, 200_000] {
+            let (plain, mut data, compressed_size) = fixture(size);
+            let original = data.clone();
+
+            encrypt_mixed(&mut data, compressed_size);
+            assert_ne!(data, original);
+
+            decrypt_mixed(&mut data, compressed_size);
+            assert_eq!(data, original);
+
+            let (decompressed, _checksum) = decompress(&data[..compressed_size], Format::Zlib).unwrap();
+            assert_eq!(decompressed, plain);
+        }
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let (plain, mut data, compressed_size) = fixture(40_000);
+        let original = data.clone();
+
+        blocks(&mut data).take(ENCRYPTED_HEADER_BLOCKS).for_each(des_block);
+        assert_ne!(data[..ENCRYPTED_HEADER_BLOCKS * 8], original[..ENCRYPTED_HEADER_BLOCKS * 8]);
+        assert_eq!(data[ENCRYPTED_HEADER_BLOCKS * 8..], original[ENCRYPTED_HEADER_BLOCKS * 8..]);
+
+        decrypt_header(&mut data);
+        let (decompressed, _checksum) = decompress(&data[..compressed_size], Format::Zlib).unwrap();
+        assert_eq!(decompressed, plain);
+    }
+}
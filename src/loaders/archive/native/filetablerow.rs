@@ -1,12 +1,79 @@
-use procedural::ByteConvertable;
+use super::header::ArchiveVersion;
+use crate::loaders::archive::path::{decode_game_path, encode_path};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError};
 
 /// Represents file information about each of the files stored in the GRF.
-#[derive(Clone, Debug, ByteConvertable)]
-pub(super) struct FileTableRow {
+///
+/// The layout only differs in the size of the offset, which is 32 bit for
+/// version 0x200 and 64 bit for version 0x300.
+#[derive(Clone, Debug)]
+pub struct FileTableRow {
     pub file_name: String,
     pub compressed_size: u32,
     pub compressed_size_aligned: u32,
     pub uncompressed_size: u32,
     pub flags: u8,
-    pub offset: u32,
+    pub offset: u64,
+}
+
+impl FileTableRow {
+    /// Only the first 20 blocks of the entry are DES encrypted.
+    const FLAG_DES: u8 = 0x04;
+    /// Entries without this flag are directories.
+    const FLAG_FILE: u8 = 0x01;
+    /// The entry is DES encrypted and partially shuffled.
+    const FLAG_MIXCRYPT: u8 = 0x02;
+
+    pub fn is_file(&self) -> bool {
+        self.flags & Self::FLAG_FILE != 0
+    }
+
+    pub fn is_mixcrypt(&self) -> bool {
+        self.flags & Self::FLAG_MIXCRYPT != 0
+    }
+
+    pub fn is_des(&self) -> bool {
+        self.flags & Self::FLAG_DES != 0
+    }
+
+    pub(super) fn from_bytes(byte_stream: &mut ByteStream, version: ArchiveVersion) -> Result<Self, ConversionError> {
+        // File names are stored as null terminated CP949 strings.
+        let file_name = decode_game_path(&String::from_bytes(byte_stream, None)?);
+        let compressed_size = u32::from_bytes(byte_stream, None)?;
+        let compressed_size_aligned = u32::from_bytes(byte_stream, None)?;
+        let uncompressed_size = u32::from_bytes(byte_stream, None)?;
+        let flags = u8::from_bytes(byte_stream, None)?;
+        let offset = match version {
+            ArchiveVersion::Version200 => u32::from_bytes(byte_stream, None)? as u64,
+            ArchiveVersion::Version300 => u64::from_bytes(byte_stream, None)?,
+        };
+
+        Ok(Self {
+            file_name,
+            compressed_size,
+            compressed_size_aligned,
+            uncompressed_size,
+            flags,
+            offset,
+        })
+    }
+
+    pub(super) fn to_bytes(&self, version: ArchiveVersion) -> Vec<u8> {
+        let mut bytes = encode_path(&self.file_name);
+        bytes.push(0);
+        bytes.extend(self.compressed_size.to_bytes(None));
+        bytes.extend(self.compressed_size_aligned.to_bytes(None));
+        bytes.extend(self.uncompressed_size.to_bytes(None));
+        bytes.extend(self.flags.to_bytes(None));
+
+        match version {
+            ArchiveVersion::Version200 => {
+                let offset = u32::try_from(self.offset).expect("grf 0x200 is limited to 4GB, use 0x300 instead");
+                bytes.extend(offset.to_bytes(None));
+            }
+            ArchiveVersion::Version300 => bytes.extend(self.offset.to_bytes(None)),
+        }
+
+        bytes
+    }
 }
@@ -1,27 +1,158 @@
 use derive_new::new;
-use procedural::{ByteConvertable, FixedByteSize};
+
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, FixedByteSize};
+
+/// Number of reserved entries that version 0x200 adds to the stored file
+/// count.
+const RESERVED_FILE_COUNT: u32 = 7;
+
+/// Versions of the GRF format that can be read and written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArchiveVersion {
+    /// Version 0x200, using 32 bit offsets.
+    #[default]
+    Version200,
+    /// Version 0x300, using 64 bit offsets to allow archives larger than 4GB.
+    Version300,
+}
+
+impl ArchiveVersion {
+    pub fn from_raw(version: u32) -> Option<Self> {
+        match version {
+            0x200 => Some(Self::Version200),
+            0x300 => Some(Self::Version300),
+            _ => None,
+        }
+    }
+
+    pub fn raw(self) -> u32 {
+        match self {
+            Self::Version200 => 0x200,
+            Self::Version300 => 0x300,
+        }
+    }
+}
 
 /// Represents the Header of the GRF file.
-#[derive(Clone, ByteConvertable, FixedByteSize, new)]
+///
+/// Both versions share the same size, but 0x300 uses the space of the seed
+/// to store a 64 bit file table offset.
+#[derive(Clone, new)]
 pub(super) struct Header {
     #[new(default)]
     encryption: [u8; 14],
-    file_table_offset: u32,
-    reserved_files: u32,
+    file_table_offset: u64,
     file_count: u32,
-    version: u32,
+    version: ArchiveVersion,
 }
 
 impl Header {
-    pub fn validate_version(&self) {
-        assert_eq!(self.version, 0x200, "invalid grf version");
+    pub fn get_version(&self) -> ArchiveVersion {
+        self.version
     }
 
-    pub fn get_file_table_offset(&self) -> usize {
-        self.file_table_offset as usize
+    pub fn get_file_table_offset(&self) -> u64 {
+        self.file_table_offset
     }
 
     pub fn get_file_count(&self) -> usize {
-        (self.file_count - self.reserved_files) as usize - 7
+        self.file_count as usize
+    }
+}
+
+impl ByteConvertable for Header {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        assert!(length_hint.is_none(), "header may not have a length hint");
+
+        let encryption = <[u8; 14]>::from_bytes(byte_stream, None)?;
+        // The layout of the next 12 bytes depends on the version, which is stored
+        // after them.
+        let layout_offset = byte_stream.get_offset();
+        let layout = byte_stream.slice(12)?.to_vec();
+        let version_offset = byte_stream.get_offset();
+        let raw_version = u32::from_bytes(byte_stream, None)?;
+        let version = ArchiveVersion::from_raw(raw_version)
+            .ok_or_else(|| ConversionError::invalid_data(version_offset, format!("unsupported grf version {raw_version:#x}")))?;
+
+        let mut layout_stream = ByteStream::new(&layout);
+        let (file_table_offset, file_count) = match version {
+            ArchiveVersion::Version200 => {
+                let file_table_offset = u32::from_bytes(&mut layout_stream, None)? as u64;
+                let seed = u32::from_bytes(&mut layout_stream, None)?;
+                let raw_file_count = u32::from_bytes(&mut layout_stream, None)?;
+                let file_count = raw_file_count
+                    .checked_sub(seed)
+                    .and_then(|file_count| file_count.checked_sub(RESERVED_FILE_COUNT))
+                    .ok_or_else(|| ConversionError::invalid_data(layout_offset + 4, "invalid file count"))?;
+                (file_table_offset, file_count)
+            }
+            ArchiveVersion::Version300 => {
+                let file_table_offset = u64::from_bytes(&mut layout_stream, None)?;
+                let file_count = u32::from_bytes(&mut layout_stream, None)?;
+                (file_table_offset, file_count)
+            }
+        };
+
+        Ok(Self {
+            encryption,
+            file_table_offset,
+            file_count,
+            version,
+        })
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert!(length_hint.is_none(), "header may not have a length hint");
+
+        let mut bytes = self.encryption.to_bytes(None);
+
+        match self.version {
+            ArchiveVersion::Version200 => {
+                let file_table_offset = u32::try_from(self.file_table_offset).expect("grf 0x200 is limited to 4GB, use 0x300 instead");
+                bytes.extend(file_table_offset.to_bytes(None));
+                bytes.extend(0u32.to_bytes(None));
+                bytes.extend((self.file_count + RESERVED_FILE_COUNT).to_bytes(None));
+            }
+            ArchiveVersion::Version300 => {
+                bytes.extend(self.file_table_offset.to_bytes(None));
+                bytes.extend(self.file_count.to_bytes(None));
+            }
+        }
+
+        bytes.extend(self.version.raw().to_bytes(None));
+        bytes
+    }
+}
+
+impl const FixedByteSize for Header {
+    fn size_in_bytes() -> usize {
+        30
+    }
+}
+
+#[cfg(test)]
+mod conversion {
+    use super::{ArchiveVersion, Header};
+    use crate::loaders::{ByteConvertable, ByteStream, FixedByteSize};
+
+    fn round_trip(version: ArchiveVersion, file_table_offset: u64) {
+        let header = Header::new(file_table_offset, 42, version);
+        let bytes = header.to_bytes(None);
+        assert_eq!(bytes.len(), Header::size_in_bytes());
+
+        let parsed = Header::from_bytes(&mut ByteStream::new(&bytes), None).unwrap();
+        assert_eq!(parsed.get_version(), version);
+        assert_eq!(parsed.get_file_table_offset(), file_table_offset);
+        assert_eq!(parsed.get_file_count(), 42);
+    }
+
+    #[test]
+    fn version_200() {
+        round_trip(ArchiveVersion::Version200, 0x1234_5678);
+    }
+
+    #[test]
+    fn version_300() {
+        round_trip(ArchiveVersion::Version300, 0x1_2345_6789);
     }
 }
@@ -1,6 +1,7 @@
 //! A GRF file containing game assets.
 mod assettable;
 mod builder;
+mod des;
 mod filetablerow;
 mod header;
 
@@ -9,16 +10,17 @@ use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
-use yazi::{decompress, Format};
+use rayon::prelude::*;
 
 use self::assettable::AssetTable;
 pub use self::builder::NativeArchiveBuilder;
-use self::filetablerow::FileTableRow;
+pub use self::filetablerow::FileTableRow;
+pub use self::header::ArchiveVersion;
 use self::header::Header;
 #[cfg(feature = "debug")]
 use crate::debug::*;
-use crate::loaders::archive::Archive;
-use crate::loaders::{ByteConvertable, ByteStream, FixedByteSize};
+use crate::loaders::archive::{archive_io_error, corrupt_archive, inflate, read_asset_data, Archive};
+use crate::loaders::{AuditIssue, ByteConvertable, ByteStream, ConversionError, FixedByteSize, LoaderError};
 
 /// Represents a GRF file. GRF Files are an archive to store game assets.
 /// Each GRF contains an [`ArchiveHeader`] with metadata (number of files, size,
@@ -27,6 +29,7 @@ use crate::loaders::{ByteConvertable, ByteStream, FixedByteSize};
 type FileTable = HashMap<String, FileTableRow>;
 
 pub struct NativeArchive {
+    version: ArchiveVersion,
     file_table: FileTable,
     os_file_handler: File,
 }
@@ -35,86 +38,263 @@ const MAGIC_BYTES: &[u8] = b"Master of Magic\0";
 const UNPACKED_SIZE_OF_MAGIC_STRING: usize = MAGIC_BYTES.len();
 const UNPACKED_SIZE_OF_ARCHIVEHEADER: usize = Header::size_in_bytes();
 const UNPACKED_SIZE_OF_FILETABLE: usize = AssetTable::size_in_bytes();
+/// Version 0x300 stores an additional (unused) 32 bit value in front of the
+/// file table.
+const UNPACKED_SIZE_OF_FILETABLE_PREFIX: usize = 4;
+/// All offsets inside of the archive are relative to the end of the header.
+const UNPACKED_SIZE_OF_FULL_HEADER: u64 = (UNPACKED_SIZE_OF_MAGIC_STRING + UNPACKED_SIZE_OF_ARCHIVEHEADER) as u64;
 
-impl Archive for NativeArchive {
-    // Keeping the convenience of using [`loaders::stream::ByteStream`]
-    /// while being able to read without buffering the entire file.
-    fn from_path(path: &Path) -> Self {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new_dynamic(format!("load game data from {MAGENTA}{0}{NONE}", path.display()));
-        let mut file = File::open(path).unwrap();
+impl NativeArchive {
+    pub fn get_version(&self) -> ArchiveVersion {
+        self.version
+    }
+
+    /// Information about every entry in the archive, including directories.
+    pub fn get_file_table_rows(&self) -> impl Iterator<Item = &FileTableRow> {
+        self.file_table.values()
+    }
+
+    fn read_file(&self, path: &str, file_information: &FileTableRow) -> Result<Vec<u8>, LoaderError> {
+        if file_information.compressed_size > file_information.compressed_size_aligned {
+            return Err(LoaderError::Corrupt {
+                path: path.to_owned(),
+                offset: file_information.offset as usize,
+                reason: format!(
+                    "compressed size {} is larger than the aligned size {}",
+                    file_information.compressed_size, file_information.compressed_size_aligned
+                ),
+            });
+        }
 
-        let mut magic_number_buffer = [0u8; UNPACKED_SIZE_OF_MAGIC_STRING];
-        file.read_exact(&mut magic_number_buffer).unwrap();
+        let mut compressed_file_buffer = vec![0u8; file_information.compressed_size_aligned as usize];
 
-        let mut file_header_buffer = [0u8; UNPACKED_SIZE_OF_ARCHIVEHEADER];
-        file.read_exact(&mut file_header_buffer).unwrap();
-        let file_header = Header::from_bytes(&mut ByteStream::new(&file_header_buffer), None);
-        file_header.validate_version();
+        let position = file_information.offset + UNPACKED_SIZE_OF_FULL_HEADER;
+        read_asset_data(&self.os_file_handler, path, position, &mut compressed_file_buffer)?;
 
-        let _ = file.seek(SeekFrom::Current(file_header.get_file_table_offset() as i64)).unwrap();
-        let mut file_table_buffer = [0u8; UNPACKED_SIZE_OF_FILETABLE];
+        let compressed_size = file_information.compressed_size as usize;
 
-        file.read_exact(&mut file_table_buffer).unwrap();
-        let file_table = AssetTable::from_bytes(&mut ByteStream::new(&file_table_buffer), None);
+        if file_information.is_mixcrypt() {
+            des::decrypt_mixed(&mut compressed_file_buffer, compressed_size);
+        } else if file_information.is_des() {
+            des::decrypt_header(&mut compressed_file_buffer);
+        }
 
-        let mut compressed_file_table_buffer = vec![0u8; file_table.get_compressed_size()];
-        file.read_exact(&mut compressed_file_table_buffer).unwrap();
-        let (decompressed, _checksum) = decompress(&compressed_file_table_buffer, Format::Zlib).unwrap();
+        inflate(path, &compressed_file_buffer[..compressed_size])
+    }
 
-        let file_count = file_header.get_file_count();
+    /// Checks that the data of an entry is inside of the archive and inflates
+    /// to the size stored in the file table.
+    fn verify_file(&self, path: &str, file_information: &FileTableRow, archive_size: u64) -> Result<(), AuditIssue> {
+        let invalid_entry = |reason: String| AuditIssue::InvalidEntry {
+            path: path.to_owned(),
+            reason,
+        };
 
-        let mut file_table_byte_stream = ByteStream::new(&decompressed);
-        let mut assets = HashMap::with_capacity(file_count);
+        if file_information.compressed_size > file_information.compressed_size_aligned {
+            return Err(invalid_entry(format!(
+                "compressed size {} is larger than the aligned size {}",
+                file_information.compressed_size, file_information.compressed_size_aligned
+            )));
+        }
 
-        for _index in 0..file_count {
-            let file_information = FileTableRow::from_bytes(&mut file_table_byte_stream, None);
-            let file_name = file_information.file_name.to_lowercase();
+        let data_end = file_information
+            .offset
+            .saturating_add(UNPACKED_SIZE_OF_FULL_HEADER)
+            .saturating_add(file_information.compressed_size_aligned as u64);
 
-            assets.insert(file_name, file_information);
+        if data_end > archive_size {
+            return Err(invalid_entry(format!(
+                "data ends at {data_end:#x}, but the archive is only {archive_size:#x} bytes long"
+            )));
         }
 
-        #[cfg(feature = "debug")]
-        timer.stop();
+        let data = self.read_file(path, file_information).map_err(|error| AuditIssue::Unreadable {
+            path: path.to_owned(),
+            reason: error.to_string(),
+        })?;
 
-        // TODO: only take 64..? bytes so that loaded game archives can be extended
-        // aswell
-        Self {
-            file_table: assets,
-            os_file_handler: file,
+        if data.len() != file_information.uncompressed_size as usize {
+            return Err(invalid_entry(format!(
+                "inflated to {} bytes, but the file table expects {}",
+                data.len(),
+                file_information.uncompressed_size
+            )));
         }
+
+        Ok(())
+    }
+}
+
+/// Reads the header and the file table of a GRF file.
+fn load_file_table(file: &mut File, path: &Path) -> Result<(Header, FileTable), LoaderError> {
+    let io_error = |error| archive_io_error(path, error);
+
+    let mut magic_number_buffer = [0u8; UNPACKED_SIZE_OF_MAGIC_STRING];
+    file.read_exact(&mut magic_number_buffer).map_err(io_error)?;
+
+    let mut file_header_buffer = [0u8; UNPACKED_SIZE_OF_ARCHIVEHEADER];
+    file.read_exact(&mut file_header_buffer).map_err(io_error)?;
+    let file_header = Header::from_bytes(&mut ByteStream::new(&file_header_buffer), None)
+        .map_err(|error| corrupt_archive(path, error.add_offset(UNPACKED_SIZE_OF_MAGIC_STRING)))?;
+    let version = file_header.get_version();
+
+    let mut file_table_position = file_header.get_file_table_offset() + UNPACKED_SIZE_OF_FULL_HEADER;
+
+    if version == ArchiveVersion::Version300 {
+        file_table_position += UNPACKED_SIZE_OF_FILETABLE_PREFIX as u64;
+    }
+
+    let truncated = |_| {
+        corrupt_archive(
+            path,
+            ConversionError::invalid_data(file_table_position as usize, "file table is truncated"),
+        )
+    };
+
+    let _ = file.seek(SeekFrom::Start(file_table_position)).map_err(io_error)?;
+
+    let mut file_table_buffer = [0u8; UNPACKED_SIZE_OF_FILETABLE];
+    file.read_exact(&mut file_table_buffer).map_err(truncated)?;
+    let file_table = AssetTable::from_bytes(&mut ByteStream::new(&file_table_buffer), None)
+        .map_err(|error| corrupt_archive(path, error.add_offset(file_table_position as usize)))?;
+
+    let mut compressed_file_table_buffer = vec![0u8; file_table.get_compressed_size()];
+    file.read_exact(&mut compressed_file_table_buffer).map_err(truncated)?;
+    let decompressed = inflate(&path.display().to_string(), &compressed_file_table_buffer)?;
+
+    let file_count = file_header.get_file_count();
+
+    let mut file_table_byte_stream = ByteStream::new(&decompressed);
+    let mut assets = HashMap::with_capacity(file_count);
+
+    for _index in 0..file_count {
+        let file_information =
+            FileTableRow::from_bytes(&mut file_table_byte_stream, version).map_err(|error| corrupt_archive(path, error))?;
+        let file_name = file_information.file_name.to_lowercase();
+
+        assets.insert(file_name, file_information);
     }
 
-    /// Returns an asset from the archive.
-    /// Checks if the file is cached and if so, returns it from memory.
-    /// If not, read from the [`GameArchive`]
-    fn get_file_by_path(&mut self, path: &str) -> Option<Vec<u8>> {
-        self.file_table.get(path).and_then(|file_information| {
-            let mut compressed_file_buffer = vec![0u8; file_information.compressed_size_aligned as usize];
+    Ok((file_header, assets))
+}
 
-            // TODO: Figure out what the GRF_FLAG_MIXCRYPT flag actually means and load the
-            // file correctly
-            if file_information.flags > 1 {
-                return None;
-            }
+impl Archive for NativeArchive {
+    // Keeping the convenience of using [`loaders::stream::ByteStream`]
+    /// while being able to read without buffering the entire file.
+    fn from_path(path: &Path) -> Result<Self, LoaderError> {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("load game data from {MAGENTA}{0}{NONE}", path.display()));
 
-            let position = file_information.offset as u64 + UNPACKED_SIZE_OF_MAGIC_STRING as u64 + UNPACKED_SIZE_OF_ARCHIVEHEADER as u64;
-            self.os_file_handler.seek(SeekFrom::Start(position)).unwrap();
-            self.os_file_handler.read_exact(&mut compressed_file_buffer).unwrap();
+        let mut file = File::open(path).map_err(|error| archive_io_error(path, error))?;
+        let (file_header, file_table) = load_file_table(&mut file, path)?;
 
-            let (uncompressed_file_buffer, _checksum) = decompress(&compressed_file_buffer, Format::Zlib).unwrap();
+        #[cfg(feature = "debug")]
+        timer.stop();
 
-            Some(uncompressed_file_buffer)
+        Ok(Self {
+            version: file_header.get_version(),
+            file_table,
+            os_file_handler: file,
         })
     }
 
+    /// Returns an asset from the archive. Files are read at their offset
+    /// without seeking, so multiple threads can read at the same time.
+    fn get_file_by_path(&self, path: &str) -> Result<Vec<u8>, LoaderError> {
+        let file_information = self
+            .file_table
+            .get(path)
+            .filter(|file_information| file_information.is_file())
+            .ok_or_else(|| LoaderError::NotFound { path: path.to_owned() })?;
+
+        self.read_file(path, file_information)
+    }
+
     fn get_lua_files(&self, lua_files: &mut Vec<String>) {
         let files = self
             .file_table
             .iter()
-            .filter(|(file_name, row)| file_name.ends_with(".lub") && row.flags == 0x01)
+            .filter(|(file_name, row)| file_name.ends_with(".lub") && row.is_file())
             .map(|(file_name, _)| file_name.clone());
 
         lua_files.extend(files);
     }
+
+    fn get_file_paths(&self, file_paths: &mut Vec<String>) {
+        let files = self
+            .file_table
+            .iter()
+            .filter(|(_, row)| row.is_file())
+            .map(|(file_name, _)| file_name.clone());
+
+        file_paths.extend(files);
+    }
+
+    /// Checks every file of the file table in parallel.
+    fn verify(&self, issues: &mut Vec<AuditIssue>) -> std::io::Result<()> {
+        let archive_size = self.os_file_handler.metadata()?.len();
+
+        let file_issues: Vec<AuditIssue> = self
+            .file_table
+            .par_iter()
+            .filter(|(_, file_information)| file_information.is_file())
+            .filter_map(|(file_name, file_information)| self.verify_file(file_name, file_information, archive_size).err())
+            .collect();
+
+        issues.extend(file_issues);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod verification {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+
+    use super::{ArchiveVersion, NativeArchive, NativeArchiveBuilder, UNPACKED_SIZE_OF_FULL_HEADER};
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::{AuditIssue, LoaderError, TemporaryPath};
+
+    #[test]
+    fn corrupt_data() {
+        let path = TemporaryPath::new("verification.grf");
+
+        let mut builder = NativeArchiveBuilder::from_path(&path, ArchiveVersion::default());
+        builder.add_file("data\\first.txt", b"first".to_vec());
+        builder.add_file("data\\second.txt", b"second".to_vec());
+        builder.save();
+        drop(builder);
+
+        let mut issues = Vec::new();
+        NativeArchive::from_path(&path).unwrap().verify(&mut issues).unwrap();
+        assert_eq!(issues, Vec::new());
+
+        // The first file is stored directly after the header, so this overwrites its
+        // zlib header.
+        let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(UNPACKED_SIZE_OF_FULL_HEADER)).unwrap();
+        file.write_all(&[0xFF, 0xFF]).unwrap();
+        drop(file);
+
+        let mut issues = Vec::new();
+        NativeArchive::from_path(&path).unwrap().verify(&mut issues).unwrap();
+        assert!(matches!(issues.as_slice(), [AuditIssue::Unreadable { path, .. }] if path == "data\\first.txt"));
+    }
+
+    #[test]
+    fn compressed_size_larger_than_aligned_size() {
+        let path = TemporaryPath::new("compressed_size.grf");
+
+        let mut builder = NativeArchiveBuilder::from_path(&path, ArchiveVersion::default());
+        builder.add_file("data\\first.txt", b"first".to_vec());
+        builder.save();
+        drop(builder);
+
+        let archive = NativeArchive::from_path(&path).unwrap();
+        let mut file_information = archive.file_table["data\\first.txt"].clone();
+        file_information.compressed_size = file_information.compressed_size_aligned + 1;
+
+        let result = archive.read_file("data\\first.txt", &file_information);
+        assert!(matches!(result, Err(LoaderError::Corrupt { .. })));
+    }
 }
@@ -0,0 +1,140 @@
+//! Patch files distributed by private servers. Both `.thor` and `.rgz`
+//! patches can be layered on top of other archives or merged into a GRF.
+mod rgz;
+mod thor;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+
+use super::native::{ArchiveVersion, NativeArchiveBuilder};
+use super::path::decode_path;
+use super::{inflate, read_asset_data, Archive, Writable};
+#[cfg(feature = "debug")]
+use crate::debug::*;
+use crate::loaders::{ByteStream, ConversionError, LoaderError};
+
+/// A single file inside of a patch.
+enum PatchEntry {
+    /// The file is zlib compressed and stored inside the patch file.
+    Stored { offset: u64, compressed_size: usize },
+    /// The file was already decompressed while loading the patch.
+    Unpacked(Vec<u8>),
+    /// The patch removes the file.
+    Removed,
+}
+
+pub struct PatchArchive {
+    file_table: HashMap<String, PatchEntry>,
+    os_file_handler: Option<File>,
+    target_archive: Option<String>,
+}
+
+/// Reads a CP949 encoded string with a known length that may or may not be
+/// null terminated.
+fn read_string(byte_stream: &mut ByteStream, length: usize) -> Result<String, ConversionError> {
+    let bytes: Vec<u8> = byte_stream.slice(length)?.iter().copied().take_while(|byte| *byte != 0).collect();
+    Ok(decode_path(&bytes))
+}
+
+impl PatchArchive {
+    /// The name of the GRF the patch wants to be merged into. [`None`] means
+    /// that the files belong into the client folder or the default GRF.
+    pub fn get_target_archive(&self) -> Option<&str> {
+        self.target_archive.as_deref()
+    }
+
+    /// Merges the patch into the GRF at `archive_path`, keeping all files that
+    /// are not changed by the patch. If the GRF does not exist yet, a new one
+    /// will be created.
+    pub fn merge_into(&self, archive_path: &Path) {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("merge patch into {MAGENTA}{}{NONE}", archive_path.display()));
+
+        let mut builder = match archive_path.exists() {
+            true => NativeArchiveBuilder::open(archive_path),
+            false => NativeArchiveBuilder::from_path(archive_path, ArchiveVersion::default()),
+        };
+
+        for (file_path, entry) in &self.file_table {
+            if let PatchEntry::Removed = entry {
+                builder.remove_file(file_path);
+                continue;
+            }
+
+            match self.get_file_by_path(file_path) {
+                Ok(data) => builder.add_file(file_path, data),
+                Err(_error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!("[{}error{}] {_error}", RED, NONE);
+                }
+            }
+        }
+
+        builder.save();
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+    }
+}
+
+impl Archive for PatchArchive {
+    fn from_path(path: &Path) -> Result<Self, LoaderError> {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("load patch from {MAGENTA}{0}{NONE}", path.display()));
+
+        let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+        let archive = match extension.as_deref() {
+            Some("thor") => thor::load(path)?,
+            Some("rgz") => rgz::load(path)?,
+            _ => {
+                return Err(LoaderError::UnsupportedFormat {
+                    path: path.display().to_string(),
+                    format: "patch without a .thor or .rgz extension".to_owned(),
+                });
+            }
+        };
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+
+        Ok(archive)
+    }
+
+    fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError> {
+        let not_found = || LoaderError::NotFound {
+            path: asset_path.to_owned(),
+        };
+
+        match self.file_table.get(asset_path).ok_or_else(not_found)? {
+            PatchEntry::Stored { offset, compressed_size } => {
+                let os_file_handler = self.os_file_handler.as_ref().unwrap();
+                let mut compressed_file_buffer = vec![0u8; *compressed_size];
+
+                read_asset_data(os_file_handler, asset_path, *offset, &mut compressed_file_buffer)?;
+                inflate(asset_path, &compressed_file_buffer)
+            }
+            PatchEntry::Unpacked(data) => Ok(data.clone()),
+            PatchEntry::Removed => Err(LoaderError::Removed {
+                path: asset_path.to_owned(),
+            }),
+        }
+    }
+
+    fn get_lua_files(&self, lua_files: &mut Vec<String>) {
+        let mut file_paths = Vec::new();
+        self.get_file_paths(&mut file_paths);
+
+        lua_files.extend(file_paths.into_iter().filter(|file_path| file_path.ends_with(".lub")));
+    }
+
+    fn get_file_paths(&self, file_paths: &mut Vec<String>) {
+        let files = self
+            .file_table
+            .iter()
+            .filter(|(_, entry)| !matches!(entry, PatchEntry::Removed))
+            .map(|(file_name, _)| file_name.clone());
+
+        file_paths.extend(files);
+    }
+}
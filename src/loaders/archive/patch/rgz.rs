@@ -0,0 +1,104 @@
+//! RGZ patches, which are gzip compressed streams of files and directories.
+use std::collections::HashMap;
+use std::path::Path;
+
+use yazi::{decompress, Format};
+
+use super::{read_string, PatchArchive, PatchEntry};
+use crate::loaders::archive::{archive_io_error, corrupt_archive};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, LoaderError};
+
+const GZIP_MAGIC_BYTES: &[u8] = &[0x1F, 0x8B];
+const GZIP_METHOD_DEFLATE: u8 = 8;
+
+const GZIP_FLAG_HEADER_CRC: u8 = 0x02;
+const GZIP_FLAG_EXTRA: u8 = 0x04;
+const GZIP_FLAG_NAME: u8 = 0x08;
+const GZIP_FLAG_COMMENT: u8 = 0x10;
+
+const ENTRY_FILE: u8 = b'f';
+const ENTRY_DIRECTORY: u8 = b'd';
+const ENTRY_END: u8 = b'e';
+
+/// Strips the gzip header and inflates the deflate stream following it.
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, ConversionError> {
+    let mut byte_stream = ByteStream::new(data);
+
+    if byte_stream.slice(2)? != GZIP_MAGIC_BYTES {
+        return Err(ConversionError::invalid_data(0, "rgz patch is not gzip compressed"));
+    }
+
+    if u8::from_bytes(&mut byte_stream, None)? != GZIP_METHOD_DEFLATE {
+        return Err(ConversionError::invalid_data(2, "unsupported gzip compression method"));
+    }
+
+    let flags = u8::from_bytes(&mut byte_stream, None)?;
+    // Modification time, extra flags and operating system.
+    byte_stream.skip(6)?;
+
+    if flags & GZIP_FLAG_EXTRA != 0 {
+        let extra_length = u16::from_bytes(&mut byte_stream, None)? as usize;
+        byte_stream.skip(extra_length)?;
+    }
+
+    if flags & GZIP_FLAG_NAME != 0 {
+        let _name = String::from_bytes(&mut byte_stream, None)?;
+    }
+
+    if flags & GZIP_FLAG_COMMENT != 0 {
+        let _comment = String::from_bytes(&mut byte_stream, None)?;
+    }
+
+    if flags & GZIP_FLAG_HEADER_CRC != 0 {
+        byte_stream.skip(2)?;
+    }
+
+    let deflated_offset = byte_stream.get_offset();
+    let deflated = byte_stream.slice(byte_stream.get_remaining_length())?;
+    let (decompressed, _checksum) = decompress(deflated, Format::Raw)
+        .map_err(|error| ConversionError::invalid_data(deflated_offset, format!("failed to inflate rgz patch: {error:?}")))?;
+    Ok(decompressed)
+}
+
+pub(super) fn load(path: &Path) -> Result<PatchArchive, LoaderError> {
+    let data = std::fs::read(path).map_err(|error| archive_io_error(path, error))?;
+    let file_table = gunzip(&data)
+        .and_then(|data| read_file_table(&data))
+        .map_err(|error| corrupt_archive(path, error))?;
+
+    Ok(PatchArchive {
+        file_table,
+        os_file_handler: None,
+        target_archive: None,
+    })
+}
+
+fn read_file_table(data: &[u8]) -> Result<HashMap<String, PatchEntry>, ConversionError> {
+    let mut byte_stream = ByteStream::new(data);
+    let mut file_table = HashMap::new();
+
+    while !byte_stream.is_empty() {
+        let entry_offset = byte_stream.get_offset();
+        let entry_type = u8::from_bytes(&mut byte_stream, None)?;
+        let name_length = u8::from_bytes(&mut byte_stream, None)? as usize;
+        let name = read_string(&mut byte_stream, name_length)?;
+
+        match entry_type {
+            ENTRY_FILE => {
+                let size = u32::from_bytes(&mut byte_stream, None)? as usize;
+                let data = byte_stream.slice(size)?.to_vec();
+                file_table.insert(name.to_lowercase(), PatchEntry::Unpacked(data));
+            }
+            ENTRY_DIRECTORY => {}
+            ENTRY_END => break,
+            invalid => {
+                return Err(ConversionError::invalid_data(
+                    entry_offset,
+                    format!("invalid entry type {invalid:#x}"),
+                ));
+            }
+        }
+    }
+
+    Ok(file_table)
+}
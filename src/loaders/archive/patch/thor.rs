@@ -0,0 +1,109 @@
+//! Thor patches, as created by the Thor patcher.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use yazi::{decompress, Format};
+
+use super::{read_string, PatchArchive, PatchEntry};
+use crate::loaders::archive::{archive_io_error, corrupt_archive};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, LoaderError};
+
+const MAGIC_BYTES: &[u8] = b"ASSF (C) 2007 Aeomin DEV";
+/// Enough bytes to contain the header and the entry of a single file patch.
+const HEADER_BUFFER_SIZE: u64 = 1024;
+
+const MODE_SINGLE_FILE: i16 = 0x30;
+const MODE_MULTIPLE_FILES: i16 = 0x21;
+
+const FLAG_REMOVE: u8 = 0x01;
+
+pub(super) fn load(path: &Path) -> Result<PatchArchive, LoaderError> {
+    let io_error = |error| archive_io_error(path, error);
+
+    let mut file = File::open(path).map_err(io_error)?;
+    let file_size = file.metadata().map_err(io_error)?.len();
+
+    let mut header_buffer = vec![0u8; HEADER_BUFFER_SIZE.min(file_size) as usize];
+    file.read_exact(&mut header_buffer).map_err(io_error)?;
+
+    let (file_table, target_archive) = read_file_table(&mut file, &header_buffer).map_err(|error| corrupt_archive(path, error))?;
+
+    Ok(PatchArchive {
+        file_table,
+        os_file_handler: Some(file),
+        target_archive,
+    })
+}
+
+fn read_file_table(file: &mut File, header_buffer: &[u8]) -> Result<(HashMap<String, PatchEntry>, Option<String>), ConversionError> {
+    let mut byte_stream = ByteStream::new(header_buffer);
+
+    if byte_stream.slice(MAGIC_BYTES.len())? != MAGIC_BYTES {
+        return Err(ConversionError::invalid_data(0, "invalid magic bytes"));
+    }
+
+    let _use_grf_merging = u8::from_bytes(&mut byte_stream, None)? == 1;
+    let file_count = u32::from_bytes(&mut byte_stream, None)? as usize;
+    let mode_offset = byte_stream.get_offset();
+    let mode = i16::from_bytes(&mut byte_stream, None)?;
+    let target_archive_length = u8::from_bytes(&mut byte_stream, None)? as usize;
+    let target_archive = read_string(&mut byte_stream, target_archive_length)?;
+    let target_archive = (!target_archive.is_empty()).then_some(target_archive);
+
+    let mut file_table = HashMap::with_capacity(file_count);
+
+    match mode {
+        MODE_SINGLE_FILE => {
+            byte_stream.skip(1)?;
+
+            let compressed_size = i32::from_bytes(&mut byte_stream, None)? as usize;
+            let _uncompressed_size = i32::from_bytes(&mut byte_stream, None)?;
+            let file_name_length = u8::from_bytes(&mut byte_stream, None)? as usize;
+            let file_name = read_string(&mut byte_stream, file_name_length)?;
+            let offset = byte_stream.get_offset() as u64;
+
+            file_table.insert(file_name.to_lowercase(), PatchEntry::Stored { offset, compressed_size });
+        }
+        MODE_MULTIPLE_FILES => {
+            let file_table_compressed_size = i32::from_bytes(&mut byte_stream, None)? as usize;
+            let file_table_offset = i32::from_bytes(&mut byte_stream, None)? as u64;
+
+            let invalid_file_table = |reason: String| ConversionError::invalid_data(file_table_offset as usize, reason);
+
+            let mut compressed_file_table_buffer = vec![0u8; file_table_compressed_size];
+            file.seek(SeekFrom::Start(file_table_offset))
+                .and_then(|_| file.read_exact(&mut compressed_file_table_buffer))
+                .map_err(|error| invalid_file_table(error.to_string()))?;
+            let (decompressed, _checksum) = decompress(&compressed_file_table_buffer, Format::Zlib)
+                .map_err(|error| invalid_file_table(format!("failed to inflate file table: {error:?}")))?;
+
+            let mut file_table_byte_stream = ByteStream::new(&decompressed);
+
+            while !file_table_byte_stream.is_empty() {
+                let file_name_length = u8::from_bytes(&mut file_table_byte_stream, None)? as usize;
+                let file_name = read_string(&mut file_table_byte_stream, file_name_length)?;
+                let flags = u8::from_bytes(&mut file_table_byte_stream, None)?;
+
+                let entry = match flags & FLAG_REMOVE != 0 {
+                    true => PatchEntry::Removed,
+                    false => {
+                        let offset = u32::from_bytes(&mut file_table_byte_stream, None)? as u64;
+                        let compressed_size = i32::from_bytes(&mut file_table_byte_stream, None)? as usize;
+                        let _uncompressed_size = i32::from_bytes(&mut file_table_byte_stream, None)?;
+
+                        PatchEntry::Stored { offset, compressed_size }
+                    }
+                };
+
+                file_table.insert(file_name.to_lowercase(), entry);
+            }
+        }
+        invalid => {
+            return Err(ConversionError::invalid_data(mode_offset, format!("invalid mode {invalid:#x}")));
+        }
+    }
+
+    Ok((file_table, target_archive))
+}
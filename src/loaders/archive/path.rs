@@ -0,0 +1,70 @@
+//! Conversion between the CP949 encoded paths used by the game files and the
+//! UTF-8 strings used by the client.
+//!
+//! Archives decode their paths when loading and encode them again when
+//! writing, so everything in between (including the file system of a
+//! [`FolderArchive`](super::folder::FolderArchive)) only ever sees real Hangul.
+use encoding_rs::EUC_KR;
+
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+/// Decode a path stored in the game files. Despite its name, `EUC_KR` in
+/// `encoding_rs` is Microsoft's CP949, which is a superset of EUC-KR.
+pub fn decode_path(bytes: &[u8]) -> String {
+    EUC_KR.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Encode a path the same way the game files store them.
+pub fn encode_path(path: &str) -> Vec<u8> {
+    let (bytes, _encoding, _unmappable) = EUC_KR.encode(path);
+
+    #[cfg(feature = "debug")]
+    if _unmappable {
+        print_debug!(
+            "[{}warning{}] path {}{path}{} can not be represented in CP949",
+            YELLOW,
+            NONE,
+            MAGENTA,
+            NONE
+        );
+    }
+
+    bytes.into_owned()
+}
+
+/// Strings that are read from game files using
+/// [`ByteConvertable`](crate::loaders::ByteConvertable) map every byte to a
+/// single character, so they can be written back unchanged. Before such a
+/// string can be used as a path, it has to be converted back to bytes and
+/// decoded. Paths from any other source are already real Hangul and must not
+/// be passed to this function.
+pub fn decode_game_path(path: &str) -> String {
+    decode_path(&path.chars().map(|character| character as u8).collect::<Vec<u8>>())
+}
+
+#[cfg(test)]
+mod encoding {
+    use super::{decode_game_path, decode_path, encode_path};
+
+    const ENCODED: &[u8] = &[
+        0xC0, 0xAF, 0xC0, 0xFA, 0xC0, 0xCE, 0xC5, 0xCD, 0xC6, 0xE4, 0xC0, 0xCC, 0xBD, 0xBA, b'\\', b'i', b't', b'e', b'm',
+    ];
+    const DECODED: &str = "유저인터페이스\\item";
+
+    #[test]
+    fn decode() {
+        assert_eq!(decode_path(ENCODED), DECODED);
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(encode_path(DECODED), ENCODED);
+    }
+
+    #[test]
+    fn decode_game() {
+        assert_eq!(decode_game_path("À¯ÀúÀÎÅÍÆäÀÌ½º\\item"), DECODED);
+        assert_eq!(decode_game_path("data\\texture\\backside.bmp"), "data\\texture\\backside.bmp");
+    }
+}
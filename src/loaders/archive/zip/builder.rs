@@ -0,0 +1,226 @@
+//! Creates zip archives. Like the
+//! [`NativeArchiveBuilder`](crate::loaders::archive::native::NativeArchiveBuilder),
+//! files are compressed and written as soon as they are added and the central
+//! directory is written once the archive is saved.
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+
+use yazi::{compress, CompressionLevel, Format};
+
+use super::records::*;
+use super::{archive_path, SIZE_OF_LOCAL_FILE_HEADER};
+use crate::loaders::archive::Writable;
+use crate::loaders::ByteConvertable;
+
+/// CRC-32 as used by zip archives.
+fn crc32(data: &[u8]) -> u32 {
+    const POLYNOMIAL: u32 = 0xEDB88320;
+
+    let checksum = data.iter().fold(!0u32, |checksum, byte| {
+        (0..8).fold(checksum ^ *byte as u32, |checksum, _| {
+            let mask = (checksum & 1).wrapping_neg();
+            (checksum >> 1) ^ (POLYNOMIAL & mask)
+        })
+    });
+
+    !checksum
+}
+
+pub struct ZipArchiveBuilder {
+    os_file_handler: File,
+    /// Maps the lowercase path of every file to its path and its entry in the
+    /// central directory.
+    file_table: HashMap<String, (String, CentralDirectoryHeader)>,
+    /// Offset where the next file will be written.
+    data_end: u64,
+}
+
+impl ZipArchiveBuilder {
+    /// Creates a new, empty archive. An existing file at `path` will be
+    /// overwritten.
+    pub fn from_path(path: &Path) -> Self {
+        let os_file_handler = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .expect("unable to create file");
+
+        Self {
+            os_file_handler,
+            file_table: HashMap::new(),
+            data_end: 0,
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> u32 {
+        let offset = u32::try_from(self.data_end).expect("zip archives are limited to 4GB");
+
+        self.os_file_handler.seek(SeekFrom::Start(self.data_end)).unwrap();
+        self.os_file_handler.write_all(data).expect("unable to write file");
+        self.data_end += data.len() as u64;
+
+        offset
+    }
+}
+
+impl Writable for ZipArchiveBuilder {
+    /// Adds a new file or replaces an existing one. Files that don't get
+    /// smaller when compressed are stored as they are.
+    fn add_file(&mut self, path: &str, asset: Vec<u8>) {
+        let compressed = compress(&asset, Format::Raw, CompressionLevel::Default).unwrap();
+
+        let (compression_method, data) = match compressed.len() < asset.len() {
+            true => (METHOD_DEFLATED, compressed.as_slice()),
+            false => (METHOD_STORED, asset.as_slice()),
+        };
+
+        // Names are always written as UTF-8.
+        let file_name = archive_path(path).into_bytes();
+        let crc32 = crc32(&asset);
+        let compressed_size = data.len() as u32;
+        let uncompressed_size = asset.len() as u32;
+        let file_name_length = file_name.len() as u16;
+
+        let local_header = LocalFileHeader {
+            version_needed: VERSION,
+            flags: FLAG_UTF8,
+            compression_method,
+            modification_time: 0,
+            modification_date: DOS_EPOCH_DATE,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            file_name_length,
+            extra_field_length: 0,
+        };
+
+        let mut bytes = Vec::with_capacity(SIZE_OF_LOCAL_FILE_HEADER + file_name.len() + data.len());
+        bytes.extend(LOCAL_FILE_HEADER_SIGNATURE.to_bytes(None));
+        bytes.extend(local_header.to_bytes(None));
+        bytes.extend_from_slice(&file_name);
+        bytes.extend_from_slice(data);
+
+        let local_header_offset = self.append(&bytes);
+
+        let header = CentralDirectoryHeader {
+            version_made_by: VERSION,
+            version_needed: VERSION,
+            flags: FLAG_UTF8,
+            compression_method,
+            modification_time: 0,
+            modification_date: DOS_EPOCH_DATE,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            file_name_length,
+            extra_field_length: 0,
+            comment_length: 0,
+            disk_number: 0,
+            internal_attributes: 0,
+            external_attributes: 0,
+            local_header_offset,
+        };
+
+        self.file_table.insert(path.to_lowercase(), (path.to_owned(), header));
+    }
+
+    fn remove_file(&mut self, path: &str) {
+        self.file_table.remove(&path.to_lowercase());
+    }
+
+    fn save(&mut self) {
+        let mut central_directory = Vec::new();
+
+        for (path, header) in self.file_table.values() {
+            central_directory.extend(CENTRAL_DIRECTORY_HEADER_SIGNATURE.to_bytes(None));
+            central_directory.extend(header.to_bytes(None));
+            central_directory.extend(archive_path(path).into_bytes());
+        }
+
+        let entry_count = u16::try_from(self.file_table.len()).expect("zip archives are limited to 65535 files");
+        let central_directory_size = central_directory.len() as u32;
+        let central_directory_offset = self.append(&central_directory);
+
+        let end_of_central_directory = EndOfCentralDirectory {
+            disk_number: 0,
+            central_directory_disk: 0,
+            disk_entry_count: entry_count,
+            entry_count,
+            central_directory_size,
+            central_directory_offset,
+            comment_length: 0,
+        };
+
+        let mut bytes = END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_bytes(None);
+        bytes.extend(end_of_central_directory.to_bytes(None));
+        // Files added after saving will leave this central directory behind as dead
+        // space.
+        self.append(&bytes);
+        self.os_file_handler.sync_all().expect("unable to write file");
+    }
+}
+
+#[cfg(test)]
+mod creation {
+    use super::{crc32, ZipArchiveBuilder};
+    use crate::loaders::archive::zip::ZipArchive;
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::{LoaderError, TemporaryPath};
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn write_and_read() {
+        let path = TemporaryPath::new("creation.zip");
+        let compressible = b"compressible ".repeat(64);
+
+        let mut builder = ZipArchiveBuilder::from_path(&path);
+        builder.add_file("data\\stored.txt", b"stored".to_vec());
+        builder.add_file("data\\compressed.txt", compressible.clone());
+        builder.add_file("data\\removed.txt", b"removed".to_vec());
+        builder.add_file("data\\유저인터페이스\\Item.txt", b"item".to_vec());
+        builder.remove_file("data\\removed.txt");
+        builder.save();
+        drop(builder);
+
+        let archive = ZipArchive::from_path(&path).unwrap();
+        assert_eq!(archive.get_file_by_path("data\\stored.txt"), Ok(b"stored".to_vec()));
+        assert_eq!(archive.get_file_by_path("data/compressed.txt"), Ok(compressible));
+        assert!(archive.get_file_by_path("data\\removed.txt").is_err());
+        assert_eq!(archive.get_file_by_path("data\\유저인터페이스\\item.txt"), Ok(b"item".to_vec()));
+
+        let mut file_paths = Vec::new();
+        archive.get_file_paths(&mut file_paths);
+        file_paths.sort();
+        assert_eq!(file_paths, [
+            "data\\compressed.txt",
+            "data\\stored.txt",
+            "data\\유저인터페이스\\item.txt"
+        ]);
+    }
+
+    #[test]
+    fn truncated_archive() {
+        let path = TemporaryPath::new("truncated.zip");
+
+        let mut builder = ZipArchiveBuilder::from_path(&path);
+        builder.add_file("data\\stored.txt", b"stored".to_vec());
+        builder.save();
+        drop(builder);
+
+        // Cutting off the end of central directory record.
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() - 4]).unwrap();
+        assert!(matches!(ZipArchive::from_path(&path), Err(LoaderError::Corrupt { .. })));
+
+        std::fs::write(&path, []).unwrap();
+        assert!(matches!(ZipArchive::from_path(&path), Err(LoaderError::Corrupt { .. })));
+    }
+}
@@ -0,0 +1,216 @@
+//! Zip (and PK3) archives, as used by the community to distribute asset
+//! packs. Only stored and deflated files are supported.
+mod builder;
+mod records;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use yazi::{decompress, Format};
+
+pub use self::builder::ZipArchiveBuilder;
+use self::records::*;
+use super::path::decode_path;
+use super::{archive_io_error, corrupt_archive, read_asset_data, Archive};
+#[cfg(feature = "debug")]
+use crate::debug::*;
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, FixedByteSize, LoaderError};
+
+const SIZE_OF_SIGNATURE: usize = 4;
+const SIZE_OF_LOCAL_FILE_HEADER: usize = SIZE_OF_SIGNATURE + LocalFileHeader::size_in_bytes();
+const SIZE_OF_END_OF_CENTRAL_DIRECTORY: u64 = (SIZE_OF_SIGNATURE + EndOfCentralDirectory::size_in_bytes()) as u64;
+/// The end of central directory record may be followed by a comment of up to
+/// 64KB.
+const MAXIMUM_SIZE_OF_COMMENT: u64 = u16::MAX as u64;
+
+/// Paths inside of zip archives use forward slashes, the client uses
+/// backslashes.
+fn archive_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+fn client_path(path: &str) -> String {
+    path.replace('/', "\\")
+}
+
+/// Decode the name of a file. Zip tools on Korean systems store names in
+/// CP949 unless the UTF-8 flag is set.
+fn decode_file_name(bytes: &[u8], flags: u16) -> String {
+    match flags & FLAG_UTF8 != 0 {
+        true => String::from_utf8_lossy(bytes).into_owned(),
+        false => decode_path(bytes),
+    }
+}
+
+fn read_signature(byte_stream: &mut ByteStream, signature: u32) -> Result<(), ConversionError> {
+    let offset = byte_stream.get_offset();
+
+    match u32::from_bytes(byte_stream, None)? == signature {
+        true => Ok(()),
+        false => Err(ConversionError::invalid_data(
+            offset,
+            format!("expected signature {signature:#x}"),
+        )),
+    }
+}
+
+pub struct ZipArchive {
+    /// Maps the lowercase path of every file, using backslashes, to its entry
+    /// in the central directory.
+    file_table: HashMap<String, CentralDirectoryHeader>,
+    os_file_handler: File,
+}
+
+/// Reads the central directory of a zip archive.
+fn load_file_table(file: &mut File, path: &Path) -> Result<HashMap<String, CentralDirectoryHeader>, LoaderError> {
+    let io_error = |error| archive_io_error(path, error);
+    let invalid_archive = |offset: u64, reason: &str| corrupt_archive(path, ConversionError::invalid_data(offset as usize, reason));
+
+    let file_size = file.metadata().map_err(io_error)?.len();
+    let search_size = file_size.min(SIZE_OF_END_OF_CENTRAL_DIRECTORY + MAXIMUM_SIZE_OF_COMMENT);
+    let search_start = file_size - search_size;
+
+    let mut search_buffer = vec![0u8; search_size as usize];
+    file.seek(SeekFrom::Start(search_start)).map_err(io_error)?;
+    file.read_exact(&mut search_buffer).map_err(io_error)?;
+
+    let record_size = SIZE_OF_END_OF_CENTRAL_DIRECTORY as usize;
+    let last_position = search_buffer
+        .len()
+        .checked_sub(record_size)
+        .ok_or_else(|| invalid_archive(0, "file is too small to be a zip archive"))?;
+
+    // The comment may contain the signature as well, so the record is only valid if
+    // its comment, which has its length stored in the last two bytes of the record,
+    // ends exactly at the end of the file.
+    let is_record = |position: &usize| {
+        let record = &search_buffer[*position..];
+        let comment_length = u16::from_le_bytes([record[record_size - 2], record[record_size - 1]]) as usize;
+        record.starts_with(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes()) && record.len() == record_size + comment_length
+    };
+
+    let record_position = (0..=last_position)
+        .rev()
+        .find(is_record)
+        .ok_or_else(|| invalid_archive(search_start, "end of central directory not found"))?;
+
+    let mut byte_stream = ByteStream::new(&search_buffer[record_position..]);
+    let end_of_central_directory = read_signature(&mut byte_stream, END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+        .and_then(|_| EndOfCentralDirectory::from_bytes(&mut byte_stream, None))
+        .map_err(|error| corrupt_archive(path, error.add_offset(search_start as usize + record_position)))?;
+
+    if end_of_central_directory.entry_count == u16::MAX || end_of_central_directory.central_directory_offset == u32::MAX {
+        return Err(LoaderError::UnsupportedFormat {
+            path: path.display().to_string(),
+            format: "zip64 archive".to_owned(),
+        });
+    }
+
+    let central_directory_offset = end_of_central_directory.central_directory_offset as u64;
+    let mut central_directory_buffer = vec![0u8; end_of_central_directory.central_directory_size as usize];
+    file.seek(SeekFrom::Start(central_directory_offset)).map_err(io_error)?;
+    file.read_exact(&mut central_directory_buffer)
+        .map_err(|_| invalid_archive(central_directory_offset, "central directory is truncated"))?;
+
+    read_central_directory(&central_directory_buffer, end_of_central_directory.entry_count as usize)
+        .map_err(|error| corrupt_archive(path, error.add_offset(central_directory_offset as usize)))
+}
+
+fn read_central_directory(data: &[u8], entry_count: usize) -> Result<HashMap<String, CentralDirectoryHeader>, ConversionError> {
+    let mut byte_stream = ByteStream::new(data);
+    let mut file_table = HashMap::with_capacity(entry_count);
+
+    for _index in 0..entry_count {
+        read_signature(&mut byte_stream, CENTRAL_DIRECTORY_HEADER_SIGNATURE)?;
+        let header = CentralDirectoryHeader::from_bytes(&mut byte_stream, None)?;
+
+        let file_name = decode_file_name(byte_stream.slice(header.file_name_length as usize)?, header.flags);
+        byte_stream.skip(header.extra_field_length as usize + header.comment_length as usize)?;
+
+        // Directories are stored as empty files with a trailing slash.
+        if !file_name.ends_with('/') {
+            file_table.insert(client_path(&file_name).to_lowercase(), header);
+        }
+    }
+
+    Ok(file_table)
+}
+
+impl Archive for ZipArchive {
+    fn from_path(path: &Path) -> Result<Self, LoaderError> {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("load zip archive from {MAGENTA}{0}{NONE}", path.display()));
+
+        let mut file = File::open(path).map_err(|error| archive_io_error(path, error))?;
+        let file_table = load_file_table(&mut file, path)?;
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+
+        Ok(Self {
+            file_table,
+            os_file_handler: file,
+        })
+    }
+
+    fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError> {
+        let header = self
+            .file_table
+            .get(&client_path(asset_path).to_lowercase())
+            .ok_or_else(|| LoaderError::NotFound {
+                path: asset_path.to_owned(),
+            })?;
+
+        if header.flags & FLAG_ENCRYPTED != 0 {
+            return Err(LoaderError::UnsupportedFormat {
+                path: asset_path.to_owned(),
+                format: "encrypted zip entry".to_owned(),
+            });
+        }
+
+        // The sizes of the variable fields may differ from the central directory, so
+        // the local header needs to be read to find the data.
+        let mut local_header_buffer = [0u8; SIZE_OF_LOCAL_FILE_HEADER];
+        let local_header_offset = header.local_header_offset as u64;
+        read_asset_data(&self.os_file_handler, asset_path, local_header_offset, &mut local_header_buffer)?;
+
+        let mut byte_stream = ByteStream::new(&local_header_buffer);
+        let local_header = read_signature(&mut byte_stream, LOCAL_FILE_HEADER_SIGNATURE)
+            .and_then(|_| LocalFileHeader::from_bytes(&mut byte_stream, None))
+            .map_err(|error| LoaderError::conversion(asset_path, error.add_offset(local_header_offset as usize)))?;
+        let data_offset = local_header_offset
+            + SIZE_OF_LOCAL_FILE_HEADER as u64
+            + local_header.file_name_length as u64
+            + local_header.extra_field_length as u64;
+
+        let mut data = vec![0u8; header.compressed_size as usize];
+        read_asset_data(&self.os_file_handler, asset_path, data_offset, &mut data)?;
+
+        match header.compression_method {
+            METHOD_STORED => Ok(data),
+            METHOD_DEFLATED => decompress(&data, Format::Raw)
+                .map(|(uncompressed, _checksum)| uncompressed)
+                .map_err(|error| LoaderError::Decompression {
+                    path: asset_path.to_owned(),
+                    reason: format!("{error:?}"),
+                }),
+            method => Err(LoaderError::UnsupportedFormat {
+                path: asset_path.to_owned(),
+                format: format!("zip compression method {method}"),
+            }),
+        }
+    }
+
+    fn get_lua_files(&self, lua_files: &mut Vec<String>) {
+        let mut file_paths = Vec::new();
+        self.get_file_paths(&mut file_paths);
+
+        lua_files.extend(file_paths.into_iter().filter(|file_path| file_path.ends_with(".lub")));
+    }
+
+    fn get_file_paths(&self, file_paths: &mut Vec<String>) {
+        file_paths.extend(self.file_table.keys().cloned());
+    }
+}
@@ -0,0 +1,70 @@
+//! Records of the zip format. Every record starts with a signature, which is
+//! read separately, followed by a fixed size part and a variable size part
+//! that contains the file name.
+use procedural::{ByteConvertable, FixedByteSize};
+
+pub(super) const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034B50;
+pub(super) const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014B50;
+pub(super) const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054B50;
+
+/// The file name is encoded as UTF-8 instead of the system code page.
+pub(super) const FLAG_UTF8: u16 = 1 << 11;
+/// The file data is encrypted.
+pub(super) const FLAG_ENCRYPTED: u16 = 1 << 0;
+
+pub(super) const METHOD_STORED: u16 = 0;
+pub(super) const METHOD_DEFLATED: u16 = 8;
+
+/// Version 2.0 of the specification, which added deflate.
+pub(super) const VERSION: u16 = 20;
+/// 1980-01-01 in MS-DOS format, the earliest date that can be stored.
+pub(super) const DOS_EPOCH_DATE: u16 = 0x21;
+
+/// Stored in front of the data of every file.
+#[derive(Clone, ByteConvertable, FixedByteSize)]
+pub(super) struct LocalFileHeader {
+    pub version_needed: u16,
+    pub flags: u16,
+    pub compression_method: u16,
+    pub modification_time: u16,
+    pub modification_date: u16,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    pub file_name_length: u16,
+    pub extra_field_length: u16,
+}
+
+/// The entry of a single file in the central directory at the end of the
+/// archive.
+#[derive(Clone, ByteConvertable, FixedByteSize)]
+pub(super) struct CentralDirectoryHeader {
+    pub version_made_by: u16,
+    pub version_needed: u16,
+    pub flags: u16,
+    pub compression_method: u16,
+    pub modification_time: u16,
+    pub modification_date: u16,
+    pub crc32: u32,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    pub file_name_length: u16,
+    pub extra_field_length: u16,
+    pub comment_length: u16,
+    pub disk_number: u16,
+    pub internal_attributes: u16,
+    pub external_attributes: u32,
+    pub local_header_offset: u32,
+}
+
+/// The last record of the archive, followed by an optional comment.
+#[derive(Clone, ByteConvertable, FixedByteSize)]
+pub(super) struct EndOfCentralDirectory {
+    pub disk_number: u16,
+    pub central_directory_disk: u16,
+    pub disk_entry_count: u16,
+    pub entry_count: u16,
+    pub central_directory_size: u32,
+    pub central_directory_offset: u32,
+    pub comment_length: u16,
+}
@@ -0,0 +1,145 @@
+//! Verifies the integrity of archives and parses every file the client knows
+//! how to load, without touching the GPU. Used by the debug menu of the client
+//! and the `verify` command of `korangar-grf`.
+mod report;
+
+use rayon::prelude::*;
+
+pub use self::report::{AuditIssue, AuditReport};
+use super::action::validate_actions;
+use super::archive::Archive;
+use super::effect::validate_effect;
+use super::map::{validate_gat, validate_ground, validate_map};
+use super::model::validate_model;
+use super::script::validate_script;
+use super::sprite::validate_sprite;
+use super::ByteStream;
+
+/// File formats that are parsed while auditing.
+#[derive(Clone, Copy)]
+enum AuditedFormat {
+    Map,
+    Ground,
+    Altitude,
+    Model,
+    Sprite,
+    Actions,
+    Effect,
+    Script,
+}
+
+impl AuditedFormat {
+    fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+
+        match extension.to_lowercase().as_str() {
+            "rsw" => Some(Self::Map),
+            "gnd" => Some(Self::Ground),
+            "gat" => Some(Self::Altitude),
+            "rsm" => Some(Self::Model),
+            "spr" => Some(Self::Sprite),
+            "act" => Some(Self::Actions),
+            "str" => Some(Self::Effect),
+            "lua" | "lub" => Some(Self::Script),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a file with the loader for its format.
+fn parse_file(path: &str, format: AuditedFormat, data: &[u8]) -> Option<AuditIssue> {
+    let mut byte_stream = ByteStream::new(data);
+
+    let result = match format {
+        AuditedFormat::Map => validate_map(path, &mut byte_stream),
+        AuditedFormat::Ground => validate_ground(path, &mut byte_stream),
+        AuditedFormat::Altitude => validate_gat(path, &mut byte_stream),
+        AuditedFormat::Model => validate_model(path, &mut byte_stream),
+        AuditedFormat::Sprite => validate_sprite(path, &mut byte_stream),
+        AuditedFormat::Actions => validate_actions(path, &mut byte_stream),
+        AuditedFormat::Effect => validate_effect(path, &mut byte_stream),
+        AuditedFormat::Script => validate_script(path, &mut byte_stream),
+    };
+
+    let path = path.to_owned();
+
+    match result {
+        Ok(()) => match byte_stream.get_remaining_length() {
+            0 => None,
+            count => Some(AuditIssue::TrailingBytes { path, count }),
+        },
+        Err(error) => Some(AuditIssue::ParseFailed {
+            path,
+            reason: error.to_string(),
+        }),
+    }
+}
+
+/// Verifies the structure of an archive and parses every file that has a
+/// loader. Files are parsed in parallel.
+pub fn audit_archive(archive_name: &str, archive: &dyn Archive) -> std::io::Result<AuditReport> {
+    let mut issues = Vec::new();
+    archive.verify(&mut issues)?;
+
+    let mut file_paths = Vec::new();
+    archive.get_file_paths(&mut file_paths);
+
+    let parsed_files: Vec<(&String, AuditedFormat)> = file_paths
+        .iter()
+        .filter_map(|file_path| Some((file_path, AuditedFormat::from_path(file_path)?)))
+        .collect();
+
+    let parse_issues: Vec<AuditIssue> = parsed_files
+        .par_iter()
+        .filter_map(|(file_path, format)| {
+            // Files that can not be read are already reported by `verify`.
+            let data = archive.get_file_by_path(file_path).ok()?;
+            parse_file(file_path, *format, &data)
+        })
+        .collect();
+
+    issues.extend(parse_issues);
+    issues.sort_by(|first, second| first.get_path().cmp(second.get_path()));
+
+    Ok(AuditReport {
+        archive: archive_name.to_owned(),
+        file_count: file_paths.len(),
+        parsed_count: parsed_files.len(),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod parsing {
+    use super::{audit_archive, AuditIssue};
+    use crate::loaders::archive::folder::FolderArchive;
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::TemporaryPath;
+
+    #[test]
+    fn malformed_models() {
+        let path = TemporaryPath::new("audit");
+
+        let mut archive = FolderArchive::from_path(&path).unwrap();
+        archive.create();
+        archive.add_file("data\\model\\magic.rsm", b"XXXX".to_vec());
+        archive.add_file("data\\model\\truncated.rsm", b"GRSM".to_vec());
+        archive.add_file("data\\readme.txt", b"not parsed".to_vec());
+
+        let report = audit_archive("test", &archive).unwrap();
+        assert_eq!(report.file_count, 3);
+        assert_eq!(report.parsed_count, 2);
+
+        match report.issues.as_slice() {
+            [
+                AuditIssue::ParseFailed { path: first, .. },
+                AuditIssue::ParseFailed { path: second, reason },
+            ] => {
+                assert_eq!(first, "data\\model\\magic.rsm");
+                assert_eq!(second, "data\\model\\truncated.rsm");
+                assert!(reason.contains("corrupt at offset 0x4"));
+            }
+            issues => panic!("unexpected issues {issues:?}"),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt::{Display, Formatter, Result};
+
+use serde::Serialize;
+
+/// A problem with a single file of an archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum AuditIssue {
+    /// The file table entry does not match the data stored in the archive.
+    InvalidEntry { path: String, reason: String },
+    /// The file could not be read or inflated.
+    Unreadable { path: String, reason: String },
+    /// The loader for the file format failed to parse the file.
+    ParseFailed { path: String, reason: String },
+    /// The loader parsed the file without reading all of it.
+    TrailingBytes { path: String, count: usize },
+}
+
+impl AuditIssue {
+    pub fn get_path(&self) -> &str {
+        match self {
+            AuditIssue::InvalidEntry { path, .. }
+            | AuditIssue::Unreadable { path, .. }
+            | AuditIssue::ParseFailed { path, .. }
+            | AuditIssue::TrailingBytes { path, .. } => path,
+        }
+    }
+}
+
+impl Display for AuditIssue {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        match self {
+            AuditIssue::InvalidEntry { path, reason } => write!(formatter, "invalid entry {path}: {reason}"),
+            AuditIssue::Unreadable { path, reason } => write!(formatter, "unreadable {path}: {reason}"),
+            AuditIssue::ParseFailed { path, reason } => write!(formatter, "parse failed {path}: {reason}"),
+            AuditIssue::TrailingBytes { path, count } => write!(formatter, "trailing bytes {path}: {count} bytes were not read"),
+        }
+    }
+}
+
+/// Result of auditing a single archive.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditReport {
+    pub archive: String,
+    /// Number of files in the archive.
+    pub file_count: usize,
+    /// Number of files that were parsed by one of the loaders.
+    pub parsed_count: usize,
+    /// Sorted by path.
+    pub issues: Vec<AuditIssue>,
+}
@@ -1,17 +1,32 @@
 use cgmath::{Matrix3, Quaternion, Vector2, Vector3, Vector4};
 
-use crate::loaders::ByteStream;
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteStream, ConversionError, InternalVersion};
 
-pub trait ByteConvertable {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self;
+pub trait ByteConvertable: Sized {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError>;
 
     fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
         panic!()
     }
+
+    /// Counterpart to the version of the [`ByteStream`]. Types that are
+    /// stored differently depending on the version of the file they are in
+    /// need to implement this instead of [`to_bytes`](Self::to_bytes).
+    fn to_versioned_bytes(&self, _version: Option<InternalVersion>, length_hint: Option<usize>) -> Vec<u8> {
+        self.to_bytes(length_hint)
+    }
+}
+
+fn read_array<const SIZE: usize>(byte_stream: &mut ByteStream) -> Result<[u8; SIZE], ConversionError> {
+    let mut bytes = [0; SIZE];
+    bytes.copy_from_slice(byte_stream.slice(SIZE)?);
+    Ok(bytes)
 }
 
 impl ByteConvertable for u8 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "u8 may not have a length hint");
         byte_stream.next()
     }
@@ -23,9 +38,9 @@ impl ByteConvertable for u8 {
 }
 
 impl ByteConvertable for u16 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "u16 may not have a length hint");
-        Self::from_le_bytes([byte_stream.next(), byte_stream.next()])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -35,9 +50,9 @@ impl ByteConvertable for u16 {
 }
 
 impl ByteConvertable for u32 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "u32 may not have a length hint");
-        Self::from_le_bytes([byte_stream.next(), byte_stream.next(), byte_stream.next(), byte_stream.next()])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -47,18 +62,9 @@ impl ByteConvertable for u32 {
 }
 
 impl ByteConvertable for u64 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "u64 may not have a length hint");
-        Self::from_le_bytes([
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-        ])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -68,9 +74,9 @@ impl ByteConvertable for u64 {
 }
 
 impl ByteConvertable for i8 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "i8 may not have a length hint");
-        byte_stream.next() as i8
+        byte_stream.next().map(|byte| byte as i8)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -80,9 +86,9 @@ impl ByteConvertable for i8 {
 }
 
 impl ByteConvertable for i16 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "i16 may not have a length hint");
-        Self::from_le_bytes([byte_stream.next(), byte_stream.next()])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -92,9 +98,9 @@ impl ByteConvertable for i16 {
 }
 
 impl ByteConvertable for i32 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "i32 may not have a length hint");
-        Self::from_le_bytes([byte_stream.next(), byte_stream.next(), byte_stream.next(), byte_stream.next()])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -104,18 +110,9 @@ impl ByteConvertable for i32 {
 }
 
 impl ByteConvertable for i64 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "i64 may not have a length hint");
-        Self::from_le_bytes([
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-            byte_stream.next(),
-        ])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -125,107 +122,199 @@ impl ByteConvertable for i64 {
 }
 
 impl ByteConvertable for f32 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "f32 may not have a length hint");
-        Self::from_le_bytes([byte_stream.next(), byte_stream.next(), byte_stream.next(), byte_stream.next()])
+        read_array(byte_stream).map(Self::from_le_bytes)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
         assert!(length_hint.is_none(), "f32 may not have a length hint");
-        self.to_ne_bytes().to_vec()
+        self.to_le_bytes().to_vec()
     }
 }
 
 impl<T: ByteConvertable, const SIZE: usize> ByteConvertable for [T; SIZE] {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
-        use std::mem::MaybeUninit;
-
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "array may not have a length hint");
-
-        let mut data: [MaybeUninit<T>; SIZE] = unsafe { MaybeUninit::uninit().assume_init() };
-
-        for element in &mut data[..] {
-            *element = MaybeUninit::new(T::from_bytes(byte_stream, None));
-        }
-
-        // rust wont let us do this currently
-        //unsafe { mem::transmute::<_, [T; SIZE]>(data) }
-
-        // workaround from: https://github.com/rust-lang/rust/issues/61956
-        let ptr = &mut data as *mut _ as *mut [T; SIZE];
-        let result = unsafe { ptr.read() };
-        core::mem::forget(data);
-        result
+        std::array::try_from_fn(|_| T::from_bytes(byte_stream, None))
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        self.to_versioned_bytes(None, length_hint)
+    }
+
+    fn to_versioned_bytes(&self, version: Option<InternalVersion>, length_hint: Option<usize>) -> Vec<u8> {
         assert!(length_hint.is_none(), "array may not have a length hint");
 
         self.iter().fold(Vec::new(), |mut bytes, value| {
-            bytes.extend(value.to_bytes(None));
+            bytes.extend(value.to_versioned_bytes(version, None));
             bytes
         })
     }
 }
 
 impl ByteConvertable for String {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        // Strings with a length hint are not null terminated if they fill the entire
+        // space.
+        if let Some(length) = length_hint {
+            let bytes = byte_stream.slice(length)?;
+            return Ok(bytes.iter().take_while(|byte| **byte != 0).map(|byte| *byte as char).collect());
+        }
+
         let mut value = String::new();
-        let mut offset = 0;
 
         loop {
-            offset += 1;
-
-            match byte_stream.next() {
+            match byte_stream.next()? {
                 0 => break,
                 byte => value.push(byte as char),
             }
         }
 
-        if let Some(length) = length_hint {
-            byte_stream.skip(length - offset);
-        }
-
-        value
+        Ok(value)
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
         use std::iter;
 
+        // Every byte is read as a single character, so characters have to be written
+        // back the same way instead of being encoded as UTF-8.
+        let bytes = self.chars().map(|character| character as u8);
+
         match length_hint {
             Some(length) => {
-                assert!(self.len() <= length, "string is to long for the byte stream");
-                let padding = (0..length - self.len()).map(|_| 0);
-                self.bytes().chain(padding).collect()
+                let string_length = self.chars().count();
+                assert!(string_length <= length, "string is to long for the byte stream");
+                let padding = (0..length - string_length).map(|_| 0);
+                bytes.chain(padding).collect()
             }
-            None => self.bytes().chain(iter::once(0)).collect(),
+            None => bytes.chain(iter::once(0)).collect(),
+        }
+    }
+}
+
+/// A string in a field with a fixed length. Some tools don't clear the field
+/// before writing a shorter string, so the bytes after the null terminator
+/// are kept to be able to write the field back unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaddedString {
+    pub inner: String,
+    padding: Vec<u8>,
+}
+
+impl PaddedString {
+    pub fn new(inner: String) -> Self {
+        Self {
+            inner,
+            padding: Vec::new(),
         }
     }
 }
 
+impl ByteConvertable for PaddedString {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        let length = length_hint.expect("padded string requires a length hint");
+        let bytes = byte_stream.slice(length)?;
+
+        let string_length = bytes.iter().position(|byte| *byte == 0).unwrap_or(length);
+        let inner = bytes[..string_length].iter().map(|byte| *byte as char).collect();
+
+        // Padding that only consists of null bytes is not stored, since that is what
+        // `to_bytes` writes anyway.
+        let padding = bytes
+            .get(string_length + 1..)
+            .filter(|padding| padding.iter().any(|byte| *byte != 0))
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default();
+
+        Ok(Self { inner, padding })
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        let length = length_hint.expect("padded string requires a length hint");
+        let mut bytes = self.inner.to_bytes(Some(length));
+
+        // The padding is only written if the string still has the length it was read
+        // with.
+        let padding_start = length.checked_sub(self.padding.len());
+        if !self.padding.is_empty() && padding_start == Some(self.inner.chars().count() + 1) {
+            bytes[length - self.padding.len()..].copy_from_slice(&self.padding);
+        }
+
+        bytes
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for PaddedString {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        use rand::Rng;
+
+        let length = length_hint.expect("padded string requires a length hint");
+        let inner = String::arbitrary(generator, length_hint);
+        let padding_length = length.saturating_sub(inner.chars().count() + 1);
+
+        let padding = match padding_length > 0 && generator.get_random().gen_bool(0.5) {
+            true => (0..padding_length).map(|_| generator.get_random().gen_range(1..=u8::MAX)).collect(),
+            false => Vec::new(),
+        };
+
+        Self { inner, padding }
+    }
+}
+
+impl crate::interface::PrototypeElement for PaddedString {
+    fn to_element(&self, display: String) -> crate::interface::ElementCell {
+        self.inner.to_element(display)
+    }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        self.inner.write_text(display, indentation, text)
+    }
+}
+
 impl<T: ByteConvertable> ByteConvertable for Vec<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         let length = length_hint.expect("vector requires a size hint");
-        let data = byte_stream.slice(length);
+        let base_offset = byte_stream.get_offset();
+        let data = byte_stream.slice(length)?;
         let mut byte_stream = ByteStream::new(data);
         let mut vector = Vec::new();
 
         while !byte_stream.is_empty() {
-            vector.push(T::from_bytes(&mut byte_stream, None));
+            let element = T::from_bytes(&mut byte_stream, None).map_err(|error| error.add_offset(base_offset))?;
+            vector.push(element);
         }
 
-        vector
+        Ok(vector)
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        self.to_versioned_bytes(None, length_hint)
+    }
+
+    fn to_versioned_bytes(&self, version: Option<InternalVersion>, length_hint: Option<usize>) -> Vec<u8> {
+        let bytes = self.iter().fold(Vec::new(), |mut bytes, value| {
+            bytes.extend(value.to_versioned_bytes(version, None));
+            bytes
+        });
+
+        if let Some(length) = length_hint {
+            assert_eq!(bytes.len(), length, "vector does not match its length hint");
+        }
+
+        bytes
     }
 }
 
 impl<T: ByteConvertable> ByteConvertable for Vector2<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "vector2 may not have a length hint");
 
-        let first = T::from_bytes(byte_stream, None);
-        let second = T::from_bytes(byte_stream, None);
+        let first = T::from_bytes(byte_stream, None)?;
+        let second = T::from_bytes(byte_stream, None)?;
 
-        Vector2::new(first, second)
+        Ok(Vector2::new(first, second))
     }
 
     fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
@@ -236,14 +325,14 @@ impl<T: ByteConvertable> ByteConvertable for Vector2<T> {
 }
 
 impl<T: ByteConvertable> ByteConvertable for Vector3<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "vector3 may not have a length hint");
 
-        let first = T::from_bytes(byte_stream, None);
-        let second = T::from_bytes(byte_stream, None);
-        let third = T::from_bytes(byte_stream, None);
+        let first = T::from_bytes(byte_stream, None)?;
+        let second = T::from_bytes(byte_stream, None)?;
+        let third = T::from_bytes(byte_stream, None)?;
 
-        Vector3::new(first, second, third)
+        Ok(Vector3::new(first, second, third))
     }
 
     fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
@@ -255,15 +344,15 @@ impl<T: ByteConvertable> ByteConvertable for Vector3<T> {
 }
 
 impl<T: ByteConvertable> ByteConvertable for Vector4<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "vector4 may not have a length hint");
 
-        let first = T::from_bytes(byte_stream, None);
-        let second = T::from_bytes(byte_stream, None);
-        let third = T::from_bytes(byte_stream, None);
-        let fourth = T::from_bytes(byte_stream, None);
+        let first = T::from_bytes(byte_stream, None)?;
+        let second = T::from_bytes(byte_stream, None)?;
+        let third = T::from_bytes(byte_stream, None)?;
+        let fourth = T::from_bytes(byte_stream, None)?;
 
-        Vector4::new(first, second, third, fourth)
+        Ok(Vector4::new(first, second, third, fourth))
     }
 
     fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
@@ -276,35 +365,48 @@ impl<T: ByteConvertable> ByteConvertable for Vector4<T> {
 }
 
 impl<T: ByteConvertable> ByteConvertable for Quaternion<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "quaternion may not have a length hint");
 
-        let first = T::from_bytes(byte_stream, None);
-        let second = T::from_bytes(byte_stream, None);
-        let third = T::from_bytes(byte_stream, None);
-        let fourth = T::from_bytes(byte_stream, None);
+        let first = T::from_bytes(byte_stream, None)?;
+        let second = T::from_bytes(byte_stream, None)?;
+        let third = T::from_bytes(byte_stream, None)?;
+        let fourth = T::from_bytes(byte_stream, None)?;
+
+        Ok(Quaternion::new(fourth, first, second, third))
+    }
 
-        Quaternion::new(fourth, first, second, third)
+    fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
+        let mut bytes = self.v.to_bytes(None);
+        bytes.append(&mut self.s.to_bytes(None));
+        bytes
     }
 }
 
 impl<T: ByteConvertable> ByteConvertable for Matrix3<T> {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none(), "matrix may not have a length hint");
 
-        let c0r0 = T::from_bytes(byte_stream, None);
-        let c0r1 = T::from_bytes(byte_stream, None);
-        let c0r2 = T::from_bytes(byte_stream, None);
+        let c0r0 = T::from_bytes(byte_stream, None)?;
+        let c0r1 = T::from_bytes(byte_stream, None)?;
+        let c0r2 = T::from_bytes(byte_stream, None)?;
+
+        let c1r0 = T::from_bytes(byte_stream, None)?;
+        let c1r1 = T::from_bytes(byte_stream, None)?;
+        let c1r2 = T::from_bytes(byte_stream, None)?;
 
-        let c1r0 = T::from_bytes(byte_stream, None);
-        let c1r1 = T::from_bytes(byte_stream, None);
-        let c1r2 = T::from_bytes(byte_stream, None);
+        let c2r0 = T::from_bytes(byte_stream, None)?;
+        let c2r1 = T::from_bytes(byte_stream, None)?;
+        let c2r2 = T::from_bytes(byte_stream, None)?;
 
-        let c2r0 = T::from_bytes(byte_stream, None);
-        let c2r1 = T::from_bytes(byte_stream, None);
-        let c2r2 = T::from_bytes(byte_stream, None);
+        Ok(Matrix3::new(c0r0, c0r1, c0r2, c1r0, c1r1, c1r2, c2r0, c2r1, c2r2))
+    }
 
-        Matrix3::new(c0r0, c0r1, c0r2, c1r0, c1r1, c1r2, c2r0, c2r1, c2r2)
+    fn to_bytes(&self, _length_hint: Option<usize>) -> Vec<u8> {
+        let mut bytes = self.x.to_bytes(None);
+        bytes.append(&mut self.y.to_bytes(None));
+        bytes.append(&mut self.z.to_bytes(None));
+        bytes
     }
 }
 
@@ -323,7 +425,7 @@ mod default_string {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[116, 101, 115, 116, 0]);
-        let test_value = String::from_bytes(&mut byte_stream, None);
+        let test_value = String::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.as_str(), "test");
         assert!(byte_stream.is_empty());
     }
@@ -344,7 +446,7 @@ mod length_hint_string {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[116, 101, 115, 116, 0, 0, 0, 0]);
-        let test_value = String::from_bytes(&mut byte_stream, Some(8));
+        let test_value = String::from_bytes(&mut byte_stream, Some(8)).unwrap();
         assert_eq!(test_value.as_str(), "test");
         assert!(byte_stream.is_empty());
     }
@@ -376,7 +478,7 @@ mod const_length_hint_string {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[116, 101, 115, 116, 0, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.string.as_str(), "test");
         assert!(byte_stream.is_empty());
     }
@@ -407,7 +509,7 @@ mod dynamic_length_hint_string {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[4, 116, 101, 115, 116, 0, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value, TestStruct::new(4, "test".to_string()));
         assert!(byte_stream.is_empty());
     }
@@ -438,26 +540,24 @@ mod default_struct {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[16, 184, 11, 255, 255, 255, 255]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value, TestStruct::new(16, 3000, -1));
         assert!(byte_stream.is_empty());
     }
 }
 
-// TODO: reenable once the versioning is fixed
-/*
 #[cfg(test)]
 mod version_struct_smaller {
 
-    use procedural::*;
     use derive_new::new;
-    use crate::loaders::{ ByteStream, ByteConvertable };
-    use crate::loaders::Version;
+    use procedural::*;
+
+    use crate::loaders::{ByteConvertable, ByteStream, MajorFirst, Version};
 
     #[derive(ByteConvertable, new)]
     struct TestStruct {
         #[version]
-        pub version: Version,
+        pub version: Version<MajorFirst>,
         #[version_smaller(4, 1)]
         pub maybe_value: Option<u32>,
     }
@@ -465,7 +565,7 @@ mod version_struct_smaller {
     #[test]
     fn deserialize_smaller() {
         let mut byte_stream = ByteStream::new(&[4, 0, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, Some(16));
         assert!(byte_stream.is_empty());
     }
@@ -473,14 +573,14 @@ mod version_struct_smaller {
     #[test]
     fn deserialize_equals() {
         let mut byte_stream = ByteStream::new(&[4, 1, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, None);
     }
 
     #[test]
     fn deserialize_bigger() {
         let mut byte_stream = ByteStream::new(&[4, 6, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, None);
     }
 }
@@ -488,15 +588,15 @@ mod version_struct_smaller {
 #[cfg(test)]
 mod version_struct_bigger {
 
-    use procedural::*;
     use derive_new::new;
-    use crate::loaders::{ ByteStream, ByteConvertable };
-    use crate::loaders::Version;
+    use procedural::*;
+
+    use crate::loaders::{ByteConvertable, ByteStream, MajorFirst, Version};
 
     #[derive(ByteConvertable, new)]
     struct TestStruct {
         #[version]
-        pub version: Version,
+        pub version: Version<MajorFirst>,
         #[version_equals_or_above(4, 1)]
         pub maybe_value: Option<u32>,
     }
@@ -504,14 +604,14 @@ mod version_struct_bigger {
     #[test]
     fn deserialize_smaller() {
         let mut byte_stream = ByteStream::new(&[4, 0, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, None);
     }
 
     #[test]
     fn deserialize_equals() {
         let mut byte_stream = ByteStream::new(&[4, 1, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, Some(16));
         assert!(byte_stream.is_empty());
     }
@@ -519,12 +619,11 @@ mod version_struct_bigger {
     #[test]
     fn deserialize_bigger() {
         let mut byte_stream = ByteStream::new(&[4, 2, 16, 0, 0, 0]);
-        let test_value = TestStruct::from_bytes(&mut byte_stream, None);
+        let test_value = TestStruct::from_bytes(&mut byte_stream, None).unwrap();
         assert_eq!(test_value.maybe_value, Some(16));
         assert!(byte_stream.is_empty());
     }
 }
-*/
 
 #[cfg(test)]
 mod default_enum {
@@ -550,7 +649,7 @@ mod default_enum {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[1]);
-        let test_value = TestEnum::from_bytes(&mut byte_stream, None);
+        let test_value = TestEnum::from_bytes(&mut byte_stream, None).unwrap();
         assert!(matches!(test_value, TestEnum::Second));
         assert!(byte_stream.is_empty());
     }
@@ -583,7 +682,7 @@ mod numeric_value_enum {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[10]);
-        let test_value = TestEnum::from_bytes(&mut byte_stream, None);
+        let test_value = TestEnum::from_bytes(&mut byte_stream, None).unwrap();
         assert!(matches!(test_value, TestEnum::Second));
         assert!(byte_stream.is_empty());
     }
@@ -614,8 +713,68 @@ mod numeric_type_enum {
     #[test]
     fn deserialization_test() {
         let mut byte_stream = ByteStream::new(&[1, 0]);
-        let test_value = TestEnum::from_bytes(&mut byte_stream, None);
+        let test_value = TestEnum::from_bytes(&mut byte_stream, None).unwrap();
         assert!(matches!(test_value, TestEnum::Second));
         assert!(byte_stream.is_empty());
     }
 }
+
+#[cfg(test)]
+mod conversion_error {
+
+    use procedural::*;
+
+    use crate::loaders::{ByteConvertable, ByteStream, ConversionErrorKind};
+
+    #[derive(Debug, ByteConvertable)]
+    struct InnerStruct {
+        pub first: u16,
+        pub second: u32,
+    }
+
+    #[derive(Debug, ByteConvertable)]
+    struct OuterStruct {
+        pub value: u8,
+        pub inner: InnerStruct,
+    }
+
+    #[derive(Debug, ByteConvertable)]
+    enum TestEnum {
+        First,
+        Second,
+    }
+
+    #[test]
+    fn truncated_struct() {
+        let mut byte_stream = ByteStream::new(&[1, 2, 0, 3, 0]);
+        let error = OuterStruct::from_bytes(&mut byte_stream, None).unwrap_err();
+
+        assert_eq!(error.offset, 3);
+        assert_eq!(error.kind, ConversionErrorKind::ByteStreamTooShort {
+            requested: 4,
+            remaining: 2
+        });
+        assert_eq!(error.get_path(), "OuterStruct::inner > InnerStruct::second");
+    }
+
+    #[test]
+    fn invalid_enum_value() {
+        let mut byte_stream = ByteStream::new(&[0, 5]);
+        let error = <[TestEnum; 2]>::from_bytes(&mut byte_stream, None).unwrap_err();
+
+        assert_eq!(error.offset, 1);
+        assert_eq!(error.kind, ConversionErrorKind::InvalidEnumValue {
+            type_name: "TestEnum",
+            value: 5
+        });
+    }
+
+    #[test]
+    fn vector_offset() {
+        let mut byte_stream = ByteStream::new(&[0, 0, 0, 0, 0, 0]);
+        byte_stream.skip(1).unwrap();
+        let error = Vec::<u16>::from_bytes(&mut byte_stream, Some(5)).unwrap_err();
+
+        assert_eq!(error.offset, 5);
+    }
+}
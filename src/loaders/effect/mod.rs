@@ -10,7 +10,8 @@ use super::{MajorFirst, TextureLoader};
 #[cfg(feature = "debug")]
 use crate::debug::*;
 use crate::graphics::{Camera, Color, DeferredRenderer, Renderer};
-use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, Version};
+use crate::loaders::archive::path::decode_game_path;
+use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, LoaderError, Version};
 use crate::network::EntityId;
 
 #[derive(Debug, ByteConvertable, PrototypeElement)]
@@ -239,27 +240,34 @@ impl Effect {
     }
 }
 
+fn read_effect_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<EffectData, LoaderError> {
+    if <[u8; 4]>::from_bytes(byte_stream, None) != Ok([b'S', b'T', b'R', b'M']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    EffectData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+/// Parses an effect without loading its textures. Used to audit archives.
+pub(super) fn validate_effect(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_effect_data(file_path, byte_stream).map(|_| ())
+}
+
 impl EffectLoader {
     fn load(
         &mut self,
         path: &str,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
-    ) -> Result<Arc<Effect>, String> {
+    ) -> Result<Arc<Effect>, LoaderError> {
         #[cfg(feature = "debug")]
         let timer = Timer::new_dynamic(format!("load effect from {MAGENTA}{path}{NONE}"));
 
-        let bytes = game_file_loader.get(&format!("data\\texture\\effect\\{path}"))?;
-        let mut byte_stream = ByteStream::new(&bytes);
-
-        if <[u8; 4]>::from_bytes(&mut byte_stream, None) != [b'S', b'T', b'R', b'M'] {
-            return Err(format!("failed to read magic number from {path}"));
-        }
-
-        let effect_data = EffectData::from_bytes(&mut byte_stream, None);
+        let file_path = format!("data\\texture\\effect\\{path}");
+        let bytes = game_file_loader.get(&file_path)?;
+        let effect_data = read_effect_data(&file_path, &mut ByteStream::new(&bytes))?;
 
         //println!("{:#?}", effect_data);
-        //println!("remaining: {}", byte_stream.remaining_bytes().len());
 
         let prefix = match path.chars().rev().position(|character| character == '\\') {
             Some(offset) => path.split_at(path.len() - offset).0,
@@ -277,7 +285,7 @@ impl EffectLoader {
                         .texture_names
                         .into_iter()
                         .map(|name| {
-                            let path = format!("effect\\{}{}", prefix, name.name);
+                            let path = format!("effect\\{}{}", prefix, decode_game_path(&name.name));
                             texture_loader.get(&path, game_file_loader).unwrap()
                         })
                         .collect(),
@@ -332,9 +340,9 @@ impl EffectLoader {
     pub fn get(
         &mut self,
         path: &str,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
-    ) -> Result<Arc<Effect>, String> {
+    ) -> Result<Arc<Effect>, LoaderError> {
         match self.cache.get(path) {
             Some(effect) => Ok(effect.clone()),
             None => self.load(path, game_file_loader, texture_loader),
@@ -0,0 +1,179 @@
+use std::any::Any;
+use std::fmt::{Display, Formatter, Result};
+
+/// Reasons why an asset could not be loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoaderError {
+    /// None of the archives contain the file.
+    NotFound { path: String },
+    /// A patch archive removes the file, so archives below it are not
+    /// searched.
+    Removed { path: String },
+    /// The file exists but could not be read.
+    Io { path: String, reason: String },
+    /// The file is truncated or contains invalid data at `offset`.
+    Corrupt { path: String, offset: usize, reason: String },
+    /// The file is valid but uses a version that is not supported (yet).
+    UnsupportedVersion { path: String, version: String },
+    /// The loader does not know how to handle this kind of file.
+    UnsupportedFormat { path: String, format: String },
+    /// The file is stored in an archive but could not be inflated.
+    Decompression { path: String, reason: String },
+    /// The file is an image that could not be decoded.
+    Image { path: String, reason: String },
+    /// The thread that loaded the file panicked.
+    Panicked { path: String, reason: String },
+}
+
+impl LoaderError {
+    pub fn get_path(&self) -> &str {
+        match self {
+            LoaderError::NotFound { path }
+            | LoaderError::Removed { path }
+            | LoaderError::Io { path, .. }
+            | LoaderError::Corrupt { path, .. }
+            | LoaderError::UnsupportedVersion { path, .. }
+            | LoaderError::UnsupportedFormat { path, .. }
+            | LoaderError::Decompression { path, .. }
+            | LoaderError::Image { path, .. }
+            | LoaderError::Panicked { path, .. } => path,
+        }
+    }
+
+    /// Used for files that could not be decoded by their
+    /// [`ByteConvertable`](super::ByteConvertable) implementation.
+    pub fn conversion(path: impl Into<String>, error: ConversionError) -> Self {
+        LoaderError::Corrupt {
+            path: path.into(),
+            offset: error.offset,
+            reason: error.get_reason(),
+        }
+    }
+
+    /// Used for files that don't start with the expected magic number.
+    pub fn invalid_magic(path: impl Into<String>) -> Self {
+        LoaderError::Corrupt {
+            path: path.into(),
+            offset: 0,
+            reason: "failed to read magic number".to_owned(),
+        }
+    }
+
+    /// Used when joining a loader thread returned the payload of a panic.
+    pub fn panicked(path: impl Into<String>, payload: Box<dyn Any + Send>) -> Self {
+        let reason = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload.downcast_ref::<&str>().copied().unwrap_or("unknown panic").to_owned(),
+        };
+
+        LoaderError::Panicked { path: path.into(), reason }
+    }
+}
+
+impl Display for LoaderError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        match self {
+            LoaderError::NotFound { path } => write!(formatter, "failed to find file {path}"),
+            LoaderError::Removed { path } => write!(formatter, "file {path} was removed by a patch"),
+            LoaderError::Io { path, reason } => write!(formatter, "failed to read {path}: {reason}"),
+            LoaderError::Corrupt { path, offset, reason } => write!(formatter, "{path} is corrupt at offset {offset:#x}: {reason}"),
+            LoaderError::UnsupportedVersion { path, version } => write!(formatter, "{path} has unsupported version {version}"),
+            LoaderError::UnsupportedFormat { path, format } => write!(formatter, "{path} has unsupported file format {format}"),
+            LoaderError::Decompression { path, reason } => write!(formatter, "failed to decompress {path}: {reason}"),
+            LoaderError::Image { path, reason } => write!(formatter, "failed to decode image {path}: {reason}"),
+            LoaderError::Panicked { path, reason } => write!(formatter, "loading {path} panicked: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for LoaderError {}
+
+/// The specific problem that was encountered while decoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionErrorKind {
+    /// More bytes were requested than are left in the byte stream.
+    ByteStreamTooShort { requested: usize, remaining: usize },
+    /// A value that does not correspond to any variant of an enum.
+    InvalidEnumValue { type_name: &'static str, value: usize },
+    /// The header of a packet does not match the expected one.
+    IncorrectSignature { expected: u16, found: u16 },
+    /// Any other invalid data.
+    InvalidData { reason: String },
+}
+
+impl Display for ConversionErrorKind {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        match self {
+            ConversionErrorKind::ByteStreamTooShort { requested, remaining } => write!(
+                formatter,
+                "byte stream is shorter than expected ({requested} bytes requested, {remaining} bytes remaining)"
+            ),
+            ConversionErrorKind::InvalidEnumValue { type_name, value } => write!(formatter, "invalid value {value} for {type_name}"),
+            ConversionErrorKind::IncorrectSignature { expected, found } => {
+                write!(formatter, "incorrect signature {found:#06x}, expected {expected:#06x}")
+            }
+            ConversionErrorKind::InvalidData { reason } => write!(formatter, "{reason}"),
+        }
+    }
+}
+
+/// Reasons why data could not be decoded by a
+/// [`ByteConvertable`](super::ByteConvertable) implementation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConversionError {
+    /// Offset into the data where decoding failed.
+    pub offset: usize,
+    pub kind: ConversionErrorKind,
+    /// Structs and fields that were being decoded, innermost first. Every
+    /// entry has the form `Struct::field`.
+    context: Vec<&'static str>,
+}
+
+impl ConversionError {
+    pub fn from_kind(offset: usize, kind: ConversionErrorKind) -> Self {
+        Self {
+            offset,
+            kind,
+            context: Vec::new(),
+        }
+    }
+
+    pub fn invalid_data(offset: usize, reason: impl Into<String>) -> Self {
+        Self::from_kind(offset, ConversionErrorKind::InvalidData { reason: reason.into() })
+    }
+
+    /// Called for every field that the error propagates through.
+    pub fn add_context(mut self, field: &'static str) -> Self {
+        self.context.push(field);
+        self
+    }
+
+    /// Used when the error was produced by a byte stream that starts at
+    /// `offset` in the original data.
+    pub fn add_offset(mut self, offset: usize) -> Self {
+        self.offset += offset;
+        self
+    }
+
+    /// Path of the field that failed to decode, outermost first, e.g.
+    /// `MapData::water_settings > WaterSettings::wave_height`.
+    pub fn get_path(&self) -> String {
+        self.context.iter().rev().copied().collect::<Vec<_>>().join(" > ")
+    }
+
+    /// Describes the error without the offset.
+    pub fn get_reason(&self) -> String {
+        match self.context.is_empty() {
+            true => self.kind.to_string(),
+            false => format!("{} in {}", self.kind, self.get_path()),
+        }
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+        write!(formatter, "{} at offset {:#x}", self.get_reason(), self.offset)
+    }
+}
+
+impl std::error::Error for ConversionError {}
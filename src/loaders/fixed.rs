@@ -13,6 +13,12 @@ impl<T: ~const FixedByteSize> const FixedByteSizeWrapper for Vec<T> {
     }
 }
 
+impl<T: ~const FixedByteSize> const FixedByteSizeWrapper for Option<T> {
+    fn size_in_bytes() -> usize {
+        T::size_in_bytes()
+    }
+}
+
 #[const_trait]
 pub trait FixedByteSize {
     fn size_in_bytes() -> usize;
@@ -80,37 +86,37 @@ impl const FixedByteSize for f64 {
 
 impl<T: ~const FixedByteSize, const SIZE: usize> const FixedByteSize for [T; SIZE] {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * SIZE
+        T::size_in_bytes() * SIZE
     }
 }
 
 impl<T: ~const FixedByteSize> const FixedByteSize for Vector2<T> {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * 2
+        T::size_in_bytes() * 2
     }
 }
 
 impl<T: ~const FixedByteSize> const FixedByteSize for Vector3<T> {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * 3
+        T::size_in_bytes() * 3
     }
 }
 
 impl<T: ~const FixedByteSize> const FixedByteSize for Vector4<T> {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * 4
+        T::size_in_bytes() * 4
     }
 }
 
 impl<T: ~const FixedByteSize> const FixedByteSize for Quaternion<T> {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * 4
+        T::size_in_bytes() * 4
     }
 }
 
 impl<T: ~const FixedByteSize> const FixedByteSize for Matrix3<T> {
     fn size_in_bytes() -> usize {
-        core::mem::size_of::<T>() * 9
+        T::size_in_bytes() * 9
     }
 }
 
@@ -102,7 +102,7 @@ fn layout_paragraph(font: &Font<'static>, scale: Scale, width: f32, text: &str,
 }
 
 impl FontLoader {
-    pub fn new(memory_allocator: Arc<MemoryAllocator>, queue: Arc<Queue>, game_file_loader: &mut GameFileLoader) -> Self {
+    pub fn new(memory_allocator: Arc<MemoryAllocator>, queue: Arc<Queue>, game_file_loader: &GameFileLoader) -> Self {
         let cache_size = Vector2::from_value(512);
         let cache = Cache::builder().dimensions(cache_size.x, cache_size.y).build();
 
@@ -7,16 +7,75 @@ use crate::debug::*;
 const FILENAME: &str = "client/game_archives.ron";
 const DEFAULT_FILES: &[&str] = &["data.grf", "rdata.grf", "korangar.grf"];
 
+#[derive(Clone, Serialize, Deserialize, PrototypeElement)]
+pub(super) struct ArchiveSettings {
+    pub path: String,
+    /// Archives with a higher priority are searched first. Archives with the
+    /// same priority are searched in reverse order, so later archives
+    /// override earlier ones.
+    #[serde(default)]
+    pub priority: i32,
+    /// Path that all files of the archive are relative to, e.g.
+    /// `data\texture\effect\`.
+    #[serde(default)]
+    pub mount_point: Option<String>,
+}
+
+/// Replaces every request for the asset at `from` with the asset at `to`.
+/// Redirects can be chained, a chain that loops back on itself stops at the
+/// last path before the loop.
+#[derive(Clone, Serialize, Deserialize, PrototypeElement)]
+pub(super) struct Redirect {
+    pub from: String,
+    pub to: String,
+}
+
 #[derive(Serialize, Deserialize, PrototypeElement)]
 pub(super) struct GameArchiveList {
-    pub archives: Vec<String>,
+    pub archives: Vec<ArchiveSettings>,
+    /// Thor or RGZ patches that are merged into a single GRF once and loaded
+    /// on top of the archives.
+    #[serde(default)]
+    pub patches: Vec<String>,
+    #[serde(default)]
+    pub redirects: Vec<Redirect>,
+}
+
+/// The format used before archives could have settings.
+#[derive(Deserialize)]
+struct LegacyGameArchiveList {
+    archives: Vec<String>,
+    #[serde(default)]
+    patches: Vec<String>,
+}
+
+impl From<LegacyGameArchiveList> for GameArchiveList {
+    fn from(legacy: LegacyGameArchiveList) -> Self {
+        let archives = legacy
+            .archives
+            .into_iter()
+            .map(|path| ArchiveSettings {
+                path,
+                priority: 0,
+                mount_point: None,
+            })
+            .collect();
+
+        Self {
+            archives,
+            patches: legacy.patches,
+            redirects: Vec::new(),
+        }
+    }
 }
 
 impl Default for GameArchiveList {
     fn default() -> Self {
-        Self {
+        LegacyGameArchiveList {
             archives: DEFAULT_FILES.iter().map(ToString::to_string).collect(),
+            patches: Vec::new(),
         }
+        .into()
     }
 }
 
@@ -27,8 +86,21 @@ impl GameArchiveList {
 
         std::fs::read_to_string(FILENAME)
             .ok()
-            .and_then(|data| ron::from_str(&data).ok())
-            .map(|archives| Self { archives })
+            .and_then(|data| {
+                // Older versions of the file only contain the paths of the archives or
+                // just the list of archives.
+                ron::from_str(&data)
+                    .ok()
+                    .or_else(|| ron::from_str::<LegacyGameArchiveList>(&data).ok().map(Self::from))
+                    .or_else(|| {
+                        ron::from_str(&data).ok().map(|archives| {
+                            Self::from(LegacyGameArchiveList {
+                                archives,
+                                patches: Vec::new(),
+                            })
+                        })
+                    })
+            })
             .unwrap_or_else(|| {
                 #[cfg(feature = "debug")]
                 print_debug!(
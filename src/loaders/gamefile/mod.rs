@@ -2,15 +2,30 @@
 //! methods to retrieve each of them individually. The archives implement the
 //! [`Archive`](crate::loaders::archive::Archive) trait.
 mod list;
+mod mount;
+mod settings;
 
 use core::panic;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::u8;
 
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+
 use self::list::GameArchiveList;
+use self::mount::{insert_archive, MountedArchive};
+use self::settings::LoaderSettings;
+pub use self::settings::PlaceholderPolicy;
 use super::archive::folder::FolderArchive;
-use super::archive::native::{NativeArchive, NativeArchiveBuilder};
+use super::archive::native::{ArchiveVersion, NativeArchive, NativeArchiveBuilder};
+use super::archive::patch::PatchArchive;
+use super::archive::zip::{ZipArchive, ZipArchiveBuilder};
 use super::archive::{Archive, ArchiveType, Writable};
+use super::{audit_archive, AuditReport, LoaderError};
 #[cfg(feature = "debug")]
 use crate::debug::*;
 
@@ -18,78 +33,282 @@ use crate::debug::*;
 const LUA_GRF_FILE_NAME: &str = "lua_files/";
 #[cfg(not(feature = "patched_as_folder"))]
 const LUA_GRF_FILE_NAME: &str = "lua_files.grf";
+const PATCH_GRF_FILE_NAME: &str = "patches.grf";
+const APPLIED_PATCHES_FILE_NAME: &str = "client/applied_patches.ron";
+const MISSING_ASSETS_FILE_NAME: &str = "client/missing_assets.ron";
+const AUDIT_REPORT_FILE_NAME: &str = "client/audit_report.ron";
+/// How often folder archives are checked for changes if hot reloading is
+/// enabled.
+const HOT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+/// The patch and Lua archives are generated from the other archives, so they
+/// are searched before any of them.
+const GENERATED_ARCHIVE_PRIORITY: i32 = i32::MAX;
+/// The archives of the selected server override the configured archives, but
+/// not the generated ones.
+const SERVER_ARCHIVE_PRIORITY: i32 = GENERATED_ARCHIVE_PRIORITY - 1;
+
+/// An asset that could not be found in any of the archives.
+#[derive(Clone, Debug, Serialize)]
+pub struct MissingAsset {
+    pub path: String,
+    /// The asset that was loaded instead, if any.
+    pub placeholder: Option<String>,
+}
+
+/// Files that were changed in any of the folder archives. See
+/// [`GameFileLoader::get_changed_files`].
+#[derive(Default)]
+pub struct ChangedFiles(HashSet<String>);
+
+impl ChangedFiles {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Accepts the same paths as [`GameFileLoader::get`].
+    pub fn contains(&self, path: &str) -> bool {
+        self.0.contains(&lookup_path(path))
+    }
+}
+
+/// The path that is used to look up a file in the archives. Like mount
+/// points, paths from the settings may use slashes as separators.
+fn lookup_path(path: &str) -> String {
+    path.replace('/', "\\").to_lowercase()
+}
 
 /// Type implementing the game files loader.
 ///
-/// Currently, there are two types implementing
+/// Currently, there are four types implementing
 /// [`Archive`](crate::loaders::archive::Archive):
 /// - [`NativeArchive`](crate::loaders::archive::native::NativeArchive) -
 ///   Retrieve assets from GRF files.
 /// - [`FolderArchive`](crate::loaders::archive::folder::FolderArchive) -
 ///   Retrieve assets from an OS folder.
+/// - [`PatchArchive`](crate::loaders::archive::patch::PatchArchive) - Retrieve
+///   assets from Thor or RGZ patches.
+/// - [`ZipArchive`](crate::loaders::archive::zip::ZipArchive) - Retrieve assets
+///   from zip or PK3 files.
 #[derive(Default)]
 pub struct GameFileLoader {
-    archives: Vec<Box<dyn Archive>>,
+    /// Sorted by the order in which they are searched.
+    archives: Vec<MountedArchive>,
+    /// Maps the lookup path of an asset to the path of the asset that
+    /// replaces it.
+    redirects: HashMap<String, String>,
+    settings: LoaderSettings,
+    /// Every missing asset of this session and the placeholder that was used
+    /// for it.
+    missing_assets: Mutex<BTreeMap<String, Option<String>>>,
 }
 
 impl GameFileLoader {
-    fn add_archive(&mut self, game_archive: Box<dyn Archive>) {
-        self.archives.insert(0, game_archive);
+    /// Archives that are added later override archives with the same
+    /// priority that were added earlier.
+    fn add_archive(&mut self, path: &str, priority: i32, mount_point: Option<&str>, game_archive: Box<dyn Archive>) {
+        insert_archive(
+            &mut self.archives,
+            MountedArchive::new(path, priority, mount_point, game_archive),
+        );
     }
 
-    fn get_archive_type_by_path(path: &Path) -> ArchiveType {
+    /// Archives must either be a directory or have a `.grf`, `.thor`, `.rgz`,
+    /// `.zip` or `.pk3` extension.
+    fn get_archive_type_by_path(path: &Path) -> Result<ArchiveType, LoaderError> {
         if path.is_dir() || path.display().to_string().ends_with('/') {
-            ArchiveType::Folder
-        } else if let Some(extension) = path.extension() && let Some("grf") = extension.to_str() {
-            ArchiveType::Native
-        } else {
-            panic!("Provided archive must be a directory or have a .grf extension")
+            return Ok(ArchiveType::Folder);
+        }
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+
+        match extension.as_str() {
+            "grf" => Ok(ArchiveType::Native),
+            "thor" | "rgz" => Ok(ArchiveType::Patch),
+            "zip" | "pk3" => Ok(ArchiveType::Zip),
+            _ => Err(LoaderError::UnsupportedFormat {
+                path: path.display().to_string(),
+                format: extension,
+            }),
         }
     }
 
-    fn load_archive_from_path(path: &str) -> Box<dyn Archive> {
+    pub fn load_archive_from_path(path: &str) -> Result<Box<dyn Archive>, LoaderError> {
         let path = Path::new(path);
 
-        match GameFileLoader::get_archive_type_by_path(path) {
-            ArchiveType::Folder => Box::new(FolderArchive::from_path(path)),
-            ArchiveType::Native => Box::new(NativeArchive::from_path(path)),
-        }
+        Ok(match GameFileLoader::get_archive_type_by_path(path)? {
+            ArchiveType::Folder => Box::new(FolderArchive::from_path(path)?),
+            ArchiveType::Native => Box::new(NativeArchive::from_path(path)?),
+            ArchiveType::Patch => Box::new(PatchArchive::from_path(path)?),
+            ArchiveType::Zip => Box::new(ZipArchive::from_path(path)?),
+        })
     }
 
-    pub fn load_archives_from_settings(&mut self) {
+    /// Loads the archives of the game archive list and the archives of the
+    /// selected server, which override them.
+    pub fn load_archives_from_settings(&mut self, server_archives: &[String]) {
         #[cfg(feature = "debug")]
         let timer = Timer::new("load game archives");
 
+        self.settings = LoaderSettings::load();
+
         let game_archive_list = GameArchiveList::load();
 
-        game_archive_list.archives.iter().for_each(|path| {
-            let game_archive = Self::load_archive_from_path(path);
-            self.add_archive(game_archive);
-        });
+        game_archive_list
+            .archives
+            .iter()
+            .for_each(|archive_settings| match Self::load_archive_from_path(&archive_settings.path) {
+                Ok(game_archive) => self.add_archive(
+                    &archive_settings.path,
+                    archive_settings.priority,
+                    archive_settings.mount_point.as_deref(),
+                    game_archive,
+                ),
+                Err(_error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!("[{}error{}] failed to load archive: {_error}", RED, NONE);
+                }
+            });
+
+        self.add_server_archives(server_archives);
+
+        self.redirects = game_archive_list
+            .redirects
+            .iter()
+            .map(|redirect| (lookup_path(&redirect.from), redirect.to.clone()))
+            .collect();
+
+        // Record the initial state of all folder archives, so the first poll will
+        // already report changes.
+        if self.settings.hot_reload {
+            self.get_changed_files();
+        }
+
+        if !game_archive_list.patches.is_empty() {
+            Self::apply_patches(&game_archive_list.patches);
+
+            match Self::load_archive_from_path(PATCH_GRF_FILE_NAME) {
+                Ok(patch_archive) => self.add_archive(PATCH_GRF_FILE_NAME, GENERATED_ARCHIVE_PRIORITY, None, patch_archive),
+                Err(_error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!("[{}error{}] failed to load patch archive: {_error}", RED, NONE);
+                }
+            }
+        }
 
         #[cfg(feature = "debug")]
         timer.stop();
     }
 
+    fn add_server_archives(&mut self, server_archives: &[String]) {
+        server_archives.iter().for_each(|path| match Self::load_archive_from_path(path) {
+            Ok(game_archive) => self.add_archive(path, SERVER_ARCHIVE_PRIORITY, None, game_archive),
+            Err(_error) => {
+                #[cfg(feature = "debug")]
+                print_debug!("[{}error{}] failed to load server archive: {_error}", RED, NONE);
+            }
+        });
+    }
+
+    /// Merges all patches that have not been applied yet into the patch GRF.
+    /// If the list of already applied patches is no longer a prefix of the
+    /// patch list (e.g. because a patch was removed), the patch GRF is
+    /// rebuilt from scratch.
+    ///
+    /// A patch that can't be loaded stops the process, so it is tried again on
+    /// the next start.
+    ///
+    /// NOTE: Files removed by a patch are only removed from the patch GRF, so
+    /// they will still be loaded from the other archives.
+    fn apply_patches(patches: &[String]) {
+        let patch_archive_path = Path::new(PATCH_GRF_FILE_NAME);
+
+        let applied_patches: Vec<String> = patch_archive_path
+            .exists()
+            .then(|| {
+                std::fs::read_to_string(APPLIED_PATCHES_FILE_NAME)
+                    .ok()
+                    .and_then(|data| ron::from_str(&data).ok())
+            })
+            .flatten()
+            .unwrap_or_default();
+
+        let pending_patches = match patches.starts_with(&applied_patches) {
+            true => &patches[applied_patches.len()..],
+            false => {
+                std::fs::remove_file(patch_archive_path).expect("unable to remove patch archive");
+                patches
+            }
+        };
+
+        if pending_patches.is_empty() {
+            return;
+        }
+
+        let mut applied_count = patches.len() - pending_patches.len();
+
+        for patch in pending_patches {
+            #[cfg(feature = "debug")]
+            print_debug!("applying patch {}{patch}{}", MAGENTA, NONE);
+
+            match PatchArchive::from_path(Path::new(patch)) {
+                Ok(patch_archive) => patch_archive.merge_into(patch_archive_path),
+                Err(_error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!("[{}error{}] failed to load patch: {_error}", RED, NONE);
+                    break;
+                }
+            }
+
+            applied_count += 1;
+        }
+
+        let data = ron::ser::to_string_pretty(&patches[..applied_count], PrettyConfig::new()).unwrap();
+        std::fs::write(APPLIED_PATCHES_FILE_NAME, data).expect("unable to write file");
+    }
+
     pub fn load_patched_lua_files(&mut self) {
         if !Path::new(LUA_GRF_FILE_NAME).exists() {
-            self.patch_lua_files();
+            if let Err(_error) = self.patch_lua_files() {
+                #[cfg(feature = "debug")]
+                print_debug!("[{}error{}] failed to patch lua files: {_error}", RED, NONE);
+                return;
+            }
         }
 
-        let lua_archive = Self::load_archive_from_path(LUA_GRF_FILE_NAME);
-        self.add_archive(lua_archive);
+        match Self::load_archive_from_path(LUA_GRF_FILE_NAME) {
+            Ok(lua_archive) => self.add_archive(LUA_GRF_FILE_NAME, GENERATED_ARCHIVE_PRIORITY, None, lua_archive),
+            Err(_error) => {
+                #[cfg(feature = "debug")]
+                print_debug!("[{}error{}] failed to load lua archive: {_error}", RED, NONE);
+            }
+        }
     }
 
-    fn patch_lua_files(&mut self) {
+    fn patch_lua_files(&mut self) -> Result<(), LoaderError> {
         use lunify::{unify, Format, Settings};
 
         let mut lua_files = Vec::new();
-        self.archives.iter().for_each(|archive| archive.get_lua_files(&mut lua_files));
+        self.archives.iter().for_each(|mounted_archive| {
+            let mut archive_lua_files = Vec::new();
+            mounted_archive.archive.get_lua_files(&mut archive_lua_files);
+            lua_files.extend(mounted_archive.mount_paths(archive_lua_files));
+        });
 
         let path = Path::new(LUA_GRF_FILE_NAME);
-        let mut lua_archive: Box<dyn Writable> = match GameFileLoader::get_archive_type_by_path(&path) {
-            ArchiveType::Folder => Box::new(FolderArchive::from_path(&path)),
-            ArchiveType::Native => Box::new(NativeArchiveBuilder::from_path(&path)),
+        let mut lua_archive: Box<dyn Writable> = match GameFileLoader::get_archive_type_by_path(path)? {
+            ArchiveType::Folder => Box::new(FolderArchive::from_path(path)?),
+            ArchiveType::Native => Box::new(NativeArchiveBuilder::from_path(path, ArchiveVersion::default())),
+            ArchiveType::Patch => {
+                return Err(LoaderError::UnsupportedFormat {
+                    path: path.display().to_string(),
+                    format: "patch archives can not be written".to_owned(),
+                });
+            }
+            ArchiveType::Zip => Box::new(ZipArchiveBuilder::from_path(path)),
         };
 
         let bytecode_format = Format::default();
@@ -101,13 +320,13 @@ impl GameFileLoader {
         let mut failed_count = 0;
 
         for file_name in lua_files {
-            let bytes = match self.get(&file_name) {
+            let bytes = match self.find(&file_name) {
                 Ok(bytes) => bytes,
                 Err(_error) => {
                     #[cfg(feature = "debug")]
                     {
                         print_debug!(
-                            "[{}warning{}] failed to extract file {}{file_name}{} from the grf: {_error:?}",
+                            "[{}warning{}] failed to extract file {}{file_name}{} from the grf: {_error}",
                             YELLOW,
                             NONE,
                             MAGENTA,
@@ -153,31 +372,433 @@ impl GameFileLoader {
         );
 
         lua_archive.save();
+
+        Ok(())
     }
 
-    pub fn get(&mut self, path: &str) -> Result<Vec<u8>, String> {
-        let lowercase_path = path.to_lowercase();
-        let result = self
-            .archives
-            .iter_mut()
-            .find_map(|archive| archive.get_file_by_path(&lowercase_path))
-            .ok_or(format!("failed to find file {path}"));
+    /// The path that is used to look up a file in the archives, after
+    /// following all redirects.
+    fn resolve_path(&self, path: &str) -> String {
+        let mut lowercase_path = lookup_path(path);
+        let mut visited_paths = HashSet::new();
 
-        // TODO: should this be removed in the future or left in for resilience?
-        if result.is_err() {
-            #[cfg(feature = "debug")]
-            print_debug!("failed to find file {}; tying to replace it with placeholder", path);
-
-            let delimiter = path.len() - 4;
-            match &path[delimiter..] {
-                ".bmp" | ".BMP" => return self.get("data\\texture\\backside.bmp"),
-                ".rsm" => return self.get("data\\model\\abyss\\coin_j_01.rsm"),
-                ".spr" => return self.get("data\\sprite\\npc\\1_f_maria.spr"),
-                ".act" => return self.get("data\\sprite\\npc\\1_f_maria.act"),
-                _other => {}
+        while let Some(redirected_path) = self.redirects.get(&lowercase_path) {
+            if !visited_paths.insert(lowercase_path.clone()) {
+                #[cfg(feature = "debug")]
+                print_debug!("[{}warning{}] redirect of {}{path}{} loops", YELLOW, NONE, MAGENTA, NONE);
+                break;
+            }
+
+            lowercase_path = lookup_path(redirected_path);
+        }
+
+        lowercase_path
+    }
+
+    /// Retrieve a file and the archive it was loaded from, without
+    /// substituting placeholders. The search stops at the first archive that
+    /// contains the file or removes it. Archives that fail to read the file
+    /// are skipped, their error is only returned if no other archive
+    /// contains the file.
+    fn find_with_archive(&self, path: &str) -> Result<(&MountedArchive, Vec<u8>), LoaderError> {
+        let lowercase_path = self.resolve_path(path);
+        let mut first_error = None;
+
+        for mounted_archive in &self.archives {
+            match mounted_archive.get_file_by_path(&lowercase_path) {
+                Ok(data) => return Ok((mounted_archive, data)),
+                Err(LoaderError::NotFound { .. }) => {}
+                Err(error @ LoaderError::Removed { .. }) => return Err(error),
+                Err(error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!(
+                        "[{}warning{}] failed to read {}{path}{} from {}{}{}: {error}",
+                        YELLOW,
+                        NONE,
+                        MAGENTA,
+                        NONE,
+                        MAGENTA,
+                        mounted_archive.path,
+                        NONE
+                    );
+
+                    first_error.get_or_insert(error);
+                }
             }
         }
 
-        result
+        Err(first_error.unwrap_or_else(|| LoaderError::NotFound { path: path.to_owned() }))
+    }
+
+    /// Retrieve a file from the first archive that contains it, without
+    /// substituting placeholders.
+    fn find(&self, path: &str) -> Result<Vec<u8>, LoaderError> {
+        self.find_with_archive(path).map(|(_, data)| data)
+    }
+
+    /// The path of the archive that a file would be loaded from, after
+    /// applying redirects. This reads the file, so it should only be used to
+    /// debug the archive configuration.
+    pub fn get_source_archive(&self, path: &str) -> Option<&str> {
+        self.find_with_archive(path)
+            .ok()
+            .map(|(mounted_archive, _)| mounted_archive.path.as_str())
+    }
+
+    /// Paths have to be real Hangul, so strings read from other game files
+    /// need to be decoded with
+    /// [`decode_game_path`](super::archive::path::decode_game_path) first.
+    ///
+    /// If the file can not be found, it might be replaced with a placeholder,
+    /// depending on the [`PlaceholderPolicy`]. Either way, the file is added
+    /// to the list of missing assets.
+    pub fn get(&self, path: &str) -> Result<Vec<u8>, LoaderError> {
+        let error = match self.find(path) {
+            Err(error @ (LoaderError::NotFound { .. } | LoaderError::Removed { .. })) => error,
+            result => return result,
+        };
+
+        let placeholder = self
+            .settings
+            .get_placeholder(path)
+            .and_then(|placeholder_path| Some((placeholder_path, self.find(placeholder_path).ok()?)));
+
+        #[cfg(feature = "debug")]
+        match &placeholder {
+            Some((placeholder_path, _)) => print_debug!(
+                "[{}warning{}] failed to find file {}{path}{}; replacing it with {}{placeholder_path}{}",
+                YELLOW,
+                NONE,
+                MAGENTA,
+                NONE,
+                MAGENTA,
+                NONE
+            ),
+            None => print_debug!("[{}error{}] failed to find file {}{path}{}", RED, NONE, MAGENTA, NONE),
+        }
+
+        self.missing_assets.lock().unwrap().insert(
+            path.to_owned(),
+            placeholder.as_ref().map(|(placeholder_path, _)| placeholder_path.to_string()),
+        );
+
+        placeholder.map(|(_, data)| data).ok_or(error)
+    }
+
+    /// Polls all archives for files that were added, modified or removed
+    /// since the last call. Only folder archives can change while the client
+    /// is running, so this walks every folder archive.
+    pub fn get_changed_files(&self) -> ChangedFiles {
+        let mut changed_files = Vec::new();
+        self.archives.iter().for_each(|mounted_archive| {
+            let mut archive_changed_files = Vec::new();
+            mounted_archive.archive.get_changed_files(&mut archive_changed_files);
+            changed_files.extend(mounted_archive.mount_paths(archive_changed_files));
+        });
+
+        #[cfg(feature = "debug")]
+        changed_files
+            .iter()
+            .for_each(|file_path| print_debug!("file {}{file_path}{} changed", MAGENTA, NONE));
+
+        ChangedFiles(changed_files.iter().map(|file_path| lookup_path(file_path)).collect())
+    }
+
+    /// Polls the archives for changes on a background thread, since walking
+    /// large folder archives takes too long to do it every frame. Every
+    /// non-empty set of changes is sent to the returned receiver. Returns
+    /// [`None`] if hot reloading is disabled. The thread exits once it fails
+    /// to send changes because the receiver was dropped.
+    pub fn watch_changes(self: &Arc<Self>) -> Option<Receiver<ChangedFiles>> {
+        if !self.settings.hot_reload {
+            return None;
+        }
+
+        let (sender, receiver) = channel();
+        let game_file_loader = self.clone();
+
+        std::thread::Builder::new()
+            .name("hot reload".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(HOT_RELOAD_INTERVAL);
+
+                    let changed_files = game_file_loader.get_changed_files();
+
+                    if !changed_files.is_empty() && sender.send(changed_files).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn hot reload thread");
+
+        Some(receiver)
+    }
+
+    /// All assets that were missing so far, sorted by path.
+    pub fn get_missing_assets(&self) -> Vec<MissingAsset> {
+        self.missing_assets
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, placeholder)| MissingAsset {
+                path: path.clone(),
+                placeholder: placeholder.clone(),
+            })
+            .collect()
+    }
+
+    /// Writes the list of missing assets to disk, so they can be fixed after
+    /// the session. A report of a previous session is removed if nothing was
+    /// missing.
+    pub fn save_missing_asset_report(&self) {
+        let missing_assets = self.get_missing_assets();
+
+        if missing_assets.is_empty() {
+            let _ = std::fs::remove_file(MISSING_ASSETS_FILE_NAME);
+            return;
+        }
+
+        #[cfg(feature = "debug")]
+        print_debug!(
+            "saving {}{}{} missing assets to {}{MISSING_ASSETS_FILE_NAME}{}",
+            YELLOW,
+            missing_assets.len(),
+            NONE,
+            MAGENTA,
+            NONE
+        );
+
+        let data = ron::ser::to_string_pretty(&missing_assets, PrettyConfig::new()).unwrap();
+        std::fs::write(MISSING_ASSETS_FILE_NAME, data).expect("unable to write file");
+    }
+
+    /// Audits every archive and writes the reports to disk. This reads and
+    /// parses every file, so it should not be called from the main thread.
+    /// See [`audit_archive`] for details.
+    ///
+    /// Fails if an archive can't be accessed or the report can't be written.
+    pub fn save_audit_report(&self) -> std::io::Result<()> {
+        let reports: Vec<AuditReport> = self
+            .archives
+            .iter()
+            .map(|mounted_archive| audit_archive(&mounted_archive.path, mounted_archive.archive.as_ref()))
+            .collect::<std::io::Result<_>>()?;
+
+        #[cfg(feature = "debug")]
+        reports.iter().for_each(|report| {
+            print_debug!(
+                "audited {}{}{}; {}{}{} files, {}{}{} issues",
+                MAGENTA,
+                report.archive,
+                NONE,
+                YELLOW,
+                report.file_count,
+                NONE,
+                RED,
+                report.issues.len(),
+                NONE
+            )
+        });
+
+        let data = ron::ser::to_string_pretty(&reports, PrettyConfig::new())
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?;
+        std::fs::write(AUDIT_REPORT_FILE_NAME, data)
+    }
+}
+
+#[cfg(test)]
+mod lookup {
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{lookup_path, GameFileLoader, GENERATED_ARCHIVE_PRIORITY};
+    use crate::loaders::archive::folder::FolderArchive;
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::{LoaderError, TemporaryPath};
+
+    fn create_folder_archive(name: &str, files: &[(&str, &[u8])]) -> (TemporaryPath, FolderArchive) {
+        let path = TemporaryPath::new(&format!("lookup_{name}"));
+        let mut archive = FolderArchive::from_path(&path).unwrap();
+        archive.create();
+
+        files
+            .iter()
+            .for_each(|(file_path, data)| archive.add_file(file_path, data.to_vec()));
+
+        (path, archive)
+    }
+
+    fn add_folder_archive(game_file_loader: &mut GameFileLoader, name: &str, priority: i32, files: &[(&str, &[u8])]) -> TemporaryPath {
+        let (path, archive) = create_folder_archive(name, files);
+        game_file_loader.add_archive(name, priority, None, Box::new(archive));
+        path
+    }
+
+    #[test]
+    fn generated_archives_override_configured_archives() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _paths = [
+            add_folder_archive(&mut game_file_loader, "configured", 100, &[("data\\skill.lub", b"original")]),
+            add_folder_archive(&mut game_file_loader, "generated", GENERATED_ARCHIVE_PRIORITY, &[(
+                "data\\skill.lub",
+                b"unified",
+            )]),
+            add_folder_archive(&mut game_file_loader, "later", 100, &[("data\\skill.lub", b"original")]),
+        ];
+
+        assert_eq!(game_file_loader.get("data\\skill.lub"), Ok(b"unified".to_vec()));
+        assert_eq!(game_file_loader.get_source_archive("data\\skill.lub"), Some("generated"));
+        assert_eq!(game_file_loader.get_source_archive("data\\missing.lub"), None);
+    }
+
+    #[test]
+    fn server_archives_override_configured_archives() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _configured_path = add_folder_archive(&mut game_file_loader, "configured_high", 1000, &[(
+            "data\\sprite.spr",
+            b"configured",
+        )]);
+        let (server_path, _) = create_folder_archive("server", &[("data\\sprite.spr", b"server")]);
+        let server_archive = server_path.display().to_string();
+
+        game_file_loader.add_server_archives(&[server_archive.clone()]);
+
+        assert_eq!(game_file_loader.get("data\\sprite.spr"), Ok(b"server".to_vec()));
+        assert_eq!(
+            game_file_loader.get_source_archive("data\\sprite.spr"),
+            Some(server_archive.as_str())
+        );
+    }
+
+    /// Stands in for an archive with a corrupt entry for every file.
+    struct CorruptArchive;
+
+    impl Archive for CorruptArchive {
+        fn from_path(_path: &Path) -> Result<Self, LoaderError> {
+            Ok(Self)
+        }
+
+        fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError> {
+            Err(LoaderError::Decompression {
+                path: asset_path.to_owned(),
+                reason: "corrupt".to_owned(),
+            })
+        }
+
+        fn get_lua_files(&self, _lua_files: &mut Vec<String>) {}
+
+        fn get_file_paths(&self, _file_paths: &mut Vec<String>) {}
+    }
+
+    #[test]
+    fn corrupt_files_fall_back_to_lower_archives() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _path = add_folder_archive(&mut game_file_loader, "intact", 0, &[("data\\intact.bmp", b"intact")]);
+        game_file_loader.add_archive("corrupt", 1, None, Box::new(CorruptArchive));
+
+        assert_eq!(game_file_loader.get("data\\intact.bmp"), Ok(b"intact".to_vec()));
+        assert_eq!(game_file_loader.get_source_archive("data\\intact.bmp"), Some("intact"));
+        assert!(matches!(
+            game_file_loader.get("data\\missing.bmp"),
+            Err(LoaderError::Decompression { .. })
+        ));
+    }
+
+    /// Stands in for a patch archive that removes every file.
+    struct RemovingArchive;
+
+    impl Archive for RemovingArchive {
+        fn from_path(_path: &Path) -> Result<Self, LoaderError> {
+            Ok(Self)
+        }
+
+        fn get_file_by_path(&self, asset_path: &str) -> Result<Vec<u8>, LoaderError> {
+            Err(LoaderError::Removed {
+                path: asset_path.to_owned(),
+            })
+        }
+
+        fn get_lua_files(&self, _lua_files: &mut Vec<String>) {}
+
+        fn get_file_paths(&self, _file_paths: &mut Vec<String>) {}
+    }
+
+    #[test]
+    fn removed_files_hide_lower_archives() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _path = add_folder_archive(&mut game_file_loader, "removed", 0, &[("data\\removed.bmp", b"removed")]);
+        game_file_loader.add_archive("patch", 1, None, Box::new(RemovingArchive));
+
+        assert!(matches!(
+            game_file_loader.get("data\\removed.bmp"),
+            Err(LoaderError::Removed { .. })
+        ));
+        assert_eq!(game_file_loader.get_source_archive("data\\removed.bmp"), None);
+        assert_eq!(game_file_loader.get_missing_assets().len(), 1);
+    }
+
+    #[test]
+    fn paths_are_not_decoded() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _path = add_folder_archive(&mut game_file_loader, "latin", 0, &[("data\\café.bmp", b"cafe")]);
+
+        assert_eq!(game_file_loader.get("data\\Café.bmp"), Ok(b"cafe".to_vec()));
+    }
+
+    #[test]
+    fn changes_are_watched_in_the_background() {
+        let mut game_file_loader = GameFileLoader::default();
+        let path = add_folder_archive(&mut game_file_loader, "watched", 0, &[("data\\first.txt", b"first")]);
+        game_file_loader.settings.hot_reload = true;
+        game_file_loader.get_changed_files();
+
+        let game_file_loader = Arc::new(game_file_loader);
+        let receiver = game_file_loader.watch_changes().unwrap();
+
+        FolderArchive::from_path(&path)
+            .unwrap()
+            .add_file("data\\Second.txt", b"second".to_vec());
+
+        let changed_files = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(changed_files.contains("data\\second.txt"));
+        assert!(!changed_files.contains("data\\first.txt"));
+    }
+
+    #[test]
+    fn redirects() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _paths = [
+            add_folder_archive(&mut game_file_loader, "first", 0, &[("data\\old.bmp", b"old")]),
+            add_folder_archive(&mut game_file_loader, "second", 0, &[("data\\new.bmp", b"new")]),
+        ];
+        game_file_loader
+            .redirects
+            .insert(lookup_path("Data/Old.bmp"), "data\\new.bmp".to_owned());
+
+        assert_eq!(game_file_loader.get("data\\old.bmp"), Ok(b"new".to_vec()));
+        assert_eq!(game_file_loader.get_source_archive("DATA\\OLD.BMP"), Some("second"));
+    }
+
+    #[test]
+    fn chained_redirects() {
+        let mut game_file_loader = GameFileLoader::default();
+        let _path = add_folder_archive(&mut game_file_loader, "chained", 0, &[
+            ("data\\first.bmp", b"first"),
+            ("data\\third.bmp", b"third"),
+        ]);
+        game_file_loader
+            .redirects
+            .insert(lookup_path("data\\first.bmp"), "data\\second.bmp".to_owned());
+        game_file_loader
+            .redirects
+            .insert(lookup_path("data\\second.bmp"), "data\\third.bmp".to_owned());
+        game_file_loader
+            .redirects
+            .insert(lookup_path("data\\loop.bmp"), "data\\loop.bmp".to_owned());
+
+        assert_eq!(game_file_loader.get("data\\first.bmp"), Ok(b"third".to_vec()));
+        assert_eq!(game_file_loader.resolve_path("data\\loop.bmp"), "data\\loop.bmp");
     }
 }
@@ -0,0 +1,112 @@
+use crate::loaders::archive::Archive;
+use crate::loaders::LoaderError;
+
+/// An archive that is part of the virtual file system of the
+/// [`GameFileLoader`](super::GameFileLoader).
+pub(super) struct MountedArchive {
+    /// The path the archive was loaded from.
+    pub path: String,
+    pub priority: i32,
+    /// Lowercase prefix of all files in the archive, ending with a backslash.
+    /// Empty if the archive is mounted at the root.
+    mount_point: String,
+    pub archive: Box<dyn Archive>,
+}
+
+impl MountedArchive {
+    pub fn new(path: &str, priority: i32, mount_point: Option<&str>, archive: Box<dyn Archive>) -> Self {
+        let mount_point = match mount_point.map(|mount_point| mount_point.replace('/', "\\").to_lowercase()) {
+            Some(mount_point) if !mount_point.is_empty() && !mount_point.ends_with('\\') => format!("{mount_point}\\"),
+            mount_point => mount_point.unwrap_or_default(),
+        };
+
+        Self {
+            path: path.to_owned(),
+            priority,
+            mount_point,
+            archive,
+        }
+    }
+
+    /// Retrieve a file using the path of the client. Files outside of the
+    /// mount point are never found.
+    pub fn get_file_by_path(&self, lookup_path: &str) -> Result<Vec<u8>, LoaderError> {
+        match lookup_path.strip_prefix(&self.mount_point) {
+            Some(archive_path) => self.archive.get_file_by_path(archive_path),
+            None => Err(LoaderError::NotFound {
+                path: lookup_path.to_owned(),
+            }),
+        }
+    }
+
+    /// Converts paths of the archive to paths of the client.
+    pub fn mount_paths(&self, archive_paths: Vec<String>) -> impl Iterator<Item = String> + '_ {
+        archive_paths
+            .into_iter()
+            .map(|archive_path| format!("{}{archive_path}", self.mount_point))
+    }
+}
+
+/// Inserts an archive in front of all archives with the same or a lower
+/// priority, so archives that are added later override earlier ones.
+pub(super) fn insert_archive(archives: &mut Vec<MountedArchive>, archive: MountedArchive) {
+    let index = archives
+        .iter()
+        .position(|other| other.priority <= archive.priority)
+        .unwrap_or(archives.len());
+
+    archives.insert(index, archive);
+}
+
+#[cfg(test)]
+mod virtual_file_system {
+    use super::{insert_archive, MountedArchive};
+    use crate::loaders::archive::folder::FolderArchive;
+    use crate::loaders::archive::{Archive, Writable};
+    use crate::loaders::TemporaryPath;
+
+    fn folder_archive(name: &str, files: &[(&str, &[u8])]) -> (TemporaryPath, Box<dyn Archive>) {
+        let path = TemporaryPath::new(&format!("mount_{name}"));
+        let mut archive = FolderArchive::from_path(&path).unwrap();
+        archive.create();
+
+        files
+            .iter()
+            .for_each(|(file_path, data)| archive.add_file(file_path, data.to_vec()));
+
+        (path, Box::new(archive))
+    }
+
+    #[test]
+    fn mount_point() {
+        let (_path, archive) = folder_archive("point", &[("fire.bmp", b"fire")]);
+        let mounted = MountedArchive::new("effects", 0, Some("Data/Texture/Effect"), archive);
+
+        assert_eq!(
+            mounted.get_file_by_path("data\\texture\\effect\\fire.bmp"),
+            Ok(b"fire".to_vec())
+        );
+        assert!(mounted.get_file_by_path("fire.bmp").is_err());
+
+        let mut archive_paths = Vec::new();
+        mounted.archive.get_file_paths(&mut archive_paths);
+        assert_eq!(mounted.mount_paths(archive_paths).collect::<Vec<_>>(), [
+            "data\\texture\\effect\\fire.bmp"
+        ]);
+    }
+
+    #[test]
+    fn priorities() {
+        let mut archives = Vec::new();
+        let mut paths = Vec::new();
+
+        for (name, priority) in [("first", 0), ("high", 5), ("second", 0), ("low", -1)] {
+            let (path, archive) = folder_archive(name, &[]);
+            insert_archive(&mut archives, MountedArchive::new(name, priority, None, archive));
+            paths.push(path);
+        }
+
+        let order: Vec<&str> = archives.iter().map(|archive| archive.path.as_str()).collect();
+        assert_eq!(order, ["high", "second", "first", "low"]);
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+const FILENAME: &str = "client/loader_settings.ron";
+
+/// What to do when an asset can not be found in any of the archives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlaceholderPolicy {
+    /// Fail with [`LoaderError::NotFound`](crate::loaders::LoaderError).
+    Fail,
+    /// Load the placeholder for the file extension instead. Assets without a
+    /// placeholder still fail.
+    Substitute,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct LoaderSettings {
+    pub placeholder_policy: PlaceholderPolicy,
+    /// File extensions and the path of the asset that replaces missing
+    /// assets with that extension.
+    pub placeholders: Vec<(String, String)>,
+    /// Watch folder archives for changes and reload the affected assets.
+    #[serde(default)]
+    pub hot_reload: bool,
+}
+
+impl Default for LoaderSettings {
+    fn default() -> Self {
+        let placeholders = [
+            ("bmp", "data\\texture\\backside.bmp"),
+            ("rsm", "data\\model\\abyss\\coin_j_01.rsm"),
+            ("spr", "data\\sprite\\npc\\1_f_maria.spr"),
+            ("act", "data\\sprite\\npc\\1_f_maria.act"),
+        ];
+
+        Self {
+            placeholder_policy: PlaceholderPolicy::Substitute,
+            placeholders: placeholders
+                .into_iter()
+                .map(|(extension, path)| (extension.to_owned(), path.to_owned()))
+                .collect(),
+            hot_reload: false,
+        }
+    }
+}
+
+impl LoaderSettings {
+    pub(super) fn load() -> Self {
+        #[cfg(feature = "debug")]
+        print_debug!("loading loader settings from {}{FILENAME}{}", MAGENTA, NONE);
+
+        std::fs::read_to_string(FILENAME)
+            .ok()
+            .and_then(|data| ron::from_str(&data).ok())
+            .unwrap_or_else(|| {
+                #[cfg(feature = "debug")]
+                print_debug!(
+                    "[{}error{}] failed to load loader settings from {}{FILENAME}{}; trying with default",
+                    RED,
+                    NONE,
+                    MAGENTA,
+                    NONE
+                );
+
+                LoaderSettings::default()
+            })
+    }
+
+    /// The placeholder for a missing asset, if the policy allows it.
+    pub(super) fn get_placeholder(&self, path: &str) -> Option<&str> {
+        if self.placeholder_policy == PlaceholderPolicy::Fail {
+            return None;
+        }
+
+        let (_, extension) = path.rsplit_once('.')?;
+
+        self.placeholders
+            .iter()
+            .find(|(placeholder_extension, _)| placeholder_extension.eq_ignore_ascii_case(extension))
+            .map(|(_, placeholder_path)| placeholder_path.as_str())
+    }
+}
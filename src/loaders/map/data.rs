@@ -3,7 +3,9 @@ use procedural::*;
 pub use super::resource::MapResources;
 use crate::graphics::ColorBGRA;
 use crate::loaders::map::resource::{LightSettings, WaterSettings};
-use crate::loaders::{ByteConvertable, ByteStream, MajorFirst, Version};
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, InternalVersion, MajorFirst, PaddedString, Version};
 use crate::world::Tile;
 
 #[derive(Clone, ByteConvertable, PrototypeElement, PrototypeWindow)]
@@ -17,14 +19,14 @@ pub struct MapData {
     #[version_equals_or_above(2, 2)]
     pub _unknown: Option<u8>,
     #[length_hint(40)]
-    pub _ini_file: String,
+    pub _ini_file: PaddedString,
     #[length_hint(40)]
-    pub ground_file: String,
+    pub ground_file: PaddedString,
     #[length_hint(40)]
-    pub gat_file: String,
+    pub gat_file: PaddedString,
     #[version_equals_or_above(1, 4)]
     #[length_hint(40)]
-    pub _source_file: Option<String>,
+    pub _source_file: Option<PaddedString>,
     #[version_smaller(2, 6)]
     pub water_settings: Option<WaterSettings>,
     pub light_settings: LightSettings,
@@ -48,7 +50,7 @@ pub struct GatData {
     pub version: Version<MajorFirst>,
     pub map_width: i32,
     pub map_height: i32,
-    #[repeating(self.map_width * self.map_height)]
+    #[repeating((self.map_width as usize).saturating_mul(self.map_height as usize))]
     pub tiles: Vec<Tile>,
 }
 
@@ -63,21 +65,26 @@ pub struct GroundData {
     pub texture_name_length: i32,
     #[repeating(self.texture_count)]
     #[length_hint(self.texture_name_length)]
-    pub textures: Vec<String>,
+    pub textures: Vec<PaddedString>,
     pub light_map_count: i32,
     pub light_map_width: i32,
     pub light_map_height: i32,
     pub light_map_cells_per_grid: i32,
     #[version_equals_or_above(1, 7)]
-    #[length_hint(self.light_map_count * self.light_map_width * self.light_map_height * 4)]
+    #[length_hint(
+        (self.light_map_count as usize)
+            .saturating_mul(self.light_map_width as usize)
+            .saturating_mul(self.light_map_height as usize)
+            .saturating_mul(4)
+    )]
     pub _skip: Option<Vec<u8>>,
     #[version_smaller(1, 7)]
-    #[length_hint(self.light_map_count * 16)]
+    #[length_hint((self.light_map_count as usize).saturating_mul(16))]
     pub _skip2: Option<Vec<u8>>,
     pub surface_count: i32,
     #[repeating(self.surface_count)]
     pub surfaces: Vec<Surface>,
-    #[repeating(self.width as usize * self.height as usize)]
+    #[repeating((self.width as usize).saturating_mul(self.height as usize))]
     pub ground_tiles: Vec<GroundTile>,
 }
 
@@ -106,28 +113,28 @@ impl GroundTile {
 }
 
 impl ByteConvertable for GroundTile {
-    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Self {
-        let upper_left_height = f32::from_bytes(byte_stream, None);
-        let upper_right_height = f32::from_bytes(byte_stream, None);
-        let lower_left_height = f32::from_bytes(byte_stream, None);
-        let lower_right_height = f32::from_bytes(byte_stream, None);
+    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Result<Self, ConversionError> {
+        let upper_left_height = f32::from_bytes(byte_stream, None)?;
+        let upper_right_height = f32::from_bytes(byte_stream, None)?;
+        let lower_left_height = f32::from_bytes(byte_stream, None)?;
+        let lower_right_height = f32::from_bytes(byte_stream, None)?;
 
         let top_surface_index = match byte_stream.get_version().equals_or_above(1, 7) {
-            true => i32::from_bytes(byte_stream, None),
-            false => i16::from_bytes(byte_stream, None) as i32,
+            true => i32::from_bytes(byte_stream, None)?,
+            false => i16::from_bytes(byte_stream, None)? as i32,
         };
 
         let front_surface_index = match byte_stream.get_version().equals_or_above(1, 7) {
-            true => i32::from_bytes(byte_stream, None),
-            false => i16::from_bytes(byte_stream, None) as i32,
+            true => i32::from_bytes(byte_stream, None)?,
+            false => i16::from_bytes(byte_stream, None)? as i32,
         };
 
         let right_surface_index = match byte_stream.get_version().equals_or_above(1, 7) {
-            true => i32::from_bytes(byte_stream, None),
-            false => i16::from_bytes(byte_stream, None) as i32,
+            true => i32::from_bytes(byte_stream, None)?,
+            false => i16::from_bytes(byte_stream, None)? as i32,
         };
 
-        Self {
+        Ok(Self {
             upper_left_height,
             upper_right_height,
             lower_left_height,
@@ -135,6 +142,44 @@ impl ByteConvertable for GroundTile {
             top_surface_index,
             front_surface_index,
             right_surface_index,
+        })
+    }
+
+    fn to_versioned_bytes(&self, version: Option<InternalVersion>, _: Option<usize>) -> Vec<u8> {
+        let version = version.expect("ground tiles can only be serialized with a version");
+
+        let mut bytes = self.upper_left_height.to_bytes(None);
+        bytes.extend(self.upper_right_height.to_bytes(None));
+        bytes.extend(self.lower_left_height.to_bytes(None));
+        bytes.extend(self.lower_right_height.to_bytes(None));
+
+        for surface_index in [self.top_surface_index, self.front_surface_index, self.right_surface_index] {
+            match version.equals_or_above(1, 7) {
+                true => bytes.extend(surface_index.to_bytes(None)),
+                false => bytes.extend((surface_index as i16).to_bytes(None)),
+            }
+        }
+
+        bytes
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for GroundTile {
+    fn arbitrary(generator: &mut Generator, _: Option<usize>) -> Self {
+        let surface_index = |generator: &mut Generator| match generator.get_version().equals_or_above(1, 7) {
+            true => i32::arbitrary(generator, None),
+            false => i16::arbitrary(generator, None) as i32,
+        };
+
+        Self {
+            upper_left_height: f32::arbitrary(generator, None),
+            upper_right_height: f32::arbitrary(generator, None),
+            lower_left_height: f32::arbitrary(generator, None),
+            lower_right_height: f32::arbitrary(generator, None),
+            top_surface_index: surface_index(generator),
+            front_surface_index: surface_index(generator),
+            right_surface_index: surface_index(generator),
         }
     }
 }
@@ -1,65 +1,294 @@
 mod data;
+mod progress;
 mod resource;
 mod vertices;
 
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
 use cgmath::Vector3;
 use derive_new::new;
+use image::RgbaImage;
+use rayon::prelude::*;
 
 #[cfg(feature = "debug")]
 pub use self::data::MapData;
 use self::data::*;
+pub use self::progress::{MapLoadingJob, MapLoadingProgress, MapLoadingStage};
+use self::resource::ObjectData;
 pub use self::resource::{LightSettings, WaterSettings};
 use self::vertices::{generate_tile_vertices, ground_water_vertices, load_textures};
 #[cfg(feature = "debug")]
 use crate::debug::*;
 use crate::graphics::{BufferAllocator, NativeModelVertex};
-use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, ModelLoader, TextureLoader};
+use crate::loaders::archive::path::decode_game_path;
+use crate::loaders::{ByteConvertable, ByteStream, ChangedFiles, GameFileLoader, LoaderError, ModelData, ModelLoader, TextureLoader};
 use crate::world::*;
 
 const MAP_OFFSET: f32 = 5.0;
 
+/// Everything about a map that can be loaded without access to the GPU.
+/// Only contains the models and textures that were not cached when loading
+/// started.
+struct ParsedMap {
+    map_data: MapData,
+    ground_data: GroundData,
+    gat_data: GatData,
+    models: Vec<(String, bool, ModelData)>,
+    textures: Vec<(String, RgbaImage)>,
+}
+
 #[derive(new)]
 pub struct MapLoader {
     #[new(default)]
     cache: HashMap<String, Arc<Map>>,
+    /// Paths of all files that a map was loaded from, including the files of
+    /// its textures and models.
+    #[new(default)]
+    dependencies: HashMap<String, Vec<String>>,
 }
 
 impl MapLoader {
     pub fn get(
         &mut self,
         resource_file: String,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         buffer_allocator: &mut BufferAllocator,
         model_loader: &mut ModelLoader,
         texture_loader: &mut TextureLoader,
-    ) -> Result<Arc<Map>, String> {
+    ) -> Result<Arc<Map>, LoaderError> {
         match self.cache.get(&resource_file) {
             Some(map) => Ok(map.clone()),
             None => self.load(resource_file, game_file_loader, buffer_allocator, model_loader, texture_loader),
         }
     }
 
+    /// Removes all cached maps where any of the files they were loaded from
+    /// was changed on disk. Returns `true` if any maps were removed.
+    pub fn invalidate(&mut self, changed_files: &ChangedFiles) -> bool {
+        let invalidated: Vec<String> = self
+            .dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().any(|path| changed_files.contains(path)))
+            .map(|(resource_file, _)| resource_file.clone())
+            .collect();
+
+        invalidated.iter().for_each(|resource_file| {
+            self.cache.remove(resource_file);
+            self.dependencies.remove(resource_file);
+        });
+
+        !invalidated.is_empty()
+    }
+
+    /// Starts loading a map on a background thread, so the caller can keep
+    /// running while the files are read and decoded. Only the steps that need
+    /// the GPU are performed by [`finish_loading`](Self::finish_loading).
+    pub fn start_loading(
+        &self,
+        resource_file: String,
+        game_file_loader: Arc<GameFileLoader>,
+        model_loader: &ModelLoader,
+        texture_loader: &TextureLoader,
+    ) -> MapLoadingJob {
+        let progress = Arc::new(Mutex::new(MapLoadingProgress::default()));
+
+        let handle = (!self.cache.contains_key(&resource_file)).then(|| {
+            let resource_file = resource_file.clone();
+            let progress = progress.clone();
+            let cached_models = model_loader.get_cached_models();
+            let cached_textures = texture_loader.get_cached_paths();
+
+            std::thread::Builder::new()
+                .name("map loader".to_string())
+                .spawn(move || Self::parse(&resource_file, &game_file_loader, &cached_models, &cached_textures, &progress))
+                .expect("failed to spawn map loading thread")
+        });
+
+        MapLoadingJob {
+            resource_file,
+            progress,
+            handle,
+        }
+    }
+
+    /// Uploads a map that was loaded in the background. Should only be called
+    /// once [`MapLoadingJob::is_finished`] returns `true`, otherwise this will
+    /// block until the background thread is done.
+    pub fn finish_loading(
+        &mut self,
+        job: MapLoadingJob,
+        game_file_loader: &GameFileLoader,
+        buffer_allocator: &mut BufferAllocator,
+        model_loader: &mut ModelLoader,
+        texture_loader: &mut TextureLoader,
+    ) -> Result<Arc<Map>, LoaderError> {
+        let MapLoadingJob { resource_file, handle, .. } = job;
+
+        let Some(handle) = handle else {
+            return self.get(resource_file, game_file_loader, buffer_allocator, model_loader, texture_loader);
+        };
+
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("upload map {}", &resource_file));
+
+        let parsed_map = handle
+            .join()
+            .map_err(|payload| LoaderError::panicked(resource_file.clone(), payload))??;
+        let map = self.build(
+            resource_file,
+            parsed_map,
+            game_file_loader,
+            buffer_allocator,
+            model_loader,
+            texture_loader,
+        );
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+
+        map
+    }
+
     fn load(
         &mut self,
         resource_file: String,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         buffer_allocator: &mut BufferAllocator,
         model_loader: &mut ModelLoader,
         texture_loader: &mut TextureLoader,
-    ) -> Result<Arc<Map>, String> {
+    ) -> Result<Arc<Map>, LoaderError> {
         #[cfg(feature = "debug")]
         let timer = Timer::new_dynamic(format!("load map from {}", &resource_file));
 
-        let mut map_data = parse_map_data(&resource_file, game_file_loader)?;
+        let parsed_map = Self::parse(
+            &resource_file,
+            game_file_loader,
+            &model_loader.get_cached_models(),
+            &texture_loader.get_cached_paths(),
+            &Mutex::default(),
+        )?;
+        let map = self.build(
+            resource_file,
+            parsed_map,
+            game_file_loader,
+            buffer_allocator,
+            model_loader,
+            texture_loader,
+        );
+
+        #[cfg(feature = "debug")]
+        timer.stop();
+
+        map
+    }
+
+    /// Reads and decodes all files of a map. Models and textures are decoded
+    /// in parallel. This may run on any thread, so it must not use the
+    /// profiler or [`Timer`](crate::debug::Timer)s.
+    fn parse(
+        resource_file: &str,
+        game_file_loader: &GameFileLoader,
+        cached_models: &HashSet<(String, bool)>,
+        cached_textures: &HashSet<String>,
+        progress: &Mutex<MapLoadingProgress>,
+    ) -> Result<ParsedMap, LoaderError> {
+        MapLoadingProgress::start_stage(progress, MapLoadingStage::Resources, 1);
+        let map_data = parse_map_data(resource_file, game_file_loader)?;
+
+        MapLoadingProgress::start_stage(progress, MapLoadingStage::Ground, 1);
+        let ground_data = parse_ground_data(&decode_game_path(&map_data.ground_file.inner), game_file_loader)?;
+
+        MapLoadingProgress::start_stage(progress, MapLoadingStage::Altitude, 1);
+        let gat_data = parse_gat_data(&decode_game_path(&map_data.gat_file.inner), game_file_loader)?;
+
+        let pending_models: HashSet<(String, bool)> = map_data
+            .resources
+            .objects
+            .iter()
+            .map(|object_data| (decode_game_path(&object_data.model_name.inner), is_reverse_order(object_data)))
+            .filter(|model| !cached_models.contains(model))
+            .collect();
+
+        // Models that fail to parse are skipped here, so loading them again from
+        // `build` will report the error.
+        MapLoadingProgress::start_stage(progress, MapLoadingStage::Models, pending_models.len());
+        let models: Vec<(String, bool, ModelData)> = pending_models
+            .into_par_iter()
+            .filter_map(|(model_file, reverse_order)| {
+                let model_data = ModelLoader::parse(game_file_loader, &model_file);
+                MapLoadingProgress::complete_step(progress);
+                Some((model_file, reverse_order, model_data.ok()?))
+            })
+            .collect();
+
+        let model_textures = models
+            .iter()
+            .flat_map(|(_, _, model_data)| model_data.texture_names.iter())
+            .map(|texture_name| texture_name.inner.as_str());
+        let pending_textures: HashSet<String> = ground_data
+            .textures
+            .iter()
+            .map(|texture_name| texture_name.inner.as_str())
+            .chain(model_textures)
+            .map(decode_game_path)
+            .filter(|path| !cached_textures.contains(path))
+            .collect();
+
+        MapLoadingProgress::start_stage(progress, MapLoadingStage::Textures, pending_textures.len());
+        let textures: Vec<(String, RgbaImage)> = pending_textures
+            .into_par_iter()
+            .filter_map(|path| {
+                let image_buffer = TextureLoader::decode(&path, game_file_loader);
+                MapLoadingProgress::complete_step(progress);
+                Some((path, image_buffer.ok()?))
+            })
+            .collect();
+
+        Ok(ParsedMap {
+            map_data,
+            ground_data,
+            gat_data,
+            models,
+            textures,
+        })
+    }
+
+    /// Creates all GPU resources of a map and adds it to the cache.
+    fn build(
+        &mut self,
+        resource_file: String,
+        parsed_map: ParsedMap,
+        game_file_loader: &GameFileLoader,
+        buffer_allocator: &mut BufferAllocator,
+        model_loader: &mut ModelLoader,
+        texture_loader: &mut TextureLoader,
+    ) -> Result<Arc<Map>, LoaderError> {
+        let ParsedMap {
+            map_data,
+            ground_data,
+            mut gat_data,
+            models,
+            textures,
+        } = parsed_map;
 
         #[cfg(feature = "debug")]
         let map_data_clone = map_data.clone();
 
-        let ground_data = parse_ground_data(map_data.ground_file.as_str(), game_file_loader)?;
-        let mut gat_data = parse_gat_data(map_data.gat_file.as_str(), game_file_loader)?;
+        for (path, image_buffer) in textures {
+            texture_loader.upload(&path, image_buffer);
+        }
+
+        for (model_file, reverse_order, model_data) in models {
+            let _ = model_loader.build(
+                buffer_allocator,
+                game_file_loader,
+                texture_loader,
+                &model_file,
+                reverse_order,
+                model_data,
+            );
+        }
 
         let (tile_vertices, tile_picker_vertices) = generate_tile_vertices(&mut gat_data);
         let water_level = -map_data
@@ -76,35 +305,60 @@ impl MapLoader {
         let tile_picker_vertex_buffer =
             (!tile_picker_vertices.is_empty()).then(|| buffer_allocator.allocate_vertex_buffer(tile_picker_vertices));
 
-        let textures = load_textures(&ground_data, texture_loader, game_file_loader);
-        apply_map_offset(&ground_data, &mut map_data.resources);
+        let textures = load_textures(&ground_data, texture_loader, game_file_loader)?;
+        let map_offset = get_map_offset(&ground_data);
+        let (light_sources, sound_sources, effect_sources) = offset_sources(&map_data.resources, map_offset);
 
         // Loading object models
-        let objects: Vec<Object> = map_data
+        let objects = map_data
             .resources
             .objects
             .iter()
-            .map(|object_data| {
-                let array: [f32; 3] = object_data.transform.scale.into();
-                let reverse_order = array.into_iter().fold(1.0, |a, b| a * b).is_sign_negative();
+            .enumerate()
+            .map(|(index, object_data)| {
                 let model = model_loader.get(
                     buffer_allocator,
                     game_file_loader,
                     texture_loader,
-                    object_data.model_name.as_str(),
-                    reverse_order,
-                );
-
-                Object::new(
-                    object_data.name.to_owned(),
-                    object_data.model_name.to_owned(),
-                    model.unwrap(),
-                    object_data.transform,
-                )
+                    &decode_game_path(&object_data.model_name.inner),
+                    is_reverse_order(object_data),
+                )?;
+
+                // offset the objects slightly to avoid depth buffer fighting
+                let mut transform = object_data.get_transform();
+                transform.position += map_offset + Vector3::new(0.0, 0.0005, 0.0) * index as f32;
+
+                Ok(Object::new(
+                    object_data.name.as_ref().map(|name| name.inner.clone()),
+                    object_data.model_name.inner.clone(),
+                    model,
+                    transform,
+                ))
             })
-            .collect();
+            .collect::<Result<Vec<Object>, LoaderError>>()?;
+
+        let mut dependencies = vec![
+            format!("data\\{resource_file}.rsw"),
+            format!("data\\{}", decode_game_path(&map_data.ground_file.inner)),
+            format!("data\\{}", decode_game_path(&map_data.gat_file.inner)),
+        ];
+        dependencies.extend(
+            ground_data
+                .textures
+                .iter()
+                .map(|texture_name| format!("data\\texture\\{}", decode_game_path(&texture_name.inner))),
+        );
+        dependencies.extend(map_data.resources.objects.iter().flat_map(|object_data| {
+            model_loader
+                .get_dependencies(&decode_game_path(&object_data.model_name.inner), is_reverse_order(object_data))
+                .iter()
+                .cloned()
+        }));
+        dependencies.sort();
+        dependencies.dedup();
 
         let map = Arc::new(Map::new(
+            resource_file.clone(),
             gat_data.map_width as usize,
             gat_data.map_height as usize,
             map_data.water_settings,
@@ -114,55 +368,56 @@ impl MapLoader {
             water_vertex_buffer,
             textures,
             objects,
-            map_data.resources.light_sources,
-            map_data.resources.sound_sources,
-            map_data.resources.effect_sources,
+            light_sources,
+            sound_sources,
+            effect_sources,
             tile_picker_vertex_buffer.unwrap(),
             tile_vertex_buffer.unwrap(),
             #[cfg(feature = "debug")]
             map_data_clone,
         ));
 
-        self.cache.insert(resource_file, map.clone());
-
-        #[cfg(feature = "debug")]
-        timer.stop();
+        self.cache.insert(resource_file.clone(), map.clone());
+        self.dependencies.insert(resource_file, dependencies);
 
         Ok(map)
     }
 }
 
-fn apply_map_offset(ground_data: &GroundData, resources: &mut MapResources) {
-    let offset = Vector3::new(
+/// Models with a negative scale need to have their vertex order reversed.
+fn is_reverse_order(object_data: &ObjectData) -> bool {
+    let array: [f32; 3] = object_data.scale.into();
+    array.into_iter().fold(1.0, |a, b| a * b).is_sign_negative()
+}
+
+fn get_map_offset(ground_data: &GroundData) -> Vector3<f32> {
+    Vector3::new(
         ground_data.width as f32 * MAP_OFFSET,
         0.0,
         ground_data.height as f32 * MAP_OFFSET,
-    );
-
-    resources.objects.iter_mut().for_each(|object| object.offset(offset));
-    resources
-        .sound_sources
-        .iter_mut()
-        .for_each(|sound_source| sound_source.offset(offset));
-    resources
-        .light_sources
-        .iter_mut()
-        .for_each(|light_source| light_source.offset(offset));
-    resources
-        .effect_sources
-        .iter_mut()
-        .for_each(|effect_source| effect_source.offset(offset));
-}
-
-fn parse_map_data(resource_file: &str, game_file_loader: &mut GameFileLoader) -> Result<MapData, String> {
-    let bytes = game_file_loader.get(&format!("data\\{}.rsw", &resource_file))?;
-    let mut byte_stream = ByteStream::new(&bytes);
+    )
+}
 
-    if <[u8; 4]>::from_bytes(&mut byte_stream, None) != [b'G', b'R', b'S', b'W'] {
-        return Err(format!("failed to read magic number from {}.rsw", &resource_file));
-    }
+/// Copies the light, sound and effect sources into the coordinates of the
+/// world. Like the objects, the map data itself keeps the positions the way
+/// they are stored in the file, so it can be written back.
+fn offset_sources(resources: &MapResources, offset: Vector3<f32>) -> (Vec<LightSource>, Vec<SoundSource>, Vec<EffectSource>) {
+    let mut light_sources = resources.light_sources.clone();
+    let mut sound_sources = resources.sound_sources.clone();
+    let mut effect_sources = resources.effect_sources.clone();
+
+    light_sources.iter_mut().for_each(|light_source| light_source.offset(offset));
+    sound_sources.iter_mut().for_each(|sound_source| sound_source.offset(offset));
+    effect_sources.iter_mut().for_each(|effect_source| effect_source.offset(offset));
 
-    let map_data = MapData::from_bytes(&mut byte_stream, None);
+    (light_sources, sound_sources, effect_sources)
+}
+
+fn parse_map_data(resource_file: &str, game_file_loader: &GameFileLoader) -> Result<MapData, LoaderError> {
+    let file_path = format!("data\\{}.rsw", &resource_file);
+    let bytes = game_file_loader.get(&file_path)?;
+    let mut byte_stream = ByteStream::new(&bytes);
+    let map_data = read_map_data(&file_path, &mut byte_stream)?;
 
     #[cfg(feature = "debug")]
     byte_stream.assert_empty(resource_file);
@@ -170,15 +425,11 @@ fn parse_map_data(resource_file: &str, game_file_loader: &mut GameFileLoader) ->
     Ok(map_data)
 }
 
-fn parse_ground_data(ground_file: &str, game_file_loader: &mut GameFileLoader) -> Result<GroundData, String> {
-    let bytes = game_file_loader.get(&format!("data\\{}", &ground_file))?;
+fn parse_ground_data(ground_file: &str, game_file_loader: &GameFileLoader) -> Result<GroundData, LoaderError> {
+    let file_path = format!("data\\{}", &ground_file);
+    let bytes = game_file_loader.get(&file_path)?;
     let mut byte_stream = ByteStream::new(&bytes);
-
-    if <[u8; 4]>::from_bytes(&mut byte_stream, None) != [b'G', b'R', b'G', b'N'] {
-        return Err(format!("failed to read magic number from {}", &ground_file));
-    }
-
-    let ground_data = GroundData::from_bytes(&mut byte_stream, None);
+    let ground_data = read_ground_data(&file_path, &mut byte_stream)?;
 
     #[cfg(feature = "debug")]
     byte_stream.assert_empty(ground_file);
@@ -186,18 +437,425 @@ fn parse_ground_data(ground_file: &str, game_file_loader: &mut GameFileLoader) -
     Ok(ground_data)
 }
 
-fn parse_gat_data(gat_file: &str, game_file_loader: &mut GameFileLoader) -> Result<GatData, String> {
-    let bytes = game_file_loader.get(&format!("data\\{}", &gat_file))?;
+fn parse_gat_data(gat_file: &str, game_file_loader: &GameFileLoader) -> Result<GatData, LoaderError> {
+    let file_path = format!("data\\{}", &gat_file);
+    let bytes = game_file_loader.get(&file_path)?;
     let mut byte_stream = ByteStream::new(&bytes);
-
-    if <[u8; 4]>::from_bytes(&mut byte_stream, None) != [b'G', b'R', b'A', b'T'] {
-        return Err(format!("failed to read magic number from {}", &gat_file));
-    }
-
-    let gat_data = GatData::from_bytes(&mut byte_stream, None);
+    let gat_data = read_gat_data(&file_path, &mut byte_stream)?;
 
     #[cfg(feature = "debug")]
     byte_stream.assert_empty(gat_file);
 
     Ok(gat_data)
 }
+
+fn read_map_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<MapData, LoaderError> {
+    if <[u8; 4]>::from_bytes(byte_stream, None) != Ok([b'G', b'R', b'S', b'W']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    MapData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+fn read_ground_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<GroundData, LoaderError> {
+    if <[u8; 4]>::from_bytes(byte_stream, None) != Ok([b'G', b'R', b'G', b'N']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    GroundData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+fn read_gat_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<GatData, LoaderError> {
+    if <[u8; 4]>::from_bytes(byte_stream, None) != Ok([b'G', b'R', b'A', b'T']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    GatData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+/// Parses a resource (RSW) file without loading the map. Used to audit
+/// archives.
+pub(super) fn validate_map(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_map_data(file_path, byte_stream).map(|_| ())
+}
+
+/// Parses a ground (GND) file. Used to audit archives.
+pub(super) fn validate_ground(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_ground_data(file_path, byte_stream).map(|_| ())
+}
+
+/// Parses an altitude (GAT) file. Used to audit archives.
+pub(super) fn validate_gat(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_gat_data(file_path, byte_stream).map(|_| ())
+}
+
+#[cfg(test)]
+mod serialization {
+    use cgmath::Vector3;
+
+    use super::{decode_game_path, offset_sources, read_gat_data, read_ground_data, read_map_data};
+    use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader};
+
+    /// Maps that are part of every official client.
+    const REAL_MAPS: &[&str] = &[
+        "prontera",
+        "geffen",
+        "payon",
+        "morocc",
+        "alberta",
+        "izlude",
+        "aldebaran",
+        "prt_fild08",
+    ];
+
+    /// `유저인터페이스\바닥.bmp` and `내부소품\의자.rsm`, encoded as CP949 like
+    /// they are in the files of the official client.
+    const KOREAN_TEXTURE: &[u8] = b"\xc0\xaf\xc0\xfa\xc0\xce\xc5\xcd\xc6\xe4\xc0\xcc\xbd\xba\\\xb9\xd9\xb4\xda.bmp";
+    const KOREAN_MODEL: &[u8] = b"\xb3\xbb\xba\xce\xbc\xd2\xc7\xb0\\\xc0\xc7\xc0\xda.rsm";
+
+    fn string(value: &[u8], length: usize) -> Vec<u8> {
+        let mut bytes = value.to_vec();
+        bytes.resize(length, 0);
+        bytes
+    }
+
+    /// A string followed by the leftovers of a longer string that was stored in
+    /// the same field before.
+    fn string_with_garbage(value: &[u8], garbage: &[u8], length: usize) -> Vec<u8> {
+        string(&[value, b"\0", garbage].concat(), length)
+    }
+
+    fn floats(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_le_bytes()).collect()
+    }
+
+    fn object(version: (u8, u8), model_name: &[u8]) -> Vec<u8> {
+        let mut bytes = 1i32.to_le_bytes().to_vec();
+        bytes.extend(string_with_garbage(b"object", b"01", 40));
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend(floats(&[1.5]));
+        bytes.extend(0i32.to_le_bytes());
+
+        if version >= (2, 6) {
+            bytes.push(1);
+        }
+
+        bytes.extend(string(model_name, 80));
+        bytes.extend(string(b"node", 80));
+        // Rotations that don't survive a conversion to radians and back.
+        bytes.extend(floats(&[12.5, -3.0, 40.25, 33.3, 270.1, -0.7, 1.0, 1.0, -1.0]));
+        bytes
+    }
+
+    fn light_source() -> Vec<u8> {
+        let mut bytes = 2i32.to_le_bytes().to_vec();
+        bytes.extend(string(b"light", 80));
+        bytes.extend(floats(&[5.0, -12.0, 7.5, 1.0, 0.5, 0.25, 40.0]));
+        bytes
+    }
+
+    fn sound_source(version: (u8, u8)) -> Vec<u8> {
+        let mut bytes = 3i32.to_le_bytes().to_vec();
+        bytes.extend(string(b"sound", 80));
+        bytes.extend(string(b"wind.wav", 80));
+        bytes.extend(floats(&[1.0, 2.0, 3.0, 0.8]));
+        bytes.extend(10u32.to_le_bytes());
+        bytes.extend(12u32.to_le_bytes());
+        bytes.extend(floats(&[150.0]));
+
+        if version >= (2, 0) {
+            bytes.extend(floats(&[8.0]));
+        }
+
+        bytes
+    }
+
+    fn effect_source() -> Vec<u8> {
+        let mut bytes = 4i32.to_le_bytes().to_vec();
+        bytes.extend(string(b"effect", 80));
+        bytes.extend(floats(&[-4.0, 6.0, 0.0]));
+        bytes.extend(47u32.to_le_bytes());
+        bytes.extend(floats(&[0.1, 1.0, 2.0, 3.0, 4.0]));
+        bytes
+    }
+
+    fn map_file(version: (u8, u8)) -> Vec<u8> {
+        let mut bytes = b"GRSW".to_vec();
+        bytes.extend(<[u8; 2]>::from(version));
+
+        if version >= (2, 5) {
+            bytes.extend(186i32.to_le_bytes());
+        }
+
+        if version >= (2, 2) {
+            bytes.push(0);
+        }
+
+        bytes.extend(string(b"test.ini", 40));
+        bytes.extend(string(b"test.gnd", 40));
+        bytes.extend(string(b"test.gat", 40));
+        bytes.extend(string(b"test.src", 40));
+
+        if version < (2, 6) {
+            bytes.extend(floats(&[-1.5]));
+            bytes.extend(2i32.to_le_bytes());
+            bytes.extend(floats(&[1.0, 2.0, 50.0]));
+            bytes.extend(3u32.to_le_bytes());
+        }
+
+        bytes.extend(45i32.to_le_bytes());
+        bytes.extend(45i32.to_le_bytes());
+        bytes.extend(floats(&[1.0, 1.0, 1.0, 0.3, 0.3, 0.3, 0.5]));
+        [-500i32, 500, -500, 500].iter().for_each(|bound| bytes.extend(bound.to_le_bytes()));
+
+        // Resources of different types are interleaved in the file.
+        bytes.extend(6i32.to_le_bytes());
+        bytes.extend(object(version, KOREAN_MODEL));
+        bytes.extend(light_source());
+        bytes.extend(sound_source(version));
+        bytes.extend(object(version, b"second.rsm"));
+        bytes.extend(effect_source());
+        bytes.extend(light_source());
+        bytes
+    }
+
+    fn ground_file(version: (u8, u8)) -> Vec<u8> {
+        let mut bytes = b"GRGN".to_vec();
+        bytes.extend(<[u8; 2]>::from(version));
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(floats(&[10.0]));
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend(80i32.to_le_bytes());
+        bytes.extend(string(KOREAN_TEXTURE, 80));
+        bytes.extend(string_with_garbage(b"stone.bmp", b"old.bmp", 80));
+        [1i32, 8, 8, 1].iter().for_each(|value| bytes.extend(value.to_le_bytes()));
+
+        match version >= (1, 7) {
+            true => bytes.extend((0..8 * 8 * 4).map(|index| index as u8)),
+            false => bytes.extend((0..16).map(|index| index as u8)),
+        }
+
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(floats(&[0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0]));
+        bytes.extend(1i16.to_le_bytes());
+        bytes.extend(0i16.to_le_bytes());
+        bytes.extend([255, 128, 64, 255]);
+
+        for surface_indices in [[0i16, -1, -1], [0, 0, -1]] {
+            bytes.extend(floats(&[1.0, 2.0, 3.0, 4.0]));
+
+            for surface_index in surface_indices {
+                match version >= (1, 7) {
+                    true => bytes.extend((surface_index as i32).to_le_bytes()),
+                    false => bytes.extend(surface_index.to_le_bytes()),
+                }
+            }
+        }
+
+        bytes
+    }
+
+    fn gat_file() -> Vec<u8> {
+        let mut bytes = b"GRAT".to_vec();
+        bytes.extend([1, 2]);
+        bytes.extend(2i32.to_le_bytes());
+        bytes.extend(2i32.to_le_bytes());
+
+        for tile_type in [0, 1, 5, 6] {
+            bytes.extend(floats(&[0.0, -1.0, 2.5, 3.0]));
+            bytes.extend([tile_type, 0, 0, 0]);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn map_data() {
+        for version in [(1, 9), (2, 1), (2, 6)] {
+            let bytes = map_file(version);
+            let map_data = read_map_data("test.rsw", &mut ByteStream::new(&bytes)).unwrap();
+
+            assert_eq!(map_data.resources.objects.len(), 2);
+            assert_eq!(map_data.resources.light_sources.len(), 2);
+            // Sound sources of older maps get a default cycle, which is not written.
+            let cycle = match version >= (2, 0) {
+                true => 8.0,
+                false => 4.0,
+            };
+            assert_eq!(map_data.resources.sound_sources[0].cycle, Some(cycle));
+            assert_eq!([b"GRSW".to_vec(), map_data.to_bytes(None)].concat(), bytes);
+        }
+    }
+
+    #[test]
+    fn ground_data() {
+        for version in [(1, 6), (1, 7)] {
+            let bytes = ground_file(version);
+            let ground_data = read_ground_data("test.gnd", &mut ByteStream::new(&bytes)).unwrap();
+
+            assert_eq!(ground_data.ground_tiles[0].front_surface_index, -1);
+            assert_eq!([b"GRGN".to_vec(), ground_data.to_bytes(None)].concat(), bytes);
+        }
+    }
+
+    #[test]
+    fn gat_data() {
+        let bytes = gat_file();
+        let gat_data = read_gat_data("test.gat", &mut ByteStream::new(&bytes)).unwrap();
+
+        assert!(gat_data.tiles[0].is_walkable());
+        assert!(gat_data.tiles[1].tile_type.is_none());
+        assert_eq!([b"GRAT".to_vec(), gat_data.to_bytes(None)].concat(), bytes);
+    }
+
+    #[test]
+    #[should_panic(expected = "GatData::tiles does not match its repeat count")]
+    fn repeat_count_mismatch() {
+        let bytes = gat_file();
+        let mut gat_data = read_gat_data("test.gat", &mut ByteStream::new(&bytes)).unwrap();
+
+        gat_data.tiles.pop();
+        gat_data.to_bytes(None);
+    }
+
+    #[test]
+    fn offset_sources_keep_map_data() {
+        let bytes = map_file((2, 6));
+        let map_data = read_map_data("test.rsw", &mut ByteStream::new(&bytes)).unwrap();
+        let offset = Vector3::new(10.0, 0.0, 20.0);
+
+        let (light_sources, sound_sources, effect_sources) = offset_sources(&map_data.resources, offset);
+
+        assert_eq!(light_sources[1].position, map_data.resources.light_sources[1].position + offset);
+        assert_eq!(sound_sources[0].position, map_data.resources.sound_sources[0].position + offset);
+        assert_eq!(
+            effect_sources[0].position,
+            map_data.resources.effect_sources[0].position + offset
+        );
+        assert_eq!([b"GRSW".to_vec(), map_data.to_bytes(None)].concat(), bytes);
+    }
+
+    #[test]
+    fn korean_resource_names() {
+        let bytes = map_file((2, 1));
+        let map_data = read_map_data("test.rsw", &mut ByteStream::new(&bytes)).unwrap();
+
+        let model_name = &map_data.resources.objects[0].model_name.inner;
+        assert_eq!(decode_game_path(model_name), "내부소품\\의자.rsm");
+        assert_eq!(map_data.resources.objects[0].name.as_ref().unwrap().inner, "object");
+        assert_eq!([b"GRSW".to_vec(), map_data.to_bytes(None)].concat(), bytes);
+
+        let bytes = ground_file((1, 7));
+        let ground_data = read_ground_data("test.gnd", &mut ByteStream::new(&bytes)).unwrap();
+
+        assert_eq!(decode_game_path(&ground_data.textures[0].inner), "유저인터페이스\\바닥.bmp");
+        assert_eq!(ground_data.textures[1].inner, "stone.bmp");
+        assert_eq!([b"GRGN".to_vec(), ground_data.to_bytes(None)].concat(), bytes);
+    }
+
+    #[test]
+    fn edited_strings_drop_their_padding() {
+        let bytes = ground_file((1, 7));
+        let mut ground_data = read_ground_data("test.gnd", &mut ByteStream::new(&bytes)).unwrap();
+        ground_data.textures[1].inner = "sand.bmp".to_owned();
+
+        let bytes = [b"GRGN".to_vec(), ground_data.to_bytes(None)].concat();
+        let texture_start = 26 + 80;
+        assert_eq!(&bytes[texture_start..texture_start + 80], string(b"sand.bmp", 80));
+    }
+
+    /// A map in the format of the official client, with CP949 encoded
+    /// resource names and leftover bytes after the null terminator of some
+    /// names.
+    #[test]
+    fn fixture_map() {
+        let bytes = include_bytes!("fixtures/korean.rsw");
+        let map_data = read_map_data("korean.rsw", &mut ByteStream::new(bytes)).unwrap();
+        assert_eq!(decode_game_path(&map_data.ground_file.inner), "프론테라.gnd");
+        assert_eq!(
+            decode_game_path(&map_data.resources.sound_sources[0].sound_file.inner),
+            "물소리.wav"
+        );
+        assert_eq!([b"GRSW".to_vec(), map_data.to_bytes(None)].concat(), bytes);
+
+        let bytes = include_bytes!("fixtures/korean.gnd");
+        let ground_data = read_ground_data("korean.gnd", &mut ByteStream::new(bytes)).unwrap();
+        assert_eq!(decode_game_path(&ground_data.textures[0].inner), "유저인터페이스\\바닥.bmp");
+        assert_eq!([b"GRGN".to_vec(), ground_data.to_bytes(None)].concat(), bytes);
+
+        let bytes = include_bytes!("fixtures/korean.gat");
+        let gat_data = read_gat_data("korean.gat", &mut ByteStream::new(bytes)).unwrap();
+        assert_eq!([b"GRAT".to_vec(), gat_data.to_bytes(None)].concat(), bytes);
+    }
+
+    /// Uses the archives of the client, so it has to be run from the same
+    /// directory with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn real_maps() {
+        let mut game_file_loader = GameFileLoader::default();
+        game_file_loader.load_archives_from_settings(&[]);
+
+        let mut checked_count = 0;
+
+        for map_name in REAL_MAPS {
+            let Ok(bytes) = game_file_loader.get(&format!("data\\{map_name}.rsw")) else {
+                continue;
+            };
+            let map_data = read_map_data(map_name, &mut ByteStream::new(&bytes)).unwrap();
+            assert_eq!([b"GRSW".to_vec(), map_data.to_bytes(None)].concat(), bytes, "{map_name}.rsw");
+
+            let bytes = game_file_loader
+                .get(&format!("data\\{}", decode_game_path(&map_data.ground_file.inner)))
+                .unwrap();
+            let ground_data = read_ground_data(&map_data.ground_file.inner, &mut ByteStream::new(&bytes)).unwrap();
+            assert_eq!(
+                [b"GRGN".to_vec(), ground_data.to_bytes(None)].concat(),
+                bytes,
+                "{}",
+                map_data.ground_file.inner
+            );
+
+            let bytes = game_file_loader
+                .get(&format!("data\\{}", decode_game_path(&map_data.gat_file.inner)))
+                .unwrap();
+            let gat_data = read_gat_data(&map_data.gat_file.inner, &mut ByteStream::new(&bytes)).unwrap();
+            assert_eq!(
+                [b"GRAT".to_vec(), gat_data.to_bytes(None)].concat(),
+                bytes,
+                "{}",
+                map_data.gat_file.inner
+            );
+
+            checked_count += 1;
+        }
+
+        assert!(checked_count > 0, "none of the maps are in the configured archives");
+    }
+
+    #[test]
+    fn oversized_dimensions() {
+        let mut bytes = gat_file();
+        bytes[6..10].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(read_gat_data("test.gat", &mut ByteStream::new(&bytes)).is_err());
+
+        let mut bytes = ground_file((1, 7));
+        bytes[6..10].copy_from_slice(&(-1i32).to_le_bytes());
+        assert!(read_ground_data("test.gnd", &mut ByteStream::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn added_resources() {
+        let bytes = map_file((2, 6));
+        let mut map_data = read_map_data("test.rsw", &mut ByteStream::new(&bytes)).unwrap();
+        let added = map_data.resources.effect_sources[0].clone();
+        map_data.resources.effect_sources.push(added);
+
+        let bytes = [b"GRSW".to_vec(), map_data.to_bytes(None)].concat();
+        assert!(bytes.ends_with(&effect_source()));
+
+        let map_data = read_map_data("test.rsw", &mut ByteStream::new(&bytes)).unwrap();
+        assert_eq!(map_data.resources.effect_sources.len(), 2);
+    }
+}
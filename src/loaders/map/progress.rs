@@ -0,0 +1,88 @@
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use super::ParsedMap;
+use crate::loaders::LoaderError;
+
+/// Steps of loading a map on a background thread, in the order they are
+/// performed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapLoadingStage {
+    #[default]
+    Resources,
+    Ground,
+    Altitude,
+    Models,
+    Textures,
+}
+
+impl MapLoadingStage {
+    const COUNT: usize = 5;
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            MapLoadingStage::Resources => "loading map resources",
+            MapLoadingStage::Ground => "loading ground",
+            MapLoadingStage::Altitude => "loading altitude",
+            MapLoadingStage::Models => "loading models",
+            MapLoadingStage::Textures => "loading textures",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MapLoadingProgress {
+    pub stage: MapLoadingStage,
+    pub completed: usize,
+    pub total: usize,
+}
+
+impl MapLoadingProgress {
+    pub(super) fn start_stage(progress: &Mutex<Self>, stage: MapLoadingStage, total: usize) {
+        *progress.lock().unwrap() = Self {
+            stage,
+            completed: 0,
+            total,
+        };
+    }
+
+    pub(super) fn complete_step(progress: &Mutex<Self>) {
+        progress.lock().unwrap().completed += 1;
+    }
+
+    /// Progress of the entire map, where every stage takes the same amount of
+    /// space.
+    pub fn get_fraction(&self) -> f32 {
+        let stage_fraction = match self.total {
+            0 => 0.0,
+            total => self.completed as f32 / total as f32,
+        };
+
+        (self.stage as usize as f32 + stage_fraction) / MapLoadingStage::COUNT as f32
+    }
+}
+
+/// A map that is being loaded on a background thread. Created by
+/// [`MapLoader::start_loading`](super::MapLoader::start_loading) and turned
+/// into a [`Map`](crate::world::Map) by
+/// [`MapLoader::finish_loading`](super::MapLoader::finish_loading).
+pub struct MapLoadingJob {
+    pub(super) resource_file: String,
+    pub(super) progress: Arc<Mutex<MapLoadingProgress>>,
+    /// `None` if the map was already cached when loading started.
+    pub(super) handle: Option<JoinHandle<Result<ParsedMap, LoaderError>>>,
+}
+
+impl MapLoadingJob {
+    pub fn get_resource_file(&self) -> &str {
+        &self.resource_file
+    }
+
+    pub fn get_progress(&self) -> MapLoadingProgress {
+        *self.progress.lock().unwrap()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().map_or(true, JoinHandle::is_finished)
+    }
+}
@@ -1,8 +1,10 @@
-use cgmath::Vector3;
+use cgmath::{Deg, Vector3};
 use procedural::{ByteConvertable, PrototypeElement};
 
 use crate::graphics::{ColorRGB, Transform};
-use crate::loaders::{ByteConvertable, ByteStream};
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, ConversionErrorKind, InternalVersion, PaddedString};
 use crate::world::{EffectSource, LightSource, SoundSource};
 
 #[derive(Copy, Clone, Debug)]
@@ -14,13 +16,22 @@ pub enum ResourceType {
 }
 
 impl ResourceType {
-    pub fn from(index: i32) -> Self {
+    pub fn from(index: i32) -> Option<Self> {
         match index {
-            1 => ResourceType::Object,
-            2 => ResourceType::LightSource,
-            3 => ResourceType::SoundSource,
-            4 => ResourceType::EffectSource,
-            _ => panic!("invalid object type {index}"),
+            1 => Some(ResourceType::Object),
+            2 => Some(ResourceType::LightSource),
+            3 => Some(ResourceType::SoundSource),
+            4 => Some(ResourceType::EffectSource),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self) -> i32 {
+        match self {
+            ResourceType::Object => 1,
+            ResourceType::LightSource => 2,
+            ResourceType::SoundSource => 3,
+            ResourceType::EffectSource => 4,
         }
     }
 }
@@ -29,7 +40,7 @@ impl ResourceType {
 pub struct ObjectData {
     #[length_hint(40)]
     #[version_equals_or_above(1, 3)]
-    pub name: Option<String>,
+    pub name: Option<PaddedString>,
     #[version_equals_or_above(1, 3)]
     pub _animation_type: Option<i32>,
     #[version_equals_or_above(1, 3)]
@@ -40,22 +51,29 @@ pub struct ObjectData {
     #[version_equals_or_above(2, 6)]
     pub _unknown: Option<u8>,
     #[length_hint(80)]
-    pub model_name: String,
+    pub model_name: PaddedString,
     #[length_hint(80)]
-    pub _node_name: String,
-    pub transform: Transform,
+    pub _node_name: PaddedString,
+    pub position: Vector3<f32>,
+    /// Rotation in degrees. Kept as it is stored in the file, since converting
+    /// to radians and back is not lossless.
+    pub rotation: Vector3<f32>,
+    pub scale: Vector3<f32>,
 }
 
 impl ObjectData {
-    pub fn offset(&mut self, offset: Vector3<f32>) {
-        self.transform.position += offset;
+    pub fn get_transform(&self) -> Transform {
+        let position = Vector3::new(self.position.x, -self.position.y, self.position.z);
+        Transform::from(position, self.rotation.map(Deg), self.scale)
     }
 }
 
-#[allow(dead_code)]
 #[derive(Clone, PrototypeElement)]
 pub struct MapResources {
-    resources_amount: usize,
+    /// Order of the resources in the file, so they can be written back the same
+    /// way.
+    #[hidden_element]
+    resource_types: Vec<ResourceType>,
     pub objects: Vec<ObjectData>,
     pub light_sources: Vec<LightSource>,
     pub sound_sources: Vec<SoundSource>,
@@ -63,32 +81,43 @@ pub struct MapResources {
 }
 
 impl ByteConvertable for MapResources {
-    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Self {
-        let resources_amount = i32::from_bytes(byte_stream, None) as usize;
+    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Result<Self, ConversionError> {
+        let amount_offset = byte_stream.get_offset();
+        let resources_amount = i32::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::resources_amount"))?;
+        let resources_amount = usize::try_from(resources_amount)
+            .map_err(|_| ConversionError::invalid_data(amount_offset, format!("negative resource count {resources_amount}")))?;
 
+        let mut resource_types = Vec::new();
         let mut objects = Vec::new();
         let mut light_sources = Vec::new();
         let mut sound_sources = Vec::new();
         let mut effect_sources = Vec::new();
 
-        for index in 0..resources_amount {
-            let type_index = i32::from_bytes(byte_stream, None);
-            let resource_type = ResourceType::from(type_index);
+        for _ in 0..resources_amount {
+            let type_offset = byte_stream.get_offset();
+            let type_index = i32::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::resource_type"))?;
+            let resource_type = ResourceType::from(type_index).ok_or_else(|| {
+                let kind = ConversionErrorKind::InvalidEnumValue {
+                    type_name: "ResourceType",
+                    value: type_index as usize,
+                };
+                ConversionError::from_kind(type_offset, kind)
+            })?;
 
             match resource_type {
                 ResourceType::Object => {
-                    let mut object = ObjectData::from_bytes(byte_stream, None);
-                    // offset the objects slightly to avoid depth buffer fighting
-                    object.transform.position += Vector3::new(0.0, 0.0005, 0.0) * index as f32;
+                    let object = ObjectData::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::objects"))?;
                     objects.push(object);
                 }
                 ResourceType::LightSource => {
-                    let mut light_source = LightSource::from_bytes(byte_stream, None);
+                    let mut light_source =
+                        LightSource::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::light_sources"))?;
                     light_source.position.y = -light_source.position.y;
                     light_sources.push(light_source);
                 }
                 ResourceType::SoundSource => {
-                    let mut sound_source = SoundSource::from_bytes(byte_stream, None);
+                    let mut sound_source =
+                        SoundSource::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::sound_sources"))?;
                     sound_source.position.y = -sound_source.position.y;
 
                     if sound_source.cycle.is_none() {
@@ -98,20 +127,78 @@ impl ByteConvertable for MapResources {
                     sound_sources.push(sound_source);
                 }
                 ResourceType::EffectSource => {
-                    let mut effect_source = EffectSource::from_bytes(byte_stream, None);
+                    let mut effect_source =
+                        EffectSource::from_bytes(byte_stream, None).map_err(|error| error.add_context("MapResources::effect_sources"))?;
                     effect_source.position.y = -effect_source.position.y;
                     effect_sources.push(effect_source);
                 }
             }
+
+            resource_types.push(resource_type);
         }
 
-        Self {
-            resources_amount,
+        Ok(Self {
+            resource_types,
             objects,
             light_sources,
             sound_sources,
             effect_sources,
+        })
+    }
+
+    /// Resources are written in the order they were read in. Resources that
+    /// were added after loading are written after all others.
+    fn to_versioned_bytes(&self, version: Option<InternalVersion>, _: Option<usize>) -> Vec<u8> {
+        let resources_amount = self.objects.len() + self.light_sources.len() + self.sound_sources.len() + self.effect_sources.len();
+
+        let mut objects = self.objects.iter();
+        let mut light_sources = self.light_sources.iter();
+        let mut sound_sources = self.sound_sources.iter();
+        let mut effect_sources = self.effect_sources.iter();
+
+        let mut next_resource = |resource_type: ResourceType| -> Option<Vec<u8>> {
+            let bytes = match resource_type {
+                ResourceType::Object => objects.next()?.to_versioned_bytes(version, None),
+                ResourceType::LightSource => {
+                    let mut light_source = light_sources.next()?.clone();
+                    light_source.position.y = -light_source.position.y;
+                    light_source.to_versioned_bytes(version, None)
+                }
+                ResourceType::SoundSource => {
+                    let mut sound_source = sound_sources.next()?.clone();
+                    sound_source.position.y = -sound_source.position.y;
+                    sound_source.to_versioned_bytes(version, None)
+                }
+                ResourceType::EffectSource => {
+                    let mut effect_source = effect_sources.next()?.clone();
+                    effect_source.position.y = -effect_source.position.y;
+                    effect_source.to_versioned_bytes(version, None)
+                }
+            };
+
+            let mut resource_bytes = resource_type.get_index().to_bytes(None);
+            resource_bytes.extend(bytes);
+            Some(resource_bytes)
+        };
+
+        let mut bytes = (resources_amount as i32).to_bytes(None);
+
+        for resource_type in &self.resource_types {
+            bytes.extend(next_resource(*resource_type).unwrap_or_default());
         }
+
+        for resource_type in [
+            ResourceType::Object,
+            ResourceType::LightSource,
+            ResourceType::SoundSource,
+            ResourceType::EffectSource,
+        ] {
+            while let Some(resource_bytes) = next_resource(resource_type) {
+                bytes.extend(resource_bytes);
+            }
+        }
+
+        bytes
     }
 }
 
@@ -144,3 +231,31 @@ pub struct LightSettings {
     #[version_equals_or_above(1, 7)]
     pub light_intensity: Option<f32>,
 }
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for MapResources {
+    fn arbitrary(generator: &mut Generator, _: Option<usize>) -> Self {
+        let mut resources = Self {
+            resource_types: Vec::new(),
+            objects: Vec::new(),
+            light_sources: Vec::new(),
+            sound_sources: Vec::new(),
+            effect_sources: Vec::new(),
+        };
+
+        for _ in 0..generator.choose(8) {
+            let resource_type = ResourceType::from(generator.choose(4) as i32 + 1).unwrap();
+
+            match resource_type {
+                ResourceType::Object => resources.objects.push(Arbitrary::arbitrary(generator, None)),
+                ResourceType::LightSource => resources.light_sources.push(Arbitrary::arbitrary(generator, None)),
+                ResourceType::SoundSource => resources.sound_sources.push(Arbitrary::arbitrary(generator, None)),
+                ResourceType::EffectSource => resources.effect_sources.push(Arbitrary::arbitrary(generator, None)),
+            }
+
+            resources.resource_types.push(resource_type);
+        }
+
+        resources
+    }
+}
@@ -5,7 +5,8 @@ use vulkano::image::view::ImageView;
 
 use super::data::{GatData, GroundData, GroundTile, SurfaceType};
 use crate::graphics::{ModelVertex, NativeModelVertex, PickerTarget, TileVertex, WaterVertex};
-use crate::loaders::{GameFileLoader, TextureLoader};
+use crate::loaders::archive::path::decode_game_path;
+use crate::loaders::{GameFileLoader, LoaderError, TextureLoader};
 
 const TILE_SIZE: f32 = 10.0;
 
@@ -150,12 +151,12 @@ pub fn ground_water_vertices(ground_data: &GroundData, water_level: f32) -> (Vec
 pub fn load_textures(
     ground_data: &GroundData,
     texture_loader: &mut TextureLoader,
-    game_file_loader: &mut GameFileLoader,
-) -> Vec<Arc<ImageView>> {
+    game_file_loader: &GameFileLoader,
+) -> Result<Vec<Arc<ImageView>>, LoaderError> {
     ground_data
         .textures
         .iter()
-        .map(|texture_name| texture_loader.get(texture_name, game_file_loader).unwrap())
+        .map(|texture_name| texture_loader.get(&decode_game_path(&texture_name.inner), game_file_loader))
         .collect()
 }
 
@@ -1,7 +1,11 @@
 mod action;
-mod archive;
+#[cfg(any(test, feature = "arbitrary"))]
+mod arbitrary;
+pub mod archive;
+mod audit;
 mod convertable;
 mod effect;
+mod error;
 mod fixed;
 mod font;
 mod gamefile;
@@ -10,21 +14,29 @@ mod model;
 mod script;
 mod sprite;
 mod stream;
+#[cfg(test)]
+mod temporary;
 mod texture;
 mod version;
 
 pub use self::action::*;
-pub use self::convertable::ByteConvertable;
+#[cfg(any(test, feature = "arbitrary"))]
+pub use self::arbitrary::{check_packet_round_trip, check_round_trip, Arbitrary, Generator};
+pub use self::audit::{audit_archive, AuditIssue, AuditReport};
+pub use self::convertable::{ByteConvertable, PaddedString};
 pub use self::effect::{EffectHolder, EffectLoader, *};
+pub use self::error::{ConversionError, ConversionErrorKind, LoaderError};
 pub use self::fixed::{FixedByteSize, FixedByteSizeWrapper};
 pub use self::font::FontLoader;
-pub use self::gamefile::GameFileLoader;
+pub use self::gamefile::{ChangedFiles, GameFileLoader, MissingAsset, PlaceholderPolicy};
 #[cfg(feature = "debug")]
 pub use self::map::MapData;
-pub use self::map::{LightSettings, MapLoader, WaterSettings};
+pub use self::map::{LightSettings, MapLoader, MapLoadingJob, MapLoadingProgress, MapLoadingStage, WaterSettings};
 pub use self::model::*;
 pub use self::script::ScriptLoader;
 pub use self::sprite::*;
 pub use self::stream::ByteStream;
+#[cfg(test)]
+pub use self::temporary::TemporaryPath;
 pub use self::texture::TextureLoader;
 pub use self::version::{InternalVersion, MajorFirst, MinorFirst, Version};
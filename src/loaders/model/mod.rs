@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use cgmath::{Matrix3, Matrix4, Quaternion, Rad, SquareMatrix, Vector2, Vector3};
@@ -9,7 +9,13 @@ use vulkano::image::view::ImageView;
 #[cfg(feature = "debug")]
 use crate::debug::*;
 use crate::graphics::{BufferAllocator, NativeModelVertex};
-use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, MajorFirst, TextureLoader, Version};
+use crate::loaders::archive::path::decode_game_path;
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{
+    ByteConvertable, ByteStream, ChangedFiles, ConversionError, GameFileLoader, InternalVersion, LoaderError, MajorFirst, TextureLoader,
+    Version,
+};
 use crate::system::multiply_matrix4_and_vector3;
 use crate::world::{BoundingBox, Model, Node};
 
@@ -83,17 +89,39 @@ pub struct ModelString {
 }
 
 impl ByteConvertable for ModelString {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        // Strings of newer versions are prefixed with their length. The string is
+        // read up to the first null byte, so a terminator that is included in the
+        // length doesn't end up in the string.
         let inner = if byte_stream.get_version().equals_or_above(2, 2) {
-            let length = u32::from_bytes(byte_stream, None) as usize;
-            let mut inner = String::from_bytes(byte_stream, Some(length));
-            // need to remove the last character for some reason
-            inner.pop();
-            inner
+            let length = u32::from_bytes(byte_stream, None)? as usize;
+            String::from_bytes(byte_stream, Some(length))?
         } else {
-            String::from_bytes(byte_stream, length_hint)
+            String::from_bytes(byte_stream, length_hint)?
         };
 
+        Ok(Self { inner })
+    }
+
+    fn to_versioned_bytes(&self, version: Option<InternalVersion>, length_hint: Option<usize>) -> Vec<u8> {
+        match version
+            .expect("model strings can only be serialized with a version")
+            .equals_or_above(2, 2)
+        {
+            true => {
+                let mut bytes = (self.inner.len() as u32).to_bytes(None);
+                bytes.extend(self.inner.to_bytes(Some(self.inner.len())));
+                bytes
+            }
+            false => self.inner.to_bytes(length_hint),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for ModelString {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        let inner = String::arbitrary(generator, length_hint);
         Self { inner }
     }
 }
@@ -102,6 +130,10 @@ impl crate::interface::PrototypeElement for ModelString {
     fn to_element(&self, display: String) -> crate::interface::ElementCell {
         self.inner.to_element(display)
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        self.inner.write_text(display, indentation, text)
+    }
 }
 
 #[derive(Debug, ByteConvertable, PrototypeElement)]
@@ -129,10 +161,26 @@ pub struct ModelData {
     pub nodes: Vec<NodeData>,
 }
 
+fn read_model_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<ModelData, LoaderError> {
+    if <[u8; 4]>::from_bytes(byte_stream, None) != Ok([b'G', b'R', b'S', b'M']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    ModelData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+/// Parses a model without building it. Used to audit archives.
+pub(super) fn validate_model(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_model_data(file_path, byte_stream).map(|_| ())
+}
+
 #[derive(new)]
 pub struct ModelLoader {
     #[new(default)]
     cache: HashMap<(String, bool), Arc<Model>>,
+    /// Paths of the model file and all of its textures.
+    #[new(default)]
+    dependencies: HashMap<(String, bool), Vec<String>>,
 }
 
 impl ModelLoader {
@@ -309,31 +357,28 @@ impl ModelLoader {
         )
     }
 
-    fn load(
+    /// Reads and parses a model file. This does not touch the GPU, so it can
+    /// be called from any thread.
+    pub(super) fn parse(game_file_loader: &GameFileLoader, model_file: &str) -> Result<ModelData, LoaderError> {
+        let file_path = format!("data\\model\\{model_file}");
+        let bytes = game_file_loader.get(&file_path)?;
+        read_model_data(&file_path, &mut ByteStream::new(&bytes))
+    }
+
+    pub(super) fn build(
         &mut self,
         buffer_allocator: &mut BufferAllocator,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         model_file: &str,
         reverse_order: bool,
-    ) -> Result<Arc<Model>, String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new_dynamic(format!("load rsm model from {MAGENTA}{model_file}{NONE}"));
-
-        let bytes = game_file_loader.get(&format!("data\\model\\{model_file}"))?;
-        let mut byte_stream = ByteStream::new(&bytes);
-
-        if <[u8; 4]>::from_bytes(&mut byte_stream, None) != [b'G', b'R', b'S', b'M'] {
-            return Err(format!("failed to read magic number from {model_file}"));
-        }
-
-        let model_data = ModelData::from_bytes(&mut byte_stream, None);
-
+        model_data: ModelData,
+    ) -> Result<Arc<Model>, LoaderError> {
         let textures = model_data
             .texture_names
             .iter()
-            .map(|texture_name| texture_loader.get(&texture_name.inner, game_file_loader).unwrap())
-            .collect();
+            .map(|texture_name| texture_loader.get(&decode_game_path(&texture_name.inner), game_file_loader))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let root_node_name = &model_data.root_node_name;
 
@@ -354,6 +399,16 @@ impl ModelLoader {
             root_node_name,
             reverse_order,
         );
+
+        let dependencies = std::iter::once(format!("data\\model\\{model_file}"))
+            .chain(
+                model_data
+                    .texture_names
+                    .iter()
+                    .map(|texture_name| format!("data\\texture\\{}", decode_game_path(&texture_name.inner))),
+            )
+            .collect();
+
         let model = Arc::new(Model::new(
             root_node,
             bounding_box,
@@ -362,6 +417,31 @@ impl ModelLoader {
         ));
 
         self.cache.insert((model_file.to_string(), reverse_order), model.clone());
+        self.dependencies.insert((model_file.to_string(), reverse_order), dependencies);
+
+        Ok(model)
+    }
+
+    fn load(
+        &mut self,
+        buffer_allocator: &mut BufferAllocator,
+        game_file_loader: &GameFileLoader,
+        texture_loader: &mut TextureLoader,
+        model_file: &str,
+        reverse_order: bool,
+    ) -> Result<Arc<Model>, LoaderError> {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("load rsm model from {MAGENTA}{model_file}{NONE}"));
+
+        let model_data = Self::parse(game_file_loader, model_file)?;
+        let model = self.build(
+            buffer_allocator,
+            game_file_loader,
+            texture_loader,
+            model_file,
+            reverse_order,
+            model_data,
+        )?;
 
         #[cfg(feature = "debug")]
         timer.stop();
@@ -369,18 +449,50 @@ impl ModelLoader {
         Ok(model)
     }
 
+    /// Model files and vertex orders of all models that are currently cached.
+    pub(super) fn get_cached_models(&self) -> HashSet<(String, bool)> {
+        self.cache.keys().cloned().collect()
+    }
+
     pub fn get(
         &mut self,
         buffer_allocator: &mut BufferAllocator,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         texture_loader: &mut TextureLoader,
         model_file: &str,
         reverse_order: bool,
-    ) -> Result<Arc<Model>, String> {
+    ) -> Result<Arc<Model>, LoaderError> {
         match self.cache.get(&(model_file.to_string(), reverse_order)) {
             // kinda dirty
             Some(model) => Ok(model.clone()),
             None => self.load(buffer_allocator, game_file_loader, texture_loader, model_file, reverse_order),
         }
     }
+
+    /// Paths of all files that a cached model was loaded from.
+    pub fn get_dependencies(&self, model_file: &str, reverse_order: bool) -> &[String] {
+        self.dependencies
+            .get(&(model_file.to_string(), reverse_order))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Removes all cached models where either the model file or one of its
+    /// textures was changed on disk. Returns `true` if any models were
+    /// removed.
+    pub fn invalidate(&mut self, changed_files: &ChangedFiles) -> bool {
+        let invalidated: Vec<(String, bool)> = self
+            .dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().any(|path| changed_files.contains(path)))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        invalidated.iter().for_each(|key| {
+            self.cache.remove(key);
+            self.dependencies.remove(key);
+        });
+
+        !invalidated.is_empty()
+    }
 }
@@ -1,14 +1,42 @@
 use mlua::Lua;
 
-use crate::loaders::GameFileLoader;
+use crate::loaders::archive::path::decode_path;
+use crate::loaders::{ByteStream, GameFileLoader, LoaderError};
 use crate::network::ItemId;
 
+/// Compiles a Lua file without running it. Bytecode is unified the same way
+/// as when the Lua files are patched. Used to audit archives.
+pub(super) fn validate_script(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    use lunify::{unify, Format, LunifyError, Settings};
+
+    let bytes = byte_stream
+        .slice(byte_stream.get_remaining_length())
+        .map_err(|error| LoaderError::conversion(file_path, error))?;
+
+    let corrupt = |reason: String| LoaderError::Corrupt {
+        path: file_path.to_owned(),
+        offset: 0,
+        reason,
+    };
+
+    let bytes = match unify(bytes, &Format::default(), &Settings::default()) {
+        Ok(bytes) => bytes,
+        // Not pre-compiled, so this is a source file.
+        Err(LunifyError::IncorrectSignature) => bytes.to_vec(),
+        Err(error) => return Err(corrupt(format!("{error:?}"))),
+    };
+
+    let state = Lua::new();
+    let result = state.load(&bytes).into_function().map(|_| ());
+    result.map_err(|error| corrupt(error.to_string()))
+}
+
 pub struct ScriptLoader {
     state: Lua,
 }
 
 impl ScriptLoader {
-    pub fn new(game_file_loader: &mut GameFileLoader) -> Self {
+    pub fn new(game_file_loader: &GameFileLoader) -> Self {
         let state = Lua::new();
 
         let data = game_file_loader
@@ -96,14 +124,7 @@ end
             .get::<_, LuaTable>("tbl")
             .unwrap()
             .get::<_, LuaTable>(item_id.0)
-            .map(|table| {
-                table
-                    .get::<_, LuaString>("unidentifiedResourceName")
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_owned()
-            })
-            .unwrap_or_else(|_| "»ç°ú".to_owned())
+            .map(|table| decode_path(table.get::<_, LuaString>("unidentifiedResourceName").unwrap().as_bytes()))
+            .unwrap_or_else(|_| "사과".to_owned())
     }
 }
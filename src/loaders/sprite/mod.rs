@@ -19,7 +19,9 @@ use vulkano::sync::GpuFuture;
 use crate::debug::*;
 use crate::graphics::MemoryAllocator;
 use crate::interface::{ElementCell, PrototypeElement};
-use crate::loaders::{ByteConvertable, ByteStream, GameFileLoader, MinorFirst, Version};
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteConvertable, ByteStream, ChangedFiles, ConversionError, GameFileLoader, LoaderError, MinorFirst, Version};
 
 #[derive(Clone, Debug, PrototypeElement)]
 pub struct Sprite {
@@ -33,27 +35,42 @@ pub struct Sprite {
 struct EncodedData(pub Vec<u8>);
 
 impl ByteConvertable for EncodedData {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         let image_size = length_hint.unwrap();
 
         if image_size == 0 {
-            return Self(Vec::new());
+            return Ok(Self(Vec::new()));
+        }
+
+        let mut encoded = u16::from_bytes(byte_stream, None)?;
+
+        // Two encoded bytes expand to at most 255 bytes, so this catches invalid
+        // sizes before allocating the image.
+        if image_size > encoded as usize * 128 {
+            return Err(ConversionError::invalid_data(
+                byte_stream.get_offset(),
+                "palette image is larger than its encoded data",
+            ));
         }
 
         let mut data = vec![0; image_size];
-        let mut encoded = u16::from_bytes(byte_stream, None);
         let mut next = 0;
 
         while next < image_size && encoded > 0 {
-            let byte = byte_stream.next();
+            let byte = byte_stream.next()?;
             encoded -= 1;
 
             if byte == 0 {
-                let length = usize::max(byte_stream.next() as usize, 1);
-                encoded -= 1;
+                let length = usize::max(byte_stream.next()? as usize, 1);
+                encoded = encoded
+                    .checked_sub(1)
+                    .ok_or_else(|| ConversionError::invalid_data(byte_stream.get_offset(), "badly encoded palette image"))?;
 
                 if next + length > image_size {
-                    panic!("too much data encoded in palette image");
+                    return Err(ConversionError::invalid_data(
+                        byte_stream.get_offset(),
+                        "too much data encoded in palette image",
+                    ));
                 }
 
                 next += length;
@@ -64,9 +81,58 @@ impl ByteConvertable for EncodedData {
         }
 
         if next != image_size || encoded > 0 {
-            panic!("badly encoded palette image");
+            return Err(ConversionError::invalid_data(
+                byte_stream.get_offset(),
+                "badly encoded palette image",
+            ));
+        }
+
+        Ok(Self(data))
+    }
+
+    /// Run-length encodes the transparent pixels of the image.
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert_eq!(Some(self.0.len()), length_hint, "palette image does not match its size");
+
+        if self.0.is_empty() {
+            return Vec::new();
+        }
+
+        let mut encoded = Vec::new();
+        let mut pixels = self.0.iter().peekable();
+
+        while let Some(&pixel) = pixels.next() {
+            if pixel != 0 {
+                encoded.push(pixel);
+                continue;
+            }
+
+            let mut length = 1u8;
+
+            while length < u8::MAX && pixels.next_if_eq(&&0).is_some() {
+                length += 1;
+            }
+
+            encoded.extend([0, length]);
         }
 
+        let mut bytes = u16::try_from(encoded.len()).expect("palette image is too big").to_bytes(None);
+        bytes.extend(encoded);
+        bytes
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for EncodedData {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        // Use a lot of transparent pixels, so the run-length encoding is tested.
+        let data = (0..length_hint.unwrap())
+            .map(|_| match generator.choose(2) {
+                0 => 0,
+                _ => u8::arbitrary(generator, None),
+            })
+            .collect();
+
         Self(data)
     }
 }
@@ -75,6 +141,10 @@ impl PrototypeElement for EncodedData {
     fn to_element(&self, display: String) -> ElementCell {
         self.0.to_element(display)
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        self.0.write_text(display, indentation, text)
+    }
 }
 
 #[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
@@ -136,6 +206,19 @@ struct SpriteData {
     pub palette: Option<Palette>,
 }
 
+fn read_sprite_data(file_path: &str, byte_stream: &mut ByteStream) -> Result<SpriteData, LoaderError> {
+    if <[u8; 2]>::from_bytes(byte_stream, None) != Ok([b'S', b'P']) {
+        return Err(LoaderError::invalid_magic(file_path));
+    }
+
+    SpriteData::from_bytes(byte_stream, None).map_err(|error| LoaderError::conversion(file_path, error))
+}
+
+/// Parses a sprite without uploading it to the GPU. Used to audit archives.
+pub(super) fn validate_sprite(file_path: &str, byte_stream: &mut ByteStream) -> Result<(), LoaderError> {
+    read_sprite_data(file_path, byte_stream).map(|_| ())
+}
+
 #[derive(new)]
 pub struct SpriteLoader {
     memory_allocator: Arc<MemoryAllocator>,
@@ -147,23 +230,21 @@ pub struct SpriteLoader {
 }
 
 impl SpriteLoader {
-    fn load(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<Sprite>, String> {
+    fn load(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<Sprite>, LoaderError> {
         #[cfg(feature = "debug")]
         let timer = Timer::new_dynamic(format!("load sprite from {MAGENTA}{path}{NONE}"));
 
-        let bytes = game_file_loader.get(&format!("data\\sprite\\{path}"))?;
-        let mut byte_stream = ByteStream::new(&bytes);
-
-        if <[u8; 2]>::from_bytes(&mut byte_stream, None) != [b'S', b'P'] {
-            return Err(format!("failed to read magic number from {path}"));
-        }
-
-        let sprite_data = SpriteData::from_bytes(&mut byte_stream, None);
+        let file_path = format!("data\\sprite\\{path}");
+        let bytes = game_file_loader.get(&file_path)?;
+        let sprite_data = read_sprite_data(&file_path, &mut ByteStream::new(&bytes))?;
         #[cfg(feature = "debug")]
         let cloned_sprite_data = sprite_data.clone();
 
-        let palette = sprite_data.palette.unwrap(); // unwrap_or_default() as soon as i know what
-        // the default palette is
+        // TODO: use a default palette as soon as i know what the default palette is
+        let palette = sprite_data.palette.ok_or_else(|| LoaderError::UnsupportedVersion {
+            path: file_path,
+            version: sprite_data.version.to_string(),
+        })?;
 
         let rgba_images/*: Vec<Arc<ImmutableImage>>*/ = sprite_data
             .rgba_image_data
@@ -246,13 +327,23 @@ impl SpriteLoader {
         Ok(sprite)
     }
 
-    pub fn get(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<Sprite>, String> {
+    pub fn get(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<Sprite>, LoaderError> {
         match self.cache.get(path) {
             Some(sprite) => Ok(sprite.clone()),
             None => self.load(path, game_file_loader),
         }
     }
 
+    /// Removes all cached sprites that were changed on disk, so they are loaded
+    /// again the next time they are requested. Returns `true` if any sprites
+    /// were removed.
+    pub fn invalidate(&mut self, changed_files: &ChangedFiles) -> bool {
+        let cache_size = self.cache.len();
+        self.cache
+            .retain(|path, _| !changed_files.contains(&format!("data\\sprite\\{path}")));
+        self.cache.len() != cache_size
+    }
+
     pub fn submit_load_buffer(&mut self) -> Option<FenceSignalFuture<Box<dyn GpuFuture>>> {
         self.load_buffer.take().map(|buffer| {
             buffer
@@ -4,7 +4,7 @@ use std::cell::UnsafeCell;
 use derive_new::new;
 
 use super::version::InternalVersion;
-use super::Version;
+use super::{ConversionError, ConversionErrorKind};
 #[cfg(feature = "debug")]
 use crate::debug::*;
 #[cfg(feature = "debug")]
@@ -14,6 +14,7 @@ use crate::interface::TrackedState;
 use crate::interface::WeakElementCell;
 #[cfg(feature = "debug")]
 use crate::network::Packet;
+use crate::network::PacketVersion;
 
 #[derive(new)]
 pub struct ByteStream<'b> {
@@ -22,29 +23,34 @@ pub struct ByteStream<'b> {
     offset: usize,
     #[new(default)]
     version: Option<InternalVersion>,
+    #[new(default)]
+    packet_version: PacketVersion,
     #[cfg(feature = "debug")]
     #[new(default)]
     packet_history: Vec<PacketEntry>,
 }
 
 impl<'b> ByteStream<'b> {
-    pub fn next(&mut self) -> u8 {
-        assert!(self.offset < self.data.len(), "byte stream is shorter than expected");
-        let byte = self.data[self.offset];
+    fn too_short(&self, requested: usize) -> ConversionError {
+        let remaining = self.get_remaining_length();
+        ConversionError::from_kind(self.offset, ConversionErrorKind::ByteStreamTooShort { requested, remaining })
+    }
+
+    pub fn next(&mut self) -> Result<u8, ConversionError> {
+        let byte = *self.data.get(self.offset).ok_or_else(|| self.too_short(1))?;
         self.offset += 1;
-        byte
+        Ok(byte)
     }
 
-    pub fn peek(&self, index: usize) -> u8 {
-        assert!(self.offset + index < self.data.len(), "byte stream is shorter than expected");
-        self.data[self.offset + index]
+    pub fn peek(&self, index: usize) -> Result<u8, ConversionError> {
+        self.data.get(self.offset + index).copied().ok_or_else(|| self.too_short(index + 1))
     }
 
     pub fn is_empty(&self) -> bool {
         self.offset >= self.data.len()
     }
 
-    pub fn set_version<T>(&mut self, version: Version<T>) {
+    pub fn set_version(&mut self, version: impl Into<InternalVersion>) {
         self.version = Some(version.into());
     }
 
@@ -52,8 +58,18 @@ impl<'b> ByteStream<'b> {
         self.version.unwrap()
     }
 
+    /// Version of the packets in the stream. Unlike the version of files, it
+    /// isn't part of the data, so it needs to be set by whoever receives it.
+    pub fn set_packet_version(&mut self, packet_version: PacketVersion) {
+        self.packet_version = packet_version;
+    }
+
+    pub fn get_packet_version(&self) -> PacketVersion {
+        self.packet_version
+    }
+
     pub fn match_signature(&mut self, signature: u16) -> bool {
-        if self.data.len() - self.offset < 2 {
+        if self.get_remaining_length() < 2 {
             return false;
         }
 
@@ -66,26 +82,41 @@ impl<'b> ByteStream<'b> {
         signature_matches
     }
 
-    pub fn slice(&mut self, count: usize) -> &[u8] {
-        assert!(self.offset + count <= self.data.len(), "byte stream is shorter than expected");
-
+    pub fn slice(&mut self, count: usize) -> Result<&[u8], ConversionError> {
         let start_index = self.offset;
-        self.offset += count;
+        let end_index = start_index
+            .checked_add(count)
+            .filter(|end_index| *end_index <= self.data.len())
+            .ok_or_else(|| self.too_short(count))?;
 
-        &self.data[start_index..self.offset]
+        self.offset = end_index;
+        Ok(&self.data[start_index..end_index])
     }
 
-    pub fn skip(&mut self, count: usize) {
-        self.offset += count;
+    pub fn skip(&mut self, count: usize) -> Result<(), ConversionError> {
+        self.slice(count).map(|_| ())
     }
 
     pub fn get_offset(&self) -> usize {
         self.offset
     }
 
+    /// Moves back to an earlier offset, e.g. to try a different packet after
+    /// decoding failed.
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Number of bytes that were not read yet.
+    pub fn get_remaining_length(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+
     #[cfg(feature = "debug")]
     pub fn remaining_bytes(&mut self) -> Vec<u8> {
-        self.slice(self.data.len() - self.offset).to_vec()
+        let bytes = self.data[self.offset.min(self.data.len())..].to_vec();
+        self.offset = self.data.len();
+        bytes
     }
 
     #[cfg(feature = "debug")]
@@ -126,3 +157,26 @@ impl<'b> ByteStream<'b> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ByteStream;
+
+    #[test]
+    fn skip_past_end() {
+        let mut byte_stream = ByteStream::new(&[1, 2, 3]);
+
+        assert!(byte_stream.skip(4).is_err());
+        assert_eq!(byte_stream.get_offset(), 0);
+        assert!(byte_stream.skip(3).is_ok());
+        assert!(byte_stream.is_empty());
+    }
+
+    #[test]
+    fn match_signature_at_end() {
+        let mut byte_stream = ByteStream::new(&[0x50, 0x4b]);
+        byte_stream.set_offset(3);
+
+        assert!(!byte_stream.match_signature(0x4b50));
+    }
+}
@@ -0,0 +1,44 @@
+//! Files and folders that tests create outside of the repository.
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A path inside of the temporary directory that is removed once it goes out
+/// of scope, so failing tests don't leave anything behind.
+pub struct TemporaryPath(PathBuf);
+
+impl TemporaryPath {
+    /// Tests run in parallel, so `name` has to be unique among all tests. The
+    /// extension of `name` is kept, since archives are selected by it.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("korangar_{}_{name}", std::process::id()));
+        Self::remove(&path);
+        Self(path)
+    }
+
+    fn remove(path: &Path) {
+        let _ = match path.is_dir() {
+            true => std::fs::remove_dir_all(path),
+            false => std::fs::remove_file(path),
+        };
+    }
+}
+
+impl Deref for TemporaryPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TemporaryPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TemporaryPath {
+    fn drop(&mut self) {
+        Self::remove(&self.0);
+    }
+}
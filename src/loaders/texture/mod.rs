@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
 use std::sync::Arc;
 
 use derive_new::new;
 use image::io::Reader as ImageReader;
-use image::{EncodableLayout, ImageFormat, Rgba};
+use image::{EncodableLayout, ImageFormat, Rgba, RgbaImage};
 use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
 use vulkano::command_buffer::{
     AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
@@ -20,7 +20,7 @@ use vulkano::sync::GpuFuture;
 #[cfg(feature = "debug")]
 use crate::debug::*;
 use crate::graphics::MemoryAllocator;
-use crate::loaders::GameFileLoader;
+use crate::loaders::{ChangedFiles, GameFileLoader, LoaderError};
 
 #[derive(new)]
 pub struct TextureLoader {
@@ -33,22 +33,30 @@ pub struct TextureLoader {
 }
 
 impl TextureLoader {
-    fn load(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<ImageView>, String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new_dynamic(format!("load texture from {MAGENTA}{path}{NONE}"));
-
+    /// Reads and decodes a texture. This does not touch the GPU, so it can be
+    /// called from any thread.
+    pub(super) fn decode(path: &str, game_file_loader: &GameFileLoader) -> Result<RgbaImage, LoaderError> {
+        let file_path = format!("data\\texture\\{path}");
         let image_format = match &path[path.len() - 4..] {
             ".png" => ImageFormat::Png,
             ".bmp" | ".BMP" => ImageFormat::Bmp,
             ".tga" | ".TGA" => ImageFormat::Tga,
-            extension => return Err(format!("unsupported file format {extension}")),
+            extension => {
+                return Err(LoaderError::UnsupportedFormat {
+                    path: file_path,
+                    format: extension.to_owned(),
+                });
+            }
         };
 
-        let file_data = game_file_loader.get(&format!("data\\texture\\{path}"))?;
+        let file_data = game_file_loader.get(&file_path)?;
         let reader = ImageReader::with_format(Cursor::new(file_data), image_format);
         let mut image_buffer = reader
             .decode()
-            .map_err(|error| format!("failed to decode image file ({error})"))?
+            .map_err(|error| LoaderError::Image {
+                path: file_path,
+                reason: error.to_string(),
+            })?
             .to_rgba8();
 
         if image_format == ImageFormat::Bmp {
@@ -59,6 +67,10 @@ impl TextureLoader {
                 .for_each(|pixel| *pixel = Rgba([0; 4]));
         }
 
+        Ok(image_buffer)
+    }
+
+    pub(super) fn upload(&mut self, path: &str, image_buffer: RgbaImage) -> Arc<ImageView> {
         let load_buffer = self.load_buffer.get_or_insert_with(|| {
             AutoCommandBufferBuilder::primary(
                 &*self.memory_allocator,
@@ -101,19 +113,44 @@ impl TextureLoader {
         let texture = ImageView::new_default(image).unwrap();
         self.cache.insert(path.to_string(), texture.clone());
 
+        texture
+    }
+
+    fn load(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<ImageView>, LoaderError> {
+        #[cfg(feature = "debug")]
+        let timer = Timer::new_dynamic(format!("load texture from {MAGENTA}{path}{NONE}"));
+
+        let image_buffer = Self::decode(path, game_file_loader)?;
+        let texture = self.upload(path, image_buffer);
+
         #[cfg(feature = "debug")]
         timer.stop();
 
         Ok(texture)
     }
 
-    pub fn get(&mut self, path: &str, game_file_loader: &mut GameFileLoader) -> Result<Arc<ImageView>, String> {
+    /// Paths of all textures that are currently cached.
+    pub(super) fn get_cached_paths(&self) -> HashSet<String> {
+        self.cache.keys().cloned().collect()
+    }
+
+    pub fn get(&mut self, path: &str, game_file_loader: &GameFileLoader) -> Result<Arc<ImageView>, LoaderError> {
         match self.cache.get(path) {
             Some(texture) => Ok(texture.clone()),
             None => self.load(path, game_file_loader),
         }
     }
 
+    /// Removes all cached textures that were changed on disk, so they are
+    /// loaded again the next time they are requested. Returns `true` if any
+    /// textures were removed.
+    pub fn invalidate(&mut self, changed_files: &ChangedFiles) -> bool {
+        let cache_size = self.cache.len();
+        self.cache
+            .retain(|path, _| !changed_files.contains(&format!("data\\texture\\{path}")));
+        self.cache.len() != cache_size
+    }
+
     pub fn submit_load_buffer(&mut self) -> Option<FenceSignalFuture<Box<dyn GpuFuture>>> {
         self.load_buffer.take().map(|buffer| {
             buffer
@@ -1,9 +1,9 @@
-use std::fmt::{Display, Formatter, Result};
+use std::fmt::{Display, Formatter};
 use std::marker::PhantomData;
 
 use derive_new::new;
 
-use super::ByteConvertable;
+use super::{ByteConvertable, ConversionError};
 
 #[derive(Copy, Clone, Debug)]
 pub struct MajorFirst;
@@ -17,37 +17,61 @@ pub struct Version<T> {
 }
 
 impl ByteConvertable for Version<MajorFirst> {
-    fn from_bytes(byte_stream: &mut super::ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut super::ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none());
 
-        let major = byte_stream.next();
-        let minor = byte_stream.next();
+        let major = byte_stream.next()?;
+        let minor = byte_stream.next()?;
 
-        Self {
+        Ok(Self {
             major,
             minor,
             phantom_data: PhantomData,
-        }
+        })
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert!(length_hint.is_none());
+        vec![self.major, self.minor]
     }
 }
 
 impl ByteConvertable for Version<MinorFirst> {
-    fn from_bytes(byte_stream: &mut super::ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut super::ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none());
 
-        let minor = byte_stream.next();
-        let major = byte_stream.next();
+        let minor = byte_stream.next()?;
+        let major = byte_stream.next()?;
 
-        Self {
+        Ok(Self {
             minor,
             major,
             phantom_data: PhantomData,
+        })
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert!(length_hint.is_none());
+        vec![self.minor, self.major]
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl<T> super::Arbitrary for Version<T> {
+    fn arbitrary(generator: &mut super::Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none());
+        let InternalVersion { major, minor } = generator.get_version();
+
+        Self {
+            major,
+            minor,
+            phantom_data: PhantomData,
         }
     }
 }
 
 impl<T> Display for Version<T> {
-    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         write!(formatter, "{}.{}", self.major, self.minor)
     }
 }
@@ -76,7 +100,7 @@ impl InternalVersion {
 }
 
 impl Display for InternalVersion {
-    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
         write!(formatter, "{}.{}", self.major, self.minor)
     }
 }
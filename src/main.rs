@@ -15,29 +15,31 @@
 #![feature(lazy_cell)]
 #![feature(div_duration)]
 #![feature(iter_next_chunk)]
-
-#[cfg(feature = "debug")]
-#[macro_use]
-mod debug;
-mod input;
-#[macro_use]
-mod system;
-mod graphics;
-mod interface;
-mod inventory;
-mod loaders;
-mod network;
-mod world;
+#![feature(array_try_from_fn)]
 
 use std::cell::RefCell;
 use std::io::Cursor;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 
 use cgmath::{Vector2, Vector3, Zero};
 use image::io::Reader as ImageReader;
 use image::{EncodableLayout, ImageFormat};
-use network::SkillType;
+#[cfg(feature = "debug")]
+use korangar::debug::*;
+use korangar::graphics::*;
+use korangar::input::{FocusState, InputSystem, UserEvent};
+use korangar::interface::*;
+use korangar::inventory::{Hotbar, Inventory, SkillTree};
+use korangar::loaders::*;
+use korangar::network::{
+    AccountId, CaptureMode, CharacterInformation, ChatMessage, DisconnectReason, NetworkEvent, NetworkingSystem, SkillId, SkillType, UnitId,
+};
+#[cfg(feature = "debug")]
+use korangar::profile_block;
+use korangar::system::{choose_physical_device, get_device_extensions, get_layers, GameTimer};
+use korangar::world::*;
 use procedural::debug_condition;
 use vulkano::device::{Device, DeviceCreateInfo, QueueCreateInfo};
 use vulkano::instance::debug::DebugUtilsMessengerCallback;
@@ -51,22 +53,38 @@ use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{Icon, WindowBuilder};
 
-#[cfg(feature = "debug")]
-use crate::debug::*;
-use crate::graphics::*;
-use crate::input::{FocusState, InputSystem, UserEvent};
-use crate::interface::*;
-use crate::inventory::{Hotbar, Inventory, SkillTree};
-use crate::loaders::*;
-use crate::network::{ChatMessage, NetworkEvent, NetworkingSystem, SkillId, UnitId};
-use crate::system::{choose_physical_device, get_device_extensions, get_layers, GameTimer};
-use crate::world::*;
-
 const ROLLING_CUTTER_ID: SkillId = SkillId(2036);
 
+/// What to do once a map finished loading in the background.
+enum MapChange {
+    /// The player entered the game with the selected character.
+    EnterGame(AccountId, CharacterInformation),
+    /// The player was moved to a different map.
+    Warp(Vector2<usize>),
+}
+
+const USAGE: &str = "usage: korangar [--capture <file> | --replay <file>]";
+
+fn parse_capture_mode() -> Option<CaptureMode> {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    match arguments.as_slice() {
+        [] => Some(CaptureMode::Disabled),
+        ["--capture", path] => Some(CaptureMode::Record(PathBuf::from(path))),
+        ["--replay", path] => Some(CaptureMode::Replay(PathBuf::from(path))),
+        _ => None,
+    }
+}
+
 fn main() {
     const DEFAULT_MAP: &str = "geffen";
 
+    let Some(capture_mode) = parse_capture_mode() else {
+        eprintln!("{USAGE}");
+        return;
+    };
+
     // We start a frame so that functions trying to start a measurement don't panic.
     #[cfg(feature = "debug")]
     let _measurement = profiler_start_main_thread();
@@ -178,6 +196,21 @@ fn main() {
     #[cfg(feature = "debug")]
     timer.stop();
 
+    #[cfg(feature = "debug")]
+    let timer = Timer::new("initialize networking");
+
+    let mut networking_system = match NetworkingSystem::new(capture_mode) {
+        Ok(networking_system) => networking_system,
+        Err(error) => {
+            eprintln!("failed to open packet capture: {error}");
+            eprintln!("{USAGE}");
+            return;
+        }
+    };
+
+    #[cfg(feature = "debug")]
+    timer.stop();
+
     #[cfg(feature = "debug")]
     let timer = Timer::new("create resource managers");
 
@@ -185,15 +218,18 @@ fn main() {
 
     let mut game_file_loader = GameFileLoader::default();
 
-    game_file_loader.load_archives_from_settings();
+    game_file_loader.load_archives_from_settings(networking_system.get_archives());
     game_file_loader.load_patched_lua_files();
 
+    // Shared with the map loading thread.
+    let game_file_loader = Arc::new(game_file_loader);
+
     let memory_allocator = Arc::new(MemoryAllocator::new(device.clone()));
 
     let font_loader = Rc::new(RefCell::new(FontLoader::new(
         memory_allocator.clone(),
         queue.clone(),
-        &mut game_file_loader,
+        &game_file_loader,
     )));
 
     let mut buffer_allocator = BufferAllocator::new(memory_allocator.clone(), queue.clone());
@@ -203,7 +239,7 @@ fn main() {
     let mut sprite_loader = SpriteLoader::new(memory_allocator.clone(), queue.clone());
     let mut action_loader = ActionLoader::default();
     let mut effect_loader = EffectLoader::default();
-    let script_loader = ScriptLoader::new(&mut game_file_loader);
+    let script_loader = ScriptLoader::new(&game_file_loader);
 
     #[cfg(feature = "debug")]
     timer.stop();
@@ -214,7 +250,7 @@ fn main() {
     let mut map = map_loader
         .get(
             DEFAULT_MAP.to_string(),
-            &mut game_file_loader,
+            &game_file_loader,
             &mut buffer_allocator,
             &mut model_loader,
             &mut texture_loader,
@@ -239,7 +275,7 @@ fn main() {
     let mut deferred_renderer = DeferredRenderer::new(
         memory_allocator.clone(),
         &mut buffer_allocator,
-        &mut game_file_loader,
+        &game_file_loader,
         &mut texture_loader,
         queue.clone(),
         swapchain_holder.swapchain_format(),
@@ -249,7 +285,7 @@ fn main() {
 
     let mut interface_renderer = InterfaceRenderer::new(
         memory_allocator.clone(),
-        &mut game_file_loader,
+        &game_file_loader,
         &mut texture_loader,
         font_loader.clone(),
         queue.clone(),
@@ -264,7 +300,7 @@ fn main() {
         swapchain_holder.window_size_u32(),
     );
 
-    let shadow_renderer = ShadowRenderer::new(memory_allocator, &mut game_file_loader, &mut texture_loader, queue);
+    let shadow_renderer = ShadowRenderer::new(memory_allocator, &game_file_loader, &mut texture_loader, queue);
 
     #[cfg(feature = "debug")]
     timer.stop();
@@ -299,7 +335,7 @@ fn main() {
     let timer = Timer::new("initialize interface");
 
     let mut interface = Interface::new(
-        &mut game_file_loader,
+        &game_file_loader,
         &mut sprite_loader,
         &mut action_loader,
         swapchain_holder.window_size_f32(),
@@ -336,18 +372,7 @@ fn main() {
     #[cfg(feature = "debug")]
     timer.stop();
 
-    #[cfg(feature = "debug")]
-    let timer = Timer::new("initialize networking");
-
-    let mut networking_system = NetworkingSystem::new();
-
-    interface.open_window(
-        &mut focus_state,
-        &LoginWindow::new(networking_system.get_login_settings().clone()),
-    );
-
-    #[cfg(feature = "debug")]
-    timer.stop();
+    interface.open_window(&mut focus_state, &networking_system.login_window());
 
     let mut particle_holder = ParticleHolder::default();
     let mut effect_holder = EffectHolder::default();
@@ -355,6 +380,10 @@ fn main() {
     let mut player_inventory = Inventory::default();
     let mut player_skill_tree = SkillTree::default();
     let mut hotbar = Hotbar::default();
+    let hot_reload_receiver = game_file_loader.watch_changes();
+    let mut map_loading: Option<(MapLoadingJob, MapChange)> = None;
+    let mut map_loading_progress = TrackedState::new(MapLoadingProgress::default());
+    let mut queued_network_events = Vec::new();
 
     let welcome_message = ChatMessage::new("Welcome to Korangar!".to_string(), Color::rgb(220, 170, 220));
     let chat_messages = Rc::new(RefCell::new(vec![welcome_message]));
@@ -367,6 +396,7 @@ fn main() {
                 event: WindowEvent::CloseRequested,
                 ..
             } => control_flow.set_exit(),
+            Event::LoopDestroyed => game_file_loader.save_missing_asset_report(),
             Event::WindowEvent {
                 event: WindowEvent::Resized(_),
                 ..
@@ -438,8 +468,94 @@ fn main() {
                 #[cfg(feature = "debug")]
                 timer_measuremen.stop();
 
-                networking_system.keep_alive(delta_time, client_tick);
-                let network_events = networking_system.network_events();
+                networking_system.set_client_tick(client_tick);
+                let mut network_events = networking_system.network_events();
+
+                if let Some((map_loading_job, _)) = &map_loading {
+                    let progress = map_loading_job.get_progress();
+
+                    if progress != *map_loading_progress.borrow() {
+                        map_loading_progress.set(progress);
+                    }
+                }
+
+                if map_loading
+                    .as_ref()
+                    .is_some_and(|(map_loading_job, _)| map_loading_job.is_finished())
+                {
+                    let (map_loading_job, map_change) = map_loading.take().unwrap();
+                    interface.close_window_with_class(&mut focus_state, LoadingWindow::WINDOW_CLASS);
+
+                    let loaded_map = map_loader.finish_loading(
+                        map_loading_job,
+                        &game_file_loader,
+                        &mut buffer_allocator,
+                        &mut model_loader,
+                        &mut texture_loader,
+                    );
+
+                    match loaded_map {
+                        Ok(loaded_map) => {
+                            map = loaded_map;
+
+                            match map_change {
+                                MapChange::EnterGame(account_id, character_information) => {
+                                    let player = Player::new(
+                                        &game_file_loader,
+                                        &mut sprite_loader,
+                                        &mut action_loader,
+                                        &script_loader,
+                                        &map,
+                                        account_id,
+                                        character_information,
+                                        Vector2::zero(),
+                                        client_tick,
+                                    );
+                                    let player = Entity::Player(player);
+
+                                    player_camera.set_focus_point(player.get_position());
+                                    entities.push(player);
+
+                                    interface.open_window(&mut focus_state, &CharacterOverviewWindow::new());
+                                    interface.open_window(&mut focus_state, &ChatWindow::new(chat_messages.clone(), font_loader.clone()));
+                                    interface.open_window(&mut focus_state, &HotbarWindow::new(hotbar.get_skills()));
+
+                                    particle_holder.clear();
+                                    game_timer.set_client_tick(client_tick);
+                                }
+                                MapChange::Warp(player_position) => {
+                                    entities[0].set_position(&map, player_position, client_tick);
+                                    player_camera.set_focus_point(entities[0].get_position());
+
+                                    particle_holder.clear();
+                                    effect_holder.clear();
+                                }
+                            }
+
+                            networking_system.map_loaded();
+                            // TODO: this is just a workaround until i find a better solution to make the
+                            // cursor always look correct.
+                            interface.set_start_time(client_tick);
+                        }
+                        Err(error) => {
+                            // Events that refer to the map would find no player, so they are dropped
+                            // and the player goes back to the character selection.
+                            queued_network_events.clear();
+                            network_events.retain(|event| !event.requires_map());
+                            network_events.insert(
+                                0,
+                                NetworkEvent::Disconnect(DisconnectReason::MapLoadingFailed(format!("failed to load map: {error}"))),
+                            );
+                        }
+                    }
+                }
+
+                // Events that were queued while the map was loading are processed before any
+                // newer events.
+                if map_loading.is_none() && !queued_network_events.is_empty() {
+                    queued_network_events.append(&mut network_events);
+                    network_events = std::mem::take(&mut queued_network_events);
+                }
 
                 let (user_events, hovered_element, focused_element, mouse_target) = input_system.user_events(
                     &mut interface,
@@ -477,7 +593,35 @@ fn main() {
                 let network_event_measuremen = start_measurement("process network events");
 
                 for event in network_events {
+                    // Events that refer to the map have to wait until it is loaded.
+                    if map_loading.is_some() && event.requires_map() {
+                        queued_network_events.push(event);
+                        continue;
+                    }
+
                     match event {
+                        NetworkEvent::LoggedIn => {
+                            // TODO: this will do one unnecessary restore_focus. check if
+                            // that will be problematic
+                            interface.close_window_with_class(&mut focus_state, LoginWindow::WINDOW_CLASS);
+
+                            let character_selection_window = networking_system.character_selection_window();
+                            interface.open_window(&mut focus_state, &character_selection_window);
+                        }
+                        NetworkEvent::CharacterCreated => {
+                            interface.close_window_with_class(&mut focus_state, CharacterCreationWindow::WINDOW_CLASS)
+                        }
+                        NetworkEvent::CharacterSelected(account_id, character_information, map_name) => {
+                            // TODO: this will do one unnecessary restore_focus. check if
+                            // that will be problematic
+                            interface.close_window_with_class(&mut focus_state, CharacterSelectionWindow::WINDOW_CLASS);
+
+                            let map_loading_job =
+                                map_loader.start_loading(map_name, game_file_loader.clone(), &model_loader, &texture_loader);
+                            map_loading = Some((map_loading_job, MapChange::EnterGame(account_id, character_information)));
+                            interface.open_window(&mut focus_state, &LoadingWindow::new(map_loading_progress.new_remote()));
+                        }
+                        NetworkEvent::RequestFailed(message) => interface.open_window(&mut focus_state, &ErrorWindow::new(message)),
                         NetworkEvent::AddEntity(entity_appeared_data) => {
                             // Sometimes (like after a job change) the server will tell the client
                             // that a new entity appeared, even though it was already on screen. So
@@ -485,7 +629,7 @@ fn main() {
                             entities.retain(|entity| entity.get_entity_id() != entity_appeared_data.entity_id);
 
                             let npc = Npc::new(
-                                &mut game_file_loader,
+                                &game_file_loader,
                                 &mut sprite_loader,
                                 &mut action_loader,
                                 &script_loader,
@@ -518,25 +662,10 @@ fn main() {
                         NetworkEvent::ChangeMap(map_name, player_position) => {
                             entities.truncate(1);
 
-                            map = map_loader
-                                .get(
-                                    map_name,
-                                    &mut game_file_loader,
-                                    &mut buffer_allocator,
-                                    &mut model_loader,
-                                    &mut texture_loader,
-                                )
-                                .unwrap();
-
-                            entities[0].set_position(&map, player_position, client_tick);
-                            player_camera.set_focus_point(entities[0].get_position());
-
-                            particle_holder.clear();
-                            effect_holder.clear();
-                            networking_system.map_loaded();
-                            // TODO: this is just a workaround until i find a better solution to make the
-                            // cursor always look correct.
-                            interface.set_start_time(client_tick);
+                            let map_loading_job =
+                                map_loader.start_loading(map_name, game_file_loader.clone(), &model_loader, &texture_loader);
+                            map_loading = Some((map_loading_job, MapChange::Warp(player_position)));
+                            interface.open_window(&mut focus_state, &LoadingWindow::new(map_loading_progress.new_remote()));
                         }
                         NetworkEvent::SetPlayerPosition(player_position) => {
                             entities[0].set_position(&map, player_position, client_tick);
@@ -590,15 +719,15 @@ fn main() {
                         NetworkEvent::AddCloseButton => interface.add_close_button(),
                         NetworkEvent::AddChoiceButtons(choices) => interface.add_choice_buttons(choices),
                         NetworkEvent::AddQuestEffect(quest_effect) => {
-                            particle_holder.add_quest_icon(&mut game_file_loader, &mut texture_loader, &map, quest_effect)
+                            particle_holder.add_quest_icon(&game_file_loader, &mut texture_loader, &map, quest_effect)
                         }
                         NetworkEvent::RemoveQuestEffect(entity_id) => particle_holder.remove_quest_icon(entity_id),
                         NetworkEvent::Inventory(item_data) => {
-                            player_inventory.fill(&mut game_file_loader, &mut texture_loader, &script_loader, item_data);
+                            player_inventory.fill(&game_file_loader, &mut texture_loader, &script_loader, item_data);
                         }
                         NetworkEvent::AddIventoryItem(item_index, item_data, equip_position, equipped_position) => {
                             player_inventory.add_item(
-                                &mut game_file_loader,
+                                &game_file_loader,
                                 &mut texture_loader,
                                 &script_loader,
                                 item_index,
@@ -608,7 +737,7 @@ fn main() {
                             );
                         }
                         NetworkEvent::SkillTree(skill_information) => {
-                            player_skill_tree.fill(&mut game_file_loader, &mut sprite_loader, &mut action_loader, skill_information);
+                            player_skill_tree.fill(&game_file_loader, &mut sprite_loader, &mut action_loader, skill_information);
                         }
                         NetworkEvent::UpdateEquippedPosition { index, equipped_position } => {
                             player_inventory.update_equipped_position(index, equipped_position);
@@ -617,10 +746,16 @@ fn main() {
                             let entity = entities.iter_mut().find(|entity| entity.get_entity_id().0 == account_id.0).unwrap();
 
                             entity.set_job(job_id as usize);
-                            entity.reload_sprite(&mut game_file_loader, &mut sprite_loader, &mut action_loader, &script_loader);
+                            entity.reload_sprite(&game_file_loader, &mut sprite_loader, &mut action_loader, &script_loader);
+                        }
+                        NetworkEvent::Disconnect(DisconnectReason::LoginServerUnavailable(message)) => {
+                            interface.open_window(&mut focus_state, &ErrorWindow::new(message))
                         }
-                        NetworkEvent::Disconnect => {
+                        NetworkEvent::Disconnect(reason) => {
                             networking_system.disconnect_from_map_server();
+                            // The background thread can't be stopped, but its result is discarded.
+                            map_loading = None;
+                            queued_network_events.clear();
                             entities.clear();
                             particle_holder.clear();
                             effect_holder.clear();
@@ -628,7 +763,7 @@ fn main() {
                             map = map_loader
                                 .get(
                                     DEFAULT_MAP.to_string(),
-                                    &mut game_file_loader,
+                                    &game_file_loader,
                                     &mut buffer_allocator,
                                     &mut model_loader,
                                     &mut texture_loader,
@@ -637,15 +772,27 @@ fn main() {
 
                             interface.close_all_windows(&mut focus_state);
 
-                            let character_selection_window = networking_system.character_selection_window();
-                            interface.open_window(&mut focus_state, &character_selection_window);
+                            match reason {
+                                DisconnectReason::CharacterServerLost(message) => {
+                                    interface.open_window(&mut focus_state, &networking_system.login_window());
+                                    interface.open_window(&mut focus_state, &ErrorWindow::new(message));
+                                }
+                                reason => {
+                                    let character_selection_window = networking_system.character_selection_window();
+                                    interface.open_window(&mut focus_state, &character_selection_window);
+
+                                    if let DisconnectReason::MapServerLost(message) | DisconnectReason::MapLoadingFailed(message) = reason {
+                                        interface.open_window(&mut focus_state, &ErrorWindow::new(message));
+                                    }
+                                }
+                            }
 
                             start_camera.set_focus_point(cgmath::Point3::new(600.0, 0.0, 240.0));
                             directional_shadow_camera.set_focus_point(cgmath::Point3::new(600.0, 0.0, 240.0));
                         }
                         NetworkEvent::FriendRequest(friend) => interface.open_window(&mut focus_state, &FriendRequestWindow::new(friend)),
                         NetworkEvent::VisualEffect(path, entity_id) => {
-                            let effect = effect_loader.get(path, &mut game_file_loader, &mut texture_loader).unwrap();
+                            let effect = effect_loader.get(path, &game_file_loader, &mut texture_loader).unwrap();
                             let frame_timer = effect.new_frame_timer();
 
                             effect_holder.add_effect(Box::new(EffectWithLight::new(
@@ -662,9 +809,7 @@ fn main() {
                         NetworkEvent::AddSkillUnit(entity_id, unit_id, position) => match unit_id {
                             UnitId::Firewall => {
                                 let position = map.get_world_position(position);
-                                let effect = effect_loader
-                                    .get("firewall.str", &mut game_file_loader, &mut texture_loader)
-                                    .unwrap();
+                                let effect = effect_loader.get("firewall.str", &game_file_loader, &mut texture_loader).unwrap();
                                 let frame_timer = effect.new_frame_timer();
 
                                 effect_holder.add_unit(
@@ -683,9 +828,7 @@ fn main() {
                             }
                             UnitId::Pneuma => {
                                 let position = map.get_world_position(position);
-                                let effect = effect_loader
-                                    .get("pneuma1.str", &mut game_file_loader, &mut texture_loader)
-                                    .unwrap();
+                                let effect = effect_loader.get("pneuma1.str", &game_file_loader, &mut texture_loader).unwrap();
                                 let frame_timer = effect.new_frame_timer();
 
                                 effect_holder.add_unit(
@@ -718,17 +861,10 @@ fn main() {
 
                 for event in user_events {
                     match event {
-                        UserEvent::LogIn(username, password) => match networking_system.log_in(username, password) {
-                            Ok(()) => {
-                                // TODO: this will do one unnecessary restore_focus. check if
-                                // that will be problematic
-                                interface.close_window_with_class(&mut focus_state, LoginWindow::WINDOW_CLASS);
-
-                                let character_selection_window = networking_system.character_selection_window();
-                                interface.open_window(&mut focus_state, &character_selection_window);
-                            }
-                            Err(message) => interface.open_window(&mut focus_state, &ErrorWindow::new(message)),
-                        },
+                        UserEvent::SelectServer(name) => interface.handle_result(&mut focus_state, networking_system.select_server(&name)),
+                        UserEvent::LogIn(username, password) => {
+                            interface.handle_result(&mut focus_state, networking_system.log_in(username, password))
+                        }
                         UserEvent::LogOut => networking_system.log_out().unwrap(),
                         UserEvent::Exit => *control_flow = ControlFlow::Exit,
                         UserEvent::ToggleRemeberUsername => networking_system.toggle_remember_username(),
@@ -776,59 +912,13 @@ fn main() {
                         UserEvent::SaveTheme => interface.save_theme(),
                         UserEvent::ReloadTheme => interface.reload_theme(),
                         UserEvent::SelectCharacter(character_slot) => {
-                            match networking_system.select_character(character_slot) {
-                                Ok((account_id, character_information, map_name)) => {
-                                    map = map_loader
-                                        .get(
-                                            map_name,
-                                            &mut game_file_loader,
-                                            &mut buffer_allocator,
-                                            &mut model_loader,
-                                            &mut texture_loader,
-                                        )
-                                        .unwrap();
-
-                                    let player = Player::new(
-                                        &mut game_file_loader,
-                                        &mut sprite_loader,
-                                        &mut action_loader,
-                                        &script_loader,
-                                        &map,
-                                        account_id,
-                                        character_information,
-                                        Vector2::zero(),
-                                        client_tick,
-                                    );
-                                    let player = Entity::Player(player);
-
-                                    player_camera.set_focus_point(player.get_position());
-                                    entities.push(player);
-
-                                    // TODO: this will do one unnecessary restore_focus. check if
-                                    // that will be problematic
-                                    interface.close_window_with_class(&mut focus_state, CharacterSelectionWindow::WINDOW_CLASS);
-                                    interface.open_window(&mut focus_state, &CharacterOverviewWindow::new());
-                                    interface.open_window(&mut focus_state, &ChatWindow::new(chat_messages.clone(), font_loader.clone()));
-                                    interface.open_window(&mut focus_state, &HotbarWindow::new(hotbar.get_skills()));
-
-                                    particle_holder.clear();
-                                    networking_system.map_loaded();
-                                    // TODO: this is just a workaround until i find a better solution to make the
-                                    // cursor always look correct.
-                                    interface.set_start_time(client_tick);
-                                    game_timer.set_client_tick(client_tick);
-                                }
-                                Err(message) => interface.open_window(&mut focus_state, &ErrorWindow::new(message)),
-                            }
+                            interface.handle_result(&mut focus_state, networking_system.select_character(character_slot))
                         }
                         UserEvent::OpenCharacterCreationWindow(character_slot) => {
                             interface.open_window(&mut focus_state, &CharacterCreationWindow::new(character_slot))
                         }
                         UserEvent::CreateCharacter(character_slot, name) => {
-                            match networking_system.create_character(character_slot, name) {
-                                Ok(..) => interface.close_window_with_class(&mut focus_state, CharacterCreationWindow::WINDOW_CLASS),
-                                Err(message) => interface.open_window(&mut focus_state, &ErrorWindow::new(message)),
-                            }
+                            interface.handle_result(&mut focus_state, networking_system.create_character(character_slot, name))
                         }
                         UserEvent::DeleteCharacter(character_id) => {
                             interface.handle_result(&mut focus_state, networking_system.delete_character(character_id))
@@ -893,7 +983,8 @@ fn main() {
                             _ => {}
                         },
                         UserEvent::CastSkill(slot) => {
-                            if let Some(skill) = hotbar.get_skill_in_slot(slot).as_ref() {
+                            // There is no player while no map is loaded.
+                            if let (Some(skill), Some(player)) = (hotbar.get_skill_in_slot(slot).as_ref(), entities.first()) {
                                 match skill.skill_type {
                                     SkillType::Passive => {}
                                     SkillType::Attack => {
@@ -910,17 +1001,15 @@ fn main() {
                                         true => networking_system.cast_channeling_skill(
                                             skill.skill_id,
                                             skill.skill_level,
-                                            entities[0].get_entity_id(),
+                                            player.get_entity_id(),
                                         ),
-                                        false => {
-                                            networking_system.cast_skill(skill.skill_id, skill.skill_level, entities[0].get_entity_id())
-                                        }
+                                        false => networking_system.cast_skill(skill.skill_id, skill.skill_level, player.get_entity_id()),
                                     },
                                     SkillType::Support => {
                                         if let Some(PickerTarget::Entity(entity_id)) = mouse_target {
                                             networking_system.cast_skill(skill.skill_id, skill.skill_level, entity_id);
                                         } else {
-                                            networking_system.cast_skill(skill.skill_id, skill.skill_level, entities[0].get_entity_id());
+                                            networking_system.cast_skill(skill.skill_id, skill.skill_level, player.get_entity_id());
                                         }
                                     }
                                 }
@@ -962,6 +1051,23 @@ fn main() {
                         #[cfg(feature = "debug")]
                         UserEvent::OpenMapsWindow => interface.open_window(&mut focus_state, &MapsWindow::default()),
                         #[cfg(feature = "debug")]
+                        UserEvent::OpenMissingAssetsWindow => interface.open_window(
+                            &mut focus_state,
+                            &MissingAssetsWindow::new(game_file_loader.get_missing_assets()),
+                        ),
+                        #[cfg(feature = "debug")]
+                        UserEvent::AuditArchives => {
+                            let game_file_loader = game_file_loader.clone();
+                            std::thread::Builder::new()
+                                .name("archive audit".to_string())
+                                .spawn(move || {
+                                    if let Err(error) = game_file_loader.save_audit_report() {
+                                        print_debug!("[{}error{}] failed to audit archives: {}", RED, NONE, error);
+                                    }
+                                })
+                                .expect("failed to spawn archive audit thread");
+                        }
+                        #[cfg(feature = "debug")]
                         UserEvent::OpenCommandsWindow => interface.open_window(&mut focus_state, &CommandsWindow::default()),
                         #[cfg(feature = "debug")]
                         UserEvent::OpenTimeWindow => interface.open_window(&mut focus_state, &TimeWindow::default()),
@@ -1066,6 +1172,46 @@ fn main() {
                 #[cfg(feature = "debug")]
                 user_event_measuremen.stop();
 
+                if let Some(changed_files) = hot_reload_receiver.as_ref().and_then(|receiver| receiver.try_recv().ok()) {
+                    #[cfg(feature = "debug")]
+                    let hot_reload_measurement = start_measurement("hot reload assets");
+
+                    // Models and maps keep references to their textures and models, so they
+                    // are invalidated by their dependencies rather than the caches of the other
+                    // loaders.
+                    texture_loader.invalidate(&changed_files);
+                    model_loader.invalidate(&changed_files);
+                    let sprites_changed = sprite_loader.invalidate(&changed_files);
+                    let actions_changed = action_loader.invalidate(&changed_files);
+
+                    if map_loader.invalidate(&changed_files) {
+                        let reloaded_map = map_loader.get(
+                            map.get_resource_file().to_owned(),
+                            &game_file_loader,
+                            &mut buffer_allocator,
+                            &mut model_loader,
+                            &mut texture_loader,
+                        );
+
+                        match reloaded_map {
+                            Ok(reloaded_map) => map = reloaded_map,
+                            Err(_error) => {
+                                #[cfg(feature = "debug")]
+                                print_debug!("[{}error{}] failed to reload map: {_error}", RED, NONE);
+                            }
+                        }
+                    }
+
+                    if sprites_changed || actions_changed {
+                        entities.iter_mut().for_each(|entity| {
+                            entity.reload_sprite(&game_file_loader, &mut sprite_loader, &mut action_loader, &script_loader)
+                        });
+                    }
+
+                    #[cfg(feature = "debug")]
+                    hot_reload_measurement.stop();
+                }
+
                 let buffer_fence = buffer_allocator.submit_load_buffer();
                 let texture_fence = texture_loader.submit_load_buffer();
                 let sprite_fence = sprite_loader.submit_load_buffer();
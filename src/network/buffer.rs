@@ -0,0 +1,198 @@
+use std::io::{ErrorKind, Read};
+
+use super::PacketLength;
+use crate::loaders::ByteStream;
+
+const READ_SIZE: usize = 4096;
+
+/// Data received from a server that wasn't handled yet. TCP doesn't preserve
+/// the boundaries of packets, so a single read may contain multiple packets
+/// and the end of a packet may only arrive with a later read. The buffer
+/// keeps incomplete packets around until the rest of them is received.
+///
+/// The size of a packet is looked up by its header, which is why the
+/// functions that split off packets take the length table of the server.
+#[derive(Default)]
+pub(super) struct ReceiveBuffer {
+    data: Vec<u8>,
+}
+
+impl ReceiveBuffer {
+    pub fn push(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+    }
+
+    /// Reads everything that is available without blocking. The stream has to
+    /// be non-blocking.
+    pub fn read_available(&mut self, stream: &mut impl Read) -> std::io::Result<()> {
+        let mut buffer = [0; READ_SIZE];
+
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(length) => self.push(&buffer[..length]),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Removes `count` bytes from the start of the buffer if enough were
+    /// received. Used for data that isn't sent as a packet.
+    pub fn take_bytes(&mut self, count: usize) -> Option<Vec<u8>> {
+        (self.data.len() >= count).then(|| self.data.drain(..count).collect())
+    }
+
+    /// Removes all complete packets from the start of the buffer. Returns
+    /// `None` if the first packet isn't complete yet.
+    ///
+    /// If the header of a packet is unknown or its length is invalid, the end
+    /// of the packet can't be found and the data can't be split any further.
+    /// The packets in front of it are still returned, and the error is
+    /// returned once the invalid packet is at the start of the buffer.
+    pub fn take_packets(&mut self, get_length: impl Fn(u16) -> Option<PacketLength>) -> Result<Option<Vec<u8>>, String> {
+        let mut size = 0;
+
+        loop {
+            match self.get_complete_packet_size(size, &get_length) {
+                Ok(Some(packet_size)) => size += packet_size,
+                Ok(None) => break,
+                Err(error) if size == 0 => return Err(error),
+                Err(_) => break,
+            }
+        }
+
+        Ok((size > 0).then(|| self.data.drain(..size).collect()))
+    }
+
+    /// The size of the packet at `offset` if it was fully received.
+    fn get_complete_packet_size(&self, offset: usize, get_length: impl Fn(u16) -> Option<PacketLength>) -> Result<Option<usize>, String> {
+        let data = &self.data[offset..];
+
+        if data.len() < 2 {
+            return Ok(None);
+        }
+
+        let header = u16::from_le_bytes([data[0], data[1]]);
+        let length = get_length(header).ok_or_else(|| format!("unknown packet {header:#06x}"))?;
+
+        match length.get_size(&ByteStream::new(data)) {
+            Some(size) if size < length.minimum_size() => Err(format!("packet {header:#06x} has an invalid length of {size}")),
+            Some(size) if size <= data.len() => Ok(Some(size)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_length(header: u16) -> Option<PacketLength> {
+        match header {
+            0x0101 => Some(PacketLength::Fixed(6)),
+            0x0102 => Some(PacketLength::Variable),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn partial_packets_are_kept() {
+        let mut buffer = ReceiveBuffer::default();
+
+        buffer.push(&[1, 1, 5, 0, 0, 0, 1]);
+        assert_eq!(buffer.take_packets(get_length), Ok(Some(vec![1, 1, 5, 0, 0, 0])));
+        assert_eq!(buffer.take_packets(get_length), Ok(None));
+
+        buffer.push(&[1, 7, 0, 0, 0]);
+        assert_eq!(buffer.take_packets(get_length), Ok(Some(vec![1, 1, 7, 0, 0, 0])));
+        assert!(buffer.data.is_empty());
+    }
+
+    #[test]
+    fn variable_packets_use_their_length_field() {
+        let mut buffer = ReceiveBuffer::default();
+
+        buffer.push(&[2, 1]);
+        assert_eq!(buffer.take_packets(get_length), Ok(None));
+
+        buffer.push(&[7, 0, b'a', b'b']);
+        assert_eq!(buffer.take_packets(get_length), Ok(None));
+
+        buffer.push(&[b'c', 1, 1, 3, 0]);
+        assert_eq!(buffer.take_packets(get_length), Ok(Some(vec![2, 1, 7, 0, b'a', b'b', b'c'])));
+        assert_eq!(buffer.data, vec![1, 1, 3, 0]);
+    }
+
+    #[test]
+    fn unknown_header_is_reported_after_preceding_packets() {
+        let mut buffer = ReceiveBuffer::default();
+
+        buffer.push(&[1, 1, 5, 0, 0, 0, 0xFF, 0xFF, 1, 1]);
+        assert_eq!(buffer.take_packets(get_length), Ok(Some(vec![1, 1, 5, 0, 0, 0])));
+        assert_eq!(buffer.take_packets(get_length), Err("unknown packet 0xffff".to_owned()));
+        assert_eq!(buffer.data, vec![0xFF, 0xFF, 1, 1]);
+    }
+
+    #[test]
+    fn invalid_length_is_reported() {
+        let mut buffer = ReceiveBuffer::default();
+
+        buffer.push(&[2, 1, 1, 0, 1, 1]);
+        assert_eq!(
+            buffer.take_packets(get_length),
+            Err("packet 0x0102 has an invalid length of 1".to_owned())
+        );
+        assert_eq!(buffer.data, vec![2, 1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn take_bytes_waits_for_enough_data() {
+        let mut buffer = ReceiveBuffer::default();
+
+        buffer.push(&[1, 2, 3]);
+        assert_eq!(buffer.take_bytes(4), None);
+
+        buffer.push(&[4, 1, 1]);
+        assert_eq!(buffer.take_bytes(4), Some(vec![1, 2, 3, 4]));
+        assert_eq!(buffer.data, vec![1, 1]);
+    }
+
+    /// Returns its chunks one read at a time and then behaves like a
+    /// non-blocking socket without new data.
+    struct ChunkedReader(Vec<Vec<u8>>);
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.is_empty() {
+                true => Err(ErrorKind::WouldBlock.into()),
+                false => {
+                    let chunk = self.0.remove(0);
+                    buffer[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn read_available_reads_until_it_would_block() {
+        let mut buffer = ReceiveBuffer::default();
+        let mut reader = ChunkedReader(vec![vec![1, 1, 5], vec![0, 0, 0, 2, 1]]);
+
+        buffer.read_available(&mut reader).unwrap();
+        assert_eq!(buffer.take_packets(get_length), Ok(Some(vec![1, 1, 5, 0, 0, 0])));
+        assert_eq!(buffer.data, vec![2, 1]);
+    }
+
+    #[test]
+    fn read_available_reports_closed_connection() {
+        let mut buffer = ReceiveBuffer::default();
+        let mut reader: &[u8] = &[1, 1];
+
+        let error = buffer.read_available(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(buffer.data, vec![1, 1]);
+    }
+}
@@ -0,0 +1,282 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::connection::ServerKind;
+use super::PacketVersion;
+
+const MAGIC: &[u8; 4] = b"KCAP";
+const FORMAT_VERSION: u8 = 2;
+/// The first byte of every entry in a capture.
+const PACKETS_ENTRY: u8 = 0;
+const PACKET_VERSION_ENTRY: u8 = 1;
+
+/// How the client is connected to the servers.
+pub enum CaptureMode {
+    /// Connects to the servers without recording anything.
+    Disabled,
+    /// Connects to the servers and records all packets to the file.
+    Record(PathBuf),
+    /// Replays the packets of a recorded file instead of connecting to the
+    /// servers.
+    Replay(PathBuf),
+}
+
+/// The direction of a captured packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from the server.
+    Incoming,
+    /// Sent to the server.
+    Outgoing,
+    /// Sent by the network thread on its own, so a replay can skip it.
+    Keepalive,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct CaptureRecord {
+    /// Time since the capture was started.
+    pub timestamp: Duration,
+    pub server: ServerKind,
+    pub direction: Direction,
+    /// The packet version that the client used when the data was recorded.
+    pub packet_version: PacketVersion,
+    /// One or more complete packets.
+    pub data: Vec<u8>,
+}
+
+/// A recorded session. Captures start with a header containing the packet
+/// version, followed by entries that start with their kind. Most entries are
+/// records:
+///
+/// | field     | size |                                    |
+/// |-----------|------|------------------------------------|
+/// | kind      | 1    | 0                                  |
+/// | timestamp | 8    | microseconds since the start       |
+/// | server    | 1    | login, character or map server     |
+/// | direction | 1    | incoming, outgoing or keepalive    |
+/// | length    | 4    | the length of the data             |
+/// | data      |      | one or more complete packets       |
+///
+/// When the player picks a server with a different packet version, an entry
+/// with kind 1 and the new packet version (4 bytes) is written. It applies to
+/// all following records.
+///
+/// All numbers are little endian.
+pub(super) struct Capture {
+    /// The packet version at the start of the capture.
+    pub packet_version: PacketVersion,
+    pub records: Vec<CaptureRecord>,
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, message)
+}
+
+fn read_array<const SIZE: usize>(reader: &mut impl Read) -> std::io::Result<[u8; SIZE]> {
+    let mut buffer = [0; SIZE];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+impl Capture {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader(mut reader: impl Read) -> std::io::Result<Self> {
+        if &read_array::<4>(&mut reader)? != MAGIC {
+            return Err(invalid_data("not a packet capture"));
+        }
+
+        if read_array::<1>(&mut reader)?[0] != FORMAT_VERSION {
+            return Err(invalid_data("unsupported capture format version"));
+        }
+
+        let packet_version = PacketVersion(u32::from_le_bytes(read_array(&mut reader)?));
+        let mut current_packet_version = packet_version;
+        let mut records = Vec::new();
+
+        while let Some(record) = read_record(&mut reader, &mut current_packet_version)? {
+            records.push(record);
+        }
+
+        Ok(Self { packet_version, records })
+    }
+}
+
+/// Returns `None` at the end of the capture. A record that was only partially
+/// written, for example because the client crashed, is treated as the end.
+/// Changes of the packet version are applied to `packet_version`.
+fn read_record(reader: &mut impl Read, packet_version: &mut PacketVersion) -> std::io::Result<Option<CaptureRecord>> {
+    match read_complete_record(reader, packet_version) {
+        Ok(record) => Ok(Some(record)),
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn read_complete_record(reader: &mut impl Read, packet_version: &mut PacketVersion) -> std::io::Result<CaptureRecord> {
+    loop {
+        match read_array::<1>(reader)?[0] {
+            PACKETS_ENTRY => break,
+            PACKET_VERSION_ENTRY => *packet_version = PacketVersion(u32::from_le_bytes(read_array(reader)?)),
+            _ => return Err(invalid_data("invalid entry in capture")),
+        }
+    }
+
+    let timestamp = Duration::from_micros(u64::from_le_bytes(read_array(reader)?));
+    let [server, direction] = read_array(reader)?;
+    let length = u32::from_le_bytes(read_array(reader)?) as usize;
+    let mut data = vec![0; length];
+    reader.read_exact(&mut data)?;
+
+    let server = match server {
+        0 => ServerKind::Login,
+        1 => ServerKind::Character,
+        2 => ServerKind::Map,
+        _ => return Err(invalid_data("invalid server in capture")),
+    };
+
+    let direction = match direction {
+        0 => Direction::Incoming,
+        1 => Direction::Outgoing,
+        2 => Direction::Keepalive,
+        _ => return Err(invalid_data("invalid direction in capture")),
+    };
+
+    Ok(CaptureRecord {
+        timestamp,
+        server,
+        direction,
+        packet_version: *packet_version,
+        data,
+    })
+}
+
+fn write_record(writer: &mut impl Write, record: &CaptureRecord) -> std::io::Result<()> {
+    let server = match record.server {
+        ServerKind::Login => 0u8,
+        ServerKind::Character => 1,
+        ServerKind::Map => 2,
+    };
+
+    let direction = match record.direction {
+        Direction::Incoming => 0u8,
+        Direction::Outgoing => 1,
+        Direction::Keepalive => 2,
+    };
+
+    writer.write_all(&[PACKETS_ENTRY])?;
+    writer.write_all(&(record.timestamp.as_micros() as u64).to_le_bytes())?;
+    writer.write_all(&[server, direction])?;
+    writer.write_all(&(record.data.len() as u32).to_le_bytes())?;
+    writer.write_all(&record.data)
+}
+
+/// Records packets while the client is connected. Every record is flushed
+/// right away, so the capture is still usable if the client crashes.
+pub(super) struct CaptureWriter<W: Write = BufWriter<File>> {
+    writer: W,
+    started: Instant,
+    packet_version: PacketVersion,
+}
+
+impl CaptureWriter {
+    pub fn create(path: &Path, packet_version: PacketVersion) -> std::io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), packet_version)
+    }
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn new(mut writer: W, packet_version: PacketVersion) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&packet_version.0.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+            packet_version,
+        })
+    }
+
+    /// Records that the following packets use a different packet version.
+    pub fn set_packet_version(&mut self, packet_version: PacketVersion) -> std::io::Result<()> {
+        self.packet_version = packet_version;
+        self.writer.write_all(&[PACKET_VERSION_ENTRY])?;
+        self.writer.write_all(&packet_version.0.to_le_bytes())?;
+        self.writer.flush()
+    }
+
+    pub fn record(&mut self, server: ServerKind, direction: Direction, data: &[u8]) -> std::io::Result<()> {
+        let record = CaptureRecord {
+            timestamp: self.started.elapsed(),
+            server,
+            direction,
+            packet_version: self.packet_version,
+            data: data.to_vec(),
+        };
+
+        write_record(&mut self.writer, &record)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records_round_trip() {
+        let mut writer = CaptureWriter::new(Vec::new(), PacketVersion(20180620)).unwrap();
+        writer.record(ServerKind::Login, Direction::Outgoing, &[1, 2, 3]).unwrap();
+        writer.record(ServerKind::Map, Direction::Incoming, &[4, 5]).unwrap();
+
+        let capture = Capture::from_reader(writer.writer.as_slice()).unwrap();
+
+        assert_eq!(capture.packet_version, PacketVersion(20180620));
+        assert_eq!(capture.records.len(), 2);
+        assert_eq!(capture.records[0].server, ServerKind::Login);
+        assert_eq!(capture.records[0].direction, Direction::Outgoing);
+        assert_eq!(capture.records[0].data, vec![1, 2, 3]);
+        assert_eq!(capture.records[1].server, ServerKind::Map);
+        assert_eq!(capture.records[1].data, vec![4, 5]);
+        assert!(capture.records[0].timestamp <= capture.records[1].timestamp);
+    }
+
+    #[test]
+    fn packet_version_applies_to_following_records() {
+        let mut writer = CaptureWriter::new(Vec::new(), PacketVersion(20180620)).unwrap();
+        writer.record(ServerKind::Login, Direction::Outgoing, &[1, 2, 3]).unwrap();
+        writer.set_packet_version(PacketVersion(20211103)).unwrap();
+        writer.record(ServerKind::Login, Direction::Outgoing, &[4, 5]).unwrap();
+
+        let capture = Capture::from_reader(writer.writer.as_slice()).unwrap();
+
+        assert_eq!(capture.packet_version, PacketVersion(20180620));
+        assert_eq!(capture.records.len(), 2);
+        assert_eq!(capture.records[0].packet_version, PacketVersion(20180620));
+        assert_eq!(capture.records[1].packet_version, PacketVersion(20211103));
+    }
+
+    #[test]
+    fn truncated_record_ends_capture() {
+        let mut writer = CaptureWriter::new(Vec::new(), PacketVersion::default()).unwrap();
+        writer.record(ServerKind::Character, Direction::Incoming, &[1, 2, 3]).unwrap();
+        writer.record(ServerKind::Character, Direction::Incoming, &[4, 5, 6]).unwrap();
+
+        let data = &writer.writer[..writer.writer.len() - 2];
+        let capture = Capture::from_reader(data).unwrap();
+
+        assert_eq!(capture.records.len(), 1);
+    }
+
+    #[test]
+    fn invalid_header_is_rejected() {
+        let error = Capture::from_reader([0u8; 9].as_slice()).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
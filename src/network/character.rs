@@ -0,0 +1,74 @@
+use super::handler::{HandlerResult, PacketHandler};
+use super::lengths::UNHANDLED_CHARACTER_SERVER_PACKETS;
+use super::*;
+
+pub(super) enum CharacterServerResponse {
+    LoginSuccess(CharacterServerLoginSuccessPacket),
+    CharacterList(Vec<CharacterInformation>),
+    CharacterCreated(CharacterInformation),
+    CharacterDeleted,
+    CharacterSelected(CharacterSelectionSuccessPacket),
+    CharacterSlotSwitched,
+    Failure(&'static str),
+}
+
+fn failure(message: &'static str) -> HandlerResult<CharacterServerResponse> {
+    HandlerResult::Event(CharacterServerResponse::Failure(message))
+}
+
+/// All packets that the character server may send, except for the account id
+/// that it sends right after connecting, since that doesn't have a header.
+pub(super) fn character_server_handler(packet_version: PacketVersion) -> PacketHandler<(), CharacterServerResponse> {
+    let mut handler = PacketHandler::new(packet_version);
+
+    handler.register(|packet: LoginFailedPacket, _| failure(packet.reason.get_message()));
+    handler.register(|packet: CharacterServerLoginSuccessPacket, _| HandlerResult::Event(CharacterServerResponse::LoginSuccess(packet)));
+    handler.register(|packet: RequestCharacterListSuccessPacket, _| {
+        HandlerResult::Event(CharacterServerResponse::CharacterList(packet.character_information))
+    });
+    handler.register(|packet: Packet6b00, _| HandlerResult::Event(CharacterServerResponse::CharacterList(packet.character_information)));
+    handler.register_noop::<CharacterListPageCountPacket>();
+
+    handler.register(|packet: CharacterCreationFailedPacket, _| match packet.reason {
+        CharacterCreationFailedReason::CharacterNameAlreadyUsed => failure("character name is already used"),
+        CharacterCreationFailedReason::NotOldEnough => failure("you are not old enough to create a character"),
+        CharacterCreationFailedReason::NotAllowedToUseSlot => failure("you are not allowed to use that character slot"),
+        CharacterCreationFailedReason::CharacterCerationFailed => failure("character creation failed"),
+    });
+    handler.register(|packet: CreateCharacterSuccessPacket, _| {
+        HandlerResult::Event(CharacterServerResponse::CharacterCreated(packet.character_information))
+    });
+
+    handler.register(|packet: CharacterDeletionFailedPacket, _| match packet.reason {
+        CharacterDeletionFailedReason::NotAllowed => failure("you are not allowed to delete this character"),
+        CharacterDeletionFailedReason::CharacterNotFound => failure("character was not found"),
+        CharacterDeletionFailedReason::NotEligible => failure("character is not eligible for deletion"),
+    });
+    handler.register(|_: CharacterDeletionSuccessPacket, _| HandlerResult::Event(CharacterServerResponse::CharacterDeleted));
+
+    handler.register(|packet: CharacterSelectionFailedPacket, _| match packet.reason {
+        CharacterSelectionFailedReason::RejectedFromServer => failure("rejected from server"),
+    });
+    handler.register(|_: MapServerUnavailablePacket, _| failure("map server currently unavailable"));
+    handler.register(|packet: CharacterSelectionSuccessPacket, _| HandlerResult::Event(CharacterServerResponse::CharacterSelected(packet)));
+
+    handler.register(|packet: SwitchCharacterSlotResponsePacket, _| match packet.status {
+        SwitchCharacterSlotResponseStatus::Success => HandlerResult::Event(CharacterServerResponse::CharacterSlotSwitched),
+        SwitchCharacterSlotResponseStatus::Error => failure("failed to move character to a different slot"),
+    });
+
+    handler.register_unhandled(UNHANDLED_CHARACTER_SERVER_PACKETS);
+
+    handler
+}
+
+impl CharacterServerResponse {
+    /// The error message for a request that didn't receive the expected
+    /// response.
+    pub(super) fn into_error(self, request: &str) -> String {
+        match self {
+            CharacterServerResponse::Failure(reason) => reason.to_string(),
+            _ => format!("unexpected response to {request}"),
+        }
+    }
+}
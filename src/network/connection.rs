@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use super::buffer::ReceiveBuffer;
+use super::capture::{CaptureWriter, Direction};
+use super::handler::PacketLengths;
+use super::{CharacterServerKeepalivePacket, ClientTick, LoginServerKeepalivePacket, Packet, PacketVersion, RequestServerTickPacket};
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+/// How long the network thread waits for commands before it checks the
+/// connections for new data again.
+pub(super) const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// The character server sends the account id without a header right after
+/// the client logs in.
+const CHARACTER_SERVER_PREAMBLE_LENGTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ServerKind {
+    Login,
+    Character,
+    Map,
+}
+
+impl ServerKind {
+    fn get_keepalive_period(self) -> Duration {
+        match self {
+            ServerKind::Login => Duration::from_secs(58),
+            ServerKind::Character => Duration::from_secs(10),
+            ServerKind::Map => Duration::from_secs(4),
+        }
+    }
+}
+
+impl Display for ServerKind {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerKind::Login => write!(formatter, "login server"),
+            ServerKind::Character => write!(formatter, "character server"),
+            ServerKind::Map => write!(formatter, "map server"),
+        }
+    }
+}
+
+/// Schedules attempts to reconnect to a server. The delay doubles with every
+/// failed attempt, so an unreachable server isn't flooded with connections.
+#[derive(Default)]
+pub(super) struct Backoff {
+    failed_attempts: u32,
+    next_attempt: Option<Instant>,
+}
+
+impl Backoff {
+    const INITIAL_DELAY: Duration = Duration::from_secs(1);
+    const MAXIMUM_DELAY: Duration = Duration::from_secs(32);
+
+    /// Called once the connection is established.
+    pub fn reset(&mut self) {
+        self.failed_attempts = 0;
+        self.next_attempt = None;
+    }
+
+    /// Schedules the next attempt after a failed one and returns the delay.
+    pub fn schedule(&mut self) -> Duration {
+        let delay = Self::INITIAL_DELAY
+            .saturating_mul(2u32.saturating_pow(self.failed_attempts))
+            .min(Self::MAXIMUM_DELAY);
+
+        self.failed_attempts += 1;
+        self.next_attempt = Some(Instant::now() + delay);
+        delay
+    }
+
+    /// Attempts the next connection right away instead of waiting.
+    pub fn skip_delay(&mut self) {
+        if self.next_attempt.is_some() {
+            self.next_attempt = Some(Instant::now());
+        }
+    }
+
+    pub fn get_failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// Returns `true` once the scheduled attempt is due.
+    pub fn take_due_attempt(&mut self) -> bool {
+        let is_due = self.next_attempt.is_some_and(|next_attempt| next_attempt <= Instant::now());
+
+        if is_due {
+            self.next_attempt = None;
+        }
+
+        is_due
+    }
+}
+
+/// Sent from the main thread to the network thread.
+pub(super) enum NetworkCommand {
+    /// Connects to a server by host name or IP address and port, replacing
+    /// the previous connection to a server of the same kind. Host names are
+    /// resolved and connected to on a separate thread, so the other
+    /// connections aren't blocked in the meantime.
+    Connect(ServerKind, String, u16),
+    Send(ServerKind, Vec<u8>),
+    Disconnect(ServerKind),
+    /// Keeps the client tick of the map server keepalive in sync with the
+    /// game timer.
+    SetClientTick(ClientTick),
+    /// Used when the player picks a server with a different packet version.
+    SetPacketVersion(PacketVersion, HashMap<ServerKind, PacketLengths>),
+}
+
+/// Sent from the network thread to the main thread.
+pub(super) enum ConnectionEvent {
+    Connected(ServerKind),
+    /// The connection failed or was closed. Also sent if the client tries to
+    /// send data to a server that it isn't connected to.
+    Disconnected(ServerKind, String),
+    /// One or more complete packets.
+    Received(ServerKind, Vec<u8>),
+    /// Only sent by the replay. The following packets were recorded with a
+    /// different packet version.
+    PacketVersionChanged(PacketVersion),
+    /// The network thread sent a keepalive packet on its own.
+    #[cfg(feature = "debug")]
+    KeepaliveSent(ServerKind, ClientTick),
+}
+
+struct Connection {
+    stream: TcpStream,
+    receive_buffer: ReceiveBuffer,
+    /// Data that couldn't be written without blocking yet.
+    send_buffer: Vec<u8>,
+    /// The number of bytes at the start of the connection that aren't part of
+    /// a packet.
+    preamble_length: usize,
+    last_keepalive: Instant,
+}
+
+impl Connection {
+    fn new(server: ServerKind, stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        let preamble_length = match server {
+            ServerKind::Character => CHARACTER_SERVER_PREAMBLE_LENGTH,
+            ServerKind::Login | ServerKind::Map => 0,
+        };
+
+        Ok(Self {
+            stream,
+            receive_buffer: ReceiveBuffer::default(),
+            send_buffer: Vec::new(),
+            preamble_length,
+            last_keepalive: Instant::now(),
+        })
+    }
+
+    /// Tries every address that the host resolves to until one accepts the
+    /// connection.
+    fn connect(host: &str, port: u16) -> std::io::Result<TcpStream> {
+        let mut last_error = None;
+
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => return Ok(stream),
+                Err(error) => last_error = Some(error),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| std::io::Error::new(ErrorKind::NotFound, format!("{host} has no address"))))
+    }
+
+    /// Writes as much of the send buffer as possible without blocking.
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.send_buffer.is_empty() {
+            match self.stream.write(&self.send_buffer) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(length) => drop(self.send_buffer.drain(..length)),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The result of connecting to a server on a helper thread, together with the
+/// attempt that it belongs to.
+type ConnectResult = (ServerKind, u64, std::io::Result<TcpStream>);
+
+/// A connection that is still being established. Data sent in the meantime is
+/// kept until the connection is ready.
+struct PendingConnection {
+    attempt: u64,
+    send_buffer: Vec<u8>,
+}
+
+/// Owns the connections to all servers. Runs on its own thread, so reading,
+/// writing and keepalives continue while the main thread is busy, for example
+/// while it uploads a map.
+struct NetworkThread {
+    packet_version: PacketVersion,
+    packet_lengths: HashMap<ServerKind, PacketLengths>,
+    connections: HashMap<ServerKind, Connection>,
+    pending_connections: HashMap<ServerKind, PendingConnection>,
+    /// Used to tell apart the results of multiple attempts to connect to the
+    /// same server, since only the latest one is kept.
+    connect_attempts: u64,
+    connect_sender: Sender<ConnectResult>,
+    connect_receiver: Receiver<ConnectResult>,
+    /// The last client tick received from the main thread and when it was
+    /// received.
+    client_tick: (ClientTick, Instant),
+    command_receiver: Receiver<NetworkCommand>,
+    event_sender: Sender<ConnectionEvent>,
+    capture: Option<CaptureWriter>,
+}
+
+/// Starts the network thread. The thread stops once the command sender is
+/// dropped. If a capture path is given, all packets are recorded to that
+/// file.
+pub(super) fn spawn_network_thread(
+    packet_version: PacketVersion,
+    packet_lengths: HashMap<ServerKind, PacketLengths>,
+    capture_path: Option<&Path>,
+) -> std::io::Result<(Sender<NetworkCommand>, Receiver<ConnectionEvent>)> {
+    let (command_sender, command_receiver) = channel();
+    let (event_sender, event_receiver) = channel();
+    let (connect_sender, connect_receiver) = channel();
+
+    let capture = capture_path.map(|path| CaptureWriter::create(path, packet_version)).transpose()?;
+
+    let network_thread = NetworkThread {
+        packet_version,
+        packet_lengths,
+        connections: HashMap::new(),
+        pending_connections: HashMap::new(),
+        connect_attempts: 0,
+        connect_sender,
+        connect_receiver,
+        client_tick: (ClientTick(0), Instant::now()),
+        command_receiver,
+        event_sender,
+        capture,
+    };
+
+    std::thread::Builder::new()
+        .name("network".to_string())
+        .spawn(move || network_thread.run())
+        .expect("failed to spawn network thread");
+
+    Ok((command_sender, event_receiver))
+}
+
+impl NetworkThread {
+    fn run(mut self) {
+        loop {
+            match self.command_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(command) => self.handle_command(command),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            while let Ok((server, attempt, result)) = self.connect_receiver.try_recv() {
+                self.finish_connect(server, attempt, result);
+            }
+
+            let servers: Vec<ServerKind> = self.connections.keys().copied().collect();
+
+            for server in servers {
+                if let Err(reason) = self.update_connection(server) {
+                    self.disconnect(server, reason);
+                }
+            }
+        }
+    }
+
+    fn emit(&self, event: ConnectionEvent) {
+        // The receiver is only dropped when the main thread shuts down, at
+        // which point the command sender is dropped too and the thread stops.
+        let _ = self.event_sender.send(event);
+    }
+
+    fn record(&mut self, server: ServerKind, direction: Direction, data: &[u8]) {
+        self.write_capture(|capture| capture.record(server, direction, data));
+    }
+
+    fn write_capture(&mut self, write: impl FnOnce(&mut CaptureWriter) -> std::io::Result<()>) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+
+        if let Err(_error) = write(capture) {
+            #[cfg(feature = "debug")]
+            print_debug!("[{}error{}] failed to write capture, stopping to record: {}", RED, NONE, _error);
+
+            self.capture = None;
+        }
+    }
+
+    fn get_client_tick(&self) -> ClientTick {
+        let (client_tick, received_at) = self.client_tick;
+        ClientTick(client_tick.0 + received_at.elapsed().as_millis() as u32)
+    }
+
+    fn handle_command(&mut self, command: NetworkCommand) {
+        match command {
+            NetworkCommand::Connect(server, host, port) => {
+                #[cfg(feature = "debug")]
+                print_debug!("connecting to {} at {}{}:{}{}", server, MAGENTA, host, port, NONE);
+
+                self.connections.remove(&server);
+                self.connect_attempts += 1;
+
+                let attempt = self.connect_attempts;
+                let connect_sender = self.connect_sender.clone();
+                let spawn_result = std::thread::Builder::new().name(format!("connect to {server}")).spawn(move || {
+                    let result = Connection::connect(&host, port);
+                    let _ = connect_sender.send((server, attempt, result));
+                });
+
+                match spawn_result {
+                    Ok(_) => {
+                        let pending_connection = PendingConnection {
+                            attempt,
+                            send_buffer: Vec::new(),
+                        };
+                        self.pending_connections.insert(server, pending_connection);
+                    }
+                    Err(error) => {
+                        self.pending_connections.remove(&server);
+                        self.emit(ConnectionEvent::Disconnected(
+                            server,
+                            format!("failed to connect to {server}: {error}"),
+                        ));
+                    }
+                }
+            }
+            NetworkCommand::Send(server, data) => {
+                if let Some(pending_connection) = self.pending_connections.get_mut(&server) {
+                    pending_connection.send_buffer.extend_from_slice(&data);
+                    self.record(server, Direction::Outgoing, &data);
+                    return;
+                }
+
+                if !self.connections.contains_key(&server) {
+                    self.emit(ConnectionEvent::Disconnected(server, format!("not connected to {server}")));
+                    return;
+                }
+
+                self.record(server, Direction::Outgoing, &data);
+
+                let connection = self.connections.get_mut(&server).unwrap();
+                connection.send_buffer.extend(data);
+
+                if let Err(error) = connection.flush() {
+                    self.disconnect(server, format!("failed to send packet to {server}: {error}"));
+                }
+            }
+            // Dropping the TcpStream will also close the connection. The result
+            // of a pending connection is ignored once it arrives.
+            NetworkCommand::Disconnect(server) => {
+                self.connections.remove(&server);
+                self.pending_connections.remove(&server);
+            }
+            NetworkCommand::SetClientTick(client_tick) => self.client_tick = (client_tick, Instant::now()),
+            NetworkCommand::SetPacketVersion(packet_version, packet_lengths) => {
+                self.packet_version = packet_version;
+                self.packet_lengths = packet_lengths;
+                self.write_capture(|capture| capture.set_packet_version(packet_version));
+            }
+        }
+    }
+
+    fn finish_connect(&mut self, server: ServerKind, attempt: u64, result: std::io::Result<TcpStream>) {
+        // The connection was replaced or cancelled in the meantime.
+        if self
+            .pending_connections
+            .get(&server)
+            .map_or(true, |pending_connection| pending_connection.attempt != attempt)
+        {
+            return;
+        }
+
+        let pending_connection = self.pending_connections.remove(&server).unwrap();
+
+        match result.and_then(|stream| Connection::new(server, stream)) {
+            Ok(mut connection) => {
+                connection.send_buffer = pending_connection.send_buffer;
+                self.connections.insert(server, connection);
+                self.emit(ConnectionEvent::Connected(server));
+            }
+            Err(error) => self.emit(ConnectionEvent::Disconnected(
+                server,
+                format!("failed to connect to {server}: {error}"),
+            )),
+        }
+    }
+
+    fn disconnect(&mut self, server: ServerKind, reason: String) {
+        #[cfg(feature = "debug")]
+        print_debug!("[{}error{}] {}", RED, NONE, reason);
+
+        self.connections.remove(&server);
+        self.emit(ConnectionEvent::Disconnected(server, reason));
+    }
+
+    fn update_connection(&mut self, server: ServerKind) -> Result<(), String> {
+        if self.connections[&server].last_keepalive.elapsed() >= server.get_keepalive_period() {
+            let keepalive = self.get_keepalive(server);
+            self.record(server, Direction::Keepalive, &keepalive);
+
+            #[cfg(feature = "debug")]
+            self.emit(ConnectionEvent::KeepaliveSent(server, self.get_client_tick()));
+
+            let connection = self.connections.get_mut(&server).unwrap();
+            connection.last_keepalive = Instant::now();
+            connection.send_buffer.extend(keepalive);
+        }
+
+        let connection = self.connections.get_mut(&server).unwrap();
+
+        connection
+            .flush()
+            .map_err(|error| format!("failed to send packet to {server}: {error}"))?;
+
+        // Data that arrived before the connection was closed is still handed
+        // to the main thread.
+        let read_result = connection.receive_buffer.read_available(&mut connection.stream);
+
+        if connection.preamble_length > 0 && connection.receive_buffer.take_bytes(connection.preamble_length).is_some() {
+            connection.preamble_length = 0;
+        }
+
+        if connection.preamble_length == 0 {
+            // Packets in front of invalid data are still handed to the main
+            // thread. The invalid data desyncs the stream, so the connection is
+            // closed once it is reached.
+            loop {
+                let packet_lengths = &self.packet_lengths[&server];
+                let connection = self.connections.get_mut(&server).unwrap();
+
+                match connection
+                    .receive_buffer
+                    .take_packets(|header| packet_lengths.get(&header).copied())
+                {
+                    Ok(Some(data)) => {
+                        self.record(server, Direction::Incoming, &data);
+                        self.emit(ConnectionEvent::Received(server, data));
+                    }
+                    Ok(None) => break,
+                    Err(error) => return Err(format!("connection to {server} is corrupt: {error}")),
+                }
+            }
+        }
+
+        read_result.map_err(|error| match error.kind() {
+            ErrorKind::UnexpectedEof => format!("{server} closed the connection"),
+            _ => format!("failed to receive data from {server}: {error}"),
+        })
+    }
+
+    fn get_keepalive(&self, server: ServerKind) -> Vec<u8> {
+        match server {
+            ServerKind::Login => LoginServerKeepalivePacket::default().to_bytes(self.packet_version),
+            ServerKind::Character => CharacterServerKeepalivePacket::new().to_bytes(self.packet_version),
+            ServerKind::Map => RequestServerTickPacket::new(self.get_client_tick()).to_bytes(self.packet_version),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_delay_up_to_maximum() {
+        let mut backoff = Backoff::default();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.schedule().as_secs()).collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 32, 32]);
+        assert_eq!(backoff.get_failed_attempts(), 8);
+
+        backoff.reset();
+        assert_eq!(backoff.schedule().as_secs(), 1);
+    }
+
+    #[test]
+    fn backoff_attempt_is_taken_once() {
+        let mut backoff = Backoff::default();
+        assert!(!backoff.take_due_attempt());
+
+        backoff.schedule();
+        assert!(!backoff.take_due_attempt());
+
+        backoff.skip_delay();
+        assert!(backoff.take_due_attempt());
+        assert!(!backoff.take_due_attempt());
+    }
+
+    #[test]
+    fn data_sent_while_connecting_is_delivered() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let packet_lengths = HashMap::from([(ServerKind::Map, PacketLengths::new())]);
+        let (command_sender, event_receiver) = spawn_network_thread(PacketVersion::default(), packet_lengths, None).unwrap();
+
+        command_sender
+            .send(NetworkCommand::Connect(ServerKind::Map, "127.0.0.1".to_string(), port))
+            .unwrap();
+        command_sender.send(NetworkCommand::Send(ServerKind::Map, vec![1, 2, 3])).unwrap();
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut data = [0; 3];
+        stream.read_exact(&mut data).unwrap();
+
+        assert_eq!(data, [1, 2, 3]);
+        assert!(matches!(
+            event_receiver.recv_timeout(Duration::from_secs(5)),
+            Ok(ConnectionEvent::Connected(ServerKind::Map))
+        ));
+    }
+}
@@ -0,0 +1,235 @@
+use std::path::Path;
+use std::time::Duration;
+
+use super::capture::{Capture, Direction};
+use super::character::{character_server_handler, CharacterServerResponse};
+use super::connection::ServerKind;
+use super::handler::{HandlerError, PacketHandler};
+use super::login::{login_server_handler, LoginServerResponse};
+use super::map::{map_server_handler, MapServerState};
+use super::*;
+
+/// A packet found while dissecting raw data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DissectedPacket {
+    /// The text lists the name of the packet and all of its fields.
+    Decoded { offset: usize, text: String },
+    /// The header is known, but the packet failed to decode.
+    Invalid { offset: usize, error: String },
+    /// The client doesn't decode packets with this header. If their length is
+    /// known, `length` is the size of the packet. Otherwise the end of the
+    /// packet can't be found, so `length` is the size of the remaining data,
+    /// which isn't dissected.
+    Unknown { offset: usize, header: u16, length: usize },
+}
+
+/// A record of a capture with the packets that it contains.
+pub struct DissectedRecord {
+    pub timestamp: Duration,
+    pub server: ServerKind,
+    pub direction: Direction,
+    pub packet_version: PacketVersion,
+    pub packets: Vec<DissectedPacket>,
+}
+
+/// Knows all packets that the client sends, so outgoing data can be dissected
+/// the same way as incoming data.
+pub(super) fn client_handler(packet_version: PacketVersion) -> PacketHandler<(), ()> {
+    let mut handler = PacketHandler::new(packet_version);
+
+    handler.register_noop::<LoginServerLoginPacket>();
+    handler.register_noop::<LoginServerKeepalivePacket>();
+
+    handler.register_noop::<CharacterServerLoginPacket>();
+    handler.register_noop::<CharacterServerKeepalivePacket>();
+    handler.register_noop::<RequestCharacterListPacket>();
+    handler.register_noop::<SelectCharacterPacket>();
+    handler.register_noop::<CreateCharacterPacket>();
+    handler.register_noop::<DeleteCharacterPacket>();
+    handler.register_noop::<SwitchCharacterSlotPacket>();
+
+    handler.register_noop::<MapServerLoginPacket>();
+    handler.register_noop::<MapLoadedPacket>();
+    handler.register_noop::<RequestServerTickPacket>();
+    handler.register_noop::<RestartPacket>();
+    handler.register_noop::<RequestPlayerMovePacket>();
+    handler.register_noop::<RequestWarpToMapPacket>();
+    handler.register_noop::<RequestDetailsPacket>();
+    handler.register_noop::<RequestActionPacket>();
+    handler.register_noop::<GlobalMessagePacket>();
+    handler.register_noop::<StartDialogPacket>();
+    handler.register_noop::<NextDialogPacket>();
+    handler.register_noop::<CloseDialogPacket>();
+    handler.register_noop::<ChooseDialogOptionPacket>();
+    handler.register_noop::<RequestEquipItemPacket>();
+    handler.register_noop::<RequestUnequipItemPacket>();
+    handler.register_noop::<StartUseSkillPacket>();
+    handler.register_noop::<EndUseSkillPacket>();
+    handler.register_noop::<UseSkillAtIdPacket>();
+    handler.register_noop::<UseSkillOnGroundPacket>();
+    handler.register_noop::<AddFriendPacket>();
+    handler.register_noop::<RemoveFriendPacket>();
+    handler.register_noop::<FriendRequestResponsePacket>();
+
+    handler
+}
+
+/// Splits raw data into packets and decodes them, using the same packet
+/// definitions as the client.
+pub struct Dissector {
+    login_server_handler: PacketHandler<(), LoginServerResponse>,
+    character_server_handler: PacketHandler<(), CharacterServerResponse>,
+    map_server_handler: PacketHandler<MapServerState, NetworkEvent>,
+    client_handler: PacketHandler<(), ()>,
+}
+
+impl Dissector {
+    pub fn new(packet_version: PacketVersion) -> Self {
+        Self {
+            login_server_handler: login_server_handler(packet_version),
+            character_server_handler: character_server_handler(packet_version),
+            map_server_handler: map_server_handler(packet_version),
+            client_handler: client_handler(packet_version),
+        }
+    }
+
+    /// Dissects data that was exchanged with the server in the given
+    /// direction. The data has to start at the beginning of a packet.
+    pub fn dissect(&self, server: ServerKind, direction: Direction, data: &[u8]) -> Vec<DissectedPacket> {
+        match (server, direction) {
+            (ServerKind::Login, Direction::Incoming) => dissect_with(&self.login_server_handler, data),
+            (ServerKind::Character, Direction::Incoming) => dissect_with(&self.character_server_handler, data),
+            (ServerKind::Map, Direction::Incoming) => dissect_with(&self.map_server_handler, data),
+            (_, Direction::Outgoing | Direction::Keepalive) => dissect_with(&self.client_handler, data),
+        }
+    }
+}
+
+fn dissect_with<State, Event>(handler: &PacketHandler<State, Event>, data: &[u8]) -> Vec<DissectedPacket> {
+    let mut byte_stream = ByteStream::new(data);
+    let mut packets = Vec::new();
+
+    while !byte_stream.is_empty() {
+        let offset = byte_stream.get_offset();
+
+        match handler.describe_packet(&mut byte_stream) {
+            Ok(Some(text)) => packets.push(DissectedPacket::Decoded { offset, text }),
+            Ok(None) => packets.push(DissectedPacket::Unknown {
+                offset,
+                header: u16::from_le_bytes([data[offset], data[offset + 1]]),
+                length: byte_stream.get_offset() - offset,
+            }),
+            Err(HandlerError::UnknownHeader { header, offset }) => {
+                let length = data.len() - offset;
+                packets.push(DissectedPacket::Unknown { offset, header, length });
+                break;
+            }
+            Err(error) => {
+                packets.push(DissectedPacket::Invalid {
+                    offset,
+                    error: error.to_string(),
+                });
+
+                // The length of the packet is unknown, so the next packet can't be found.
+                if byte_stream.get_offset() == offset {
+                    break;
+                }
+            }
+        }
+    }
+
+    packets
+}
+
+/// Loads a capture and dissects all of its records, using the packet version
+/// that each of them was recorded with. Returns the packet version at the start
+/// of the capture.
+pub fn dissect_capture(path: &Path) -> std::io::Result<(PacketVersion, Vec<DissectedRecord>)> {
+    let capture = Capture::load(path)?;
+    let mut packet_version = capture.packet_version;
+    let mut dissector = Dissector::new(packet_version);
+
+    let records = capture
+        .records
+        .into_iter()
+        .map(|record| {
+            if record.packet_version != packet_version {
+                packet_version = record.packet_version;
+                dissector = Dissector::new(packet_version);
+            }
+
+            DissectedRecord {
+                packets: dissector.dissect(record.server, record.direction, &record.data),
+                timestamp: record.timestamp,
+                server: record.server,
+                direction: record.direction,
+                packet_version: record.packet_version,
+            }
+        })
+        .collect();
+
+    Ok((capture.packet_version, records))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn client_packets_have_unique_headers() {
+        // Registering a header twice panics.
+        client_handler(PacketVersion::default());
+    }
+
+    #[test]
+    fn outgoing_packets_are_decoded() {
+        let packet_version = PacketVersion::default();
+        let data = [
+            RequestCharacterListPacket::default().to_bytes(packet_version),
+            SelectCharacterPacket::new(2).to_bytes(packet_version),
+        ]
+        .concat();
+
+        let packets = Dissector::new(packet_version).dissect(ServerKind::Character, Direction::Outgoing, &data);
+
+        assert_eq!(packets.len(), 2);
+        assert!(
+            matches!(&packets[0], DissectedPacket::Decoded { offset: 0, text } if text.starts_with(RequestCharacterListPacket::PACKET_NAME))
+        );
+        assert!(matches!(&packets[1], DissectedPacket::Decoded { text, .. } if text.contains("selected slot: 2")));
+    }
+
+    #[test]
+    fn unknown_header_is_flagged() {
+        let packet_version = PacketVersion::default();
+        let mut data = RestartPacket::new(RestartType::Disconnect).to_bytes(packet_version);
+        let offset = data.len();
+        data.extend_from_slice(&[0xff, 0xff, 1, 2, 3]);
+
+        let packets = Dissector::new(packet_version).dissect(ServerKind::Map, Direction::Outgoing, &data);
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1], DissectedPacket::Unknown {
+            offset,
+            header: 0xffff,
+            length: 5
+        });
+    }
+
+    #[test]
+    fn unhandled_packet_has_its_own_length() {
+        let packet_version = PacketVersion::default();
+        // An accepted quit request without a body, followed by the server tick.
+        let data = [0x83, 0x00, 0x7f, 0x00, 0x10, 0x27, 0x00, 0x00];
+
+        let packets = Dissector::new(packet_version).dissect(ServerKind::Map, Direction::Incoming, &data);
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0], DissectedPacket::Unknown {
+            offset: 0,
+            header: 0x0083,
+            length: 2
+        });
+        assert!(matches!(&packets[1], DissectedPacket::Decoded { offset: 2, text } if text.starts_with(ServerTickPacket::PACKET_NAME)));
+    }
+}
@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "debug")]
+use super::UnknownPacket;
+use super::{Packet, PacketLength, PacketVersion};
+#[cfg(feature = "debug")]
+use crate::debug::*;
+use crate::interface::PrototypeElement;
+use crate::loaders::{ByteStream, ConversionError};
+
+/// The outcome of handling a single packet.
+pub enum HandlerResult<Event> {
+    /// The packet produced an event for the rest of the client.
+    Event(Event),
+    /// The packet produced multiple events, which are processed in order.
+    Events(Vec<Event>),
+    /// The packet was handled, but it doesn't concern the rest of the client.
+    Handled,
+}
+
+#[derive(Debug)]
+pub enum HandlerError {
+    /// Neither a packet nor the length of an unhandled packet is registered
+    /// for the header, so the data following it can't be handled either.
+    UnknownHeader { header: u16, offset: usize },
+    /// A registered packet failed to decode. If the length of the packet is
+    /// known, it is skipped.
+    Conversion(ConversionError),
+}
+
+impl Display for HandlerError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandlerError::UnknownHeader { header, offset } => write!(formatter, "unknown packet {header:#06x} at offset {offset:#x}"),
+            HandlerError::Conversion(error) => write!(formatter, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for HandlerError {}
+
+/// Maps the header of a packet to its length.
+pub type PacketLengths = HashMap<u16, PacketLength>;
+
+/// The header of a packet that the client doesn't decode, the first packet
+/// version that sends it with the given length, and the length itself.
+pub type UnhandledPacket = (u16, u32, PacketLength);
+
+type Decoder<State, Event> = Box<dyn Fn(&mut ByteStream, &mut State) -> Result<HandlerResult<Event>, ConversionError>>;
+
+/// Decodes a packet into its text form, which lists all of its fields.
+type Describer = fn(&mut ByteStream) -> Result<String, ConversionError>;
+
+struct RegisteredPacket<State, Event> {
+    packet_name: &'static str,
+    length: PacketLength,
+    decoder: Decoder<State, Event>,
+    describer: Describer,
+}
+
+fn describe<P>(byte_stream: &mut ByteStream) -> Result<String, ConversionError>
+where
+    P: Packet,
+{
+    let packet = P::try_from_bytes(byte_stream)?;
+    let mut text = String::new();
+    packet.write_text(P::PACKET_NAME, 0, &mut text);
+    Ok(text)
+}
+
+/// The size of the packet at the current offset of the byte stream, if it fits
+/// into the remaining data and is large enough to hold its own header.
+fn get_valid_size(length: PacketLength, byte_stream: &ByteStream) -> Option<usize> {
+    length
+        .get_size(byte_stream)
+        .filter(|size| (length.minimum_size()..=byte_stream.get_remaining_length()).contains(size))
+}
+
+/// Maps the header of every packet that a server may send to a function that
+/// decodes and handles it. `State` is the part of the client that handlers
+/// may modify. Since headers depend on the packet version, a handler only
+/// works for the version it was created for.
+pub struct PacketHandler<State, Event> {
+    packet_version: PacketVersion,
+    packets: HashMap<u16, RegisteredPacket<State, Event>>,
+    unhandled_packets: PacketLengths,
+}
+
+impl<State, Event> PacketHandler<State, Event> {
+    pub fn new(packet_version: PacketVersion) -> Self {
+        Self {
+            packet_version,
+            packets: HashMap::new(),
+            unhandled_packets: HashMap::new(),
+        }
+    }
+
+    /// Registers the function that handles packets of type `P`. Every header
+    /// may only be registered once.
+    pub fn register<P>(&mut self, handler: impl Fn(P, &mut State) -> HandlerResult<Event> + 'static)
+    where
+        P: Packet + 'static,
+    {
+        let decoder: Decoder<State, Event> =
+            Box::new(move |byte_stream, state| P::try_from_bytes(byte_stream).map(|packet| handler(packet, state)));
+
+        let header = P::header(self.packet_version);
+        assert!(
+            !self.unhandled_packets.contains_key(&header),
+            "{} is registered for header {:#06x}, which is also registered as unhandled",
+            P::PACKET_NAME,
+            header
+        );
+
+        let registered_packet = RegisteredPacket {
+            packet_name: P::PACKET_NAME,
+            length: P::length(self.packet_version),
+            decoder,
+            describer: describe::<P>,
+        };
+
+        if let Some(previous) = self.packets.insert(header, registered_packet) {
+            panic!(
+                "{} and {} are both registered for header {:#06x}",
+                previous.packet_name,
+                P::PACKET_NAME,
+                header
+            );
+        }
+    }
+
+    /// Registers a packet that is decoded, but that the client doesn't act on.
+    pub fn register_noop<P>(&mut self)
+    where
+        P: Packet + 'static,
+    {
+        self.register(|_: P, _| HandlerResult::Handled);
+    }
+
+    /// Registers the lengths of packets that the server may send, but that the
+    /// client doesn't decode. They are skipped, so the packets following them
+    /// can still be handled. Entries for newer packet versions than the one of
+    /// the handler are ignored.
+    pub fn register_unhandled(&mut self, unhandled_packets: &[UnhandledPacket]) {
+        let unhandled_packets = unhandled_packets
+            .iter()
+            .filter(|(_, since, _)| self.packet_version.equals_or_above(*since))
+            .map(|(header, _, length)| (*header, *length));
+
+        for (header, length) in unhandled_packets {
+            if let Some(registered_packet) = self.packets.get(&header) {
+                panic!(
+                    "{} is registered for header {:#06x}, which is also registered as unhandled",
+                    registered_packet.packet_name, header
+                );
+            }
+
+            self.unhandled_packets.insert(header, length);
+        }
+    }
+
+    /// The lengths of all registered packets, which are needed to find the end
+    /// of a packet before decoding it.
+    pub fn get_packet_lengths(&self) -> PacketLengths {
+        self.packets
+            .iter()
+            .map(|(header, registered_packet)| (*header, registered_packet.length))
+            .chain(self.unhandled_packets.iter().map(|(header, length)| (*header, *length)))
+            .collect()
+    }
+
+    /// Decodes and handles the packet at the start of the byte stream. A
+    /// registered packet that fails to decode is skipped if its length is
+    /// known, so the following packets can still be handled. Unhandled packets
+    /// are skipped as well.
+    pub fn handle_packet(&self, byte_stream: &mut ByteStream, state: &mut State) -> Result<HandlerResult<Event>, HandlerError> {
+        self.process_packet(byte_stream, |registered_packet, byte_stream| {
+            (registered_packet.decoder)(byte_stream, state)
+        })
+        .map(|result| result.unwrap_or(HandlerResult::Handled))
+    }
+
+    /// Like [`handle_packet`](Self::handle_packet), but returns the text form
+    /// of the packet instead of handling it. Returns `None` if the packet was
+    /// skipped because the client doesn't decode it.
+    pub fn describe_packet(&self, byte_stream: &mut ByteStream) -> Result<Option<String>, HandlerError> {
+        self.process_packet(byte_stream, |registered_packet, byte_stream| {
+            (registered_packet.describer)(byte_stream)
+        })
+    }
+
+    fn process_packet<T>(
+        &self,
+        byte_stream: &mut ByteStream,
+        process: impl FnOnce(&RegisteredPacket<State, Event>, &mut ByteStream) -> Result<T, ConversionError>,
+    ) -> Result<Option<T>, HandlerError> {
+        byte_stream.set_packet_version(self.packet_version);
+
+        let offset = byte_stream.get_offset();
+        let header = u16::from_le_bytes([
+            byte_stream.peek(0).map_err(HandlerError::Conversion)?,
+            byte_stream.peek(1).map_err(HandlerError::Conversion)?,
+        ]);
+
+        let Some(registered_packet) = self.packets.get(&header) else {
+            let length = self
+                .unhandled_packets
+                .get(&header)
+                .ok_or(HandlerError::UnknownHeader { header, offset })?;
+            let packet_size = get_valid_size(*length, byte_stream).ok_or_else(|| {
+                HandlerError::Conversion(ConversionError::invalid_data(
+                    offset,
+                    format!("invalid length of unhandled packet {header:#06x}"),
+                ))
+            })?;
+
+            byte_stream.set_offset(offset + packet_size);
+            return Ok(None);
+        };
+
+        process(registered_packet, byte_stream).map(Some).map_err(|error| {
+            if let Some(packet_size) = get_valid_size(registered_packet.length, byte_stream) {
+                byte_stream.set_offset(offset + packet_size);
+            }
+
+            HandlerError::Conversion(error.add_context(registered_packet.packet_name))
+        })
+    }
+
+    /// Handles all packets in the byte stream and collects their events.
+    /// Handling stops at the first packet that can't be skipped, such as one
+    /// with an unknown header, since the start of the next packet is unknown.
+    pub fn handle_all(&self, byte_stream: &mut ByteStream, state: &mut State, events: &mut Vec<Event>) {
+        while !byte_stream.is_empty() {
+            let offset = byte_stream.get_offset();
+
+            match self.handle_packet(byte_stream, state) {
+                Ok(HandlerResult::Event(event)) => events.push(event),
+                Ok(HandlerResult::Events(new_events)) => events.extend(new_events),
+                Ok(HandlerResult::Handled) => {}
+                Err(_error) => {
+                    #[cfg(feature = "debug")]
+                    print_debug!("[{}error{}] {}", RED, NONE, _error);
+
+                    if byte_stream.get_offset() == offset {
+                        #[cfg(feature = "debug")]
+                        {
+                            let packet = UnknownPacket::new(byte_stream.remaining_bytes());
+                            byte_stream.incoming_packet(&packet);
+                        }
+
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use procedural::*;
+
+    use super::*;
+    use crate::loaders::ByteConvertable;
+
+    #[derive(Clone, Debug, Packet, PrototypeElement)]
+    #[header(0x0101)]
+    struct FixedPacket {
+        pub value: u32,
+    }
+
+    #[derive(Clone, Debug, Packet, PrototypeElement)]
+    #[header(0x0102)]
+    struct VariablePacket {
+        #[packet_length]
+        pub packet_length: u16,
+        #[length_hint(self.packet_length - 4)]
+        pub message: String,
+    }
+
+    #[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
+    enum Kind {
+        First,
+        Second,
+    }
+
+    #[derive(Clone, Debug, Packet, PrototypeElement)]
+    #[header(0x0103)]
+    struct KindPacket {
+        pub kind: Kind,
+    }
+
+    fn test_handler() -> PacketHandler<usize, u32> {
+        let mut handler = PacketHandler::new(PacketVersion(20211103));
+        handler.register(|packet: FixedPacket, _| HandlerResult::Event(packet.value));
+        handler.register(|packet: KindPacket, _| HandlerResult::Event(packet.kind as u32));
+        handler.register(|packet: VariablePacket, handled: &mut usize| {
+            *handled += packet.message.len();
+            HandlerResult::Handled
+        });
+        handler.register_unhandled(&[
+            (0x0201, 0, PacketLength::Fixed(4)),
+            (0x0202, 0, PacketLength::Variable),
+            (0x0203, 20211103, PacketLength::Fixed(3)),
+            (0x0204, 20221103, PacketLength::Fixed(3)),
+        ]);
+        handler
+    }
+
+    #[test]
+    fn handle_all_dispatches_by_header() {
+        let bytes = [[2, 1, 7, 0].as_slice(), b"abc", &[1, 1, 5, 0, 0, 0]].concat();
+        let mut byte_stream = ByteStream::new(&bytes);
+        let mut handled = 0;
+        let mut events = Vec::new();
+
+        test_handler().handle_all(&mut byte_stream, &mut handled, &mut events);
+
+        assert!(byte_stream.is_empty());
+        assert_eq!(handled, 3);
+        assert_eq!(events, vec![5]);
+    }
+
+    #[test]
+    fn invalid_packet_is_skipped_by_length() {
+        // There is no variant with index 2, so decoding fails.
+        let bytes = [3, 1, 2, 1, 1, 9, 0, 0, 0];
+        let mut byte_stream = ByteStream::new(&bytes);
+        let mut handled = 0;
+        let handler = test_handler();
+
+        let result = handler.handle_packet(&mut byte_stream, &mut handled);
+
+        assert!(matches!(result, Err(HandlerError::Conversion(..))));
+        assert_eq!(byte_stream.get_offset(), 3);
+        assert!(matches!(
+            handler.handle_packet(&mut byte_stream, &mut handled),
+            Ok(HandlerResult::Event(9))
+        ));
+    }
+
+    #[test]
+    fn unhandled_packets_are_skipped() {
+        let bytes = [[1, 2, 0, 0].as_slice(), &[2, 2, 6, 0, 0, 0], &[3, 2, 0], &[1, 1, 3, 0, 0, 0]].concat();
+        let mut byte_stream = ByteStream::new(&bytes);
+        let mut handled = 0;
+        let mut events = Vec::new();
+
+        test_handler().handle_all(&mut byte_stream, &mut handled, &mut events);
+
+        assert!(byte_stream.is_empty());
+        assert_eq!(events, vec![3]);
+    }
+
+    #[test]
+    fn unhandled_packets_depend_on_packet_version() {
+        let packet_lengths = test_handler().get_packet_lengths();
+
+        assert_eq!(packet_lengths.get(&0x0203), Some(&PacketLength::Fixed(3)));
+        assert_eq!(packet_lengths.get(&0x0204), None);
+    }
+
+    #[test]
+    fn unknown_header_stops_handling() {
+        let bytes = [1, 1, 3, 0, 0, 0, 0xFF, 0xFF, 0, 0, 1, 1, 4, 0, 0, 0];
+        let mut byte_stream = ByteStream::new(&bytes);
+        let mut handled = 0;
+        let mut events = Vec::new();
+        let handler = test_handler();
+
+        handler.handle_all(&mut byte_stream, &mut handled, &mut events);
+
+        assert_eq!(events, vec![3]);
+        assert_eq!(byte_stream.get_offset(), 6);
+        assert!(matches!(
+            handler.handle_packet(&mut byte_stream, &mut handled),
+            Err(HandlerError::UnknownHeader { header: 0xFFFF, offset: 6 })
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+use super::handler::UnhandledPacket;
+use super::PacketLength::{Fixed, Variable};
+
+// The lengths match the packet definitions of rAthena. A header may be listed
+// multiple times if its length changed, in which case the last entry that
+// applies to the packet version of the server is used.
+
+/// Packets that the login server may send, but that the client doesn't decode.
+pub(super) const UNHANDLED_LOGIN_SERVER_PACKETS: &[UnhandledPacket] = &[
+    // AC_ACCEPT_LOGIN, replaced by 0x0ac4
+    (0x0069, 0, Variable),
+    // AC_REFUSE_LOGIN
+    (0x006a, 0, Fixed(23)),
+    // AC_ACK_HASH
+    (0x01dc, 0, Variable),
+    // AC_LOGIN_OTP
+    (0x0ae3, 0, Variable),
+    // AC_REFUSE_LOGIN4
+    (0x0b02, 0, Fixed(26)),
+];
+
+/// Packets that the character server may send, but that the client doesn't
+/// decode.
+pub(super) const UNHANDLED_CHARACTER_SERVER_PACKETS: &[UnhandledPacket] = &[
+    // HC_NOTIFY_ZONESVR, replaced by 0x0ac5
+    (0x0071, 0, Fixed(28)),
+    // HC_BLOCK_CHARACTER
+    (0x020d, 0, Variable),
+    // HC_ACK_IS_VALID_CHARNAME
+    (0x028e, 0, Fixed(4)),
+    // HC_ACK_CHANGE_CHARNAME
+    (0x0290, 0, Fixed(4)),
+    // HC_DELETE_CHAR3_RESERVED
+    (0x0828, 0, Fixed(14)),
+    // HC_DELETE_CHAR3
+    (0x082a, 0, Fixed(10)),
+    // HC_DELETE_CHAR3_CANCEL
+    (0x082c, 0, Fixed(10)),
+    // HC_SECOND_PASSWD_LOGIN
+    (0x08b9, 0, Fixed(12)),
+    // HC_ACK_CHANGE_CHARACTER_SLOT, replaced by 0x0b70
+    (0x08d5, 0, Variable),
+];
+
+/// Packets that the map server may send, but that the client doesn't decode.
+pub(super) const UNHANDLED_MAP_SERVER_PACKETS: &[UnhandledPacket] = &[
+    // ZC_REFUSE_ENTER
+    (0x0074, 0, Fixed(3)),
+    // ZC_ACCEPT_QUIT
+    (0x0083, 0, Fixed(2)),
+    // ZC_REFUSE_QUIT
+    (0x0084, 0, Fixed(2)),
+    // ZC_NOTIFY_ACT
+    (0x008a, 0, Fixed(29)),
+    // ZC_NPCACK_SERVERMOVE
+    (0x0092, 0, Fixed(28)),
+    // ZC_ACK_REQNAME
+    (0x0095, 0, Fixed(30)),
+    // ZC_WHISPER
+    (0x0097, 0, Variable),
+    // ZC_ACK_WHISPER
+    (0x0098, 0, Fixed(3)),
+    // ZC_CHANGE_DIRECTION
+    (0x009c, 0, Fixed(9)),
+    // ZC_ITEM_ENTRY
+    (0x009d, 0, Fixed(17)),
+    // ZC_ITEM_FALL_ENTRY
+    (0x009e, 0, Fixed(17)),
+    // ZC_ITEM_DISAPPEAR
+    (0x00a1, 0, Fixed(6)),
+    // ZC_USE_ITEM_ACK
+    (0x00a8, 0, Fixed(7)),
+    // ZC_REQ_WEAR_EQUIP_ACK
+    (0x00aa, 0, Fixed(9)),
+    // ZC_REQ_TAKEOFF_EQUIP_ACK
+    (0x00ac, 0, Fixed(7)),
+    // ZC_ITEM_THROW_ACK
+    (0x00af, 0, Fixed(6)),
+    // ZC_LONGPAR_CHANGE
+    (0x00b1, 0, Fixed(8)),
+    // ZC_STATUS_CHANGE_ACK
+    (0x00bc, 0, Fixed(6)),
+    // ZC_USER_COUNT
+    (0x00c2, 0, Fixed(6)),
+    // ZC_SPRITE_CHANGE
+    (0x00c3, 0, Fixed(8)),
+    // ZC_SELECT_DEALTYPE
+    (0x00c4, 0, Fixed(6)),
+    // ZC_PC_PURCHASE_ITEMLIST
+    (0x00c6, 0, Variable),
+    // ZC_PC_SELL_ITEMLIST
+    (0x00c7, 0, Variable),
+    // ZC_PC_PURCHASE_RESULT
+    (0x00ca, 0, Fixed(3)),
+    // ZC_PC_SELL_RESULT
+    (0x00cb, 0, Fixed(3)),
+    // ZC_SETTING_WHISPER_PC
+    (0x00d1, 0, Fixed(4)),
+    // ZC_SETTING_WHISPER_STATE
+    (0x00d2, 0, Fixed(4)),
+    // ZC_ROOM_NEWENTRY
+    (0x00d7, 0, Variable),
+    // ZC_REQ_EXCHANGE_ITEM
+    (0x00e5, 0, Fixed(26)),
+    // ZC_NOTIFY_STOREITEM_COUNTINFO
+    (0x00f2, 0, Fixed(6)),
+    // ZC_ACK_MAKE_GROUP
+    (0x00fa, 0, Fixed(3)),
+    // ZC_GROUP_LIST
+    (0x00fb, 0, Variable),
+    // ZC_PARTY_JOIN_REQ_ACK
+    (0x00fd, 0, Fixed(27)),
+    // ZC_GROUPINFO_CHANGE
+    (0x0101, 0, Fixed(6)),
+    // ZC_NOTIFY_HP_TO_GROUPM
+    (0x0106, 0, Fixed(10)),
+    // ZC_NOTIFY_POSITION_TO_GROUPM
+    (0x0107, 0, Fixed(10)),
+    // ZC_NOTIFY_CHAT_PARTY
+    (0x0109, 0, Variable),
+    // ZC_MVP_GETTING_ITEM
+    (0x010a, 0, Fixed(4)),
+    // ZC_MVP
+    (0x010c, 0, Fixed(6)),
+    // ZC_SKILLINFO_UPDATE
+    (0x0111, 0, Fixed(39)),
+    // ZC_NOTIFY_SKILL
+    (0x0114, 0, Fixed(31)),
+    // ZC_ACK_REMEMBER_WARPPOINT
+    (0x011e, 0, Fixed(3)),
+    // ZC_SKILL_ENTRY
+    (0x011f, 0, Fixed(16)),
+    // ZC_NOTIFY_CARTITEM_COUNTINFO
+    (0x0121, 0, Fixed(14)),
+    // ZC_ACK_ADDITEM_TO_CART
+    (0x012c, 0, Fixed(3)),
+    // ZC_ACTION_FAILURE
+    (0x013b, 0, Fixed(4)),
+    // ZC_EQUIP_ARROW
+    (0x013c, 0, Fixed(4)),
+    // ZC_RECOVERY
+    (0x013d, 0, Fixed(6)),
+    // ZC_USESKILL_ACK
+    (0x013e, 0, Fixed(24)),
+    // ZC_OPEN_EDITDLG
+    (0x0142, 0, Fixed(6)),
+    // ZC_SHOW_IMAGE
+    (0x0145, 0, Fixed(19)),
+    // ZC_AUTORUN_SKILL
+    (0x0147, 0, Fixed(39)),
+    // ZC_RESURRECTION
+    (0x0148, 0, Fixed(8)),
+    // ZC_MYGUILD_BASIC_INFO
+    (0x014c, 0, Variable),
+    // ZC_ACK_GUILD_MENUINTERFACE
+    (0x014e, 0, Fixed(6)),
+    // ZC_GUILD_NOTICE
+    (0x016f, 0, Fixed(182)),
+    // ZC_GUILD_CHAT
+    (0x017f, 0, Variable),
+    // ZC_ACK_ITEMREFINING
+    (0x0188, 0, Fixed(8)),
+    // ZC_TALKBOX_CHATCONTENTS
+    (0x0191, 0, Fixed(86)),
+    // ZC_ACK_REQNAME_BYGID
+    (0x0194, 0, Fixed(30)),
+    // ZC_ACK_REQNAMEALL
+    (0x0195, 0, Fixed(102)),
+    // ZC_NOTIFY_MAPPROPERTY
+    (0x0199, 0, Fixed(4)),
+    // ZC_GUILD_INFO2
+    (0x01b6, 0, Fixed(114)),
+    // ZC_DISPEL
+    (0x01b9, 0, Fixed(6)),
+    // ZC_USE_ITEM_ACK2
+    (0x01c8, 0, Fixed(13)),
+    // ZC_SPIRITS
+    (0x01d0, 0, Fixed(8)),
+    // ZC_NOTIFY_MAPPROPERTY2
+    (0x01d6, 0, Fixed(4)),
+    // ZC_SPIRITS2
+    (0x01e1, 0, Fixed(8)),
+    // ZC_REQ_EXCHANGE_ITEM2
+    (0x01f4, 0, Fixed(32)),
+    // ZC_HIGHJUMP
+    (0x01ff, 0, Fixed(10)),
+    // ZC_CHANGESTATE_MER
+    (0x0230, 0, Fixed(12)),
+    // ZC_HOSKILLINFO_LIST
+    (0x0235, 0, Variable),
+    // ZC_ADD_QUEST
+    (0x02b3, 0, Fixed(107)),
+    // ZC_UPDATE_MISSION_HUNT
+    (0x02b5, 0, Variable),
+    // ZC_ACTIVE_QUEST
+    (0x02b7, 0, Fixed(7)),
+    // ZC_PARTY_JOIN_REQ_ACK
+    (0x02c5, 0, Fixed(30)),
+    // ZC_BATTLEFIELD_CHAT
+    (0x02dc, 0, Variable),
+    // ZC_NOTIFY_ACT2
+    (0x02e1, 0, Fixed(33)),
+    // ZC_MAPPROPERTY
+    (0x02e7, 0, Variable),
+    // ZC_PROGRESS
+    (0x02f0, 0, Fixed(10)),
+    // ZC_PROGRESS_CANCEL
+    (0x02f2, 0, Fixed(2)),
+    // ZC_MSG_STATE_CHANGE2
+    (0x043f, 0, Fixed(25)),
+    // ZC_REQ_GROUPINFO_CHANGE_V2
+    (0x07d8, 0, Fixed(8)),
+    // ZC_MSG_VALUE
+    (0x07e2, 0, Fixed(8)),
+    // ZC_MSG_SKILL
+    (0x07e6, 0, Fixed(8)),
+    // ZC_NOTIFY_HP_TO_GROUPM_R2
+    (0x080e, 0, Fixed(14)),
+    // ZC_SHOWSCRIPT
+    (0x08b3, 0, Variable),
+    // ZC_PERSONAL_INFOMATION
+    (0x08cb, 0, Variable),
+    // ZC_SPIRITS_ATTRIBUTE
+    (0x08cf, 0, Fixed(10)),
+    // ZC_FASTMOVE
+    (0x08d2, 0, Fixed(10)),
+    // ZC_CLEAR_DIALOG
+    (0x08d6, 0, Fixed(6)),
+    // ZC_EFST_SET_ENTER
+    (0x08ff, 0, Fixed(24)),
+    // ZC_INVENTORY_ITEM_FAVORITE
+    (0x0908, 0, Fixed(5)),
+    // ZC_PERSONAL_INFOMATION2
+    (0x097b, 0, Variable),
+    // ZC_EFST_SET_ENTER2
+    (0x0984, 0, Fixed(28)),
+    // ZC_NOTIFY_CLAN_CHAT
+    (0x098d, 0, Variable),
+    // ZC_WHISPER02
+    (0x09de, 0, Variable),
+    // ZC_ACK_WHISPER02
+    (0x09df, 0, Fixed(7)),
+    // ZC_SHORTCUT_KEY_LIST_V3
+    (0x0a00, 0, Fixed(269)),
+    // ZC_HP_INFO_TINY
+    (0x0a36, 0, Fixed(7)),
+    // ZC_HAT_EFFECT
+    (0x0a3b, 0, Variable),
+    // ZC_NPCACK_SERVERMOVE, replacing 0x0092
+    (0x0ac7, 0, Fixed(156)),
+    // ZC_ITEM_FALL_ENTRY, replacing 0x009e
+    (0x0add, 0, Fixed(22)),
+    // ZC_OPEN_UI
+    (0x0ae2, 0, Fixed(7)),
+    // ZC_LOAD_CONFIRM
+    (0x0b1b, 20190227, Fixed(2)),
+];
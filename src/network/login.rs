@@ -1,6 +1,9 @@
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 
+use super::handler::{HandlerResult, PacketHandler};
+use super::lengths::UNHANDLED_LOGIN_SERVER_PACKETS;
+use super::{LoginFailedPacket, LoginFailedPacket2, LoginFailedReason, LoginFailedReason2, LoginServerLoginSuccessPacket, PacketVersion};
 #[cfg(feature = "debug")]
 use crate::debug::*;
 
@@ -10,6 +13,9 @@ pub struct LoginSettings {
     pub password: String,
     pub remember_username: bool,
     pub remember_password: bool,
+    /// The name of the server that was selected last.
+    #[serde(default)]
+    pub server_name: Option<String>,
 }
 
 impl LoginSettings {
@@ -40,8 +46,52 @@ impl LoginSettings {
     }
 }
 
+// Tests must not overwrite the settings of the player.
+#[cfg(not(test))]
 impl Drop for LoginSettings {
     fn drop(&mut self) {
         self.save();
     }
 }
+
+pub(super) enum LoginServerResponse {
+    LoginSuccess(LoginServerLoginSuccessPacket),
+    Failure(&'static str),
+}
+
+impl LoginFailedReason {
+    pub(super) fn get_message(&self) -> &'static str {
+        match self {
+            LoginFailedReason::ServerClosed => "server closed",
+            LoginFailedReason::AlreadyLoggedIn => "someone has already logged in with this id",
+            LoginFailedReason::AlreadyOnline => "already online",
+        }
+    }
+}
+
+/// All packets that the login server may send.
+pub(super) fn login_server_handler(packet_version: PacketVersion) -> PacketHandler<(), LoginServerResponse> {
+    let mut handler = PacketHandler::new(packet_version);
+
+    handler.register(|packet: LoginFailedPacket, _| HandlerResult::Event(LoginServerResponse::Failure(packet.reason.get_message())));
+    handler.register(|packet: LoginFailedPacket2, _| {
+        let message = match packet.reason {
+            LoginFailedReason2::UnregisteredId => "unregistered id",
+            LoginFailedReason2::IncorrectPassword => "incorrect password",
+            LoginFailedReason2::IdExpired => "id has expired",
+            LoginFailedReason2::RejectedFromServer => "rejected from server",
+            LoginFailedReason2::BlockedByGMTeam => "blocked by gm team",
+            LoginFailedReason2::GameOutdated => "game outdated",
+            LoginFailedReason2::LoginProhibitedUntil => "login prohibited until",
+            LoginFailedReason2::ServerFull => "server is full",
+            LoginFailedReason2::CompanyAccountLimitReached => "company account limit reached",
+        };
+
+        HandlerResult::Event(LoginServerResponse::Failure(message))
+    });
+    handler.register(|packet: LoginServerLoginSuccessPacket, _| HandlerResult::Event(LoginServerResponse::LoginSuccess(packet)));
+
+    handler.register_unhandled(UNHANDLED_LOGIN_SERVER_PACKETS);
+
+    handler
+}
@@ -0,0 +1,324 @@
+use std::cell::UnsafeCell;
+
+use super::handler::{HandlerResult, PacketHandler};
+use super::lengths::UNHANDLED_MAP_SERVER_PACKETS;
+use super::*;
+use crate::graphics::Color;
+
+/// Parts of the client that packets from the map server modify directly.
+#[derive(Default)]
+pub(super) struct MapServerState {
+    pub friend_list: FriendList,
+    /// The inventory is sent as a start packet, followed by any number of item
+    /// lists and an end packet. Items are collected here until the end packet
+    /// arrives.
+    inventory_items: Option<Vec<(ItemIndex, ItemId, EquipPosition, EquipPosition)>>,
+}
+
+fn chat_message(message: String, color: Color) -> HandlerResult<NetworkEvent> {
+    HandlerResult::Event(NetworkEvent::ChatMessage(ChatMessage::new(message, color)))
+}
+
+fn register_chat_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: BroadcastMessagePacket, _| chat_message(packet.message, Color::rgb(220, 200, 30)));
+    handler.register(|packet: Broadcast2MessagePacket, _| {
+        // NOTE: Drop the alpha channel because it might be 0.
+        let color = Color::rgb(packet.font_color.red, packet.font_color.green, packet.font_color.blue);
+        chat_message(packet.message, color)
+    });
+    handler.register(|packet: OverheadMessagePacket, _| chat_message(packet.message, Color::monochrome(230)));
+    handler.register(|packet: ServerMessagePacket, _| chat_message(packet.message, Color::monochrome(255)));
+    handler.register(|packet: EntityMessagePacket, _| {
+        // NOTE: Drop the alpha channel because it might be 0.
+        let color = Color::rgb(packet.color.red, packet.color.green, packet.color.blue);
+        chat_message(packet.message, color)
+    });
+}
+
+fn register_entity_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: EntityMovePacket, _| {
+        let (origin, destination) = packet.from_to.to_vectors();
+        HandlerResult::Event(NetworkEvent::EntityMove(
+            packet.entity_id,
+            origin,
+            destination,
+            packet.timestamp,
+        ))
+    });
+    handler.register(|packet: PlayerMovePacket, _| {
+        let (origin, destination) = packet.from_to.to_vectors();
+        HandlerResult::Event(NetworkEvent::PlayerMove(origin, destination, packet.timestamp))
+    });
+    handler.register(|packet: ChangeMapPacket, _| {
+        HandlerResult::Event(NetworkEvent::ChangeMap(
+            packet.map_name.replace(".gat", ""),
+            packet.position.map(|component| component as usize),
+        ))
+    });
+    handler.register(|packet: EntityAppearedPacket, _| HandlerResult::Event(NetworkEvent::AddEntity(packet.into())));
+    handler.register(|packet: EntityAppeared2Packet, _| HandlerResult::Event(NetworkEvent::AddEntity(packet.into())));
+    handler.register(|packet: MovingEntityAppearedPacket, _| HandlerResult::Event(NetworkEvent::AddEntity(packet.into())));
+    handler.register(|packet: EntityDisappearedPacket, _| HandlerResult::Event(NetworkEvent::RemoveEntity(packet.entity_id)));
+    handler.register(|packet: SpriteChangePacket, _| match packet.sprite_type {
+        0 => HandlerResult::Event(NetworkEvent::ChangeJob(packet.account_id, packet.value)),
+        _ => HandlerResult::Handled,
+    });
+    handler.register(|packet: RequestPlayerDetailsSuccessPacket, _| {
+        HandlerResult::Event(NetworkEvent::UpdateEntityDetails(EntityId(packet.character_id.0), packet.name))
+    });
+    handler.register(|packet: RequestEntityDetailsSuccessPacket, _| {
+        HandlerResult::Event(NetworkEvent::UpdateEntityDetails(packet.entity_id, packet.name))
+    });
+    handler.register(|packet: UpdateEntityHealthPointsPacket, _| {
+        HandlerResult::Event(NetworkEvent::UpdateEntityHealth(
+            packet.entity_id,
+            packet.health_points as usize,
+            packet.maximum_health_points as usize,
+        ))
+    });
+    handler.register(|packet: DamagePacket, _| {
+        HandlerResult::Event(NetworkEvent::DamageEffect(
+            packet.destination_entity_id,
+            packet.damage_amount as usize,
+        ))
+    });
+    handler.register(|packet: VisualEffectPacket, _| {
+        let path = match packet.effect {
+            VisualEffect::BaseLevelUp => "angel.str",
+            VisualEffect::JobLevelUp => "joblvup.str",
+            VisualEffect::RefineFailure => "bs_refinefailed.str",
+            VisualEffect::RefineSuccess => "bs_refinesuccess.str",
+            VisualEffect::GameOver => "help_angel\\help_angel\\help_angel.str",
+            VisualEffect::PharmacySuccess => "p_success.str",
+            VisualEffect::PharmacyFailure => "p_failed.str",
+            VisualEffect::BaseLevelUpSuperNovice => "help_angel\\help_angel\\help_angel.str",
+            VisualEffect::JobLevelUpSuperNovice => "help_angel\\help_angel\\help_angel.str",
+            VisualEffect::BaseLevelUpTaekwon => "help_angel\\help_angel\\help_angel.str",
+        };
+
+        HandlerResult::Event(NetworkEvent::VisualEffect(path, packet.entity_id))
+    });
+    handler.register(|packet: QuestEffectPacket, _| {
+        let event = match packet.effect {
+            QuestEffect::None => NetworkEvent::RemoveQuestEffect(packet.entity_id),
+            _ => NetworkEvent::AddQuestEffect(packet),
+        };
+        HandlerResult::Event(event)
+    });
+
+    handler.register_noop::<DisplayEmotionPacket>();
+    handler.register_noop::<EntityStopMovePacket>();
+    handler.register_noop::<StateChangePacket>();
+    handler.register_noop::<RequestPlayerAttackFailedPacket>();
+}
+
+fn register_player_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: MapServerLoginSuccessPacket, _| {
+        HandlerResult::Events(vec![
+            NetworkEvent::UpdateClientTick(packet.client_tick),
+            NetworkEvent::SetPlayerPosition(packet.position.to_vector()),
+        ])
+    });
+    handler.register(|packet: ServerTickPacket, _| HandlerResult::Event(NetworkEvent::UpdateClientTick(packet.client_tick)));
+    handler.register(|packet: UpdateStatusPacket, _| HandlerResult::Event(NetworkEvent::UpdateStatus(packet.status_type)));
+    handler.register(|packet: UpdateStatusPacket1, _| HandlerResult::Event(NetworkEvent::UpdateStatus(packet.status_type)));
+    handler.register(|packet: UpdateStatusPacket2, _| HandlerResult::Event(NetworkEvent::UpdateStatus(packet.status_type)));
+    handler.register(|packet: UpdateStatusPacket3, _| HandlerResult::Event(NetworkEvent::UpdateStatus(packet.status_type)));
+    handler.register(|packet: UpdateSkillTreePacket, _| HandlerResult::Event(NetworkEvent::SkillTree(packet.skill_information)));
+    handler.register(|packet: RestartResponsePacket, _| match packet.result {
+        RestartResponseStatus::Ok => HandlerResult::Event(NetworkEvent::Disconnect(DisconnectReason::LoggedOut)),
+        RestartResponseStatus::Nothing => chat_message("Failed to log out.".to_string(), Color::rgb(255, 100, 100)),
+    });
+    handler.register(|packet: DisconnectResponsePacket, _| match packet.result {
+        DisconnectResponseStatus::Ok => HandlerResult::Event(NetworkEvent::Disconnect(DisconnectReason::LoggedOut)),
+        DisconnectResponseStatus::Wait10Seconds => chat_message(
+            "Please wait 10 seconds before trying to log out.".to_string(),
+            Color::rgb(255, 100, 100),
+        ),
+    });
+
+    handler.register_noop::<UpdateAttackRangePacket>();
+    handler.register_noop::<NewMailStatusPacket>();
+    handler.register_noop::<AchievementUpdatePacket>();
+    handler.register_noop::<AchievementListPacket>();
+    handler.register_noop::<CriticalWeightUpdatePacket>();
+    handler.register_noop::<MapTypePacket>();
+    handler.register_noop::<UpdateHotkeysPacket>();
+    handler.register_noop::<InitialStatusPacket>();
+    handler.register_noop::<UpdatePartyInvitationStatePacket>();
+    handler.register_noop::<UpdateShowEquipPacket>();
+    handler.register_noop::<UpdateConfigurationPacket>();
+    handler.register_noop::<DisplayGainedExperiencePacket>();
+    handler.register_noop::<StatusChangePacket>();
+    handler.register_noop::<StatusChangeSequencePacket>();
+    handler.register_noop::<ReputationPacket>();
+    handler.register_noop::<ClanInfoPacket>();
+    handler.register_noop::<ClanOnlineCountPacket>();
+    handler.register_noop::<ChangeMapCellPacket>();
+    handler.register_noop::<PartyInvitePacket>();
+    handler.register_noop::<Packet8302>();
+    handler.register_noop::<Packet180b>();
+}
+
+fn register_inventory_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|_: InventoyStartPacket, state: &mut MapServerState| {
+        state.inventory_items = Some(Vec::new());
+        HandlerResult::Handled
+    });
+    handler.register(|packet: RegularItemListPacket, state: &mut MapServerState| {
+        if let Some(inventory_items) = &mut state.inventory_items {
+            inventory_items.extend(packet.item_information.into_iter().map(|item_information| {
+                // TODO: Don't add that data here, only equippable items need this data.
+                (
+                    item_information.index,
+                    item_information.item_id,
+                    EquipPosition::None,
+                    EquipPosition::None,
+                )
+            }));
+        }
+
+        HandlerResult::Handled
+    });
+    handler.register(|packet: EquippableItemListPacket, state: &mut MapServerState| {
+        if let Some(inventory_items) = &mut state.inventory_items {
+            inventory_items.extend(packet.item_information.into_iter().map(|item_information| {
+                (
+                    item_information.index,
+                    item_information.item_id,
+                    item_information.equip_position,
+                    item_information.equipped_position,
+                )
+            }));
+        }
+
+        HandlerResult::Handled
+    });
+    handler.register(
+        |_: InventoyEndPacket, state: &mut MapServerState| match state.inventory_items.take() {
+            Some(inventory_items) => HandlerResult::Event(NetworkEvent::Inventory(inventory_items)),
+            None => HandlerResult::Handled,
+        },
+    );
+    handler.register(|packet: ItemPickupPacket, _| {
+        HandlerResult::Event(NetworkEvent::AddIventoryItem(
+            packet.index,
+            packet.item_id(),
+            packet.equip_position(),
+            EquipPosition::None,
+        ))
+    });
+    handler.register(|packet: RequestEquipItemStatusPacket, _| match packet.result {
+        RequestEquipItemStatus::Success => HandlerResult::Event(NetworkEvent::UpdateEquippedPosition {
+            index: packet.inventory_index,
+            equipped_position: packet.equipped_position,
+        }),
+        _ => HandlerResult::Handled,
+    });
+    handler.register(|packet: RequestUnequipItemStatusPacket, _| match packet.result {
+        RequestUnequipItemStatus::Success => HandlerResult::Event(NetworkEvent::UpdateEquippedPosition {
+            index: packet.inventory_index,
+            equipped_position: EquipPosition::None,
+        }),
+        _ => HandlerResult::Handled,
+    });
+
+    handler.register_noop::<EquippableSwitchItemListPacket>();
+    handler.register_noop::<RemoveItemFromInventoryPacket>();
+}
+
+fn register_dialog_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: NpcDialogPacket, _| HandlerResult::Event(NetworkEvent::OpenDialog(packet.text, packet.npc_id)));
+    handler.register(|_: NextButtonPacket, _| HandlerResult::Event(NetworkEvent::AddNextButton));
+    handler.register(|_: CloseButtonPacket, _| HandlerResult::Event(NetworkEvent::AddCloseButton));
+    handler.register(|packet: DialogMenuPacket, _| {
+        let choices = packet
+            .message
+            .split(':')
+            .map(String::from)
+            .filter(|text| !text.is_empty())
+            .collect();
+
+        HandlerResult::Event(NetworkEvent::AddChoiceButtons(choices))
+    });
+
+    handler.register_noop::<NavigateToMonsterPacket>();
+    handler.register_noop::<MarkMinimapPositionPacket>();
+    handler.register_noop::<DisplayImagePacket>();
+    handler.register_noop::<QuestNotificationPacket1>();
+    handler.register_noop::<HuntingQuestNotificationPacket>();
+    handler.register_noop::<HuntingQuestUpdateObjectivePacket>();
+    handler.register_noop::<QuestRemovedPacket>();
+    handler.register_noop::<QuestListPacket>();
+}
+
+fn register_skill_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: DisplaySkillEffectNoDamagePacket, _| {
+        HandlerResult::Event(NetworkEvent::HealEffect(
+            packet.destination_entity_id,
+            packet.heal_amount() as usize,
+        ))
+    });
+    handler.register(|packet: NotifySkillUnitPacket, _| {
+        HandlerResult::Event(NetworkEvent::AddSkillUnit(
+            packet.entity_id,
+            packet.unit_id,
+            packet.position.map(|component| component as usize),
+        ))
+    });
+    handler.register(|packet: SkillUnitDisappearPacket, _| HandlerResult::Event(NetworkEvent::RemoveSkillUnit(packet.entity_id)));
+
+    handler.register_noop::<DisplaySpecialEffectPacket>();
+    handler.register_noop::<DisplaySkillCooldownPacket>();
+    handler.register_noop::<DisplaySkillEffectAndDamagePacket>();
+    handler.register_noop::<DisplayPlayerHealEffect>();
+    handler.register_noop::<UseSkillSuccessPacket>();
+    handler.register_noop::<ToUseSkillSuccessPacket>();
+    handler.register_noop::<NotifyGroundSkillPacket>();
+}
+
+fn register_friend_packets(handler: &mut PacketHandler<MapServerState, NetworkEvent>) {
+    handler.register(|packet: FriendListPacket, state: &mut MapServerState| {
+        state.friend_list.with_mut(|friends, changed| {
+            *friends = packet.friends.into_iter().map(|friend| (friend, UnsafeCell::new(None))).collect();
+            changed();
+        });
+
+        HandlerResult::Handled
+    });
+    handler.register(|packet: FriendRequestPacket, _| HandlerResult::Event(NetworkEvent::FriendRequest(packet.friend)));
+    handler.register(|packet: FriendRequestResultPacket, state: &mut MapServerState| {
+        if packet.result == FriendRequestResult::Accepted {
+            state.friend_list.push((packet.friend.clone(), UnsafeCell::new(None)));
+        }
+
+        chat_message(packet.into_message(), Color::rgb(220, 200, 30))
+    });
+    handler.register(|packet: NotifyFriendRemovedPacket, state: &mut MapServerState| {
+        state.friend_list.with_mut(|friends, changed| {
+            friends.retain(|(friend, _)| !(friend.account_id == packet.account_id && friend.character_id == packet.character_id));
+            changed();
+        });
+
+        HandlerResult::Handled
+    });
+
+    handler.register_noop::<FriendOnlineStatusPacket>();
+}
+
+/// All packets that the map server may send.
+pub(super) fn map_server_handler(packet_version: PacketVersion) -> PacketHandler<MapServerState, NetworkEvent> {
+    let mut handler = PacketHandler::new(packet_version);
+
+    register_chat_packets(&mut handler);
+    register_entity_packets(&mut handler);
+    register_player_packets(&mut handler);
+    register_inventory_packets(&mut handler);
+    register_dialog_packets(&mut handler);
+    register_skill_packets(&mut handler);
+    register_friend_packets(&mut handler);
+
+    handler.register_unhandled(UNHANDLED_MAP_SERVER_PACKETS);
+
+    handler
+}
@@ -1,17 +1,44 @@
+mod buffer;
+mod capture;
+mod character;
+mod connection;
+mod dissector;
+mod handler;
+mod lengths;
 mod login;
+mod map;
+mod replay;
+mod servers;
+#[cfg(test)]
+mod test_server;
+mod version;
 
 use std::cell::UnsafeCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
-use std::io::prelude::*;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
-use std::time::Duration;
+use std::net::Ipv4Addr;
+use std::sync::mpsc::{Receiver, SendError, Sender};
+use std::time::{Duration, Instant};
 
 use cgmath::Vector2;
 use chrono::Local;
 use derive_new::new;
 use procedural::*;
 
+use self::capture::Capture;
+pub use self::capture::{CaptureMode, Direction};
+use self::character::{character_server_handler, CharacterServerResponse};
+pub use self::connection::ServerKind;
+use self::connection::{spawn_network_thread, Backoff, ConnectionEvent, NetworkCommand};
+pub use self::dissector::{dissect_capture, DissectedPacket, DissectedRecord, Dissector};
+use self::handler::{PacketHandler, PacketLengths};
 pub use self::login::LoginSettings;
+use self::login::{login_server_handler, LoginServerResponse};
+use self::map::{map_server_handler, MapServerState};
+use self::replay::spawn_replay_thread;
+use self::servers::load_servers;
+pub use self::servers::ServerSettings;
+pub use self::version::PacketVersion;
 #[cfg(feature = "debug")]
 use crate::debug::*;
 use crate::graphics::{Color, ColorBGRA, ColorRGBA};
@@ -20,9 +47,12 @@ use crate::interface::PacketEntry;
 #[cfg(feature = "debug")]
 use crate::interface::PacketWindow;
 use crate::interface::{
-    CharacterSelectionWindow, ElementCell, ElementWrap, Expandable, FriendsWindow, PrototypeElement, TrackedState, WeakElementCell,
+    CharacterSelectionWindow, ElementCell, ElementWrap, Expandable, FriendsWindow, LoginWindow, PrototypeElement, TrackedState,
+    WeakElementCell,
 };
-use crate::loaders::{ByteConvertable, ByteStream, FixedByteSize};
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, ConversionErrorKind, FixedByteSize};
 
 #[derive(Clone, Copy, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub struct ClientTick(pub u32);
@@ -52,8 +82,14 @@ pub struct SkillLevel(pub u16);
 pub struct ItemIndex(u16);
 
 impl ByteConvertable for ItemIndex {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
-        Self(u16::from_bytes(byte_stream, length_hint) - 2)
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        let offset = byte_stream.get_offset();
+        let index = u16::from_bytes(byte_stream, length_hint)?;
+
+        index
+            .checked_sub(2)
+            .map(Self)
+            .ok_or_else(|| ConversionError::invalid_data(offset, format!("invalid item index {index}")))
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -61,6 +97,13 @@ impl ByteConvertable for ItemIndex {
     }
 }
 
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for ItemIndex {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        Self(u16::arbitrary(generator, length_hint).saturating_sub(2))
+    }
+}
+
 #[derive(Clone, Copy, Debug, ByteConvertable, FixedByteSize, PrototypeElement, PartialEq, Eq, Hash)]
 pub struct ItemId(pub u32);
 
@@ -69,17 +112,66 @@ pub struct ItemId(pub u32);
 /// followed by the packet data. If the packet does not have a fixed size,
 /// the first two bytes will be the size of the packet in bytes *including* the
 /// header. Packets are sent in little endian.
+///
+/// The header and layout of some packets depend on the [`PacketVersion`] of
+/// the server.
 pub trait Packet: PrototypeElement + Clone {
     const PACKET_NAME: &'static str;
     const IS_PING: bool;
 
-    fn header() -> u16;
+    fn header(packet_version: PacketVersion) -> u16;
+
+    fn length(packet_version: PacketVersion) -> PacketLength;
+
+    /// Decodes the packet if the header matches. The packet version is taken
+    /// from the byte stream. On failure, the byte stream is left untouched, so
+    /// a different packet can be tried.
+    fn try_from_bytes(byte_stream: &mut ByteStream) -> Result<Self, ConversionError>;
+
+    fn to_bytes(&self, packet_version: PacketVersion) -> Vec<u8>;
+}
+
+/// The size of a packet, as far as it is known before decoding it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketLength {
+    /// Every packet of this type has the same size in bytes, including the
+    /// header.
+    Fixed(usize),
+    /// The size is stored in the two bytes after the header.
+    Variable,
+}
+
+impl PacketLength {
+    /// The size of the packet at the start of the byte stream, if enough data
+    /// is available to know it.
+    pub fn get_size(self, byte_stream: &ByteStream) -> Option<usize> {
+        match self {
+            PacketLength::Fixed(length) => Some(length),
+            PacketLength::Variable => Some(u16::from_le_bytes([byte_stream.peek(2).ok()?, byte_stream.peek(3).ok()?]) as usize),
+        }
+    }
 
-    fn to_bytes(&self) -> Vec<u8>;
+    /// The smallest size that a packet of this length can have, which is the
+    /// size of its header and, if variable, the length field.
+    pub fn minimum_size(self) -> usize {
+        match self {
+            PacketLength::Fixed(..) => 2,
+            PacketLength::Variable => 4,
+        }
+    }
 }
 
-/// An event triggered by the map server.
+/// An event triggered by the login, character or map server.
 pub enum NetworkEvent {
+    /// Logging into the login and character server succeeded and the
+    /// character list is available.
+    LoggedIn,
+    CharacterCreated,
+    /// The character was selected and the client is logging into the map
+    /// server. Carries the name of the map that the character is on.
+    CharacterSelected(AccountId, CharacterInformation, String),
+    /// A request to the login or character server failed.
+    RequestFailed(String),
     /// Add an entity to the list of entities that the client is aware of
     AddEntity(EntityData),
     /// Remove an entity from the list of entities that the client is aware of
@@ -119,13 +211,58 @@ pub enum NetworkEvent {
     },
     ChangeJob(AccountId, u32),
     SetPlayerPosition(Vector2<usize>),
-    Disconnect,
+    Disconnect(DisconnectReason),
     FriendRequest(Friend),
     VisualEffect(&'static str, EntityId),
     AddSkillUnit(EntityId, UnitId, Vector2<usize>),
     RemoveSkillUnit(EntityId),
 }
 
+/// Why the client left the map or the character selection.
+pub enum DisconnectReason {
+    /// The map server confirmed that the player logged out.
+    LoggedOut,
+    /// The login server couldn't be reached before the player logged in. The
+    /// client keeps trying to reconnect in the background.
+    LoginServerUnavailable(String),
+    /// The connection to the character server was lost, so the player has to
+    /// log in again.
+    CharacterServerLost(String),
+    /// The connection to the map server was lost, but the character server is
+    /// still available to select a character.
+    MapServerLost(String),
+    /// Not sent by a server. The client couldn't load the map it entered, so
+    /// the player has to leave the map server and select a character again.
+    MapLoadingFailed(String),
+}
+
+impl NetworkEvent {
+    /// Events that refer to entities or positions on the current map. While a
+    /// map is loading, these have to wait until it is ready.
+    pub fn requires_map(&self) -> bool {
+        matches!(
+            self,
+            NetworkEvent::AddEntity(..)
+                | NetworkEvent::RemoveEntity(..)
+                | NetworkEvent::PlayerMove(..)
+                | NetworkEvent::EntityMove(..)
+                | NetworkEvent::ChangeMap(..)
+                | NetworkEvent::UpdateEntityDetails(..)
+                | NetworkEvent::UpdateEntityHealth(..)
+                | NetworkEvent::DamageEffect(..)
+                | NetworkEvent::HealEffect(..)
+                | NetworkEvent::AddQuestEffect(..)
+                | NetworkEvent::RemoveQuestEffect(..)
+                | NetworkEvent::ChangeJob(..)
+                | NetworkEvent::SetPlayerPosition(..)
+                | NetworkEvent::UpdateStatus(..)
+                | NetworkEvent::VisualEffect(..)
+                | NetworkEvent::AddSkillUnit(..)
+                | NetworkEvent::RemoveSkillUnit(..)
+        )
+    }
+}
+
 pub struct ChatMessage {
     pub text: String,
     pub color: Color,
@@ -149,7 +286,7 @@ impl ChatMessage {
     }
 }
 
-#[derive(Copy, Clone, Debug, ByteConvertable, PrototypeElement, PartialEq)]
+#[derive(Copy, Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement, PartialEq)]
 pub enum Sex {
     Female,
     Male,
@@ -216,15 +353,29 @@ struct CharacterServerLoginSuccessPacket {
     pub unused: [u8; 20],
 }
 
+/// Sent by the character server after logging in and after switching the
+/// slot of a character. Provides the characters on the account.
 #[allow(dead_code)]
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x006b)]
 struct Packet6b00 {
-    pub unused: u16,
+    #[packet_length]
+    pub packet_length: u16,
     pub maximum_slot_count: u8,
     pub available_slot_count: u8,
     pub vip_slot_count: u8,
     pub unknown: [u8; 20],
+    #[repeating_remaining]
+    pub character_information: Vec<CharacterInformation>,
+}
+
+/// Sent by the character server after [Packet6b00]. The number of pages that
+/// the character selection should be split into.
+#[allow(dead_code)]
+#[derive(Clone, Debug, Packet, PrototypeElement)]
+#[header(0x09a0)]
+struct CharacterListPageCountPacket {
+    pub page_count: u32,
 }
 
 #[allow(dead_code)]
@@ -247,19 +398,22 @@ impl WorldPosition {
     }
 }
 
+impl const FixedByteSize for WorldPosition {
+    fn size_in_bytes() -> usize {
+        3
+    }
+}
+
 impl ByteConvertable for WorldPosition {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none());
-        let coordinates = byte_stream.slice(3);
+        let coordinates: Vec<usize> = byte_stream.slice(3)?.iter().map(|byte| *byte as usize).collect();
 
         let x = (coordinates[1] >> 6) | (coordinates[0] << 2);
         let y = (coordinates[2] >> 4) | ((coordinates[1] & 0b111111) << 4);
         //let direction = ...
 
-        Self {
-            x: x as usize,
-            y: y as usize,
-        }
+        Ok(Self { x, y })
     }
 
     fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
@@ -274,6 +428,24 @@ impl ByteConvertable for WorldPosition {
     }
 }
 
+/// Coordinates are stored with 10 bits each.
+#[cfg(any(test, feature = "arbitrary"))]
+fn arbitrary_coordinate(generator: &mut Generator) -> usize {
+    u16::arbitrary(generator, None) as usize & 0x3ff
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for WorldPosition {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none());
+
+        Self {
+            x: arbitrary_coordinate(generator),
+            y: arbitrary_coordinate(generator),
+        }
+    }
+}
+
 #[derive(Clone, Debug, new, PrototypeElement)]
 pub struct WorldPosition2 {
     pub x1: usize,
@@ -288,10 +460,16 @@ impl WorldPosition2 {
     }
 }
 
+impl const FixedByteSize for WorldPosition2 {
+    fn size_in_bytes() -> usize {
+        6
+    }
+}
+
 impl ByteConvertable for WorldPosition2 {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
         assert!(length_hint.is_none());
-        let coordinates: Vec<usize> = byte_stream.slice(6).iter().map(|byte| *byte as usize).collect();
+        let coordinates: Vec<usize> = byte_stream.slice(6)?.iter().map(|byte| *byte as usize).collect();
 
         let x1 = (coordinates[1] >> 6) | (coordinates[0] << 2);
         let y1 = (coordinates[2] >> 4) | ((coordinates[1] & 0b111111) << 4);
@@ -299,7 +477,34 @@ impl ByteConvertable for WorldPosition2 {
         let y2 = coordinates[4] | ((coordinates[3] & 0b11) << 8);
         //let direction = ...
 
-        Self { x1, y1, x2, y2 }
+        Ok(Self { x1, y1, x2, y2 })
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert!(length_hint.is_none());
+        let mut coordinates = vec![0; 6];
+
+        coordinates[0] = (self.x1 >> 2) as u8;
+        coordinates[1] = ((self.x1 << 6) as u8) | (((self.y1 >> 4) & 0x3f) as u8);
+        coordinates[2] = ((self.y1 << 4) as u8) | (((self.x2 >> 6) & 0xf) as u8);
+        coordinates[3] = ((self.x2 << 2) as u8) | (((self.y2 >> 8) & 0x3) as u8);
+        coordinates[4] = self.y2 as u8;
+
+        coordinates
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for WorldPosition2 {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        assert!(length_hint.is_none());
+
+        Self {
+            x1: arbitrary_coordinate(generator),
+            y1: arbitrary_coordinate(generator),
+            x2: arbitrary_coordinate(generator),
+            y2: arbitrary_coordinate(generator),
+        }
     }
 }
 
@@ -307,15 +512,22 @@ impl ByteConvertable for WorldPosition2 {
 #[allow(dead_code)]
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x02eb)]
+#[header_smaller(20080102, 0x0073)]
+#[header_smaller(20141022, 0x02eb)]
+#[header_smaller(20160330, 0x0a18)]
 struct MapServerLoginSuccessPacket {
     pub client_tick: ClientTick,
     pub position: WorldPosition,
     /// Always [5, 5] on rAthena
     pub ignored: [u8; 2],
-    pub font: u16,
+    #[packet_version_equals_or_above(20080102)]
+    pub font: Option<u16>,
+    #[packet_version_equals_or_above(20141016)]
+    #[packet_version_smaller(20160330)]
+    pub sex: Option<Sex>,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub enum LoginFailedReason {
     #[numeric_value(1)]
     ServerClosed,
@@ -334,12 +546,13 @@ struct LoginFailedPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x0840)]
 struct MapServerUnavailablePacket {
+    #[packet_length]
     pub packet_length: u16,
     #[length_hint(self.packet_length - 4)]
     pub unknown: String,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub enum LoginFailedReason2 {
     UnregisteredId,
     IncorrectPassword,
@@ -358,7 +571,7 @@ struct LoginFailedPacket2 {
     pub reason: LoginFailedReason2,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub enum CharacterSelectionFailedReason {
     RejectedFromServer,
 }
@@ -385,7 +598,7 @@ struct CharacterSelectionSuccessPacket {
     pub unknown: [u8; 128],
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub enum CharacterCreationFailedReason {
     CharacterNameAlreadyUsed,
     NotOldEnough,
@@ -413,9 +626,22 @@ struct LoginServerKeepalivePacket {
 }
 
 impl ByteConvertable for Ipv4Addr {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        assert!(length_hint.is_none());
+        <[u8; 4]>::from_bytes(byte_stream, None).map(Ipv4Addr::from)
+    }
+
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        assert!(length_hint.is_none());
+        self.octets().to_vec()
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for Ipv4Addr {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
         assert!(length_hint.is_none());
-        Ipv4Addr::new(byte_stream.next(), byte_stream.next(), byte_stream.next(), byte_stream.next())
+        <[u8; 4]>::arbitrary(generator, None).into()
     }
 }
 
@@ -632,7 +858,7 @@ struct DeleteCharacterPacket {
     pub unknown: [u8; 10],
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 pub enum CharacterDeletionFailedReason {
     NotAllowed,
     CharacterNotFound,
@@ -666,6 +892,7 @@ struct SelectCharacterPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x008e)]
 struct ServerMessagePacket {
+    #[packet_length]
     pub packet_length: u16,
     #[length_hint(self.packet_length - 4)]
     pub message: String,
@@ -716,7 +943,7 @@ struct NewMailStatusPacket {
     pub new_available: u8,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 struct AchievementData {
     pub acheivement_id: u32,
     pub is_completed: u8,
@@ -767,6 +994,7 @@ struct SpriteChangePacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x0b08)]
 struct InventoyStartPacket {
+    #[packet_length]
     pub packet_length: u16,
     pub inventory_type: u8,
     #[length_hint(self.packet_length - 5)]
@@ -871,6 +1099,7 @@ struct MapTypePacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x01c3)]
 struct Broadcast2MessagePacket {
+    #[packet_length]
     pub packet_length: u16,
     pub font_color: ColorRGBA,
     pub font_type: u16,
@@ -886,8 +1115,9 @@ struct Broadcast2MessagePacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x009a)]
 struct BroadcastMessagePacket {
+    #[packet_length]
     pub packet_length: u16,
-    #[length_hint(self.packet_length - 2)]
+    #[length_hint(self.packet_length - 4)]
     pub message: String,
 }
 
@@ -897,9 +1127,10 @@ struct BroadcastMessagePacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x008d)]
 struct OverheadMessagePacket {
+    #[packet_length]
     pub packet_length: u16,
     pub entity_id: EntityId,
-    #[length_hint(self.packet_length - 6)]
+    #[length_hint(self.packet_length - 8)]
     pub message: String,
 }
 
@@ -909,6 +1140,7 @@ struct OverheadMessagePacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x02c1)]
 struct EntityMessagePacket {
+    #[packet_length]
     pub packet_length: u16,
     pub entity_id: EntityId,
     pub color: ColorBGRA,
@@ -997,82 +1229,197 @@ pub enum StatusType {
 }
 
 impl ByteConvertable for StatusType {
-    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Self {
-        let data = byte_stream.slice(length_hint.unwrap());
-        let mut byte_stream = ByteStream::new(data);
-
-        match u16::from_bytes(&mut byte_stream, None) {
-            0 => Self::MovementSpeed(u32::from_bytes(&mut byte_stream, None)),
-            1 => Self::BaseExperience(u64::from_bytes(&mut byte_stream, None)),
-            2 => Self::JobExperience(u64::from_bytes(&mut byte_stream, None)),
-            3 => Self::Karma(u32::from_bytes(&mut byte_stream, None)),
-            4 => Self::Manner(u32::from_bytes(&mut byte_stream, None)),
-            5 => Self::HealthPoints(u32::from_bytes(&mut byte_stream, None)),
-            6 => Self::MaximumHealthPoints(u32::from_bytes(&mut byte_stream, None)),
-            7 => Self::SpellPoints(u32::from_bytes(&mut byte_stream, None)),
-            8 => Self::MaximumSpellPoints(u32::from_bytes(&mut byte_stream, None)),
-            9 => Self::StatusPoint(u32::from_bytes(&mut byte_stream, None)),
-            11 => Self::BaseLevel(u32::from_bytes(&mut byte_stream, None)),
-            12 => Self::SkillPoint(u32::from_bytes(&mut byte_stream, None)),
-            13 => Self::Strength(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            14 => Self::Agility(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            15 => Self::Vitality(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            16 => Self::Intelligence(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            17 => Self::Dexterity(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            18 => Self::Luck(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            20 => Self::Zeny(u32::from_bytes(&mut byte_stream, None)),
-            22 => Self::NextBaseExperience(u64::from_bytes(&mut byte_stream, None)),
-            23 => Self::NextJobExperience(u64::from_bytes(&mut byte_stream, None)),
-            24 => Self::Weight(u32::from_bytes(&mut byte_stream, None)),
-            25 => Self::MaximumWeight(u32::from_bytes(&mut byte_stream, None)),
-            32 => Self::SpUstr(u8::from_bytes(&mut byte_stream, None)),
-            33 => Self::SpUagi(u8::from_bytes(&mut byte_stream, None)),
-            34 => Self::SpUvit(u8::from_bytes(&mut byte_stream, None)),
-            35 => Self::SpUint(u8::from_bytes(&mut byte_stream, None)),
-            36 => Self::SpUdex(u8::from_bytes(&mut byte_stream, None)),
-            37 => Self::SpUluk(u8::from_bytes(&mut byte_stream, None)),
-            41 => Self::Attack1(u32::from_bytes(&mut byte_stream, None)),
-            42 => Self::Attack2(u32::from_bytes(&mut byte_stream, None)),
-            43 => Self::MagicAttack1(u32::from_bytes(&mut byte_stream, None)),
-            44 => Self::MagicAttack2(u32::from_bytes(&mut byte_stream, None)),
-            45 => Self::Defense1(u32::from_bytes(&mut byte_stream, None)),
-            46 => Self::Defense2(u32::from_bytes(&mut byte_stream, None)),
-            47 => Self::MagicDefense1(u32::from_bytes(&mut byte_stream, None)),
-            48 => Self::MagicDefense2(u32::from_bytes(&mut byte_stream, None)),
-            49 => Self::Hit(u32::from_bytes(&mut byte_stream, None)),
-            50 => Self::Flee1(u32::from_bytes(&mut byte_stream, None)),
-            51 => Self::Flee2(u32::from_bytes(&mut byte_stream, None)),
-            52 => Self::Critical(u32::from_bytes(&mut byte_stream, None)),
-            53 => Self::AttackSpeed(u32::from_bytes(&mut byte_stream, None)),
-            55 => Self::JobLevel(u32::from_bytes(&mut byte_stream, None)),
+    fn from_bytes(byte_stream: &mut ByteStream, length_hint: Option<usize>) -> Result<Self, ConversionError> {
+        let base_offset = byte_stream.get_offset();
+        let data = byte_stream.slice(length_hint.unwrap())?;
+
+        Self::read_status(&mut ByteStream::new(data)).map_err(|error| error.add_offset(base_offset))
+    }
+
+    /// Values that need less space than the packet provides are padded with
+    /// zeros.
+    fn to_bytes(&self, length_hint: Option<usize>) -> Vec<u8> {
+        let length = length_hint.unwrap();
+        let mut bytes = self.write_status();
+
+        assert!(bytes.len() <= length, "status type is too big for the packet");
+        bytes.resize(length, 0);
+        bytes
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for StatusType {
+    fn arbitrary(generator: &mut Generator, length_hint: Option<usize>) -> Self {
+        // Generate random values until they contain a status that fits into the
+        // packet.
+        loop {
+            let mut data = (u8::arbitrary(generator, None) as u16).to_bytes(None);
+            data.extend((2..length_hint.unwrap()).map(|_| u8::arbitrary(generator, None)));
+
+            if let Ok(status_type) = Self::read_status(&mut ByteStream::new(&data)) {
+                return status_type;
+            }
+        }
+    }
+}
+
+impl StatusType {
+    fn read_status(byte_stream: &mut ByteStream) -> Result<Self, ConversionError> {
+        let status_type = match u16::from_bytes(byte_stream, None)? {
+            0 => Self::MovementSpeed(u32::from_bytes(byte_stream, None)?),
+            1 => Self::BaseExperience(u64::from_bytes(byte_stream, None)?),
+            2 => Self::JobExperience(u64::from_bytes(byte_stream, None)?),
+            3 => Self::Karma(u32::from_bytes(byte_stream, None)?),
+            4 => Self::Manner(u32::from_bytes(byte_stream, None)?),
+            5 => Self::HealthPoints(u32::from_bytes(byte_stream, None)?),
+            6 => Self::MaximumHealthPoints(u32::from_bytes(byte_stream, None)?),
+            7 => Self::SpellPoints(u32::from_bytes(byte_stream, None)?),
+            8 => Self::MaximumSpellPoints(u32::from_bytes(byte_stream, None)?),
+            9 => Self::StatusPoint(u32::from_bytes(byte_stream, None)?),
+            11 => Self::BaseLevel(u32::from_bytes(byte_stream, None)?),
+            12 => Self::SkillPoint(u32::from_bytes(byte_stream, None)?),
+            13 => Self::Strength(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            14 => Self::Agility(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            15 => Self::Vitality(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            16 => Self::Intelligence(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            17 => Self::Dexterity(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            18 => Self::Luck(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            20 => Self::Zeny(u32::from_bytes(byte_stream, None)?),
+            22 => Self::NextBaseExperience(u64::from_bytes(byte_stream, None)?),
+            23 => Self::NextJobExperience(u64::from_bytes(byte_stream, None)?),
+            24 => Self::Weight(u32::from_bytes(byte_stream, None)?),
+            25 => Self::MaximumWeight(u32::from_bytes(byte_stream, None)?),
+            32 => Self::SpUstr(u8::from_bytes(byte_stream, None)?),
+            33 => Self::SpUagi(u8::from_bytes(byte_stream, None)?),
+            34 => Self::SpUvit(u8::from_bytes(byte_stream, None)?),
+            35 => Self::SpUint(u8::from_bytes(byte_stream, None)?),
+            36 => Self::SpUdex(u8::from_bytes(byte_stream, None)?),
+            37 => Self::SpUluk(u8::from_bytes(byte_stream, None)?),
+            41 => Self::Attack1(u32::from_bytes(byte_stream, None)?),
+            42 => Self::Attack2(u32::from_bytes(byte_stream, None)?),
+            43 => Self::MagicAttack1(u32::from_bytes(byte_stream, None)?),
+            44 => Self::MagicAttack2(u32::from_bytes(byte_stream, None)?),
+            45 => Self::Defense1(u32::from_bytes(byte_stream, None)?),
+            46 => Self::Defense2(u32::from_bytes(byte_stream, None)?),
+            47 => Self::MagicDefense1(u32::from_bytes(byte_stream, None)?),
+            48 => Self::MagicDefense2(u32::from_bytes(byte_stream, None)?),
+            49 => Self::Hit(u32::from_bytes(byte_stream, None)?),
+            50 => Self::Flee1(u32::from_bytes(byte_stream, None)?),
+            51 => Self::Flee2(u32::from_bytes(byte_stream, None)?),
+            52 => Self::Critical(u32::from_bytes(byte_stream, None)?),
+            53 => Self::AttackSpeed(u32::from_bytes(byte_stream, None)?),
+            55 => Self::JobLevel(u32::from_bytes(byte_stream, None)?),
             99 => Self::CartInfo(
-                u16::from_bytes(&mut byte_stream, None),
-                u32::from_bytes(&mut byte_stream, None),
-                u32::from_bytes(&mut byte_stream, None),
+                u16::from_bytes(byte_stream, None)?,
+                u32::from_bytes(byte_stream, None)?,
+                u32::from_bytes(byte_stream, None)?,
             ),
-            219 => Self::Power(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            220 => Self::Stamina(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            221 => Self::Wisdom(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            222 => Self::Spell(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            223 => Self::Concentration(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            224 => Self::Creativity(u32::from_bytes(&mut byte_stream, None), u32::from_bytes(&mut byte_stream, None)),
-            225 => Self::PhysicalAttack(u32::from_bytes(&mut byte_stream, None)),
-            226 => Self::SpellMagicAttack(u32::from_bytes(&mut byte_stream, None)),
-            227 => Self::Resistance(u32::from_bytes(&mut byte_stream, None)),
-            228 => Self::MagicResistance(u32::from_bytes(&mut byte_stream, None)),
-            229 => Self::HealingPlus(u32::from_bytes(&mut byte_stream, None)),
-            230 => Self::CriticalDamageRate(u32::from_bytes(&mut byte_stream, None)),
-            231 => Self::TraitPoint(u32::from_bytes(&mut byte_stream, None)),
-            232 => Self::ActivityPoints(u32::from_bytes(&mut byte_stream, None)),
-            233 => Self::MaximumActivityPoints(u32::from_bytes(&mut byte_stream, None)),
-            247 => Self::SpUpow(u8::from_bytes(&mut byte_stream, None)),
-            248 => Self::SpUsta(u8::from_bytes(&mut byte_stream, None)),
-            249 => Self::SpUwis(u8::from_bytes(&mut byte_stream, None)),
-            250 => Self::SpUspl(u8::from_bytes(&mut byte_stream, None)),
-            251 => Self::SpUcon(u8::from_bytes(&mut byte_stream, None)),
-            252 => Self::SpUcrt(u8::from_bytes(&mut byte_stream, None)),
-            invalid => panic!("invalid status code {invalid}"),
-        }
+            219 => Self::Power(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            220 => Self::Stamina(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            221 => Self::Wisdom(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            222 => Self::Spell(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            223 => Self::Concentration(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            224 => Self::Creativity(u32::from_bytes(byte_stream, None)?, u32::from_bytes(byte_stream, None)?),
+            225 => Self::PhysicalAttack(u32::from_bytes(byte_stream, None)?),
+            226 => Self::SpellMagicAttack(u32::from_bytes(byte_stream, None)?),
+            227 => Self::Resistance(u32::from_bytes(byte_stream, None)?),
+            228 => Self::MagicResistance(u32::from_bytes(byte_stream, None)?),
+            229 => Self::HealingPlus(u32::from_bytes(byte_stream, None)?),
+            230 => Self::CriticalDamageRate(u32::from_bytes(byte_stream, None)?),
+            231 => Self::TraitPoint(u32::from_bytes(byte_stream, None)?),
+            232 => Self::ActivityPoints(u32::from_bytes(byte_stream, None)?),
+            233 => Self::MaximumActivityPoints(u32::from_bytes(byte_stream, None)?),
+            247 => Self::SpUpow(u8::from_bytes(byte_stream, None)?),
+            248 => Self::SpUsta(u8::from_bytes(byte_stream, None)?),
+            249 => Self::SpUwis(u8::from_bytes(byte_stream, None)?),
+            250 => Self::SpUspl(u8::from_bytes(byte_stream, None)?),
+            251 => Self::SpUcon(u8::from_bytes(byte_stream, None)?),
+            252 => Self::SpUcrt(u8::from_bytes(byte_stream, None)?),
+            value => {
+                let kind = ConversionErrorKind::InvalidEnumValue {
+                    type_name: "StatusType",
+                    value: value as usize,
+                };
+                return Err(ConversionError::from_kind(0, kind));
+            }
+        };
+
+        Ok(status_type)
+    }
+
+    fn write_status(&self) -> Vec<u8> {
+        let (status_id, value): (u16, Vec<u8>) = match self {
+            Self::MovementSpeed(value) => (0, value.to_bytes(None)),
+            Self::BaseExperience(value) => (1, value.to_bytes(None)),
+            Self::JobExperience(value) => (2, value.to_bytes(None)),
+            Self::Karma(value) => (3, value.to_bytes(None)),
+            Self::Manner(value) => (4, value.to_bytes(None)),
+            Self::HealthPoints(value) => (5, value.to_bytes(None)),
+            Self::MaximumHealthPoints(value) => (6, value.to_bytes(None)),
+            Self::SpellPoints(value) => (7, value.to_bytes(None)),
+            Self::MaximumSpellPoints(value) => (8, value.to_bytes(None)),
+            Self::StatusPoint(value) => (9, value.to_bytes(None)),
+            Self::BaseLevel(value) => (11, value.to_bytes(None)),
+            Self::SkillPoint(value) => (12, value.to_bytes(None)),
+            Self::Strength(value, bonus) => (13, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Agility(value, bonus) => (14, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Vitality(value, bonus) => (15, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Intelligence(value, bonus) => (16, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Dexterity(value, bonus) => (17, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Luck(value, bonus) => (18, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Zeny(value) => (20, value.to_bytes(None)),
+            Self::NextBaseExperience(value) => (22, value.to_bytes(None)),
+            Self::NextJobExperience(value) => (23, value.to_bytes(None)),
+            Self::Weight(value) => (24, value.to_bytes(None)),
+            Self::MaximumWeight(value) => (25, value.to_bytes(None)),
+            Self::SpUstr(value) => (32, value.to_bytes(None)),
+            Self::SpUagi(value) => (33, value.to_bytes(None)),
+            Self::SpUvit(value) => (34, value.to_bytes(None)),
+            Self::SpUint(value) => (35, value.to_bytes(None)),
+            Self::SpUdex(value) => (36, value.to_bytes(None)),
+            Self::SpUluk(value) => (37, value.to_bytes(None)),
+            Self::Attack1(value) => (41, value.to_bytes(None)),
+            Self::Attack2(value) => (42, value.to_bytes(None)),
+            Self::MagicAttack1(value) => (43, value.to_bytes(None)),
+            Self::MagicAttack2(value) => (44, value.to_bytes(None)),
+            Self::Defense1(value) => (45, value.to_bytes(None)),
+            Self::Defense2(value) => (46, value.to_bytes(None)),
+            Self::MagicDefense1(value) => (47, value.to_bytes(None)),
+            Self::MagicDefense2(value) => (48, value.to_bytes(None)),
+            Self::Hit(value) => (49, value.to_bytes(None)),
+            Self::Flee1(value) => (50, value.to_bytes(None)),
+            Self::Flee2(value) => (51, value.to_bytes(None)),
+            Self::Critical(value) => (52, value.to_bytes(None)),
+            Self::AttackSpeed(value) => (53, value.to_bytes(None)),
+            Self::JobLevel(value) => (55, value.to_bytes(None)),
+            Self::CartInfo(first, second, third) => (99, [first.to_bytes(None), second.to_bytes(None), third.to_bytes(None)].concat()),
+            Self::Power(value, bonus) => (219, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Stamina(value, bonus) => (220, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Wisdom(value, bonus) => (221, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Spell(value, bonus) => (222, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Concentration(value, bonus) => (223, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::Creativity(value, bonus) => (224, [value.to_bytes(None), bonus.to_bytes(None)].concat()),
+            Self::PhysicalAttack(value) => (225, value.to_bytes(None)),
+            Self::SpellMagicAttack(value) => (226, value.to_bytes(None)),
+            Self::Resistance(value) => (227, value.to_bytes(None)),
+            Self::MagicResistance(value) => (228, value.to_bytes(None)),
+            Self::HealingPlus(value) => (229, value.to_bytes(None)),
+            Self::CriticalDamageRate(value) => (230, value.to_bytes(None)),
+            Self::TraitPoint(value) => (231, value.to_bytes(None)),
+            Self::ActivityPoints(value) => (232, value.to_bytes(None)),
+            Self::MaximumActivityPoints(value) => (233, value.to_bytes(None)),
+            Self::SpUpow(value) => (247, value.to_bytes(None)),
+            Self::SpUsta(value) => (248, value.to_bytes(None)),
+            Self::SpUwis(value) => (249, value.to_bytes(None)),
+            Self::SpUspl(value) => (250, value.to_bytes(None)),
+            Self::SpUcon(value) => (251, value.to_bytes(None)),
+            Self::SpUcrt(value) => (252, value.to_bytes(None)),
+        };
+
+        let mut bytes = status_id.to_bytes(None);
+        bytes.extend(value);
+        bytes
     }
 }
 
@@ -1081,6 +1428,10 @@ impl PrototypeElement for StatusType {
     fn to_element(&self, display: String) -> ElementCell {
         format!("{self:?}").to_element(display)
     }
+
+    fn write_text(&self, display: &str, indentation: usize, text: &mut String) {
+        format!("{self:?}").write_text(display, indentation, text)
+    }
 }
 
 #[derive(Clone, Debug, Packet, PrototypeElement)]
@@ -1172,7 +1523,7 @@ struct SwitchCharacterSlotPacket {
     pub remaining_moves: u16,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum Action {
     Attack,
     PickUpItem,
@@ -1195,6 +1546,7 @@ struct RequestActionPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement, new)]
 #[header(0x00f3)]
 struct GlobalMessagePacket {
+    #[packet_length]
     pub packet_length: u16,
     pub message: String,
 }
@@ -1250,7 +1602,7 @@ struct RequestServerTickPacket {
     pub client_tick: ClientTick,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, PartialEq, Eq, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 pub enum SwitchCharacterSlotResponseStatus {
     Success,
@@ -1273,7 +1625,7 @@ struct ChangeMapPacket {
     pub position: Vector2<u16>,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum DissapearanceReason {
     OutOfSight,
     Died,
@@ -1292,6 +1644,7 @@ struct EntityDisappearedPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x09fd)]
 struct MovingEntityAppearedPacket {
+    #[packet_length]
     pub packet_length: u16,
     pub object_type: u8,
     pub entity_id: EntityId,
@@ -1334,6 +1687,7 @@ struct MovingEntityAppearedPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x09fe)]
 struct EntityAppearedPacket {
+    #[packet_length]
     pub packet_length: u16,
     pub object_type: u8,
     pub entity_id: EntityId,
@@ -1375,6 +1729,7 @@ struct EntityAppearedPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x09ff)]
 struct EntityAppeared2Packet {
+    #[packet_length]
     pub packet_length: u16,
     pub object_type: u8,
     pub entity_id: EntityId,
@@ -1537,7 +1892,7 @@ struct UpdateSkillTreePacket {
     pub skill_information: Vec<SkillInformation>,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 struct HotkeyData {
     pub is_skill: u8,
     pub skill_id: u32,
@@ -1583,7 +1938,7 @@ struct NavigateToMonsterPacket {
     pub target_monster_id: u16,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u32)]
 enum MarkerType {
     DisplayFor15Seconds,
@@ -1616,6 +1971,7 @@ struct CloseButtonPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x00b7)]
 struct DialogMenuPacket {
+    #[packet_length]
     pub packet_length: u16,
     pub entity_id: EntityId,
     #[length_hint(self.packet_length - 8)]
@@ -1651,7 +2007,7 @@ struct DisplaySkillEffectAndDamagePacket {
     pub skill_type: u8,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 enum HealType {
     #[numeric_value(5)]
@@ -1669,14 +2025,24 @@ struct DisplayPlayerHealEffect {
 
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x09cb)]
+#[header_smaller(20130731, 0x011a)]
 struct DisplaySkillEffectNoDamagePacket {
     pub skill_id: SkillId,
-    pub heal_amount: u32,
+    #[packet_version_smaller(20130731)]
+    pub legacy_heal_amount: Option<u16>,
+    #[packet_version_equals_or_above(20130731)]
+    pub heal_amount: Option<u32>,
     pub destination_entity_id: EntityId,
     pub source_entity_id: EntityId,
     pub result: u8,
 }
 
+impl DisplaySkillEffectNoDamagePacket {
+    pub fn heal_amount(&self) -> u32 {
+        self.heal_amount.or(self.legacy_heal_amount.map(u32::from)).unwrap_or_default()
+    }
+}
+
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x0983)]
 struct StatusChangePacket {
@@ -1688,7 +2054,7 @@ struct StatusChangePacket {
     pub value: [u32; 3],
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 struct ObjectiveDetails1 {
     pub hunt_identification: u32,
     pub objective_type: u32,
@@ -1781,7 +2147,7 @@ struct QuestListPacket {
     pub quests: Vec<Quest>,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u32)]
 enum VisualEffect {
     BaseLevelUp,
@@ -1803,7 +2169,7 @@ struct VisualEffectPacket {
     pub effect: VisualEffect,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 enum ExperienceType {
     #[numeric_value(1)]
@@ -1811,7 +2177,7 @@ enum ExperienceType {
     JobExperience,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 enum ExperienceSource {
     Regular,
@@ -1820,14 +2186,18 @@ enum ExperienceSource {
 
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x0acc)]
+#[header_smaller(20170830, 0x07f6)]
 struct DisplayGainedExperiencePacket {
     pub account_id: AccountId,
-    pub amount: u64,
+    #[packet_version_smaller(20170830)]
+    pub legacy_amount: Option<u32>,
+    #[packet_version_equals_or_above(20170830)]
+    pub amount: Option<u64>,
     pub experience_type: ExperienceType,
     pub experience_source: ExperienceSource,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum ImageLocation {
     BottomLeft,
     BottomMiddle,
@@ -1856,28 +2226,74 @@ struct StateChangePacket {
     pub is_pk_mode_on: u8,
 }
 
+/// Item ids and cards are 32 bits wide starting with the renewal client of
+/// 20180704.
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x0b41)]
+#[header_smaller(20061218, 0x00a0)]
+#[header_smaller(20071002, 0x029a)]
+#[header_smaller(20120925, 0x02d4)]
+#[header_smaller(20150226, 0x0990)]
+#[header_smaller(20160921, 0x0a0c)]
+#[header_smaller(20200724, 0x0a37)]
 struct ItemPickupPacket {
     pub index: ItemIndex,
     pub count: u16,
-    pub item_id: ItemId,
+    #[packet_version_smaller(20180704)]
+    pub legacy_item_id: Option<u16>,
+    #[packet_version_equals_or_above(20180704)]
+    pub item_id: Option<ItemId>,
     pub is_identified: u8,
     pub is_broken: u8,
-    pub cards: [u32; 4],
-    pub equip_position: EquipPosition,
+    #[packet_version_smaller(20200724)]
+    pub legacy_refinement_level: Option<u8>,
+    #[packet_version_smaller(20180704)]
+    pub legacy_cards: Option<[u16; 4]>,
+    #[packet_version_equals_or_above(20180704)]
+    pub cards: Option<[u32; 4]>,
+    #[packet_version_smaller(20120925)]
+    pub legacy_equip_position: Option<u16>,
+    #[packet_version_equals_or_above(20120925)]
+    pub equip_position: Option<EquipPosition>,
     pub item_type: u8,
     pub result: u8,
-    pub hire_expiration_date: u32,
-    pub bind_on_equip_type: u16,
-    pub option_data: [ItemOptions; 5], // fix count
-    pub favorite: u8,
-    pub look: u16,
-    pub refinement_level: u8,
-    pub enchantment_level: u8,
+    #[packet_version_equals_or_above(20061218)]
+    pub hire_expiration_date: Option<u32>,
+    #[packet_version_equals_or_above(20071002)]
+    pub bind_on_equip_type: Option<u16>,
+    #[packet_version_equals_or_above(20150226)]
+    pub option_data: Option<[ItemOptions; 5]>, // fix count
+    #[packet_version_equals_or_above(20160921)]
+    pub favorite: Option<u8>,
+    #[packet_version_equals_or_above(20160921)]
+    pub look: Option<u16>,
+    #[packet_version_equals_or_above(20200724)]
+    pub refinement_level: Option<u8>,
+    #[packet_version_equals_or_above(20200724)]
+    pub enchantment_level: Option<u8>,
+}
+
+impl ItemPickupPacket {
+    pub fn item_id(&self) -> ItemId {
+        self.item_id
+            .or(self.legacy_item_id.map(|item_id| ItemId(u32::from(item_id))))
+            .unwrap_or(ItemId(0))
+    }
+
+    pub fn equip_position(&self) -> EquipPosition {
+        match (self.equip_position, self.legacy_equip_position) {
+            (Some(equip_position), _) => equip_position,
+            // Older versions send the same flags in 16 bits.
+            (None, Some(equip_position)) => {
+                let bytes = u32::from(equip_position).to_le_bytes();
+                EquipPosition::from_bytes(&mut ByteStream::new(&bytes), None).unwrap_or(EquipPosition::None)
+            }
+            (None, None) => EquipPosition::None,
+        }
+    }
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 enum RemoveItemReason {
     Normal,
@@ -1899,7 +2315,7 @@ struct RemoveItemFromInventoryPacket {
 }
 
 // TODO: improve names
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 pub enum QuestEffect {
     Quest,
@@ -1917,7 +2333,7 @@ pub enum QuestEffect {
     None,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 pub enum QuestColor {
     Yellow,
@@ -1938,6 +2354,7 @@ pub struct QuestEffectPacket {
 #[derive(Clone, Debug, Packet, PrototypeElement)]
 #[header(0x00b4)]
 struct NpcDialogPacket {
+    #[packet_length]
     pub packet_length: u16,
     pub npc_id: EntityId,
     #[length_hint(self.packet_length - 8)]
@@ -2085,7 +2502,7 @@ struct RequestEquipItemPacket {
     pub equip_position: EquipPosition,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum RequestEquipItemStatus {
     Success,
     Failed,
@@ -2107,7 +2524,7 @@ struct RequestUnequipItemPacket {
     pub inventory_index: ItemIndex,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum RequestUnequipItemStatus {
     Success,
     Failed,
@@ -2121,7 +2538,7 @@ struct RequestUnequipItemStatusPacket {
     pub result: RequestUnequipItemStatus,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum RestartType {
     Respawn,
     Disconnect,
@@ -2135,7 +2552,7 @@ struct RestartPacket {
 
 // TODO: check that this can be only 1 and 0, if not ByteConvertable should be
 // implemented manually
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement, PartialEq, Eq)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement, PartialEq, Eq)]
 enum RestartResponseStatus {
     Nothing,
     Ok,
@@ -2149,7 +2566,7 @@ struct RestartResponsePacket {
 
 // TODO: check that this can be only 1 and 0, if not ByteConvertable should be
 // implemented manually
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement, PartialEq, Eq)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement, PartialEq, Eq)]
 #[numeric_type(u16)]
 enum DisconnectResponseStatus {
     Ok,
@@ -2216,7 +2633,7 @@ struct ToUseSkillSuccessPacket {
     pub cause: u8,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u32)]
 pub enum UnitId {
     #[numeric_value(0x7e)]
@@ -2483,7 +2900,7 @@ struct FriendListPacket {
     pub friends: Vec<Friend>,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 enum OnlineState {
     Online,
     Offline,
@@ -2505,7 +2922,7 @@ struct FriendRequestPacket {
     pub friend: Friend,
 }
 
-#[derive(Clone, Debug, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u32)]
 enum FriendRequestResponse {
     Reject,
@@ -2520,7 +2937,7 @@ struct FriendRequestResponsePacket {
     pub response: FriendRequestResponse,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, ByteConvertable, PrototypeElement)]
+#[derive(Clone, Debug, PartialEq, Eq, ByteConvertable, FixedByteSize, PrototypeElement)]
 #[numeric_type(u16)]
 enum FriendRequestResult {
     Accepted,
@@ -2629,11 +3046,19 @@ impl Packet for UnknownPacket {
     const IS_PING: bool = false;
     const PACKET_NAME: &'static str = "^ff8030Unknown^000000";
 
-    fn header() -> u16 {
+    fn header(_packet_version: PacketVersion) -> u16 {
         unimplemented!()
     }
 
-    fn to_bytes(&self) -> Vec<u8> {
+    fn length(_packet_version: PacketVersion) -> PacketLength {
+        unimplemented!()
+    }
+
+    fn try_from_bytes(_byte_stream: &mut ByteStream) -> Result<Self, ConversionError> {
+        unimplemented!()
+    }
+
+    fn to_bytes(&self, _packet_version: PacketVersion) -> Vec<u8> {
         unimplemented!()
     }
 }
@@ -2644,7 +3069,7 @@ impl PrototypeElement for UnknownPacket {
 
         let elements = match self.bytes.len() > 2 {
             true => {
-                let signature = u16::from_bytes(&mut byte_stream, None);
+                let signature = u16::from_bytes(&mut byte_stream, None).unwrap();
                 let header = format!("0x{:0>4x}", signature);
                 let data = &self.bytes[byte_stream.get_offset()..];
 
@@ -2660,45 +3085,104 @@ impl PrototypeElement for UnknownPacket {
 }
 
 #[derive(new)]
-struct NetworkTimer {
-    period: Duration,
-    #[new(default)]
-    accumulator: Duration,
+struct LoginData {
+    pub account_id: AccountId,
+    pub login_id1: u32,
+    pub sex: Sex,
 }
 
-impl NetworkTimer {
-    pub fn update(&mut self, elapsed_time: f64) -> bool {
-        self.accumulator += Duration::from_secs_f64(elapsed_time);
-        let reset = self.accumulator > self.period;
+/// How long the servers may take to answer a request, including all of the
+/// steps that it takes, before the request fails.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
 
-        if reset {
-            self.accumulator -= self.period;
-        }
+/// A request to the login or character server that is waiting for its
+/// response. Since the servers answer in order, only one request may be
+/// pending at a time.
+enum PendingRequest {
+    LogIn {
+        username: String,
+        password: String,
+    },
+    /// Waiting for the connection to the character server, which is logged
+    /// into with the packet once it is established.
+    ConnectToCharacterServer(CharacterServerLoginPacket),
+    CharacterServerLogin,
+    CharacterList,
+    CreateCharacter,
+    DeleteCharacter(CharacterId),
+    SelectCharacter(usize),
+    /// Waiting for the connection to the map server.
+    ConnectToMapServer {
+        slot: usize,
+        character_selection_success_packet: CharacterSelectionSuccessPacket,
+    },
+    SwitchCharacterSlot,
+    /// The character server sends the updated character list on its own after
+    /// switching slots.
+    SwitchedCharacterSlot,
+}
 
-        reset
+impl PendingRequest {
+    fn get_server(&self) -> ServerKind {
+        match self {
+            PendingRequest::LogIn { .. } => ServerKind::Login,
+            PendingRequest::ConnectToMapServer { .. } => ServerKind::Map,
+            _ => ServerKind::Character,
+        }
     }
 }
 
-#[derive(new)]
-struct LoginData {
-    pub account_id: AccountId,
-    pub login_id1: u32,
-    pub sex: Sex,
+type FriendList = TrackedState<Vec<(Friend, UnsafeCell<Option<WeakElementCell>>)>>;
+
+/// Runs data through the same decoding as the data received from the map
+/// server. Used to fuzz the packet decoding.
+pub fn decode_map_server_data(data: &[u8], packet_version: PacketVersion) -> Vec<NetworkEvent> {
+    let mut byte_stream = ByteStream::new(data);
+    let mut map_server_state = MapServerState::default();
+    let mut events = Vec::new();
+
+    map_server_handler(packet_version).handle_all(&mut byte_stream, &mut map_server_state, &mut events);
+    events
+}
+
+fn get_packet_lengths(
+    login_server_handler: &PacketHandler<(), LoginServerResponse>,
+    character_server_handler: &PacketHandler<(), CharacterServerResponse>,
+    map_server_handler: &PacketHandler<MapServerState, NetworkEvent>,
+) -> HashMap<ServerKind, PacketLengths> {
+    HashMap::from([
+        (ServerKind::Login, login_server_handler.get_packet_lengths()),
+        (ServerKind::Character, character_server_handler.get_packet_lengths()),
+        (ServerKind::Map, map_server_handler.get_packet_lengths()),
+    ])
 }
 
 pub struct NetworkingSystem {
     login_settings: LoginSettings,
-    login_stream: TcpStream,
-    character_stream: Option<TcpStream>,
-    map_stream: Option<TcpStream>,
+    servers: Vec<ServerSettings>,
+    /// Why the server list couldn't be loaded, if it is invalid.
+    server_list_error: Option<String>,
+    login_server: ServerSettings,
+    /// The archives of the server that was selected on startup.
+    loaded_archives: Vec<String>,
+    login_server_backoff: Backoff,
+    command_sender: Sender<NetworkCommand>,
+    event_receiver: Receiver<ConnectionEvent>,
+    /// Events for commands that couldn't be passed to the network thread.
+    failed_command_events: Vec<ConnectionEvent>,
+    connected_servers: HashSet<ServerKind>,
+    pending_request: Option<PendingRequest>,
+    /// When the pending request fails if the servers didn't answer it yet.
+    request_deadline: Instant,
     login_data: Option<LoginData>,
     characters: TrackedState<Vec<CharacterInformation>>,
     move_request: TrackedState<Option<usize>>,
-    friend_list: TrackedState<Vec<(Friend, UnsafeCell<Option<WeakElementCell>>)>>,
+    packet_version: PacketVersion,
+    login_server_handler: PacketHandler<(), LoginServerResponse>,
+    character_server_handler: PacketHandler<(), CharacterServerResponse>,
+    map_server_handler: PacketHandler<MapServerState, NetworkEvent>,
+    map_server_state: MapServerState,
     slot_count: usize,
-    login_keep_alive_timer: NetworkTimer,
-    character_keep_alive_timer: NetworkTimer,
-    map_keep_alive_timer: NetworkTimer,
     player_name: String,
     #[cfg(feature = "debug")]
     update_packets: TrackedState<bool>,
@@ -2707,191 +3191,225 @@ pub struct NetworkingSystem {
 }
 
 impl NetworkingSystem {
-    pub fn new() -> Self {
-        let login_server_ip = match cfg!(feature = "local") {
-            true => "127.0.0.1:6900",
-            false => "49.12.109.207:6900",
+    /// Fails if the capture can't be created or loaded.
+    pub fn new(capture_mode: CaptureMode) -> std::io::Result<Self> {
+        let mut login_settings = LoginSettings::new();
+        let (servers, server_list_error) = load_servers();
+        let login_server = servers
+            .iter()
+            .find(|server| login_settings.server_name.as_ref() == Some(&server.name))
+            .unwrap_or(&servers[0])
+            .clone();
+        login_settings.server_name = Some(login_server.name.clone());
+        let capture = match &capture_mode {
+            CaptureMode::Replay(path) => Some(Capture::load(path)?),
+            CaptureMode::Disabled | CaptureMode::Record(..) => None,
         };
+        let packet_version = capture
+            .as_ref()
+            .map(|capture| capture.packet_version)
+            .unwrap_or(login_server.packet_version);
+
+        let mut networking_system =
+            Self::with_connection(login_settings, servers, login_server, packet_version, |packet_lengths| {
+                match (capture_mode, capture) {
+                    (CaptureMode::Record(path), _) => spawn_network_thread(packet_version, packet_lengths, Some(&path)),
+                    (_, Some(capture)) => Ok(spawn_replay_thread(capture.packet_version, capture.records)),
+                    _ => spawn_network_thread(packet_version, packet_lengths, None),
+                }
+            })?;
 
-        let login_settings = LoginSettings::new();
-        let login_stream = TcpStream::connect(login_server_ip).expect("failed to connect to login server");
+        networking_system.server_list_error = server_list_error;
+        Ok(networking_system)
+    }
 
-        let character_stream = None;
-        let map_stream = None;
+    /// `connect` starts the thread that exchanges packets with the servers,
+    /// given the packet lengths of every server.
+    fn with_connection(
+        login_settings: LoginSettings,
+        servers: Vec<ServerSettings>,
+        login_server: ServerSettings,
+        packet_version: PacketVersion,
+        connect: impl FnOnce(HashMap<ServerKind, PacketLengths>) -> std::io::Result<(Sender<NetworkCommand>, Receiver<ConnectionEvent>)>,
+    ) -> std::io::Result<Self> {
+        let server_list_error = None;
+        let loaded_archives = login_server.archives.clone();
+        let login_server_backoff = Backoff::default();
+        let failed_command_events = Vec::new();
+        let connected_servers = HashSet::new();
+        let pending_request = None;
+        let request_deadline = Instant::now();
         let login_data = None;
         let characters = TrackedState::default();
         let move_request = TrackedState::default();
-        let friend_list = TrackedState::default();
+        let login_server_handler = login_server_handler(packet_version);
+        let character_server_handler = character_server_handler(packet_version);
+        let map_server_handler = map_server_handler(packet_version);
+        let map_server_state = MapServerState::default();
         let slot_count = 0;
-        let login_keep_alive_timer = NetworkTimer::new(Duration::from_secs(58));
-        let character_keep_alive_timer = NetworkTimer::new(Duration::from_secs(10));
-        let map_keep_alive_timer = NetworkTimer::new(Duration::from_secs(4));
         let player_name = String::new();
         #[cfg(feature = "debug")]
         let update_packets = TrackedState::new(true);
         #[cfg(feature = "debug")]
         let packet_history = TrackedState::default();
 
-        login_stream.set_read_timeout(Duration::from_secs(1).into()).unwrap();
+        let packet_lengths = get_packet_lengths(&login_server_handler, &character_server_handler, &map_server_handler);
+        let (command_sender, event_receiver) = connect(packet_lengths)?;
 
-        Self {
+        let mut networking_system = Self {
             login_settings,
-            login_stream,
-            character_stream,
+            servers,
+            server_list_error,
+            login_server,
+            loaded_archives,
+            login_server_backoff,
+            command_sender,
+            event_receiver,
+            failed_command_events,
+            connected_servers,
+            pending_request,
+            request_deadline,
             slot_count,
             login_data,
-            map_stream,
             characters,
             move_request,
-            friend_list,
-            login_keep_alive_timer,
-            character_keep_alive_timer,
-            map_keep_alive_timer,
+            packet_version,
+            login_server_handler,
+            character_server_handler,
+            map_server_handler,
+            map_server_state,
             player_name,
             #[cfg(feature = "debug")]
             update_packets,
             #[cfg(feature = "debug")]
             packet_history,
-        }
-    }
+        };
 
-    pub fn get_login_settings(&self) -> &LoginSettings {
-        &self.login_settings
+        // If the login server can't be reached, the login window reports the
+        // error and the connection is retried in the background.
+        networking_system.connect_to_login_server();
+        Ok(networking_system)
     }
 
-    pub fn toggle_remember_username(&mut self) {
-        self.login_settings.remember_username = !self.login_settings.remember_username;
+    fn connect_to_login_server(&mut self) {
+        let command = NetworkCommand::Connect(ServerKind::Login, self.login_server.host.clone(), self.login_server.port);
+        self.send_command(command);
     }
 
-    pub fn toggle_remember_password(&mut self) {
-        self.login_settings.remember_password = !self.login_settings.remember_password;
+    /// The archives of the selected server, which have to be loaded with the
+    /// game archives.
+    pub fn get_archives(&self) -> &[String] {
+        &self.loaded_archives
     }
 
-    pub fn log_in(&mut self, username: String, password: String) -> Result<(), String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new("log in");
+    /// Replaces the packet handlers and tells the network thread about the
+    /// new packet lengths.
+    fn set_packet_version(&mut self, packet_version: PacketVersion) {
+        if packet_version == self.packet_version {
+            return;
+        }
 
-        self.send_packet_to_login_server(LoginServerLoginPacket::new(username.clone(), password.clone()));
+        self.packet_version = packet_version;
+        self.login_server_handler = login_server_handler(packet_version);
+        self.character_server_handler = character_server_handler(packet_version);
+        self.map_server_handler = map_server_handler(packet_version);
 
-        let response = self.get_data_from_login_server();
-        let mut byte_stream = ByteStream::new(&response);
+        let packet_lengths = get_packet_lengths(
+            &self.login_server_handler,
+            &self.character_server_handler,
+            &self.map_server_handler,
+        );
+        self.send_command(NetworkCommand::SetPacketVersion(packet_version, packet_lengths));
+    }
 
-        if let Ok(login_failed_packet) = LoginFailedPacket::try_from_bytes(&mut byte_stream) {
-            match login_failed_packet.reason {
-                LoginFailedReason::ServerClosed => return Err("server closed".to_string()),
-                LoginFailedReason::AlreadyLoggedIn => return Err("someone has already logged in with this id".to_string()),
-                LoginFailedReason::AlreadyOnline => return Err("already online".to_string()),
-            }
+    /// Switches to a different login server. Only possible before the player
+    /// logged in.
+    pub fn select_server(&mut self, name: &str) -> Result<(), String> {
+        if self.login_data.is_some() || self.pending_request.is_some() {
+            return Err("can't change the server while logging in".to_string());
         }
 
-        if let Ok(login_failed_packet) = LoginFailedPacket2::try_from_bytes(&mut byte_stream) {
-            match login_failed_packet.reason {
-                LoginFailedReason2::UnregisteredId => return Err("unregistered id".to_string()),
-                LoginFailedReason2::IncorrectPassword => return Err("incorrect password".to_string()),
-                LoginFailedReason2::IdExpired => return Err("id has expired".to_string()),
-                LoginFailedReason2::RejectedFromServer => return Err("rejected from server".to_string()),
-                LoginFailedReason2::BlockedByGMTeam => return Err("blocked by gm team".to_string()),
-                LoginFailedReason2::GameOutdated => return Err("game outdated".to_string()),
-                LoginFailedReason2::LoginProhibitedUntil => return Err("login prohibited until".to_string()),
-                LoginFailedReason2::ServerFull => return Err("server is full".to_string()),
-                LoginFailedReason2::CompanyAccountLimitReached => return Err("company account limit reached".to_string()),
-            }
+        if name == self.login_server.name {
+            return Ok(());
         }
 
-        let login_server_login_success_packet = LoginServerLoginSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-        self.login_data = LoginData::new(
-            login_server_login_success_packet.account_id,
-            login_server_login_success_packet.login_id1,
-            login_server_login_success_packet.sex,
-        )
-        .into();
-
-        let character_server_information = login_server_login_success_packet
-            .character_server_information
-            .into_iter()
-            .next()
-            .ok_or("no character server available")?;
+        let Some(server) = self.servers.iter().find(|server| server.name == name).cloned() else {
+            return Err(format!("unknown server {name}"));
+        };
 
-        let server_ip = IpAddr::V4(character_server_information.server_ip);
-        let socket_address = SocketAddr::new(server_ip, character_server_information.server_port);
-        self.character_stream = TcpStream::connect_timeout(&socket_address, Duration::from_secs(1))
-            .map_err(|_| "Failed to connect to character server. Please try again")?
-            .into();
+        self.set_packet_version(server.packet_version);
 
-        let character_server_login_packet = CharacterServerLoginPacket::new(
-            login_server_login_success_packet.account_id,
-            login_server_login_success_packet.login_id1,
-            login_server_login_success_packet.login_id2,
-            login_server_login_success_packet.sex,
-        );
+        self.login_settings.server_name = Some(server.name.clone());
+        self.login_server = server;
+        self.login_server_backoff.reset();
+        self.connected_servers.remove(&ServerKind::Login);
+        self.connect_to_login_server();
 
-        let character_stream = self.character_stream.as_mut().ok_or("no character server connection")?;
-        character_stream
-            .write_all(&character_server_login_packet.to_bytes())
-            .map_err(|_| "failed to send packet to character server")?;
+        match self.login_server.archives == self.loaded_archives {
+            true => Ok(()),
+            false => Err(format!("the archives of {name} are only loaded after restarting the client")),
+        }
+    }
 
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
+    pub fn get_login_settings(&self) -> &LoginSettings {
+        &self.login_settings
+    }
 
-        let response = self.get_data_from_character_server();
+    pub fn toggle_remember_username(&mut self) {
+        self.login_settings.remember_username = !self.login_settings.remember_username;
+    }
 
-        let mut byte_stream = ByteStream::new(&response);
-        let account_id = AccountId::from_bytes(&mut byte_stream, None);
-        assert_eq!(account_id, login_server_login_success_packet.account_id);
+    pub fn toggle_remember_password(&mut self) {
+        self.login_settings.remember_password = !self.login_settings.remember_password;
+    }
 
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
+    fn send_command(&mut self, command: NetworkCommand) {
+        // The network thread only stops if it panicked, in which case all
+        // connections are lost.
+        if let Err(SendError(NetworkCommand::Connect(server, ..) | NetworkCommand::Send(server, _))) = self.command_sender.send(command) {
+            let reason = format!("lost connection to {server}");
+            self.failed_command_events.push(ConnectionEvent::Disconnected(server, reason));
+        }
+    }
 
-        if let Ok(login_failed_packet) = LoginFailedPacket::try_from_bytes(&mut byte_stream) {
-            match login_failed_packet.reason {
-                LoginFailedReason::ServerClosed => return Err("server closed".to_string()),
-                LoginFailedReason::AlreadyLoggedIn => return Err("someone has already logged in with this id".to_string()),
-                LoginFailedReason::AlreadyOnline => return Err("already online".to_string()),
-            }
+    /// Starts a request to the login or character server. The response is
+    /// handled in [`network_events`](Self::network_events).
+    fn start_request(&mut self, request: PendingRequest) -> Result<(), String> {
+        if self.pending_request.is_some() {
+            return Err("still waiting for a response from the server".to_string());
         }
 
-        let character_server_login_success_packet = CharacterServerLoginSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
+        self.pending_request = Some(request);
+        self.request_deadline = Instant::now() + REQUEST_TIMEOUT;
+        Ok(())
+    }
 
-        self.send_packet_to_character_server(RequestCharacterListPacket::default());
+    pub fn log_in(&mut self, username: String, password: String) -> Result<(), String> {
+        if !self.connected_servers.contains(&ServerKind::Login) {
+            self.login_server_backoff.skip_delay();
+            return Err("not connected to the login server, please try again".to_string());
+        }
 
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
-
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
-
-        let request_character_list_success_packet = RequestCharacterListSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-        self.characters.set(request_character_list_success_packet.character_information);
-
-        self.login_settings.username = match self.login_settings.remember_username {
-            true => username,
-            // clear in case it was previously saved
-            false => String::new(),
-        };
-
-        self.login_settings.password = match self.login_settings.remember_password {
-            true => password,
-            // clear in case it was previously saved
-            false => String::new(),
-        };
-
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
-
-        self.slot_count = character_server_login_success_packet.normal_slot_count as usize;
-
-        #[cfg(feature = "debug")]
-        timer.stop();
+        self.start_request(PendingRequest::LogIn {
+            username: username.clone(),
+            password: password.clone(),
+        })?;
 
+        self.send_packet_to_login_server(LoginServerLoginPacket::new(username, password));
         Ok(())
     }
 
+    pub fn login_window(&self) -> LoginWindow {
+        let server_names = self.servers.iter().map(|server| server.name.clone()).collect();
+        LoginWindow::new(self.login_settings.clone(), server_names, self.server_list_error.clone())
+    }
+
     pub fn character_selection_window(&self) -> CharacterSelectionWindow {
         CharacterSelectionWindow::new(self.characters.new_remote(), self.move_request.new_remote(), self.slot_count)
     }
 
     pub fn friends_window(&self) -> FriendsWindow {
-        FriendsWindow::new(self.friend_list.new_remote())
+        FriendsWindow::new(self.map_server_state.friend_list.new_remote())
     }
 
     pub fn log_out(&mut self) -> Result<(), String> {
@@ -2929,100 +3447,46 @@ impl NetworkingSystem {
         }
     }
 
-    fn send_packet_to_login_server<T>(&mut self, packet: T)
+    fn send_packet<T>(&mut self, server: ServerKind, packet: T)
     where
         T: Packet + 'static,
     {
         #[cfg(feature = "debug")]
         self.new_outgoing(&packet);
 
-        let packet_bytes = packet.to_bytes();
-        self.login_stream
-            .write_all(&packet_bytes)
-            .expect("failed to send packet to login server");
+        let packet_bytes = packet.to_bytes(self.packet_version);
+        self.send_command(NetworkCommand::Send(server, packet_bytes));
     }
 
-    fn send_packet_to_character_server<T>(&mut self, packet: T)
+    fn send_packet_to_login_server<T>(&mut self, packet: T)
     where
         T: Packet + 'static,
     {
-        #[cfg(feature = "debug")]
-        self.new_outgoing(&packet);
-
-        let packet_bytes = packet.to_bytes();
-        let character_stream = self.character_stream.as_mut().expect("no character server connection");
-        character_stream
-            .write_all(&packet_bytes)
-            .expect("failed to send packet to character server");
+        self.send_packet(ServerKind::Login, packet);
     }
 
-    fn send_packet_to_map_server<T>(&mut self, packet: T)
+    fn send_packet_to_character_server<T>(&mut self, packet: T)
     where
         T: Packet + 'static,
     {
-        #[cfg(feature = "debug")]
-        self.new_outgoing(&packet);
-
-        let packet_bytes = packet.to_bytes();
-        let map_stream = self.map_stream.as_mut().expect("no map server connection");
-        map_stream.write_all(&packet_bytes).expect("failed to send packet to map server");
-    }
-
-    fn get_data_from_login_server(&mut self) -> Vec<u8> {
-        let mut buffer = [0; 4096];
-        let response_length = self
-            .login_stream
-            .read(&mut buffer)
-            .expect("failed to get response from login server");
-        buffer[..response_length].to_vec()
+        self.send_packet(ServerKind::Character, packet);
     }
 
-    fn get_data_from_character_server(&mut self) -> Vec<u8> {
-        let mut buffer = [0; 4096];
-        let character_stream = self.character_stream.as_mut().expect("no character server connection");
-        let response_length = character_stream
-            .read(&mut buffer)
-            .expect("failed to get response from character server");
-        buffer[..response_length].to_vec()
-    }
-
-    fn try_get_data_from_map_server(&mut self) -> Option<Vec<u8>> {
-        let mut buffer = [0; 8096];
-        let map_stream = self.map_stream.as_mut()?;
-        let response_length = map_stream.read(&mut buffer).ok()?;
-
-        match response_length {
-            // TODO: make sure this will always work
-            1400 => {
-                let mut first_buffer = buffer[..response_length].to_vec();
-                let mut second_buffer = self.try_get_data_from_map_server().unwrap();
-                first_buffer.append(&mut second_buffer);
-
-                println!("combined {}", first_buffer.len());
-                Some(first_buffer)
-            }
-            length => Some(buffer[..length].to_vec()),
-        }
+    fn send_packet_to_map_server<T>(&mut self, packet: T)
+    where
+        T: Packet + 'static,
+    {
+        self.send_packet(ServerKind::Map, packet);
     }
 
-    pub fn keep_alive(&mut self, delta_time: f64, client_tick: ClientTick) {
-        if self.login_keep_alive_timer.update(delta_time) {
-            self.send_packet_to_login_server(LoginServerKeepalivePacket::default());
-        }
-
-        if self.character_keep_alive_timer.update(delta_time) && self.character_stream.is_some() {
-            self.send_packet_to_character_server(CharacterServerKeepalivePacket::new());
-        }
-
-        if self.map_keep_alive_timer.update(delta_time) && self.map_stream.is_some() {
-            self.send_packet_to_map_server(RequestServerTickPacket::new(client_tick));
-        }
+    /// Keepalives are sent by the network thread, so they continue while the
+    /// main thread is busy. The map server keepalive needs the current client
+    /// tick.
+    pub fn set_client_tick(&mut self, client_tick: ClientTick) {
+        self.send_command(NetworkCommand::SetClientTick(client_tick));
     }
 
     pub fn create_character(&mut self, slot: usize, name: String) -> Result<(), String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new("create character");
-
         #[cfg(feature = "debug")]
         print_debug!(
             "character with name {}{}{} in slot {}{}{}",
@@ -3034,6 +3498,8 @@ impl NetworkingSystem {
             NONE
         );
 
+        self.start_request(PendingRequest::CreateCharacter)?;
+
         let hair_color = 0;
         let hair_style = 0;
         let start_job = 0;
@@ -3043,37 +3509,10 @@ impl NetworkingSystem {
             name, slot as u8, hair_color, hair_style, start_job, sex,
         ));
 
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
-
-        if let Ok(character_creation_failed_packet) = CharacterCreationFailedPacket::try_from_bytes(&mut byte_stream) {
-            match character_creation_failed_packet.reason {
-                CharacterCreationFailedReason::CharacterNameAlreadyUsed => return Err("character name is already used".to_string()),
-                CharacterCreationFailedReason::NotOldEnough => return Err("you are not old enough to create a character".to_string()),
-                CharacterCreationFailedReason::NotAllowedToUseSlot => {
-                    return Err("you are not allowed to use that character slot".to_string());
-                }
-                CharacterCreationFailedReason::CharacterCerationFailed => return Err("character creation failed".to_string()),
-            }
-        }
-
-        let create_character_success_packet = CreateCharacterSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
-
-        self.characters.push(create_character_success_packet.character_information);
-
-        #[cfg(feature = "debug")]
-        timer.stop();
-
         Ok(())
     }
 
     pub fn delete_character(&mut self, character_id: CharacterId) -> Result<(), String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new("delete character");
-
         let email = "a@a.com".to_string();
 
         #[cfg(feature = "debug")]
@@ -3087,122 +3526,25 @@ impl NetworkingSystem {
             NONE
         );
 
+        self.start_request(PendingRequest::DeleteCharacter(character_id))?;
         self.send_packet_to_character_server(DeleteCharacterPacket::new(character_id, email));
 
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
-
-        if let Ok(character_creation_failed_packet) = CharacterDeletionFailedPacket::try_from_bytes(&mut byte_stream) {
-            match character_creation_failed_packet.reason {
-                CharacterDeletionFailedReason::NotAllowed => return Err("you are not allowed to delete this character".to_string()),
-                CharacterDeletionFailedReason::CharacterNotFound => return Err("character was not found".to_string()),
-                CharacterDeletionFailedReason::NotEligible => return Err("character is not eligible for deletion".to_string()),
-            }
-        }
-
-        CharacterDeletionSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
-
-        self.characters.retain(|character| character.character_id != character_id);
-
-        #[cfg(feature = "debug")]
-        timer.stop();
-
         Ok(())
     }
 
-    pub fn select_character(&mut self, slot: usize) -> Result<(AccountId, CharacterInformation, String), String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new("select character");
-
+    pub fn select_character(&mut self, slot: usize) -> Result<(), String> {
         #[cfg(feature = "debug")]
         print_debug!("character in slot {}{}{}", MAGENTA, slot, NONE,);
 
+        self.start_request(PendingRequest::SelectCharacter(slot))?;
         self.send_packet_to_character_server(SelectCharacterPacket::new(slot as u8));
 
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
-
-        if let Ok(character_selection_failed_packet) = CharacterSelectionFailedPacket::try_from_bytes(&mut byte_stream) {
-            match character_selection_failed_packet.reason {
-                CharacterSelectionFailedReason::RejectedFromServer => return Err("rejected from server".to_string()),
-            }
-        }
-
-        if let Ok(login_failed_packet) = LoginFailedPacket::try_from_bytes(&mut byte_stream) {
-            match login_failed_packet.reason {
-                LoginFailedReason::ServerClosed => return Err("Server closed".to_string()),
-                LoginFailedReason::AlreadyLoggedIn => return Err("Someone has already logged in with this ID".to_string()),
-                LoginFailedReason::AlreadyOnline => return Err("Already online".to_string()),
-            }
-        }
-
-        if let Ok(_map_server_unavailable_packet) = MapServerUnavailablePacket::try_from_bytes(&mut byte_stream) {
-            return Err("Map server currently unavailable".to_string());
-        }
-
-        let character_selection_success_packet = CharacterSelectionSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-
-        let server_ip = IpAddr::V4(character_selection_success_packet.map_server_ip);
-        let server_port = character_selection_success_packet.map_server_port;
-
-        #[cfg(feature = "debug")]
-        print_debug!(
-            "connecting to map server at {}{}{} on port {}{}{}",
-            MAGENTA,
-            server_ip,
-            NONE,
-            MAGENTA,
-            character_selection_success_packet.map_server_port,
-            NONE
-        );
-
-        let socket_address = SocketAddr::new(server_ip, server_port);
-        let map_stream = TcpStream::connect_timeout(&socket_address, Duration::from_secs(1))
-            .map_err(|_| "Failed to connect to map server. Please try again")?;
-
-        map_stream.set_nonblocking(true).unwrap();
-        self.map_stream = Some(map_stream);
-
-        let login_data = self.login_data.as_ref().unwrap();
-        let account_id = login_data.account_id;
-
-        self.send_packet_to_map_server(MapServerLoginPacket::new(
-            account_id,
-            character_selection_success_packet.character_id,
-            login_data.login_id1,
-            ClientTick(100), // TODO: what is the logic here?
-            login_data.sex,
-        ));
-
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
-
-        let character_information = self
-            .characters
-            .borrow()
-            .iter()
-            .find(|character| character.character_number as usize == slot)
-            .cloned()
-            .unwrap();
-
-        self.player_name = character_information.name.clone();
-
-        #[cfg(feature = "debug")]
-        timer.stop();
-
-        Ok((
-            account_id,
-            character_information,
-            character_selection_success_packet.map_name.replace(".gat", ""),
-        ))
+        Ok(())
     }
 
     pub fn disconnect_from_map_server(&mut self) {
-        // Dropping the TcpStream will also close the connection.
-        self.map_stream = None;
+        self.connected_servers.remove(&ServerKind::Map);
+        self.send_command(NetworkCommand::Disconnect(ServerKind::Map));
     }
 
     pub fn request_switch_character_slot(&mut self, origin_slot: usize) {
@@ -3214,8 +3556,7 @@ impl NetworkingSystem {
     }
 
     pub fn switch_character_slot(&mut self, destination_slot: usize) -> Result<(), String> {
-        #[cfg(feature = "debug")]
-        let timer = Timer::new("switch character slot");
+        self.start_request(PendingRequest::SwitchCharacterSlot)?;
 
         let origin_slot = self.move_request.take().unwrap();
 
@@ -3232,39 +3573,217 @@ impl NetworkingSystem {
 
         self.send_packet_to_character_server(SwitchCharacterSlotPacket::new(origin_slot as u16, destination_slot as u16));
 
-        let response = self.get_data_from_character_server();
-        let mut byte_stream = ByteStream::new(&response);
+        Ok(())
+    }
 
-        let switch_character_slot_response_packet = SwitchCharacterSlotResponsePacket::try_from_bytes(&mut byte_stream).unwrap();
+    fn handle_login_server_response(&mut self, response: LoginServerResponse, events: &mut Vec<NetworkEvent>) {
+        let Some(PendingRequest::LogIn { username, password }) = self.pending_request.take() else {
+            #[cfg(feature = "debug")]
+            print_debug!("[{}warning{}] ignoring unrequested response from login server", YELLOW, NONE);
 
-        match switch_character_slot_response_packet.status {
-            SwitchCharacterSlotResponseStatus::Success => {
-                let _character_server_login_success_packet = CharacterServerLoginSuccessPacket::try_from_bytes(&mut byte_stream).unwrap();
-                let _packet_006b = Packet6b00::try_from_bytes(&mut byte_stream).unwrap();
+            return;
+        };
 
-                let character_count = self.characters.len();
-                self.characters.clear();
+        let login_server_login_success_packet = match response {
+            LoginServerResponse::LoginSuccess(packet) => packet,
+            LoginServerResponse::Failure(reason) => {
+                events.push(NetworkEvent::RequestFailed(reason.to_string()));
+                return;
+            }
+        };
 
-                for _index in 0..character_count {
-                    let character_information = CharacterInformation::from_bytes(&mut byte_stream, None);
-                    self.characters.push(character_information);
-                }
+        self.login_data = LoginData::new(
+            login_server_login_success_packet.account_id,
+            login_server_login_success_packet.login_id1,
+            login_server_login_success_packet.sex,
+        )
+        .into();
+
+        self.login_settings.username = match self.login_settings.remember_username {
+            true => username,
+            // clear in case it was previously saved
+            false => String::new(),
+        };
+
+        self.login_settings.password = match self.login_settings.remember_password {
+            true => password,
+            // clear in case it was previously saved
+            false => String::new(),
+        };
+
+        let Some(character_server_information) = login_server_login_success_packet.character_server_information.into_iter().next() else {
+            events.push(NetworkEvent::RequestFailed("no character server available".to_string()));
+            return;
+        };
+
+        let character_server_login_packet = CharacterServerLoginPacket::new(
+            login_server_login_success_packet.account_id,
+            login_server_login_success_packet.login_id1,
+            login_server_login_success_packet.login_id2,
+            login_server_login_success_packet.sex,
+        );
+
+        self.pending_request = Some(PendingRequest::ConnectToCharacterServer(character_server_login_packet));
+        self.send_command(NetworkCommand::Connect(
+            ServerKind::Character,
+            character_server_information.server_ip.to_string(),
+            character_server_information.server_port,
+        ));
+    }
 
-                // packet_length and packet 0x09a0 are left unread because we
-                // don't need them
+    /// Matches a response from the character server to the pending request.
+    /// Responses that don't belong to it, like the character list that the
+    /// server sends on its own after logging in, are skipped.
+    fn handle_character_server_response(&mut self, response: CharacterServerResponse, events: &mut Vec<NetworkEvent>) {
+        match (self.pending_request.take(), response) {
+            (Some(request), CharacterServerResponse::Failure(reason)) if request.get_server() == ServerKind::Character => {
+                events.push(NetworkEvent::RequestFailed(reason.to_string()))
+            }
+            (Some(PendingRequest::CharacterServerLogin), CharacterServerResponse::LoginSuccess(packet)) => {
+                self.slot_count = packet.normal_slot_count as usize;
+                self.pending_request = Some(PendingRequest::CharacterList);
+                self.send_packet_to_character_server(RequestCharacterListPacket::default());
+            }
+            (Some(PendingRequest::CharacterList), CharacterServerResponse::CharacterList(character_information)) => {
+                self.characters.set(character_information);
+                events.push(NetworkEvent::LoggedIn);
+            }
+            (Some(PendingRequest::CreateCharacter), CharacterServerResponse::CharacterCreated(character_information)) => {
+                self.characters.push(character_information);
+                events.push(NetworkEvent::CharacterCreated);
+            }
+            (Some(PendingRequest::DeleteCharacter(character_id)), CharacterServerResponse::CharacterDeleted) => {
+                self.characters.retain(|character| character.character_id != character_id);
+            }
+            (Some(PendingRequest::SelectCharacter(slot)), CharacterServerResponse::CharacterSelected(packet)) => {
+                let map_server_ip = packet.map_server_ip.to_string();
+                let map_server_port = packet.map_server_port;
+
+                self.pending_request = Some(PendingRequest::ConnectToMapServer {
+                    slot,
+                    character_selection_success_packet: packet,
+                });
+                self.send_command(NetworkCommand::Connect(ServerKind::Map, map_server_ip, map_server_port));
             }
-            SwitchCharacterSlotResponseStatus::Error => return Err("failed to move character to a different slot".to_string()),
+            (Some(PendingRequest::SwitchCharacterSlot), CharacterServerResponse::CharacterSlotSwitched) => {
+                self.pending_request = Some(PendingRequest::SwitchedCharacterSlot);
+            }
+            (Some(PendingRequest::SwitchedCharacterSlot), CharacterServerResponse::CharacterList(character_information)) => {
+                self.characters.set(character_information);
+            }
+            (request, _response) => self.pending_request = request,
         }
+    }
 
-        #[cfg(feature = "debug")]
-        self.update_packet_history(&mut byte_stream);
+    fn handle_connected(&mut self, server: ServerKind, events: &mut Vec<NetworkEvent>) {
+        self.connected_servers.insert(server);
 
-        self.move_request.take();
+        if server == ServerKind::Login {
+            self.login_server_backoff.reset();
+        }
 
+        match (server, self.pending_request.take()) {
+            (ServerKind::Character, Some(PendingRequest::ConnectToCharacterServer(character_server_login_packet))) => {
+                self.pending_request = Some(PendingRequest::CharacterServerLogin);
+                self.send_packet_to_character_server(character_server_login_packet);
+            }
+            (
+                ServerKind::Map,
+                Some(PendingRequest::ConnectToMapServer {
+                    slot,
+                    character_selection_success_packet,
+                }),
+            ) => {
+                let Some(login_data) = self.login_data.as_ref() else {
+                    self.disconnect_from_map_server();
+                    events.push(NetworkEvent::RequestFailed("not logged in".to_string()));
+                    return;
+                };
+                let (account_id, login_id1, sex) = (login_data.account_id, login_data.login_id1, login_data.sex);
+
+                let character_information = self
+                    .characters
+                    .borrow()
+                    .iter()
+                    .find(|character| character.character_number as usize == slot)
+                    .cloned();
+
+                let Some(character_information) = character_information else {
+                    self.disconnect_from_map_server();
+                    events.push(NetworkEvent::RequestFailed(format!("no character in slot {slot}")));
+                    return;
+                };
+
+                self.send_packet_to_map_server(MapServerLoginPacket::new(
+                    account_id,
+                    character_selection_success_packet.character_id,
+                    login_id1,
+                    ClientTick(100), // TODO: what is the logic here?
+                    sex,
+                ));
+
+                self.player_name = character_information.name.clone();
+
+                events.push(NetworkEvent::CharacterSelected(
+                    account_id,
+                    character_information,
+                    character_selection_success_packet.map_name.replace(".gat", ""),
+                ));
+            }
+            (_, request) => self.pending_request = request,
+        }
+    }
+
+    fn handle_disconnected(&mut self, server: ServerKind, reason: String, events: &mut Vec<NetworkEvent>) {
         #[cfg(feature = "debug")]
-        timer.stop();
+        print_debug!("[{}error{}] {}", RED, NONE, reason);
 
-        Ok(())
+        let was_connected = self.connected_servers.remove(&server);
+        let request_failed = self.pending_request.as_ref().is_some_and(|request| request.get_server() == server);
+
+        if request_failed {
+            self.pending_request = None;
+        }
+
+        match server {
+            ServerKind::Login => {
+                // The login server isn't needed anymore once the player is logged
+                // in.
+                if self.login_data.is_some() {
+                    return;
+                }
+
+                let delay = self.login_server_backoff.schedule();
+                let message = format!("{reason}, reconnecting in {} seconds", delay.as_secs());
+
+                // Only the first of multiple failed attempts in a row is reported.
+                if request_failed {
+                    events.push(NetworkEvent::RequestFailed(message));
+                } else if was_connected || self.login_server_backoff.get_failed_attempts() == 1 {
+                    events.push(NetworkEvent::Disconnect(DisconnectReason::LoginServerUnavailable(message)));
+                }
+            }
+            // Every packet sent after the connection was lost fails again, which
+            // was already reported.
+            ServerKind::Character | ServerKind::Map if !was_connected && !request_failed => {}
+            ServerKind::Character => {
+                // The map server can't be logged into without the character server.
+                if matches!(self.pending_request, Some(PendingRequest::ConnectToMapServer { .. })) {
+                    self.pending_request = None;
+                }
+
+                self.login_data = None;
+                self.characters.clear();
+                self.disconnect_from_map_server();
+
+                if !self.connected_servers.contains(&ServerKind::Login) {
+                    self.login_server_backoff.schedule();
+                }
+
+                events.push(NetworkEvent::Disconnect(DisconnectReason::CharacterServerLost(reason)));
+            }
+            ServerKind::Map => events.push(NetworkEvent::Disconnect(DisconnectReason::MapServerLost(reason))),
+        }
     }
 
     pub fn request_player_move(&mut self, destination: Vector2<usize>) {
@@ -3374,284 +3893,59 @@ impl NetworkingSystem {
     pub fn network_events(&mut self) -> Vec<NetworkEvent> {
         let mut events = Vec::new();
 
-        while let Some(data) = self.try_get_data_from_map_server() {
-            let mut byte_stream = ByteStream::new(&data);
-
-            while !byte_stream.is_empty() {
-                if let Ok(packet) = BroadcastMessagePacket::try_from_bytes(&mut byte_stream) {
-                    let color = Color::rgb(220, 200, 30);
-                    let chat_message = ChatMessage::new(packet.message, color);
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(packet) = Broadcast2MessagePacket::try_from_bytes(&mut byte_stream) {
-                    // NOTE: Drop the alpha channel because it might be 0.
-                    let color = Color::rgb(packet.font_color.red, packet.font_color.green, packet.font_color.blue);
-                    let chat_message = ChatMessage::new(packet.message, color);
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(packet) = OverheadMessagePacket::try_from_bytes(&mut byte_stream) {
-                    let color = Color::monochrome(230);
-                    let chat_message = ChatMessage::new(packet.message, color);
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(packet) = ServerMessagePacket::try_from_bytes(&mut byte_stream) {
-                    let chat_message = ChatMessage::new(packet.message, Color::monochrome(255));
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(packet) = EntityMessagePacket::try_from_bytes(&mut byte_stream) {
-                    // NOTE: Drop the alpha channel because it might be 0.
-                    let color = Color::rgb(packet.color.red, packet.color.green, packet.color.blue);
-                    let chat_message = ChatMessage::new(packet.message, color);
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(_) = DisplayEmotionPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = EntityMovePacket::try_from_bytes(&mut byte_stream) {
-                    let (origin, destination) = packet.from_to.to_vectors();
-                    events.push(NetworkEvent::EntityMove(
-                        packet.entity_id,
-                        origin,
-                        destination,
-                        packet.timestamp,
-                    ));
-                } else if let Ok(_) = EntityStopMovePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = PlayerMovePacket::try_from_bytes(&mut byte_stream) {
-                    let (origin, destination) = packet.from_to.to_vectors();
-                    events.push(NetworkEvent::PlayerMove(origin, destination, packet.timestamp));
-                } else if let Ok(packet) = ChangeMapPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::ChangeMap(
-                        packet.map_name.replace(".gat", ""),
-                        packet.position.map(|component| component as usize),
-                    ));
-                } else if let Ok(packet) = EntityAppearedPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddEntity(packet.into()));
-                } else if let Ok(packet) = EntityAppeared2Packet::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddEntity(packet.into()));
-                } else if let Ok(packet) = MovingEntityAppearedPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddEntity(packet.into()));
-                } else if let Ok(packet) = EntityDisappearedPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::RemoveEntity(packet.entity_id));
-                } else if let Ok(packet) = UpdateStatusPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateStatus(packet.status_type));
-                } else if let Ok(packet) = UpdateStatusPacket1::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateStatus(packet.status_type));
-                } else if let Ok(packet) = UpdateStatusPacket2::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateStatus(packet.status_type));
-                } else if let Ok(packet) = UpdateStatusPacket3::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateStatus(packet.status_type));
-                } else if let Ok(_) = UpdateAttackRangePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = NewMailStatusPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = AchievementUpdatePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = AchievementListPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = CriticalWeightUpdatePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = SpriteChangePacket::try_from_bytes(&mut byte_stream) {
-                    if packet.sprite_type == 0 {
-                        events.push(NetworkEvent::ChangeJob(packet.account_id, packet.value));
-                    }
-                } else if let Ok(_) = InventoyStartPacket::try_from_bytes(&mut byte_stream) {
-                    let mut item_data = Vec::new();
-
-                    while InventoyEndPacket::try_from_bytes(&mut byte_stream).is_err() {
-                        if let Ok(packet) = RegularItemListPacket::try_from_bytes(&mut byte_stream) {
-                            for item_information in packet.item_information {
-                                item_data.push((
-                                    item_information.index,
-                                    item_information.item_id,
-                                    EquipPosition::None,
-                                    EquipPosition::None,
-                                )); // TODO: Don't add that data here, only equippable items need this data.
-                            }
-                        } else if let Ok(packet) = EquippableItemListPacket::try_from_bytes(&mut byte_stream) {
-                            for item_information in packet.item_information {
-                                item_data.push((
-                                    item_information.index,
-                                    item_information.item_id,
-                                    item_information.equip_position,
-                                    item_information.equipped_position,
-                                ));
+        if self.login_server_backoff.take_due_attempt() {
+            self.connect_to_login_server();
+        }
+
+        if self.pending_request.is_some() && self.request_deadline <= Instant::now() {
+            let server = self.pending_request.take().unwrap().get_server();
+            events.push(NetworkEvent::RequestFailed(format!("the {server} didn't respond in time")));
+        }
+
+        let mut connection_events = std::mem::take(&mut self.failed_command_events);
+        connection_events.extend(self.event_receiver.try_iter());
+
+        for connection_event in connection_events {
+            match connection_event {
+                ConnectionEvent::Connected(server) => self.handle_connected(server, &mut events),
+                ConnectionEvent::Disconnected(server, reason) => self.handle_disconnected(server, reason, &mut events),
+                ConnectionEvent::Received(server, data) => {
+                    let mut byte_stream = ByteStream::new(&data);
+
+                    match server {
+                        ServerKind::Login => {
+                            let mut responses = Vec::new();
+                            self.login_server_handler.handle_all(&mut byte_stream, &mut (), &mut responses);
+
+                            for response in responses {
+                                self.handle_login_server_response(response, &mut events);
                             }
-                        } else {
-                            panic!("unexpected packet with header: {:x?}", byte_stream.slice(2));
                         }
-                    }
+                        ServerKind::Character => {
+                            let mut responses = Vec::new();
+                            self.character_server_handler.handle_all(&mut byte_stream, &mut (), &mut responses);
 
-                    events.push(NetworkEvent::Inventory(item_data));
-                } else if let Ok(_) = EquippableSwitchItemListPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = MapTypePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = UpdateSkillTreePacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::SkillTree(packet.skill_information));
-                } else if let Ok(_) = UpdateHotkeysPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = InitialStatusPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = UpdatePartyInvitationStatePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = UpdateShowEquipPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = UpdateConfigurationPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = NavigateToMonsterPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = MarkMinimapPositionPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = NextButtonPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddNextButton);
-                } else if let Ok(_) = CloseButtonPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddCloseButton);
-                } else if let Ok(packet) = DialogMenuPacket::try_from_bytes(&mut byte_stream) {
-                    let choices = packet
-                        .message
-                        .split(':')
-                        .map(String::from)
-                        .filter(|text| !text.is_empty())
-                        .collect();
-
-                    events.push(NetworkEvent::AddChoiceButtons(choices));
-                } else if let Ok(_) = DisplaySpecialEffectPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = DisplaySkillCooldownPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = DisplaySkillEffectAndDamagePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = DisplaySkillEffectNoDamagePacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::HealEffect(
-                        packet.destination_entity_id,
-                        packet.heal_amount as usize,
-                    ));
-
-                    //events.push(NetworkEvent::VisualEffect());
-                } else if let Ok(_) = DisplayPlayerHealEffect::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = StatusChangePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = QuestNotificationPacket1::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = HuntingQuestNotificationPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = HuntingQuestUpdateObjectivePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = QuestRemovedPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = QuestListPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = VisualEffectPacket::try_from_bytes(&mut byte_stream) {
-                    let path = match packet.effect {
-                        VisualEffect::BaseLevelUp => "angel.str",
-                        VisualEffect::JobLevelUp => "joblvup.str",
-                        VisualEffect::RefineFailure => "bs_refinefailed.str",
-                        VisualEffect::RefineSuccess => "bs_refinesuccess.str",
-                        VisualEffect::GameOver => "help_angel\\help_angel\\help_angel.str",
-                        VisualEffect::PharmacySuccess => "p_success.str",
-                        VisualEffect::PharmacyFailure => "p_failed.str",
-                        VisualEffect::BaseLevelUpSuperNovice => "help_angel\\help_angel\\help_angel.str",
-                        VisualEffect::JobLevelUpSuperNovice => "help_angel\\help_angel\\help_angel.str",
-                        VisualEffect::BaseLevelUpTaekwon => "help_angel\\help_angel\\help_angel.str",
-                    };
-
-                    events.push(NetworkEvent::VisualEffect(path, packet.entity_id));
-                } else if let Ok(_) = DisplayGainedExperiencePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = DisplayImagePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = StateChangePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = QuestEffectPacket::try_from_bytes(&mut byte_stream) {
-                    let event = match packet.effect {
-                        QuestEffect::None => NetworkEvent::RemoveQuestEffect(packet.entity_id),
-                        _ => NetworkEvent::AddQuestEffect(packet),
-                    };
-                    events.push(event);
-                } else if let Ok(packet) = ItemPickupPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddIventoryItem(
-                        packet.index,
-                        packet.item_id,
-                        packet.equip_position,
-                        EquipPosition::None,
-                    ));
-                } else if let Ok(_) = RemoveItemFromInventoryPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = ServerTickPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateClientTick(packet.client_tick));
-                } else if let Ok(packet) = RequestPlayerDetailsSuccessPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateEntityDetails(EntityId(packet.character_id.0), packet.name));
-                } else if let Ok(packet) = RequestEntityDetailsSuccessPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateEntityDetails(packet.entity_id, packet.name));
-                } else if let Ok(packet) = UpdateEntityHealthPointsPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateEntityHealth(
-                        packet.entity_id,
-                        packet.health_points as usize,
-                        packet.maximum_health_points as usize,
-                    ));
-                } else if let Ok(_) = RequestPlayerAttackFailedPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = DamagePacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::DamageEffect(
-                        packet.destination_entity_id,
-                        packet.damage_amount as usize,
-                    ));
-                } else if let Ok(packet) = NpcDialogPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::OpenDialog(packet.text, packet.npc_id));
-                } else if let Ok(packet) = RequestEquipItemStatusPacket::try_from_bytes(&mut byte_stream) {
-                    if let RequestEquipItemStatus::Success = packet.result {
-                        events.push(NetworkEvent::UpdateEquippedPosition {
-                            index: packet.inventory_index,
-                            equipped_position: packet.equipped_position,
-                        });
-                    }
-                } else if let Ok(packet) = RequestUnequipItemStatusPacket::try_from_bytes(&mut byte_stream) {
-                    if let RequestUnequipItemStatus::Success = packet.result {
-                        events.push(NetworkEvent::UpdateEquippedPosition {
-                            index: packet.inventory_index,
-                            equipped_position: EquipPosition::None,
-                        });
-                    }
-                } else if let Ok(_) = Packet8302::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = Packet180b::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = MapServerLoginSuccessPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::UpdateClientTick(packet.client_tick));
-                    events.push(NetworkEvent::SetPlayerPosition(packet.position.to_vector()));
-                } else if let Ok(packet) = RestartResponsePacket::try_from_bytes(&mut byte_stream) {
-                    match packet.result {
-                        RestartResponseStatus::Ok => events.push(NetworkEvent::Disconnect),
-                        RestartResponseStatus::Nothing => {
-                            let color = Color::rgb(255, 100, 100);
-                            let chat_message = ChatMessage::new("Failed to log out.".to_string(), color);
-                            events.push(NetworkEvent::ChatMessage(chat_message));
+                            for response in responses {
+                                self.handle_character_server_response(response, &mut events);
+                            }
                         }
-                    }
-                } else if let Ok(packet) = DisconnectResponsePacket::try_from_bytes(&mut byte_stream) {
-                    match packet.result {
-                        DisconnectResponseStatus::Ok => events.push(NetworkEvent::Disconnect),
-                        DisconnectResponseStatus::Wait10Seconds => {
-                            let color = Color::rgb(255, 100, 100);
-                            let chat_message = ChatMessage::new("Please wait 10 seconds before trying to log out.".to_string(), color);
-                            events.push(NetworkEvent::ChatMessage(chat_message));
+                        ServerKind::Map => {
+                            self.map_server_handler
+                                .handle_all(&mut byte_stream, &mut self.map_server_state, &mut events);
                         }
                     }
-                } else if let Ok(_) = UseSkillSuccessPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = ToUseSkillSuccessPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = NotifySkillUnitPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::AddSkillUnit(
-                        packet.entity_id,
-                        packet.unit_id,
-                        packet.position.map(|component| component as usize),
-                    ));
-                } else if let Ok(packet) = SkillUnitDisappearPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::RemoveSkillUnit(packet.entity_id));
-                } else if let Ok(_) = NotifyGroundSkillPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = FriendListPacket::try_from_bytes(&mut byte_stream) {
-                    self.friend_list.with_mut(|friends, chaged| {
-                        *friends = packet.friends.into_iter().map(|friend| (friend, UnsafeCell::new(None))).collect();
-                        chaged();
-                    });
-                } else if let Ok(_) = FriendOnlineStatusPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(packet) = FriendRequestPacket::try_from_bytes(&mut byte_stream) {
-                    events.push(NetworkEvent::FriendRequest(packet.friend));
-                } else if let Ok(packet) = FriendRequestResultPacket::try_from_bytes(&mut byte_stream) {
-                    if packet.result == FriendRequestResult::Accepted {
-                        self.friend_list.push((packet.friend.clone(), UnsafeCell::new(None)));
-                    }
 
-                    let color = Color::rgb(220, 200, 30);
-                    let chat_message = ChatMessage::new(packet.into_message(), color);
-                    events.push(NetworkEvent::ChatMessage(chat_message));
-                } else if let Ok(packet) = NotifyFriendRemovedPacket::try_from_bytes(&mut byte_stream) {
-                    self.friend_list.with_mut(|friends, changed| {
-                        friends
-                            .retain(|(friend, _)| !(friend.account_id == packet.account_id && friend.character_id == packet.character_id));
-                        changed();
-                    });
-                } else if let Ok(_) = PartyInvitePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = StatusChangeSequencePacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = ReputationPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = ClanInfoPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = ClanOnlineCountPacket::try_from_bytes(&mut byte_stream) {
-                } else if let Ok(_) = ChangeMapCellPacket::try_from_bytes(&mut byte_stream) {
-                } else {
                     #[cfg(feature = "debug")]
-                    {
-                        let packet = UnknownPacket::new(byte_stream.remaining_bytes());
-                        byte_stream.incoming_packet(&packet);
-                    }
-
-                    break;
+                    self.update_packet_history(&mut byte_stream);
                 }
+                ConnectionEvent::PacketVersionChanged(packet_version) => self.set_packet_version(packet_version),
+                #[cfg(feature = "debug")]
+                ConnectionEvent::KeepaliveSent(server, client_tick) => match server {
+                    ServerKind::Login => self.new_outgoing(&LoginServerKeepalivePacket::default()),
+                    ServerKind::Character => self.new_outgoing(&CharacterServerKeepalivePacket::new()),
+                    ServerKind::Map => self.new_outgoing(&RequestServerTickPacket::new(client_tick)),
+                },
             }
-
-            #[cfg(feature = "debug")]
-            self.update_packet_history(&mut byte_stream);
         }
 
         events
@@ -3670,3 +3964,360 @@ impl NetworkingSystem {
         PacketWindow::new(self.packet_history.new_remote(), self.update_packets.clone())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+    use std::time::Instant;
+
+    use super::capture::CaptureRecord;
+    use super::connection::POLL_INTERVAL;
+    use super::test_server::*;
+    use super::*;
+
+    const ACCOUNT_ID: AccountId = AccountId(2000000);
+    const CHARACTER_ID: CharacterId = CharacterId(150000);
+
+    /// Polls the networking system like the main loop does and keeps the
+    /// events in order.
+    struct TestClient {
+        networking_system: NetworkingSystem,
+        events: VecDeque<NetworkEvent>,
+    }
+
+    impl TestClient {
+        /// Returns once the client is connected to the login server.
+        fn connect(server: &TestServer) -> Self {
+            let server_settings = server.get_server_settings();
+            let packet_version = server_settings.packet_version;
+            let networking_system = NetworkingSystem::with_connection(
+                LoginSettings::default(),
+                vec![server_settings.clone()],
+                server_settings,
+                packet_version,
+                |packet_lengths| spawn_network_thread(packet_version, packet_lengths, None),
+            )
+            .unwrap();
+
+            let mut client = Self {
+                networking_system,
+                events: VecDeque::new(),
+            };
+
+            client.wait_until(|networking_system| networking_system.connected_servers.contains(&ServerKind::Login));
+            client
+        }
+
+        fn poll(&mut self, start: Instant) {
+            assert!(start.elapsed() < TIMEOUT, "timed out waiting for the networking system");
+            std::thread::sleep(POLL_INTERVAL);
+            self.events.extend(self.networking_system.network_events());
+        }
+
+        fn wait_until(&mut self, condition: impl Fn(&NetworkingSystem) -> bool) {
+            let start = Instant::now();
+
+            while !condition(&self.networking_system) {
+                self.poll(start);
+            }
+        }
+
+        fn next_event(&mut self) -> NetworkEvent {
+            let start = Instant::now();
+
+            loop {
+                if let Some(event) = self.events.pop_front() {
+                    return event;
+                }
+
+                self.poll(start);
+            }
+        }
+    }
+
+    /// Logs into the login and character server, which then send the
+    /// characters.
+    fn log_in(server: &TestServer, characters: Vec<CharacterInformation>) -> TestClient {
+        let mut client = TestClient::connect(server);
+        let character_server_port = server.get_port(ServerKind::Character);
+
+        server.respond::<LoginServerLoginPacket>(ServerKind::Login, login_success(ACCOUNT_ID, character_server_port));
+        server.respond::<CharacterServerLoginPacket>(ServerKind::Character, character_server_login_success(9));
+        server.respond::<RequestCharacterListPacket>(ServerKind::Character, character_list(characters));
+
+        client
+            .networking_system
+            .log_in("username".to_string(), "password".to_string())
+            .unwrap();
+
+        assert!(matches!(client.next_event(), NetworkEvent::LoggedIn));
+        client
+    }
+
+    /// Logs in and selects the only character, so the client is connected to
+    /// the map server.
+    fn select_character(server: &TestServer) -> TestClient {
+        let mut client = log_in(server, vec![character(CHARACTER_ID, 0, "first")]);
+        let map_server_port = server.get_port(ServerKind::Map);
+
+        server.respond::<SelectCharacterPacket>(
+            ServerKind::Character,
+            character_selected(CHARACTER_ID, "prontera", map_server_port),
+        );
+        client.networking_system.select_character(0).unwrap();
+
+        let NetworkEvent::CharacterSelected(account_id, character_information, map_name) = client.next_event() else {
+            panic!("expected the character to be selected");
+        };
+
+        assert_eq!(account_id, ACCOUNT_ID);
+        assert_eq!(character_information.name, "first");
+        assert_eq!(map_name, "prontera");
+        client
+    }
+
+    #[test]
+    fn log_in_loads_character_list() {
+        let server = TestServer::start(PacketVersion::default());
+        let client = log_in(&server, vec![character(CHARACTER_ID, 0, "first")]);
+
+        assert_eq!(server.expect::<LoginServerLoginPacket>(ServerKind::Login).name, "username");
+        assert_eq!(
+            server.expect::<CharacterServerLoginPacket>(ServerKind::Character).account_id,
+            ACCOUNT_ID
+        );
+        assert_eq!(client.networking_system.slot_count, 9);
+        assert_eq!(client.networking_system.characters.borrow().len(), 1);
+    }
+
+    #[test]
+    fn replay_switches_to_recorded_packet_version() {
+        let server = TestServer::start(PacketVersion::default());
+        let server_settings = server.get_server_settings();
+        let recorded_version = PacketVersion(20180620);
+        let records = vec![CaptureRecord {
+            timestamp: Duration::ZERO,
+            server: ServerKind::Login,
+            direction: Direction::Incoming,
+            packet_version: recorded_version,
+            data: Vec::new(),
+        }];
+        let networking_system = NetworkingSystem::with_connection(
+            LoginSettings::default(),
+            vec![server_settings.clone()],
+            server_settings,
+            PacketVersion::default(),
+            |_| Ok(spawn_replay_thread(PacketVersion::default(), records)),
+        )
+        .unwrap();
+
+        let mut client = TestClient {
+            networking_system,
+            events: VecDeque::new(),
+        };
+
+        client.wait_until(|networking_system| networking_system.packet_version == recorded_version);
+    }
+
+    #[test]
+    fn log_in_reports_failure() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = TestClient::connect(&server);
+
+        server.respond::<LoginServerLoginPacket>(ServerKind::Login, login_failure(LoginFailedReason2::IncorrectPassword));
+        client
+            .networking_system
+            .log_in("username".to_string(), "wrong".to_string())
+            .unwrap();
+
+        assert!(matches!(client.next_event(), NetworkEvent::RequestFailed(message) if message == "incorrect password"));
+        assert!(client.networking_system.login_data.is_none());
+        assert!(client.networking_system.pending_request.is_none());
+    }
+
+    #[test]
+    fn create_character_adds_character() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, Vec::new());
+
+        server.respond::<CreateCharacterPacket>(ServerKind::Character, character_created(character(CHARACTER_ID, 1, "created")));
+        client.networking_system.create_character(1, "created".to_string()).unwrap();
+
+        assert!(matches!(client.next_event(), NetworkEvent::CharacterCreated));
+        assert_eq!(server.expect::<CreateCharacterPacket>(ServerKind::Character).slot, 1);
+        assert_eq!(client.networking_system.characters.borrow()[0].name, "created");
+    }
+
+    #[test]
+    fn create_character_reports_failure() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, Vec::new());
+
+        server.respond::<CreateCharacterPacket>(ServerKind::Character, CharacterCreationFailedPacket {
+            reason: CharacterCreationFailedReason::CharacterNameAlreadyUsed,
+        });
+        client.networking_system.create_character(1, "taken".to_string()).unwrap();
+
+        assert!(matches!(client.next_event(), NetworkEvent::RequestFailed(message) if message == "character name is already used"));
+        assert!(client.networking_system.characters.borrow().is_empty());
+    }
+
+    #[test]
+    fn switch_character_slot_reloads_character_list() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, vec![character(CHARACTER_ID, 0, "first")]);
+
+        // The server sends the updated character list on its own.
+        server.respond::<SwitchCharacterSlotPacket>(
+            ServerKind::Character,
+            (
+                character_slot_switched(),
+                character_list(vec![character(CHARACTER_ID, 2, "first")]),
+            ),
+        );
+        client.networking_system.request_switch_character_slot(0);
+        client.networking_system.switch_character_slot(2).unwrap();
+
+        let packet = server.expect::<SwitchCharacterSlotPacket>(ServerKind::Character);
+        assert_eq!((packet.origin_slot, packet.destination_slot), (0, 2));
+
+        client.wait_until(|networking_system| networking_system.characters.borrow()[0].character_number == 2);
+        assert!(client.networking_system.pending_request.is_none());
+    }
+
+    #[test]
+    fn unanswered_request_fails() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, vec![character(CHARACTER_ID, 0, "first")]);
+
+        // The server confirms the switch, but never sends the updated list.
+        server.respond::<SwitchCharacterSlotPacket>(ServerKind::Character, character_slot_switched());
+        client.networking_system.request_switch_character_slot(0);
+        client.networking_system.switch_character_slot(2).unwrap();
+        client.wait_until(|networking_system| matches!(networking_system.pending_request, Some(PendingRequest::SwitchedCharacterSlot)));
+
+        client.networking_system.request_deadline = Instant::now();
+
+        assert!(
+            matches!(client.next_event(), NetworkEvent::RequestFailed(message) if message == "the character server didn't respond in time")
+        );
+        assert!(client.networking_system.pending_request.is_none());
+    }
+
+    #[test]
+    fn select_character_logs_into_map_server() {
+        let server = TestServer::start(PacketVersion::default());
+        let _client = select_character(&server);
+
+        let packet = server.expect::<MapServerLoginPacket>(ServerKind::Map);
+        assert_eq!(packet.account_id, ACCOUNT_ID);
+        assert_eq!(packet.character_id, CHARACTER_ID);
+        assert_eq!(server.expect::<SelectCharacterPacket>(ServerKind::Character).selected_slot, 0);
+    }
+
+    #[test]
+    fn select_character_missing_from_list_fails() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, Vec::new());
+        let map_server_port = server.get_port(ServerKind::Map);
+
+        server.respond::<SelectCharacterPacket>(
+            ServerKind::Character,
+            character_selected(CHARACTER_ID, "prontera", map_server_port),
+        );
+        client.networking_system.select_character(0).unwrap();
+
+        assert!(matches!(client.next_event(), NetworkEvent::RequestFailed(message) if message == "no character in slot 0"));
+        assert!(!client.networking_system.connected_servers.contains(&ServerKind::Map));
+        assert!(client.networking_system.pending_request.is_none());
+    }
+
+    #[test]
+    fn lost_character_server_cancels_map_server_connection() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, vec![character(CHARACTER_ID, 0, "first")]);
+        let map_server_port = server.get_port(ServerKind::Map);
+
+        client.networking_system.pending_request = Some(PendingRequest::ConnectToMapServer {
+            slot: 0,
+            character_selection_success_packet: character_selected(CHARACTER_ID, "prontera", map_server_port),
+        });
+        server.disconnect(ServerKind::Character);
+
+        assert!(matches!(
+            client.next_event(),
+            NetworkEvent::Disconnect(DisconnectReason::CharacterServerLost(..))
+        ));
+        assert!(client.networking_system.pending_request.is_none());
+    }
+
+    #[test]
+    fn map_server_packets_become_events() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = select_character(&server);
+        let entity_id = EntityId(5);
+
+        server.send(
+            ServerKind::Map,
+            (
+                entity_appeared(entity_id, Vector2::new(10, 20)),
+                entity_move(entity_id, Vector2::new(10, 20), Vector2::new(12, 20)),
+            ),
+        );
+        server.send(
+            ServerKind::Map,
+            (server_message("hello"), friend_request(AccountId(7), CharacterId(8), "friend")),
+        );
+
+        assert!(
+            matches!(client.next_event(), NetworkEvent::AddEntity(entity) if entity.entity_id == entity_id && entity.position == Vector2::new(10, 20))
+        );
+        assert!(
+            matches!(client.next_event(), NetworkEvent::EntityMove(id, _, destination, _) if id == entity_id && destination == Vector2::new(12, 20))
+        );
+        assert!(matches!(client.next_event(), NetworkEvent::ChatMessage(message) if message.stamped_text(false) == "hello"));
+        assert!(matches!(client.next_event(), NetworkEvent::FriendRequest(friend) if friend.name == "friend"));
+    }
+
+    #[test]
+    fn corrupt_map_server_data_is_reported() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = select_character(&server);
+
+        server.send(ServerKind::Map, (server_message("hello"), vec![0xff, 0xff, 0, 0]));
+
+        assert!(matches!(client.next_event(), NetworkEvent::ChatMessage(message) if message.stamped_text(false) == "hello"));
+        assert!(matches!(
+            client.next_event(),
+            NetworkEvent::Disconnect(DisconnectReason::MapServerLost(reason)) if reason.contains("unknown packet 0xffff")
+        ));
+    }
+
+    #[test]
+    fn lost_character_server_is_reported() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = log_in(&server, Vec::new());
+
+        server.disconnect(ServerKind::Character);
+
+        assert!(matches!(
+            client.next_event(),
+            NetworkEvent::Disconnect(DisconnectReason::CharacterServerLost(..))
+        ));
+        assert!(client.networking_system.login_data.is_none());
+    }
+
+    #[test]
+    fn lost_map_server_is_reported() {
+        let server = TestServer::start(PacketVersion::default());
+        let mut client = select_character(&server);
+
+        server.disconnect(ServerKind::Map);
+
+        assert!(matches!(
+            client.next_event(),
+            NetworkEvent::Disconnect(DisconnectReason::MapServerLost(..))
+        ));
+        assert!(client.networking_system.connected_servers.contains(&ServerKind::Character));
+    }
+}
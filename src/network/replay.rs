@@ -0,0 +1,283 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+use super::capture::{CaptureRecord, Direction};
+use super::connection::{ConnectionEvent, NetworkCommand, ServerKind, POLL_INTERVAL};
+use super::PacketVersion;
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+/// Plays back a capture in place of the network thread. Since the player
+/// doesn't act exactly like during the recording, the capture is kept in
+/// sync with the client:
+///
+/// - Incoming packets are sent once their server is connected and the same time
+///   has passed as during the recording.
+/// - Outgoing packets to the login and character server pause the replay until
+///   the client sends a packet with the same header, so responses arrive after
+///   their request.
+/// - Outgoing packets to the map server and keepalives are skipped, since they
+///   depend on what the player does.
+/// - Before an incoming packet that was recorded with a different packet
+///   version than the client uses, the client is told to switch to it.
+///
+/// Whenever the replay had to wait for the client, the remaining records are
+/// delayed by the same amount.
+struct ReplayThread {
+    records: VecDeque<CaptureRecord>,
+    connected_servers: HashSet<ServerKind>,
+    /// The headers of packets that the client sent, which weren't matched
+    /// to an outgoing record yet.
+    sent_headers: VecDeque<(ServerKind, u16)>,
+    /// The packet version that the client decodes incoming packets with.
+    packet_version: PacketVersion,
+    /// The point in time that the timestamps of the records refer to.
+    start: Instant,
+    command_receiver: Receiver<NetworkCommand>,
+    event_sender: Sender<ConnectionEvent>,
+}
+
+fn get_header(data: &[u8]) -> Option<u16> {
+    Some(u16::from_le_bytes([*data.first()?, *data.get(1)?]))
+}
+
+/// Starts the replay thread. It behaves like the network thread, so the rest
+/// of the client doesn't know that it is not connected to a server.
+pub(super) fn spawn_replay_thread(
+    packet_version: PacketVersion,
+    records: Vec<CaptureRecord>,
+) -> (Sender<NetworkCommand>, Receiver<ConnectionEvent>) {
+    let (command_sender, command_receiver) = channel();
+    let (event_sender, event_receiver) = channel();
+
+    let records = records
+        .into_iter()
+        .filter(|record| match record.direction {
+            Direction::Incoming => true,
+            Direction::Outgoing => record.server != ServerKind::Map,
+            Direction::Keepalive => false,
+        })
+        .collect();
+
+    let replay_thread = ReplayThread {
+        records,
+        connected_servers: HashSet::new(),
+        sent_headers: VecDeque::new(),
+        packet_version,
+        start: Instant::now(),
+        command_receiver,
+        event_sender,
+    };
+
+    std::thread::Builder::new()
+        .name("replay".to_string())
+        .spawn(move || replay_thread.run())
+        .expect("failed to spawn replay thread");
+
+    (command_sender, event_receiver)
+}
+
+impl ReplayThread {
+    fn run(mut self) {
+        loop {
+            match self.command_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(command) => self.handle_command(command),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            self.replay_due_records(Instant::now());
+        }
+    }
+
+    /// Delays the remaining records, so the record with the timestamp is due
+    /// no earlier than now.
+    fn synchronize(&mut self, timestamp: Duration) {
+        if let Some(start) = Instant::now().checked_sub(timestamp) {
+            self.start = self.start.max(start);
+        }
+    }
+
+    fn handle_command(&mut self, command: NetworkCommand) {
+        match command {
+            NetworkCommand::Connect(server, ..) => {
+                if let Some(record) = self.records.iter().find(|record| record.server == server) {
+                    self.synchronize(record.timestamp);
+                }
+
+                self.connected_servers.insert(server);
+                let _ = self.event_sender.send(ConnectionEvent::Connected(server));
+            }
+            NetworkCommand::Send(server, data) => {
+                if server != ServerKind::Map {
+                    if let Some(header) = get_header(&data) {
+                        self.sent_headers.push_back((server, header));
+                    }
+                }
+            }
+            NetworkCommand::Disconnect(server) => drop(self.connected_servers.remove(&server)),
+            NetworkCommand::SetPacketVersion(packet_version, _) => self.packet_version = packet_version,
+            NetworkCommand::SetClientTick(..) => {}
+        }
+    }
+
+    fn replay_due_records(&mut self, now: Instant) {
+        while let Some(record) = self.records.front() {
+            match record.direction {
+                Direction::Outgoing => {
+                    let Some(_sent) = self.sent_headers.pop_front() else {
+                        return;
+                    };
+
+                    #[cfg(feature = "debug")]
+                    if _sent != (record.server, get_header(&record.data).unwrap_or_default()) {
+                        print_debug!(
+                            "[{}warning{}] client sent {:#06x} to the {}, but the capture expects {:#06x} to the {}",
+                            YELLOW,
+                            NONE,
+                            _sent.1,
+                            _sent.0,
+                            get_header(&record.data).unwrap_or_default(),
+                            record.server
+                        );
+                    }
+
+                    let timestamp = record.timestamp;
+                    self.synchronize(timestamp);
+                    self.records.pop_front();
+                }
+                _ if !self.connected_servers.contains(&record.server) || now < self.start + record.timestamp => return,
+                _ => {
+                    let record = self.records.pop_front().unwrap();
+
+                    if record.packet_version != self.packet_version {
+                        self.packet_version = record.packet_version;
+                        let _ = self.event_sender.send(ConnectionEvent::PacketVersionChanged(record.packet_version));
+                    }
+
+                    let _ = self.event_sender.send(ConnectionEvent::Received(record.server, record.data));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    fn record(timestamp: u64, server: ServerKind, direction: Direction, data: &[u8]) -> CaptureRecord {
+        CaptureRecord {
+            timestamp: Duration::from_millis(timestamp),
+            server,
+            direction,
+            packet_version: PacketVersion::default(),
+            data: data.to_vec(),
+        }
+    }
+
+    fn connect(command_sender: &Sender<NetworkCommand>, event_receiver: &Receiver<ConnectionEvent>, server: ServerKind) {
+        command_sender.send(NetworkCommand::Connect(server, String::new(), 0)).unwrap();
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Connected(connected)) if connected == server
+        ));
+    }
+
+    #[test]
+    fn responses_wait_for_their_request() {
+        let records = vec![
+            record(0, ServerKind::Login, Direction::Keepalive, &[0x00, 0x02]),
+            record(0, ServerKind::Login, Direction::Outgoing, &[0x64, 0x00]),
+            record(1, ServerKind::Login, Direction::Incoming, &[0x69, 0x00]),
+        ];
+        let (command_sender, event_receiver) = spawn_replay_thread(PacketVersion::default(), records);
+
+        command_sender
+            .send(NetworkCommand::Connect(ServerKind::Login, String::new(), 0))
+            .unwrap();
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Connected(ServerKind::Login))
+        ));
+        assert!(event_receiver.recv_timeout(TIMEOUT).is_err());
+
+        command_sender
+            .send(NetworkCommand::Send(ServerKind::Login, vec![0x64, 0x00]))
+            .unwrap();
+        match event_receiver.recv_timeout(TIMEOUT) {
+            Ok(ConnectionEvent::Received(ServerKind::Login, data)) => assert_eq!(data, vec![0x69, 0x00]),
+            _ => panic!("expected the response"),
+        }
+    }
+
+    #[test]
+    fn incoming_packets_wait_for_their_server() {
+        let records = vec![record(0, ServerKind::Map, Direction::Incoming, &[0x7f, 0x00])];
+        let (command_sender, event_receiver) = spawn_replay_thread(PacketVersion::default(), records);
+
+        assert!(event_receiver.recv_timeout(TIMEOUT).is_err());
+
+        command_sender
+            .send(NetworkCommand::Connect(ServerKind::Map, String::new(), 0))
+            .unwrap();
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Connected(ServerKind::Map))
+        ));
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Received(ServerKind::Map, ..))
+        ));
+    }
+
+    #[test]
+    fn packet_version_changes_before_its_records() {
+        let old_version = PacketVersion(20180620);
+        let records = vec![
+            CaptureRecord {
+                packet_version: old_version,
+                ..record(0, ServerKind::Map, Direction::Incoming, &[0x73, 0x00])
+            },
+            record(0, ServerKind::Map, Direction::Incoming, &[0xeb, 0x02]),
+        ];
+        let (command_sender, event_receiver) = spawn_replay_thread(old_version, records);
+        connect(&command_sender, &event_receiver, ServerKind::Map);
+
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Received(ServerKind::Map, data)) if data == [0x73, 0x00]
+        ));
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::PacketVersionChanged(packet_version)) if packet_version == PacketVersion::default()
+        ));
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Received(ServerKind::Map, data)) if data == [0xeb, 0x02]
+        ));
+    }
+
+    #[test]
+    fn packet_version_of_client_is_tracked() {
+        let records = vec![record(0, ServerKind::Map, Direction::Incoming, &[0xeb, 0x02])];
+        let (command_sender, event_receiver) = spawn_replay_thread(PacketVersion(20180620), records);
+
+        // The client switched to the recorded version on its own, for example by
+        // selecting a server.
+        command_sender
+            .send(NetworkCommand::SetPacketVersion(PacketVersion::default(), HashMap::new()))
+            .unwrap();
+        connect(&command_sender, &event_receiver, ServerKind::Map);
+
+        assert!(matches!(
+            event_receiver.recv_timeout(TIMEOUT),
+            Ok(ConnectionEvent::Received(ServerKind::Map, ..))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use super::PacketVersion;
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+const FILENAME: &str = "client/servers.ron";
+
+/// A login server that the player can pick in the login window.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSettings {
+    pub name: String,
+    /// Host name or IP address of the login server.
+    pub host: String,
+    pub port: u16,
+    /// Falls back to the version in `client/packet_version.ron`.
+    #[serde(default = "PacketVersion::load")]
+    pub packet_version: PacketVersion,
+    /// Archives that are loaded on top of the game archives, e.g. for custom
+    /// content of the server. Since the archives are loaded on startup,
+    /// changing the server only loads them after a restart.
+    #[serde(default)]
+    pub archives: Vec<String>,
+}
+
+impl ServerSettings {
+    fn new(name: &str, host: &str, port: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            host: host.to_string(),
+            port,
+            packet_version: PacketVersion::load(),
+            archives: Vec::new(),
+        }
+    }
+}
+
+/// Loads the list of servers. The list is never empty. If the list exists but
+/// is invalid, the default servers are used and the error is returned as
+/// well, so it can be shown to the player.
+pub(super) fn load_servers() -> (Vec<ServerSettings>, Option<String>) {
+    #[cfg(feature = "debug")]
+    print_debug!("loading server list from {}{}{}", MAGENTA, FILENAME, NONE);
+
+    let Ok(data) = std::fs::read_to_string(FILENAME) else {
+        #[cfg(feature = "debug")]
+        print_debug!("no server list at {}{}{}; using default servers", MAGENTA, FILENAME, NONE);
+
+        return (default_servers(), None);
+    };
+
+    match parse_servers(&data) {
+        Ok(servers) => (servers, None),
+        Err(error) => {
+            let message = format!("failed to load {FILENAME}: {error}");
+
+            #[cfg(feature = "debug")]
+            print_debug!("[{}error{}] {}; using default servers", RED, NONE, message);
+
+            (default_servers(), Some(message))
+        }
+    }
+}
+
+fn parse_servers(data: &str) -> Result<Vec<ServerSettings>, String> {
+    let servers = ron::from_str::<Vec<ServerSettings>>(data).map_err(|error| error.to_string())?;
+
+    match servers.is_empty() {
+        true => Err("the server list is empty".to_string()),
+        false => Ok(servers),
+    }
+}
+
+fn default_servers() -> Vec<ServerSettings> {
+    vec![
+        ServerSettings::new("korangar", "49.12.109.207", 6900),
+        ServerSettings::new("local", "127.0.0.1", 6900),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn optional_fields_use_defaults() {
+        let data = r#"[
+            (name: "production", host: "play.example.com", port: 6900, packet_version: 20180620, archives: ["custom.grf"]),
+            (name: "staging", host: "::1", port: 6901),
+        ]"#;
+
+        let servers = parse_servers(data).unwrap();
+
+        assert_eq!(servers[0].host, "play.example.com");
+        assert_eq!(servers[0].packet_version, PacketVersion(20180620));
+        assert_eq!(servers[0].archives, vec!["custom.grf".to_string()]);
+        assert_eq!(servers[1].port, 6901);
+        assert!(servers[1].archives.is_empty());
+    }
+
+    #[test]
+    fn empty_list_is_rejected() {
+        assert_eq!(parse_servers("[]"), Err("the server list is empty".to_string()));
+        assert!(parse_servers("[(name: \"missing host\", port: 6900)]").is_err());
+    }
+}
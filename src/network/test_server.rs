@@ -0,0 +1,437 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::connection::POLL_INTERVAL;
+use super::dissector::client_handler;
+use super::handler::PacketLengths;
+use super::*;
+use crate::loaders::InternalVersion;
+
+/// How long to wait for the client before a test fails.
+pub(super) const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One or more packets that the server sends at once.
+pub(super) trait Response {
+    fn encode(self, packet_version: PacketVersion) -> Vec<u8>;
+}
+
+impl<P: Packet> Response for P {
+    fn encode(self, packet_version: PacketVersion) -> Vec<u8> {
+        let mut bytes = self.to_bytes(packet_version);
+
+        // Saves tests from calculating the length of variable packets.
+        if P::length(packet_version) == PacketLength::Variable {
+            let packet_length = bytes.len() as u16;
+            bytes[2..4].copy_from_slice(&packet_length.to_le_bytes());
+        }
+
+        bytes
+    }
+}
+
+/// Raw data, e.g. to send data that isn't a valid packet.
+impl Response for Vec<u8> {
+    fn encode(self, _packet_version: PacketVersion) -> Vec<u8> {
+        self
+    }
+}
+
+impl<A: Response, B: Response> Response for (A, B) {
+    fn encode(self, packet_version: PacketVersion) -> Vec<u8> {
+        [self.0.encode(packet_version), self.1.encode(packet_version)].concat()
+    }
+}
+
+#[derive(Default)]
+struct ServerState {
+    /// The connection to the client, if it is connected.
+    stream: Option<TcpStream>,
+    /// Responses that are sent when the client sends a packet with the header,
+    /// one response per packet.
+    responses: HashMap<u16, VecDeque<Vec<u8>>>,
+    /// Packets that the client sent, which the test didn't expect yet.
+    received: VecDeque<Vec<u8>>,
+}
+
+/// One of the servers. Accepts a single client at a time on its own port.
+struct ScriptedServer {
+    server: ServerKind,
+    packet_version: PacketVersion,
+    state: Mutex<ServerState>,
+    /// Notified whenever the state changes.
+    changed: Condvar,
+    stopped: AtomicBool,
+}
+
+fn get_header(packet: &[u8]) -> u16 {
+    u16::from_le_bytes([packet[0], packet[1]])
+}
+
+/// The size of the first packet if it was fully received. Packets that the
+/// client doesn't know are treated as the rest of the data.
+fn get_packet_size(data: &[u8], packet_lengths: &PacketLengths) -> Option<usize> {
+    if data.len() < 2 {
+        return None;
+    }
+
+    let Some(length) = packet_lengths.get(&get_header(data)) else {
+        return Some(data.len());
+    };
+
+    length
+        .get_size(&ByteStream::new(data))
+        .filter(|size| (2..=data.len()).contains(size))
+}
+
+impl ScriptedServer {
+    fn lock(&self) -> MutexGuard<'_, ServerState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Waits until `condition` returns a value and fails the test if that
+    /// takes too long.
+    fn wait_for<T>(&self, description: &str, mut condition: impl FnMut(&mut ServerState) -> Option<T>) -> T {
+        let deadline = Instant::now() + TIMEOUT;
+        let mut state = self.lock();
+
+        loop {
+            if let Some(value) = condition(&mut state) {
+                return value;
+            }
+
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                panic!("timed out waiting for {description}");
+            };
+
+            state = self.changed.wait_timeout(state, remaining).unwrap().0;
+        }
+    }
+
+    fn run(&self, listener: TcpListener, packet_lengths: PacketLengths) {
+        while !self.stopped.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = self.serve(stream, &packet_lengths);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+                Err(error) => panic!("{} failed to accept a connection: {error}", self.server),
+            }
+        }
+    }
+
+    /// Reads packets from the client until it disconnects.
+    fn serve(&self, mut stream: TcpStream, packet_lengths: &PacketLengths) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        self.lock().stream = Some(stream.try_clone()?);
+        self.changed.notify_all();
+
+        let mut data = Vec::new();
+        let mut buffer = [0; 4096];
+
+        while !self.stopped.load(Ordering::Relaxed) {
+            match stream.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(length) => data.extend_from_slice(&buffer[..length]),
+                // The read timeout lets the server notice that it was stopped.
+                Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                Err(_) => break,
+            }
+
+            while let Some(size) = get_packet_size(&data, packet_lengths) {
+                let packet: Vec<u8> = data.drain(..size).collect();
+                self.handle_packet(packet);
+            }
+        }
+
+        self.lock().stream = None;
+        self.changed.notify_all();
+        Ok(())
+    }
+
+    fn handle_packet(&self, packet: Vec<u8>) {
+        let header = get_header(&packet);
+        let mut state = self.lock();
+        let mut data = Vec::new();
+
+        // The character server sends the account id without a header before
+        // it responds to the login.
+        if self.server == ServerKind::Character && header == CharacterServerLoginPacket::header(self.packet_version) {
+            let mut byte_stream = ByteStream::new(&packet);
+            byte_stream.set_packet_version(self.packet_version);
+
+            if let Ok(login_packet) = CharacterServerLoginPacket::try_from_bytes(&mut byte_stream) {
+                data.extend_from_slice(&login_packet.account_id.0.to_le_bytes());
+            }
+        }
+
+        if let Some(response) = state.responses.get_mut(&header).and_then(VecDeque::pop_front) {
+            data.extend(response);
+        }
+
+        if let Some(stream) = state.stream.as_mut().filter(|_| !data.is_empty()) {
+            let _ = stream.write_all(&data);
+        }
+
+        state.received.push_back(packet);
+        self.changed.notify_all();
+    }
+}
+
+/// Stands in for the login, character and map server on the loopback
+/// interface, so the [`NetworkingSystem`] can be tested without a real
+/// server. Every server listens on its own port and only sends what the test
+/// scripted:
+///
+/// - [`respond`](Self::respond) answers the next packet of a type that the
+///   client sends.
+/// - [`send`](Self::send) sends packets right away, e.g. entities or chat
+///   messages on the map server.
+/// - [`expect`](Self::expect) waits for a packet from the client.
+///
+/// The servers stop once the test server is dropped.
+pub(super) struct TestServer {
+    packet_version: PacketVersion,
+    servers: HashMap<ServerKind, (u16, Arc<ScriptedServer>)>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl TestServer {
+    pub fn start(packet_version: PacketVersion) -> Self {
+        let packet_lengths = client_handler(packet_version).get_packet_lengths();
+        let mut servers = HashMap::new();
+        let mut threads = Vec::new();
+
+        for server in [ServerKind::Login, ServerKind::Character, ServerKind::Map] {
+            let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind test server");
+            listener.set_nonblocking(true).unwrap();
+            let port = listener.local_addr().unwrap().port();
+
+            let scripted_server = Arc::new(ScriptedServer {
+                server,
+                packet_version,
+                state: Mutex::default(),
+                changed: Condvar::new(),
+                stopped: AtomicBool::new(false),
+            });
+
+            let thread_server = scripted_server.clone();
+            let packet_lengths = packet_lengths.clone();
+            threads.push(std::thread::spawn(move || thread_server.run(listener, packet_lengths)));
+            servers.insert(server, (port, scripted_server));
+        }
+
+        Self {
+            packet_version,
+            servers,
+            threads,
+        }
+    }
+
+    fn get_server(&self, server: ServerKind) -> &ScriptedServer {
+        &self.servers[&server].1
+    }
+
+    pub fn get_port(&self, server: ServerKind) -> u16 {
+        self.servers[&server].0
+    }
+
+    /// The settings that point the client to the login server.
+    pub fn get_server_settings(&self) -> ServerSettings {
+        ServerSettings {
+            name: "test".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: self.get_port(ServerKind::Login),
+            packet_version: self.packet_version,
+            archives: Vec::new(),
+        }
+    }
+
+    /// Sends the response the next time that the client sends a `Request`.
+    /// Calling this multiple times for the same request queues responses for
+    /// the following requests.
+    pub fn respond<Request: Packet>(&self, server: ServerKind, response: impl Response) {
+        let header = Request::header(self.packet_version);
+        let response = response.encode(self.packet_version);
+
+        self.get_server(server)
+            .lock()
+            .responses
+            .entry(header)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Sends packets to the client, waiting for it to connect if necessary.
+    pub fn send(&self, server: ServerKind, packets: impl Response) {
+        let data = packets.encode(self.packet_version);
+        let description = format!("the client to connect to the {server}");
+
+        self.get_server(server)
+            .wait_for(&description, |state| {
+                state.stream.as_mut().map(|stream| stream.write_all(&data))
+            })
+            .expect("failed to send packets to the client");
+    }
+
+    /// Waits for the client to send a `P` and returns it. Packets that were
+    /// received before it, e.g. keepalives, are kept for later calls.
+    pub fn expect<P: Packet>(&self, server: ServerKind) -> P {
+        let header = P::header(self.packet_version);
+        let description = format!("the client to send {} to the {server}", P::PACKET_NAME);
+
+        let packet = self.get_server(server).wait_for(&description, |state| {
+            let index = state.received.iter().position(|packet| get_header(packet) == header)?;
+            state.received.remove(index)
+        });
+
+        let mut byte_stream = ByteStream::new(&packet);
+        byte_stream.set_packet_version(self.packet_version);
+        P::try_from_bytes(&mut byte_stream).expect("client sent an invalid packet")
+    }
+
+    /// Closes the connection to the client, like a server that crashed.
+    pub fn disconnect(&self, server: ServerKind) {
+        if let Some(stream) = self.get_server(server).lock().stream.take() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.servers
+            .values()
+            .for_each(|(_, server)| server.stopped.store(true, Ordering::Relaxed));
+
+        self.threads.drain(..).for_each(|thread| {
+            let _ = thread.join();
+        });
+    }
+}
+
+fn arbitrary<T: Arbitrary>(seed: u64) -> T {
+    let mut generator = Generator::new(seed, InternalVersion::new(0, 0), PacketVersion::default());
+    T::arbitrary(&mut generator, None)
+}
+
+pub(super) fn login_success(account_id: AccountId, character_server_port: u16) -> LoginServerLoginSuccessPacket {
+    LoginServerLoginSuccessPacket {
+        packet_length: 0,
+        login_id1: 1,
+        account_id,
+        login_id2: 2,
+        ip_address: 0,
+        name: [0; 24],
+        unknown: 0,
+        sex: Sex::Male,
+        auth_token: [0; 17],
+        character_server_information: vec![CharacterServerInformation {
+            server_ip: Ipv4Addr::LOCALHOST,
+            server_port: character_server_port,
+            server_name: [0; 20],
+            user_count: 0,
+            server_type: 0,
+            display_new: 0,
+            unknown: [0; 128],
+        }],
+    }
+}
+
+pub(super) fn login_failure(reason: LoginFailedReason2) -> LoginFailedPacket2 {
+    LoginFailedPacket2 { reason }
+}
+
+pub(super) fn character_server_login_success(slot_count: u8) -> CharacterServerLoginSuccessPacket {
+    CharacterServerLoginSuccessPacket {
+        unknown: 29,
+        normal_slot_count: slot_count,
+        vip_slot_count: 0,
+        billing_slot_count: 0,
+        poducilble_slot_count: 0,
+        vaild_slot: slot_count,
+        unused: [0; 20],
+    }
+}
+
+/// A character with random stats.
+pub(super) fn character(character_id: CharacterId, slot: u8, name: &str) -> CharacterInformation {
+    CharacterInformation {
+        character_id,
+        character_number: slot,
+        name: name.to_string(),
+        map_name: "prontera.gat".to_string(),
+        ..arbitrary(character_id.0 as u64)
+    }
+}
+
+pub(super) fn character_list(characters: Vec<CharacterInformation>) -> RequestCharacterListSuccessPacket {
+    RequestCharacterListSuccessPacket {
+        packet_length: 0,
+        character_information: characters,
+    }
+}
+
+pub(super) fn character_created(character: CharacterInformation) -> CreateCharacterSuccessPacket {
+    CreateCharacterSuccessPacket {
+        character_information: character,
+    }
+}
+
+pub(super) fn character_slot_switched() -> SwitchCharacterSlotResponsePacket {
+    SwitchCharacterSlotResponsePacket {
+        unknown: 8,
+        status: SwitchCharacterSlotResponseStatus::Success,
+        remaining_moves: 1,
+    }
+}
+
+/// Hands the client over to the map server.
+pub(super) fn character_selected(character_id: CharacterId, map_name: &str, map_server_port: u16) -> CharacterSelectionSuccessPacket {
+    CharacterSelectionSuccessPacket {
+        character_id,
+        map_name: format!("{map_name}.gat"),
+        map_server_ip: Ipv4Addr::LOCALHOST,
+        map_server_port,
+        unknown: [0; 128],
+    }
+}
+
+/// An entity with random appearance at the position.
+pub(super) fn entity_appeared(entity_id: EntityId, position: Vector2<usize>) -> EntityAppearedPacket {
+    EntityAppearedPacket {
+        entity_id,
+        position: WorldPosition::new(position.x, position.y),
+        ..arbitrary(entity_id.0 as u64)
+    }
+}
+
+pub(super) fn entity_move(entity_id: EntityId, from: Vector2<usize>, to: Vector2<usize>) -> EntityMovePacket {
+    EntityMovePacket {
+        entity_id,
+        from_to: WorldPosition2::new(from.x, from.y, to.x, to.y),
+        timestamp: ClientTick(0),
+    }
+}
+
+pub(super) fn server_message(message: &str) -> ServerMessagePacket {
+    ServerMessagePacket {
+        packet_length: message.len() as u16 + 4,
+        message: message.to_string(),
+    }
+}
+
+pub(super) fn friend_request(account_id: AccountId, character_id: CharacterId, name: &str) -> FriendRequestPacket {
+    FriendRequestPacket {
+        friend: Friend {
+            account_id,
+            character_id,
+            name: name.to_string(),
+        },
+    }
+}
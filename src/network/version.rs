@@ -0,0 +1,292 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "debug")]
+use crate::debug::*;
+
+const FILENAME: &str = "client/packet_version.ron";
+
+/// The client date that the server was built for, as set by `PACKETVER` in
+/// rAthena and Hercules, e.g. `20211103`. Packets that changed their header or
+/// layout over time are encoded and decoded according to this date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PacketVersion(pub u32);
+
+impl PacketVersion {
+    pub fn smaller(&self, date: u32) -> bool {
+        self.0 < date
+    }
+
+    pub fn equals_or_above(&self, date: u32) -> bool {
+        self.0 >= date
+    }
+
+    /// Loads the packet version that the client should use from the
+    /// configuration, falling back to the default version.
+    pub fn load() -> Self {
+        #[cfg(feature = "debug")]
+        print_debug!("loading packet version from {}{}{}", MAGENTA, FILENAME, NONE);
+
+        std::fs::read_to_string(FILENAME)
+            .ok()
+            .and_then(|data| ron::from_str(&data).ok())
+            .unwrap_or_else(|| {
+                #[cfg(feature = "debug")]
+                print_debug!("failed to load packet version from {}{}{}", MAGENTA, FILENAME, NONE);
+
+                Default::default()
+            })
+    }
+}
+
+impl Default for PacketVersion {
+    /// The version that packets without alternative headers or layouts are
+    /// defined for.
+    fn default() -> Self {
+        Self(20211103)
+    }
+}
+
+impl Display for PacketVersion {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(formatter, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PacketVersion;
+    use crate::loaders::ByteStream;
+    use crate::network::{
+        DisplayGainedExperiencePacket, DisplaySkillEffectNoDamagePacket, EquipPosition, ItemPickupPacket, MapServerLoginSuccessPacket,
+        Packet, PacketLength,
+    };
+
+    const CLIENT_TICK: [u8; 4] = [0x10, 0x27, 0x00, 0x00];
+    const POSITION: [u8; 3] = [0x04, 0x41, 0x05];
+    const ACCOUNT_ID: [u8; 4] = [0x80, 0x84, 0x1e, 0x00];
+    const ENTITY_ID: [u8; 4] = [0x81, 0x84, 0x1e, 0x00];
+    // Index 2 in the inventory, followed by a count of 1.
+    const INDEX_AND_COUNT: [u8; 4] = [0x04, 0x00, 0x01, 0x00];
+    // Identified, not broken.
+    const ITEM_STATE: [u8; 2] = [1, 0];
+    // Item type and pickup result.
+    const ITEM_TYPE_AND_RESULT: [u8; 2] = [4, 0];
+
+    fn decode<P: Packet>(packet_version: u32, bytes: &[u8]) -> P {
+        let mut byte_stream = ByteStream::new(bytes);
+        byte_stream.set_packet_version(PacketVersion(packet_version));
+
+        let packet = P::try_from_bytes(&mut byte_stream).unwrap();
+        assert!(byte_stream.is_empty());
+        assert_eq!(P::length(PacketVersion(packet_version)), PacketLength::Fixed(bytes.len()));
+        packet
+    }
+
+    #[test]
+    fn map_server_login_success_before_font() {
+        let bytes = [[0x73, 0x00].as_slice(), &CLIENT_TICK, &POSITION, &[5, 5]].concat();
+        let packet: MapServerLoginSuccessPacket = decode(20071106, &bytes);
+
+        assert_eq!(packet.client_tick.0, 10000);
+        assert!(packet.font.is_none());
+        assert!(packet.sex.is_none());
+    }
+
+    #[test]
+    fn map_server_login_success_with_sex() {
+        let bytes = [[0x18, 0x0a].as_slice(), &CLIENT_TICK, &POSITION, &[5, 5], &[2, 0], &[1]].concat();
+        let packet: MapServerLoginSuccessPacket = decode(20150513, &bytes);
+
+        assert_eq!(packet.client_tick.0, 10000);
+        assert_eq!(packet.font, Some(2));
+        assert!(packet.sex.is_some());
+    }
+
+    #[test]
+    fn map_server_login_success_current() {
+        let bytes = [[0xeb, 0x02].as_slice(), &CLIENT_TICK, &POSITION, &[5, 5], &[2, 0]].concat();
+        let packet: MapServerLoginSuccessPacket = decode(20211103, &bytes);
+
+        assert_eq!(packet.client_tick.0, 10000);
+        assert_eq!(packet.font, Some(2));
+        assert!(packet.sex.is_none());
+    }
+
+    #[test]
+    fn skill_effect_with_short_heal_amount() {
+        let bytes = [[0x1a, 0x01].as_slice(), &[28, 0], &[0xe8, 0x03], &ENTITY_ID, &ACCOUNT_ID, &[1]].concat();
+        let packet: DisplaySkillEffectNoDamagePacket = decode(20120410, &bytes);
+
+        assert_eq!(packet.skill_id.0, 28);
+        assert_eq!(packet.heal_amount(), 1000);
+        assert_eq!(packet.destination_entity_id.0, 2000001);
+    }
+
+    #[test]
+    fn skill_effect_current() {
+        let bytes = [
+            [0xcb, 0x09].as_slice(),
+            &[28, 0],
+            &[0xa0, 0x86, 0x01, 0x00],
+            &ENTITY_ID,
+            &ACCOUNT_ID,
+            &[1],
+        ]
+        .concat();
+        let packet: DisplaySkillEffectNoDamagePacket = decode(20211103, &bytes);
+
+        assert_eq!(packet.skill_id.0, 28);
+        assert_eq!(packet.heal_amount(), 100000);
+        assert_eq!(packet.destination_entity_id.0, 2000001);
+    }
+
+    #[test]
+    fn gained_experience_with_short_amount() {
+        let bytes = [[0xf6, 0x07].as_slice(), &ACCOUNT_ID, &[0x39, 0x30, 0x00, 0x00], &[1, 0], &[0, 0]].concat();
+        let packet: DisplayGainedExperiencePacket = decode(20151104, &bytes);
+
+        assert_eq!(packet.account_id.0, 2000000);
+        assert_eq!(packet.legacy_amount, Some(12345));
+        assert!(packet.amount.is_none());
+    }
+
+    #[test]
+    fn gained_experience_current() {
+        let amount = 5_000_000_000u64.to_le_bytes();
+        let bytes = [[0xcc, 0x0a].as_slice(), &ACCOUNT_ID, &amount, &[1, 0], &[0, 0]].concat();
+        let packet: DisplayGainedExperiencePacket = decode(20211103, &bytes);
+
+        assert_eq!(packet.account_id.0, 2000000);
+        assert_eq!(packet.amount, Some(5_000_000_000));
+        assert!(packet.legacy_amount.is_none());
+    }
+
+    #[test]
+    fn item_pickup_before_rental_items() {
+        let cards = [0; 8];
+        let bytes = [
+            [0xa0, 0x00].as_slice(),
+            &INDEX_AND_COUNT,
+            &[0xd3, 0x04],
+            &ITEM_STATE,
+            &[7],
+            &cards,
+            &[0x10, 0x00],
+            &ITEM_TYPE_AND_RESULT,
+        ]
+        .concat();
+        let packet: ItemPickupPacket = decode(20050628, &bytes);
+
+        assert_eq!(packet.item_id().0, 1235);
+        assert_eq!(packet.equip_position(), EquipPosition::Armor);
+        assert_eq!(packet.legacy_refinement_level, Some(7));
+        assert!(packet.hire_expiration_date.is_none());
+    }
+
+    #[test]
+    fn item_pickup_with_short_item_id() {
+        let cards = [0; 8];
+        let options = [0; 25];
+        let bytes = [
+            [0x37, 0x0a].as_slice(),
+            &INDEX_AND_COUNT,
+            &[0xd3, 0x04],
+            &ITEM_STATE,
+            &[7],
+            &cards,
+            &[0x10, 0x00, 0x00, 0x00],
+            &ITEM_TYPE_AND_RESULT,
+            &[0; 4],
+            &[0; 2],
+            &options,
+            &[0],
+            &[0; 2],
+        ]
+        .concat();
+        let packet: ItemPickupPacket = decode(20170104, &bytes);
+
+        assert_eq!(packet.item_id().0, 1235);
+        assert_eq!(packet.equip_position(), EquipPosition::Armor);
+        assert_eq!(packet.legacy_refinement_level, Some(7));
+        assert_eq!(packet.look, Some(0));
+    }
+
+    #[test]
+    fn item_pickup_current() {
+        let cards = [0; 16];
+        let options = [0; 25];
+        let bytes = [
+            [0x41, 0x0b].as_slice(),
+            &INDEX_AND_COUNT,
+            &[0x40, 0x42, 0x0f, 0x00],
+            &ITEM_STATE,
+            &cards,
+            &[0x10, 0x00, 0x00, 0x00],
+            &ITEM_TYPE_AND_RESULT,
+            &[0; 4],
+            &[0; 2],
+            &options,
+            &[0],
+            &[0; 2],
+            &[7],
+            &[2],
+        ]
+        .concat();
+        let packet: ItemPickupPacket = decode(20211103, &bytes);
+
+        assert_eq!(packet.item_id().0, 1000000);
+        assert_eq!(packet.equip_position(), EquipPosition::Armor);
+        assert!(packet.legacy_refinement_level.is_none());
+        assert_eq!(packet.refinement_level, Some(7));
+        assert_eq!(packet.enchantment_level, Some(2));
+    }
+
+    #[test]
+    fn item_pickup_headers_and_lengths() {
+        let header_and_length = |packet_version| {
+            let packet_version = PacketVersion(packet_version);
+            (
+                ItemPickupPacket::header(packet_version),
+                ItemPickupPacket::length(packet_version),
+            )
+        };
+
+        assert_eq!(header_and_length(20050628), (0x00a0, PacketLength::Fixed(23)));
+        assert_eq!(header_and_length(20070108), (0x029a, PacketLength::Fixed(27)));
+        assert_eq!(header_and_length(20100105), (0x02d4, PacketLength::Fixed(29)));
+        assert_eq!(header_and_length(20130807), (0x0990, PacketLength::Fixed(31)));
+        assert_eq!(header_and_length(20150513), (0x0a0c, PacketLength::Fixed(56)));
+        assert_eq!(header_and_length(20170104), (0x0a37, PacketLength::Fixed(59)));
+        assert_eq!(header_and_length(20190605), (0x0a37, PacketLength::Fixed(69)));
+        assert_eq!(header_and_length(20211103), (0x0b41, PacketLength::Fixed(70)));
+    }
+
+    #[test]
+    fn headers_and_lengths_depend_on_packet_version() {
+        let header_and_length = |packet_version| {
+            let packet_version = PacketVersion(packet_version);
+            (
+                MapServerLoginSuccessPacket::header(packet_version),
+                MapServerLoginSuccessPacket::length(packet_version),
+            )
+        };
+
+        assert_eq!(header_and_length(20071106), (0x0073, PacketLength::Fixed(11)));
+        assert_eq!(header_and_length(20080102), (0x02eb, PacketLength::Fixed(13)));
+        assert_eq!(header_and_length(20141016), (0x02eb, PacketLength::Fixed(14)));
+        assert_eq!(header_and_length(20150513), (0x0a18, PacketLength::Fixed(14)));
+        assert_eq!(header_and_length(20160330), (0x02eb, PacketLength::Fixed(13)));
+    }
+
+    #[test]
+    fn packet_from_other_version_is_rejected() {
+        let bytes = [[0x73, 0x00].as_slice(), &CLIENT_TICK, &POSITION, &[5, 5]].concat();
+        let mut byte_stream = ByteStream::new(&bytes);
+
+        assert!(MapServerLoginSuccessPacket::try_from_bytes(&mut byte_stream).is_err());
+        assert_eq!(byte_stream.get_offset(), 0);
+    }
+}
@@ -5,6 +5,7 @@ use procedural::*;
 
 #[cfg(feature = "debug")]
 use crate::graphics::{Camera, MarkerRenderer, Renderer};
+use crate::loaders::PaddedString;
 #[cfg(feature = "debug")]
 use crate::world::MarkerIdentifier;
 
@@ -12,7 +13,7 @@ use crate::world::MarkerIdentifier;
 #[window_title("Effect Source")]
 pub struct EffectSource {
     #[length_hint(80)]
-    pub name: String,
+    pub name: PaddedString,
     pub position: Vector3<f32>,
     pub effect_type: u32, // TODO: fix this
     pub emit_speed: f32,
@@ -74,133 +74,132 @@ pub struct Common {
 }
 
 #[profile]
-#[allow(clippy::invisible_characters)]
 fn get_sprite_path_for_player_job(job_id: usize) -> &'static str {
     match job_id {
-        0 => "ÃÊº¸ÀÚ",             // NOVICE
-        1 => "°Ë»Ç",               // SWORDMAN
-        2 => "À§Àúµå",             // MAGICIAN
-        3 => "±Ã¼Ö",               // ARCHER
-        4 => "¼ºÁ÷ÀÚ",             // ACOLYTE
-        5 => "»ÓÀÎ",               // MERCHANT
-        6 => "ΜΜΜÏ",               // THIEF
-        7 => "±â»ç",               // KNIGHT
-        8 => "¼ºÅõ»ç",             // PRIEST
-        9 => "¸¶¹Ý»Ç",             // WIZARD
-        10 => "Á¦Ã¶°ø",            // BLACKSMITH
-        11 => "ÇåÅÍ",              // HUNTER
-        12 => "¾î¼¼½Å",            // ASSASSIN
-        13 => "¿£´ë¿î",            // CHICKEN
-        14 => "Å©·ç¼¼ÀÌ´õ",        // CRUSADER
-        15 => "¸ùÅ©",              // MONK
-        16 => "¼¼ÀÌÁö",            // SAGE
-        17 => "·Î±×",              // ROGUE
-        18 => "¿¬±Ý¼ú»ç",          // ALCHEMIST
-        19 => "¹Ùµå",              // BARD
-        20 => "¹«Èñ",              // DANCER
-        23 => "½´ÆÛ³ëºñ½º",        // SUPERNOVICE
-        24 => "°Ç³Ê",              // GUNSLINGER
-        25 => "´ÑÀÚ",              // NINJA
-        4001 => "ÃÊº¸ÀÚ",          // NOVICE_H
-        4002 => "°Ë»Ç",            // SWORDMAN_H
-        4003 => "À§Àúµå",          // MAGICIAN_H
-        4004 => "±Ã¼Ö",            // ARCHER_H
-        4005 => "¼ºÁ÷ÀÚ",          // ACOLYTE_H
-        4006 => "»ÓÀÎ",            // MERCHANT_H
-        4007 => "ΜΜΜÏ",            // THIEF_H
-        4008 => "·Îµå³ªÀÌÆ®",      // KNIGHT_H
-        4009 => "ÇÏÀÌÇÁ¸®",        // PRIEST_H
-        4010 => "ÇÏÀÌÀ§Àúµå",      // WIZARD_H
-        4011 => "È­ÀÌÆ®½º¹Ì½º",    // BLACKSMITH_H
-        4012 => "½º³ªÀÌÆÛ",        // HUNTER_H
-        4013 => "¾î½Ø½ÅÅ©·Î½º",    // ASSASSIN_H
-        4014 => "¿£´ë¿î",          // CHICKEN_H
-        4015 => "Å©·ç¼¼ÀÌ´õ",      // CRUSADER_H
-        4016 => "¸ùÅ©",            // MONK_H
-        4017 => "¼¼ÀÌÁö",          // SAGE_H
-        4018 => "·Î±×",            // ROGUE_H
-        4019 => "¿¬±Ý¼ú»ç",        // ALCHEMIST_H
-        4020 => "¹Ùµå",            // BARD_H
-        4021 => "¹«Èñ",            // DANCER_H
-        4023 => "½´ÆÛ³ëºñ½º",      // NOVICE_B
-        4024 => "°Ë»Ç",            // SWORDMAN_B
-        4025 => "À§Àúµå",          // MAGICIAN_B
-        4026 => "±Ã¼Ö",            // ARCHER_B
-        4027 => "¼ºÁ÷ÀÚ",          // ACOLYTE_B
-        4028 => "»ÓÀÎ",            // MERCHANT_B
-        4029 => "µµµÏ",            // THIEF_B
-        4030 => "±â»ç",            // KNIGHT_B
-        4031 => "¼ºÅõ»ç",          // PRIEST_B
-        4032 => "¸¶¹Ý»Ç",          // WIZARD_B
-        4033 => "Á¦Ã¶°ø",          // BLACKSMITH_B
-        4034 => "ÇåÅÍ",            // HUNTER_B
-        4035 => "¾î¼¼½Å",          // ASSASSIN_B
-        4037 => "Å©·ç¼¼ÀÌ´õ",      // CRUSADER_B
-        4038 => "¸ùÅ©",            // MONK_B
-        4039 => "¼¼ÀÌÁö",          // SAGE_B
-        4040 => "·Î±×",            // ROGUE_B
-        4041 => "¿¬±Ý¼ú»ç",        // ALCHEMIST_B
-        4042 => "¹Ùµå",            // BARD_B
-        4043 => "¹«Èñ",            // DANCER_B
-        4045 => "½´ÆÛ³ëºñ½º",      // SUPERNOVICE_B
-        4054 => "·é³ªÀÌÆ®",        // RUNE_KNIGHT
-        4055 => "¿ö·Ï",            // WARLOCK
-        4056 => "·¹ÀÎÁ®",          // RANGER
-        4057 => "¾ÆÅ©ºñ¼ó",        // ARCH_BISHOP
-        4058 => "¹ÌÄÉ´Ð",          // MECHANIC
-        4059 => "±æ·ÎÆ¾Å©·Î½º",    // GUILLOTINE_CROSS
-        4066 => "°¡ΜÅ",            // ROYAL_GUARD
-        4067 => "¼Ò¼­·¯",          // SORCERER
-        4068 => "¹Î½ºÆ®·²",        // MINSTREL
-        4069 => "¿ø´õ·¯",          // WANDERER
-        4070 => "½´¶ó",            // SURA
-        4071 => "Á¦³×¸¯",          // GENETIC
-        4072 => "½¦µµ¿ìÃ¼ÀÌ¼­",    // SHADOW_CHASER
-        4060 => "·é³ªÀÌÆ®",        // RUNE_KNIGHT_H
-        4061 => "¿ö·Ï",            // WARLOCK_H
-        4062 => "·¹ÀÎÁ®",          // RANGER_H
-        4063 => "¾ÆÅ©ºñ¼ó",        // ARCH_BISHOP_H
-        4064 => "¹ÌÄÉ´Ð",          // MECHANIC_H
-        4065 => "±æ·ÎÆ¾Å©·Î½º",    // GUILLOTINE_CROSS_H
-        4073 => "°¡ΜÅ",            // ROYAL_GUARD_H
-        4074 => "¼Ò¼­·¯",          // SORCERER_H
-        4075 => "¹Î½ºÆ®·²",        // MINSTREL_H
-        4076 => "¿ø´õ·¯",          // WANDERER_H
-        4077 => "½´¶ó",            // SURA_H
-        4078 => "Á¦³×¸¯",          // GENETIC_H
-        4079 => "½¦µµ¿ìÃ¼ÀÌ¼­",    // SHADOW_CHASER_H
-        4096 => "·é³ªÀÌÆ®",        // RUNE_KNIGHT_B
-        4097 => "¿ö·Ï",            // WARLOCK_B
-        4098 => "·¹ÀÎÁ®",          // RANGER_B
-        4099 => "¾ÆÅ©ºñ¼ó",        // ARCHBISHOP_B
-        4100 => "¹ÌÄÉ´Ð",          // MECHANIC_B
-        4101 => "±æ·ÎÆ¾Å©·Î½º",    // GUILLOTINE_CROSS_B
-        4102 => "°¡µå",            // ROYAL_GUARD_B
-        4103 => "¼Ò¼­·¯",          // SORCERER_B
-        4104 => "¹Î½ºÆ®·²",        // MINSTREL_B
-        4105 => "¿ø´õ·¯",          // WANDERER_B
-        4106 => "½´¶ó",            // SURA_B
-        4107 => "Á¦³×¸¯",          // GENETIC_B
-        4108 => "½¦µµ¿ìÃ¼ÀÌ¼­",    // SHADOW_CHASER_B
-        4046 => "ÅÂ±Ç¼Ò³â",        // TAEKWON
-        4047 => "±Ç¼º",            // STAR
-        4049 => "¼Ò¿ï¸µÄ¿",        // LINKER
-        4190 => "½´ÆÛ³ëºñ½º",      // SUPERNOVICE2
+        0 => "초보자",             // NOVICE
+        1 => "검사",               // SWORDMAN
+        2 => "위저드",             // MAGICIAN
+        3 => "궁수",               // ARCHER
+        4 => "성직자",             // ACOLYTE
+        5 => "상인",               // MERCHANT
+        6 => "도둑",               // THIEF
+        7 => "기사",               // KNIGHT
+        8 => "성투사",             // PRIEST
+        9 => "마법사",             // WIZARD
+        10 => "제철공",            // BLACKSMITH
+        11 => "헌터",              // HUNTER
+        12 => "어세신",            // ASSASSIN
+        13 => "엔대운",            // CHICKEN
+        14 => "크루세이더",        // CRUSADER
+        15 => "몽크",              // MONK
+        16 => "세이지",            // SAGE
+        17 => "로그",              // ROGUE
+        18 => "연금술사",          // ALCHEMIST
+        19 => "바드",              // BARD
+        20 => "무희",              // DANCER
+        23 => "슈퍼노비스",        // SUPERNOVICE
+        24 => "건너",              // GUNSLINGER
+        25 => "닌자",              // NINJA
+        4001 => "초보자",          // NOVICE_H
+        4002 => "검사",            // SWORDMAN_H
+        4003 => "위저드",          // MAGICIAN_H
+        4004 => "궁수",            // ARCHER_H
+        4005 => "성직자",          // ACOLYTE_H
+        4006 => "상인",            // MERCHANT_H
+        4007 => "도둑",            // THIEF_H
+        4008 => "로드나이트",      // KNIGHT_H
+        4009 => "하이프리",        // PRIEST_H
+        4010 => "하이위저드",      // WIZARD_H
+        4011 => "화이트스미스",    // BLACKSMITH_H
+        4012 => "스나이퍼",        // HUNTER_H
+        4013 => "어쌔신크로스",    // ASSASSIN_H
+        4014 => "엔대운",          // CHICKEN_H
+        4015 => "크루세이더",      // CRUSADER_H
+        4016 => "몽크",            // MONK_H
+        4017 => "세이지",          // SAGE_H
+        4018 => "로그",            // ROGUE_H
+        4019 => "연금술사",        // ALCHEMIST_H
+        4020 => "바드",            // BARD_H
+        4021 => "무희",            // DANCER_H
+        4023 => "슈퍼노비스",      // NOVICE_B
+        4024 => "검사",            // SWORDMAN_B
+        4025 => "위저드",          // MAGICIAN_B
+        4026 => "궁수",            // ARCHER_B
+        4027 => "성직자",          // ACOLYTE_B
+        4028 => "상인",            // MERCHANT_B
+        4029 => "도둑",            // THIEF_B
+        4030 => "기사",            // KNIGHT_B
+        4031 => "성투사",          // PRIEST_B
+        4032 => "마법사",          // WIZARD_B
+        4033 => "제철공",          // BLACKSMITH_B
+        4034 => "헌터",            // HUNTER_B
+        4035 => "어세신",          // ASSASSIN_B
+        4037 => "크루세이더",      // CRUSADER_B
+        4038 => "몽크",            // MONK_B
+        4039 => "세이지",          // SAGE_B
+        4040 => "로그",            // ROGUE_B
+        4041 => "연금술사",        // ALCHEMIST_B
+        4042 => "바드",            // BARD_B
+        4043 => "무희",            // DANCER_B
+        4045 => "슈퍼노비스",      // SUPERNOVICE_B
+        4054 => "룬나이트",        // RUNE_KNIGHT
+        4055 => "워록",            // WARLOCK
+        4056 => "레인져",          // RANGER
+        4057 => "아크비숍",        // ARCH_BISHOP
+        4058 => "미케닉",          // MECHANIC
+        4059 => "길로틴크로스",    // GUILLOTINE_CROSS
+        4066 => "가드",            // ROYAL_GUARD
+        4067 => "소서러",          // SORCERER
+        4068 => "민스트럴",        // MINSTREL
+        4069 => "원더러",          // WANDERER
+        4070 => "슈라",            // SURA
+        4071 => "제네릭",          // GENETIC
+        4072 => "쉐도우체이서",    // SHADOW_CHASER
+        4060 => "룬나이트",        // RUNE_KNIGHT_H
+        4061 => "워록",            // WARLOCK_H
+        4062 => "레인져",          // RANGER_H
+        4063 => "아크비숍",        // ARCH_BISHOP_H
+        4064 => "미케닉",          // MECHANIC_H
+        4065 => "길로틴크로스",    // GUILLOTINE_CROSS_H
+        4073 => "가드",            // ROYAL_GUARD_H
+        4074 => "소서러",          // SORCERER_H
+        4075 => "민스트럴",        // MINSTREL_H
+        4076 => "원더러",          // WANDERER_H
+        4077 => "슈라",            // SURA_H
+        4078 => "제네릭",          // GENETIC_H
+        4079 => "쉐도우체이서",    // SHADOW_CHASER_H
+        4096 => "룬나이트",        // RUNE_KNIGHT_B
+        4097 => "워록",            // WARLOCK_B
+        4098 => "레인져",          // RANGER_B
+        4099 => "아크비숍",        // ARCHBISHOP_B
+        4100 => "미케닉",          // MECHANIC_B
+        4101 => "길로틴크로스",    // GUILLOTINE_CROSS_B
+        4102 => "가드",            // ROYAL_GUARD_B
+        4103 => "소서러",          // SORCERER_B
+        4104 => "민스트럴",        // MINSTREL_B
+        4105 => "원더러",          // WANDERER_B
+        4106 => "슈라",            // SURA_B
+        4107 => "제네릭",          // GENETIC_B
+        4108 => "쉐도우체이서",    // SHADOW_CHASER_B
+        4046 => "태권소년",        // TAEKWON
+        4047 => "권성",            // STAR
+        4049 => "소울링커",        // LINKER
+        4190 => "슈퍼노비스",      // SUPERNOVICE2
         4211 => "KAGEROU",         // KAGEROU
         4212 => "OBORO",           // OBORO
         4215 => "REBELLION",       // REBELLION
-        4222 => "´ÑÀÚ",            // NINJA_B
+        4222 => "닌자",            // NINJA_B
         4223 => "KAGEROU",         // KAGEROU_B
         4224 => "OBORO",           // OBORO_B
-        4225 => "ÅÂ±Ç¼Ò³â",        // TAEKWON_B
-        4226 => "±Ç¼º",            // STAR_B
-        4227 => "¼Ò¿ï¸µÄ¿",        // LINKER_B
-        4228 => "°Ç³Ê",            // GUNSLINGER_B
+        4225 => "태권소년",        // TAEKWON_B
+        4226 => "권성",            // STAR_B
+        4227 => "소울링커",        // LINKER_B
+        4228 => "건너",            // GUNSLINGER_B
         4229 => "REBELLION",       // REBELLION_B
-        4239 => "¼ºÁ¦",            // STAR EMPEROR
-        4240 => "¼Ò¿ï¸®ÆÛ",        // SOUL REAPER
-        4241 => "¼ºÁ¦",            // STAR_EMPEROR_B
-        4242 => "¼Ò¿ï¸®ÆÛ",        // SOUL_REAPER_B
+        4239 => "성제",            // STAR EMPEROR
+        4240 => "소울리퍼",        // SOUL REAPER
+        4241 => "성제",            // STAR_EMPEROR_B
+        4242 => "소울리퍼",        // SOUL_REAPER_B
         4252 => "DRAGON_KNIGHT",   // DRAGON KNIGHT
         4253 => "MEISTER",         // MEISTER
         4254 => "SHADOW_CROSS",    // SHADOW CROSS
@@ -220,12 +219,12 @@ fn get_sprite_path_for_player_job(job_id: usize) -> &'static str {
         4305 => "SHIRANUI",        // SHIRANUI
         4306 => "NIGHT_WATCH",     // NIGHT WATCH
         4307 => "HYPER_NOVICE",    // HYPER NOVICE
-        _ => "ÃÊº¸ÀÚ",             // NOVICE
+        _ => "초보자",             // NOVICE
     }
 }
 
 fn get_sprite_and_actions(
-    game_file_loader: &mut GameFileLoader,
+    game_file_loader: &GameFileLoader,
     sprite_loader: &mut SpriteLoader,
     action_loader: &mut ActionLoader,
     script_loader: &ScriptLoader,
@@ -234,19 +233,19 @@ fn get_sprite_and_actions(
     sex: Sex,
 ) -> (Arc<Sprite>, Arc<Actions>) {
     let sex_sprite_path = match sex == Sex::Female {
-        true => "¿©",
-        false => "³²",
+        true => "여",
+        false => "남",
     };
 
     let file_path = match entity_type {
         EntityType::Player => format!(
-            "ÀÎ°£Á·\\¸öÅë\\{}\\{}_{}",
+            "인간족\\몸통\\{}\\{}_{}",
             sex_sprite_path,
             get_sprite_path_for_player_job(job_id),
             sex_sprite_path
         ),
         EntityType::Npc => format!("npc\\{}", script_loader.get_job_name_from_id(job_id)),
-        EntityType::Monster => format!("¸ó½ºÅÍ\\{}", script_loader.get_job_name_from_id(job_id)),
+        EntityType::Monster => format!("몬스터\\{}", script_loader.get_job_name_from_id(job_id)),
         EntityType::Warp | EntityType::Hidden => format!("npc\\{}", script_loader.get_job_name_from_id(job_id)), // TODO: change
     };
 
@@ -258,7 +257,7 @@ fn get_sprite_and_actions(
 
 impl Common {
     pub fn new(
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         script_loader: &ScriptLoader,
@@ -328,7 +327,7 @@ impl Common {
 
     pub fn reload_sprite(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         script_loader: &ScriptLoader,
@@ -762,7 +761,7 @@ pub struct Player {
 
 impl Player {
     pub fn new(
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         script_loader: &ScriptLoader,
@@ -886,7 +885,7 @@ pub struct Npc {
 
 impl Npc {
     pub fn new(
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         script_loader: &ScriptLoader,
@@ -999,7 +998,7 @@ impl Entity {
 
     pub fn reload_sprite(
         &mut self,
-        game_file_loader: &mut GameFileLoader,
+        game_file_loader: &GameFileLoader,
         sprite_loader: &mut SpriteLoader,
         action_loader: &mut ActionLoader,
         script_loader: &ScriptLoader,
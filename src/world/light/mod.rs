@@ -2,6 +2,7 @@ use cgmath::Vector3;
 use procedural::*;
 
 use crate::graphics::*;
+use crate::loaders::PaddedString;
 #[cfg(feature = "debug")]
 use crate::world::MarkerIdentifier;
 
@@ -9,7 +10,7 @@ use crate::world::MarkerIdentifier;
 #[window_title("Light Source")]
 pub struct LightSource {
     #[length_hint(80)]
-    pub name: String,
+    pub name: PaddedString,
     pub position: Vector3<f32>,
     pub color: ColorRGB,
     pub range: f32,
@@ -96,6 +96,7 @@ impl MarkerIdentifier {
 
 #[derive(new)]
 pub struct Map {
+    resource_file: String,
     width: usize,
     height: usize,
     water_settings: Option<WaterSettings>,
@@ -115,6 +116,10 @@ pub struct Map {
 }
 
 impl Map {
+    pub fn get_resource_file(&self) -> &str {
+        &self.resource_file
+    }
+
     pub fn x_in_bounds(&self, x: usize) -> bool {
         x <= self.width
     }
@@ -1,6 +1,8 @@
 use procedural::ByteConvertable;
 
-use crate::loaders::{ByteConvertable, ByteStream};
+#[cfg(any(test, feature = "arbitrary"))]
+use crate::loaders::{Arbitrary, Generator};
+use crate::loaders::{ByteConvertable, ByteStream, ConversionError, ConversionErrorKind};
 
 const NONE: u8 = 0b00000000;
 const WALKABLE: u8 = 0b00000001;
@@ -8,28 +10,46 @@ const WATER: u8 = 0b00000010;
 const SNIPABLE: u8 = 0b00000100;
 const CLIFF: u8 = 0b00001000;
 
+/// Flags of every tile type, indexed by the value stored in the file.
+const TILE_TYPES: [u8; 7] = [WALKABLE, NONE, WATER, WATER | WALKABLE, WATER | SNIPABLE, CLIFF | SNIPABLE, CLIFF];
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct TileType(pub u8);
 
 impl ByteConvertable for TileType {
-    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Self {
-        Self::new(byte_stream.next())
+    fn from_bytes(byte_stream: &mut ByteStream, _: Option<usize>) -> Result<Self, ConversionError> {
+        let offset = byte_stream.get_offset();
+        let type_index = byte_stream.next()?;
+
+        TILE_TYPES.get(type_index as usize).map(|flags| Self(*flags)).ok_or_else(|| {
+            let kind = ConversionErrorKind::InvalidEnumValue {
+                type_name: "TileType",
+                value: type_index as usize,
+            };
+            ConversionError::from_kind(offset, kind)
+        })
+    }
+
+    fn to_bytes(&self, _: Option<usize>) -> Vec<u8> {
+        vec![self.get_index()]
+    }
+}
+
+#[cfg(any(test, feature = "arbitrary"))]
+impl Arbitrary for TileType {
+    fn arbitrary(generator: &mut Generator, _: Option<usize>) -> Self {
+        Self(TILE_TYPES[generator.choose(TILE_TYPES.len())])
     }
 }
 
 impl TileType {
-    pub fn new(type_index: u8) -> Self {
-        match type_index {
-            0 => Self(WALKABLE),
-            1 => Self(NONE),
-            2 => Self(WATER),
-            3 => Self(WATER | WALKABLE),
-            4 => Self(WATER | SNIPABLE),
-            5 => Self(CLIFF | SNIPABLE),
-            6 => Self(CLIFF),
-            invalid => panic!("invalid tile type {invalid}"),
-        }
+    /// The value that is stored in the file for this tile type.
+    pub fn get_index(&self) -> u8 {
+        TILE_TYPES
+            .iter()
+            .position(|flags| *flags == self.0)
+            .unwrap_or_else(|| panic!("invalid tile flags {:#010b}", self.0)) as u8
     }
 
     pub fn is_none(&self) -> bool {
@@ -3,6 +3,7 @@ use procedural::*;
 
 #[cfg(feature = "debug")]
 use crate::graphics::{Camera, MarkerRenderer, Renderer};
+use crate::loaders::PaddedString;
 #[cfg(feature = "debug")]
 use crate::world::MarkerIdentifier;
 
@@ -10,9 +11,9 @@ use crate::world::MarkerIdentifier;
 #[window_title("Sound Source")]
 pub struct SoundSource {
     #[length_hint(80)]
-    pub name: String,
+    pub name: PaddedString,
     #[length_hint(80)]
-    pub sound_file: String,
+    pub sound_file: PaddedString,
     pub position: Vector3<f32>,
     pub volume: f32,
     pub width: u32,